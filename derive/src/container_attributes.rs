@@ -1,7 +1,9 @@
+use crate::hooks::{is_hook, Hooks};
 use crate::ARBITRARY_ATTRIBUTE_NAME;
+use quote::ToTokens;
 use syn::{
     parse::Error, punctuated::Punctuated, DeriveInput, Expr, ExprLit, Lit, Meta, MetaNameValue,
-    Token, TypeParam,
+    Path, Token, Type, TypeParam,
 };
 
 pub struct ContainerAttributes {
@@ -19,14 +21,62 @@ pub struct ContainerAttributes {
     /// #[arbitrary(bound = "U: Default")]
     /// ```
     pub bounds: Option<Vec<Punctuated<TypeParam, Token![,]>>>,
+
+    /// The type of the context to derive `ArbitraryWith` for, instead of
+    /// `Arbitrary`. Types that are not plain paths can be given as a string.
+    ///
+    /// ```ignore
+    /// #[arbitrary(context = Registers)]
+    /// #[arbitrary(context = "[Reg]")]
+    /// ```
+    pub context: Option<Type>,
+
+    /// The foreign type that this one mirrors the definition of. Instead of
+    /// implementing `Arbitrary`, the derive generates inherent functions that
+    /// generate the foreign type.
+    ///
+    /// ```ignore
+    /// #[arbitrary(remote = "std::time::Duration")]
+    /// ```
+    pub remote: Option<Path>,
+
+    /// A type to generate instead, and convert with `From` or `TryFrom`.
+    /// Types that are not plain paths can be given as a string.
+    ///
+    /// ```ignore
+    /// #[arbitrary(from = RawConfig)]
+    /// #[arbitrary(try_from = "(u8, u8)")]
+    /// ```
+    pub conversion: Option<Conversion>,
+
+    /// Whether the variants of an enum are picked by their stable tags,
+    /// rather than by their position.
+    ///
+    /// ```ignore
+    /// #[arbitrary(stable_tags)]
+    /// ```
+    pub stable_tags: bool,
+
+    /// Checks and repairs for the generated value.
+    pub hooks: Hooks,
+}
+
+/// How a type is generated from another one.
+pub enum Conversion {
+    From(Type),
+    TryFrom(Type),
 }
 
 impl ContainerAttributes {
     pub fn from_derive_input(derive_input: &DeriveInput) -> Result<Self, Error> {
         let mut bounds = None;
+        let mut context = None;
+        let mut remote = None;
+        let mut conversion = None;
+        let mut stable_tags = false;
 
         for attr in &derive_input.attrs {
-            if !attr.path().is_ident(ARBITRARY_ATTRIBUTE_NAME) {
+            if !attr.path().is_ident(ARBITRARY_ATTRIBUTE_NAME) || is_hook(attr) {
                 continue;
             }
 
@@ -60,19 +110,94 @@ impl ContainerAttributes {
                             .get_or_insert_with(Vec::new)
                             .push(bound_str_lit.parse_with(Punctuated::parse_terminated)?);
                     }
+                    Meta::NameValue(MetaNameValue { path, value, .. })
+                        if path.is_ident("context") =>
+                    {
+                        if context.is_some() {
+                            return Err(Error::new_spanned(
+                                attr,
+                                format!(
+                                    "invalid `{}` attribute. multiple `context` types",
+                                    ARBITRARY_ATTRIBUTE_NAME,
+                                ),
+                            ));
+                        }
+                        context = Some(match value {
+                            Expr::Lit(ExprLit {
+                                lit: Lit::Str(context_str_lit),
+                                ..
+                            }) => context_str_lit.parse()?,
+                            value => syn::parse2(value.into_token_stream())?,
+                        });
+                    }
+                    Meta::NameValue(MetaNameValue { path, value, .. })
+                        if path.is_ident("remote") =>
+                    {
+                        if remote.is_some() {
+                            return Err(Error::new_spanned(
+                                attr,
+                                format!(
+                                    "invalid `{}` attribute. multiple `remote` types",
+                                    ARBITRARY_ATTRIBUTE_NAME,
+                                ),
+                            ));
+                        }
+                        remote = Some(match value {
+                            Expr::Lit(ExprLit {
+                                lit: Lit::Str(remote_str_lit),
+                                ..
+                            }) => remote_str_lit.parse()?,
+                            value => syn::parse2(value.into_token_stream())?,
+                        });
+                    }
+                    Meta::NameValue(MetaNameValue { path, value, .. })
+                        if path.is_ident("from") || path.is_ident("try_from") =>
+                    {
+                        if conversion.is_some() {
+                            return Err(Error::new_spanned(
+                                attr,
+                                format!(
+                                    "invalid `{}` attribute. multiple `from` or `try_from` types",
+                                    ARBITRARY_ATTRIBUTE_NAME,
+                                ),
+                            ));
+                        }
+                        let ty = match value {
+                            Expr::Lit(ExprLit {
+                                lit: Lit::Str(ty_str_lit),
+                                ..
+                            }) => ty_str_lit.parse()?,
+                            value => syn::parse2(value.into_token_stream())?,
+                        };
+                        conversion = Some(if path.is_ident("from") {
+                            Conversion::From(ty)
+                        } else {
+                            Conversion::TryFrom(ty)
+                        });
+                    }
+                    Meta::Path(path) if path.is_ident("stable_tags") => {
+                        stable_tags = true;
+                    }
                     _ => {
                         return Err(Error::new_spanned(
                             attr,
                             format!(
-                                "invalid `{}` attribute. expected `bound = \"..\"`",
-                                ARBITRARY_ATTRIBUTE_NAME,
-                            ),
+                            "invalid `{}` attribute. expected `bound = \"..\"`, `context = ..`, `remote = ..`, `from = ..`, `try_from = ..`, `stable_tags`, `filter = ..`, `fixup = ..` or `retries = ..`",
+                            ARBITRARY_ATTRIBUTE_NAME,
+                        ),
                         ))
                     }
                 }
             }
         }
 
-        Ok(Self { bounds })
+        Ok(Self {
+            bounds,
+            context,
+            remote,
+            conversion,
+            stable_tags,
+            hooks: Hooks::from_attributes(&derive_input.attrs)?,
+        })
     }
 }
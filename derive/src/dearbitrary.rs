@@ -0,0 +1,189 @@
+use crate::container_attributes::ContainerAttributes;
+use crate::field_attributes::{determine_field_constructor, FieldConstructor};
+use crate::variant_attributes::{not_skipped, variant_tags, variant_weights};
+use crate::{add_trait_bounds, check_variant_attrs};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::*;
+
+pub fn expand_derive_dearbitrary(input: syn::DeriveInput) -> Result<TokenStream> {
+    let dearbitrary_method = gen_dearbitrary_method(&input)?;
+    let name = input.ident;
+
+    // Unlike for `Arbitrary`, the `bound` attribute does not apply here: the
+    // user-supplied bounds are written in terms of `Arbitrary`, so always
+    // inject a `T: Dearbitrary` bound for every parameter.
+    let generics = add_trait_bounds(input.generics, parse_quote!(arbitrary::Dearbitrary));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics arbitrary::Dearbitrary for #name #ty_generics #where_clause {
+            #dearbitrary_method
+        }
+    })
+}
+
+fn gen_dearbitrary_method(input: &DeriveInput) -> Result<TokenStream> {
+    let container_attrs = ContainerAttributes::from_derive_input(input)?;
+    let ident = &input.ident;
+    // The raw data of a converted or remote type is that of another type,
+    // which the fields of this one say nothing about.
+    if container_attrs.conversion.is_some() || container_attrs.remote.is_some() {
+        return Err(Error::new_spanned(
+            ident,
+            "`Dearbitrary` cannot be derived with `from`, `try_from` or `remote`; implement it by hand",
+        ));
+    }
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, pushes) = destructure(&data.fields)?;
+            match pushes {
+                Some(pushes) => quote! {
+                    let #ident #pattern = self;
+                    #pushes
+                    Ok(())
+                },
+                None => quote!(Err(arbitrary::Error::Unrepresentable)),
+            }
+        }
+        Data::Enum(data) => dearbitrary_enum(data, ident, container_attrs.stable_tags)?,
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                ident,
+                "`Dearbitrary` cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        fn dearbitrary(&self, d: &mut arbitrary::Dearbitrator) -> arbitrary::Result<()> {
+            d.nested(|d| { #body })
+        }
+    })
+}
+
+fn dearbitrary_enum(
+    DataEnum { variants, .. }: &DataEnum,
+    enum_name: &Ident,
+    stable_tags: bool,
+) -> Result<TokenStream> {
+    variants
+        .iter()
+        .filter(not_skipped)
+        .try_for_each(check_variant_attrs)?;
+
+    let count = variants.iter().filter(not_skipped).count() as u64;
+    if count == 0 {
+        return Err(Error::new_spanned(
+            enum_name,
+            "Enum must have at least one variant, that is not skipped",
+        ));
+    }
+
+    let tags = variant_tags(variants.iter().filter(not_skipped), stable_tags)?;
+    let weights = variant_weights(variants.iter().filter(not_skipped))?
+        .unwrap_or_else(|| vec![1; count as usize]);
+    let total: u64 = weights.iter().map(|&w| u64::from(w)).sum();
+
+    let mut index = 0;
+    let mut cumulative = 0_u64;
+    let arms = variants
+        .iter()
+        .map(|variant| {
+            let variant_name = &variant.ident;
+            if !not_skipped(&variant) {
+                return Ok(quote! {
+                    #enum_name::#variant_name { .. } => {
+                        Err(arbitrary::Error::Unrepresentable)
+                    }
+                });
+            }
+
+            // `Arbitrary` scales `x` onto the cumulative weights with
+            // `(x * total) >> 32`, so the smallest `x` that selects this
+            // variant is `ceil(cumulative * 2^32 / total)`. With many large
+            // weights, a variant may not be reachable by any `x`.
+            let selector =
+                ((u128::from(cumulative) << 32) + u128::from(total) - 1) / u128::from(total);
+            let reachable = selector <= u128::from(u32::MAX)
+                && (selector * u128::from(total)) >> 32
+                    < u128::from(cumulative + u64::from(weights[index]));
+            // With stable tags, a variant's own tag always selects it.
+            let (selector, reachable) = match &tags {
+                Some(tags) => (tags[index], true),
+                None => (selector as u32, reachable),
+            };
+            cumulative += u64::from(weights[index]);
+            index += 1;
+
+            let (pattern, pushes) = destructure(&variant.fields)?;
+            Ok(match pushes {
+                Some(pushes) if reachable => quote! {
+                    #enum_name::#variant_name #pattern => {
+                        d.push(&#selector)?;
+                        #pushes
+                        Ok(())
+                    }
+                },
+                _ => quote! {
+                    #enum_name::#variant_name { .. } => {
+                        Err(arbitrary::Error::Unrepresentable)
+                    }
+                },
+            })
+        })
+        .collect::<Result<Vec<TokenStream>>>()?;
+
+    Ok(quote! {
+        match self {
+            #(#arms)*
+        }
+    })
+}
+
+/// Returns a pattern that binds the fields which are generated from the raw
+/// data by reference, along with the statements that push them to the
+/// `Dearbitrator` in order, or `None` if the fields cannot be turned back into
+/// raw data.
+fn destructure(fields: &Fields) -> Result<(TokenStream, Option<TokenStream>)> {
+    let mut representable = true;
+    let mut bindings = Vec::with_capacity(fields.len());
+    let mut pushes = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        match determine_field_constructor(field)? {
+            FieldConstructor::Arbitrary => {
+                let binding = format_ident!("__arbitrary_field_{}", i, span = Span::call_site());
+                pushes.push(quote! { d.push(#binding)?; });
+                bindings.push(quote!(#binding));
+            }
+            // These fields are not generated from the raw data.
+            FieldConstructor::Default | FieldConstructor::Value(_) => bindings.push(quote!(_)),
+            // There is no telling which data a custom function would turn
+            // into this value, bounded lengths, ranges, regular expressions
+            // and charsets generate values differently than their `Arbitrary`
+            // implementation does, and the context is not available here.
+            // The `Dearbitrary` docs list these attributes; keep them in sync.
+            FieldConstructor::With(_)
+            | FieldConstructor::Len(_)
+            | FieldConstructor::Range(_)
+            | FieldConstructor::Regex(_)
+            | FieldConstructor::Charset(_)
+            | FieldConstructor::Context => {
+                representable = false;
+                bindings.push(quote!(_));
+            }
+        }
+    }
+
+    let pattern = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+            quote! { { #(#names: #bindings),* } }
+        }
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => quote!(),
+    };
+
+    Ok((pattern, representable.then(|| quote! { #(#pushes)* })))
+}
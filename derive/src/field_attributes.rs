@@ -1,5 +1,7 @@
+use crate::hooks::is_hook;
+use crate::regex;
 use crate::ARBITRARY_ATTRIBUTE_NAME;
-use proc_macro2::{Span, TokenStream, TokenTree};
+use proc_macro2::{Spacing, Span, TokenStream, TokenTree};
 use quote::quote;
 use syn::{spanned::Spanned, *};
 
@@ -17,6 +19,21 @@ pub enum FieldConstructor {
 
     /// Set a field always to the given value.
     Value(TokenStream),
+
+    /// Generate a collection with a number of elements within the given range.
+    Len(TokenStream),
+
+    /// Generate a value within the given range.
+    Range(TokenStream),
+
+    /// Generate a value with `ArbitraryWith` and the context of the container.
+    Context,
+
+    /// Generate a string that matches the given regular expression.
+    Regex(LitStr),
+
+    /// Generate a string of the given `arbitrary::charset` string type.
+    Charset(TokenStream),
 }
 
 pub fn determine_field_constructor(field: &Field) -> Result<FieldConstructor> {
@@ -35,7 +52,7 @@ fn fetch_attr_from_field(field: &Field) -> Result<Option<&Attribute>> {
         .filter(|a| {
             let path = a.path();
             let name = quote!(#path).to_string();
-            name == ARBITRARY_ATTRIBUTE_NAME
+            name == ARBITRARY_ATTRIBUTE_NAME && !is_hook(a)
         })
         .collect();
     if found_attributes.len() > 1 {
@@ -65,6 +82,7 @@ fn parse_attribute_internals(meta_list: &MetaList) -> Result<FieldConstructor> {
     })?;
     match token.to_string().as_ref() {
         "default" => Ok(FieldConstructor::Default),
+        "context" => Ok(FieldConstructor::Context),
         "with" => {
             let func_path = parse_assigned_value("with", tokens_iter, meta_list.span())?;
             Ok(FieldConstructor::With(func_path))
@@ -73,6 +91,34 @@ fn parse_attribute_internals(meta_list: &MetaList) -> Result<FieldConstructor> {
             let value = parse_assigned_value("value", tokens_iter, meta_list.span())?;
             Ok(FieldConstructor::Value(value))
         }
+        "len" => {
+            let range = parse_assigned_value("len", tokens_iter, meta_list.span())?;
+            Ok(FieldConstructor::Len(range))
+        }
+        "range" => {
+            let range = parse_assigned_value("range", tokens_iter, meta_list.span())?;
+            Ok(FieldConstructor::Range(range))
+        }
+        "regex" => {
+            let pattern = parse_assigned_value("regex", tokens_iter, meta_list.span())?;
+            let pattern = parse2::<LitStr>(pattern).map_err(|e| {
+                let msg =
+                    format!("#[{ARBITRARY_ATTRIBUTE_NAME}(regex = ...)] must be a string literal");
+                syn::Error::new(e.span(), msg)
+            })?;
+            if let Err(e) = regex::validate(&pattern.value()) {
+                let msg = format!(
+                    "invalid regex: {} at byte {} of the pattern",
+                    e.message, e.offset
+                );
+                return Err(syn::Error::new(pattern.span(), msg));
+            }
+            Ok(FieldConstructor::Regex(pattern))
+        }
+        "charset" => {
+            let charset = parse_assigned_value("charset", tokens_iter, meta_list.span())?;
+            charset_string_type(charset).map(FieldConstructor::Charset)
+        }
         _ => {
             let msg = format!("Unknown option for #[{ARBITRARY_ATTRIBUTE_NAME}]: `{token}`");
             Err(syn::Error::new(token.span(), msg))
@@ -80,11 +126,45 @@ fn parse_attribute_internals(meta_list: &MetaList) -> Result<FieldConstructor> {
     }
 }
 
+// Input:
+//     "ascii_graphic"
+// Output:
+//     arbitrary::charset::CharsetString<arbitrary::charset::AsciiGraphic>
+fn charset_string_type(charset: TokenStream) -> Result<TokenStream> {
+    let name = match parse2::<LitStr>(charset.clone()) {
+        Ok(name) => name,
+        Err(_) => {
+            let charset = parse2::<Type>(charset).map_err(|e| {
+                let msg = format!(
+                    "#[{ARBITRARY_ATTRIBUTE_NAME}(charset = ...)] must be a string literal or the path of a `Charset` type"
+                );
+                syn::Error::new(e.span(), msg)
+            })?;
+            return Ok(quote!(arbitrary::charset::CharsetString<#charset>));
+        }
+    };
+    match name.value().as_str() {
+        "ascii" => Ok(quote!(arbitrary::charset::AsciiString)),
+        "ascii_graphic" => Ok(quote!(
+            arbitrary::charset::CharsetString<arbitrary::charset::AsciiGraphic>
+        )),
+        "alphanumeric" => Ok(quote!(arbitrary::charset::Alphanumeric)),
+        "printable" => Ok(quote!(arbitrary::charset::Printable)),
+        "identifier" => Ok(quote!(arbitrary::charset::Identifier)),
+        other => {
+            let msg = format!(
+                "Unknown charset `{other}`, expected one of `ascii`, `ascii_graphic`, `alphanumeric`, `printable` or `identifier`"
+            );
+            Err(syn::Error::new(name.span(), msg))
+        }
+    }
+}
+
 // Input:
 //     = 2 + 2
 // Output:
 //     2 + 2
-fn parse_assigned_value(
+pub fn parse_assigned_value(
     opt_name: &str,
     mut tokens_iter: impl Iterator<Item = TokenTree>,
     default_span: Span,
@@ -103,3 +183,69 @@ fn parse_assigned_value(
         Err(syn::Error::new(eq_sign.span(), msg))
     }
 }
+
+/// Returns the names of the earlier fields that a `with` closure takes after
+/// the `Unstructured`, like `len` in `|u, len: &u8| ...`. Functions only take
+/// the `Unstructured`.
+pub fn with_arguments(function_or_closure: &TokenStream) -> Result<Vec<Ident>> {
+    let mut tokens = function_or_closure.clone().into_iter().peekable();
+    if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+        if ident == "move" {
+            tokens.next();
+        }
+    }
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '|' => {}
+        _ => return Ok(Vec::new()),
+    }
+
+    // Split the parameters at the commas that are not inside of the
+    // generic arguments of a type.
+    let mut params = vec![Vec::new()];
+    let mut angle_depth = 0_usize;
+    let mut after_minus = false;
+    for token in tokens.by_ref() {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '|' if angle_depth == 0 => break,
+                ',' if angle_depth == 0 => {
+                    params.push(Vec::new());
+                    continue;
+                }
+                '<' => angle_depth += 1,
+                // The `>` of `->` does not close generic arguments.
+                '>' if !after_minus => angle_depth = angle_depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        after_minus = matches!(
+            &token,
+            TokenTree::Punct(punct) if punct.as_char() == '-' && punct.spacing() == Spacing::Joint
+        );
+        params.last_mut().unwrap().push(token);
+    }
+
+    params
+        .into_iter()
+        .skip(1)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let mut param = param.into_iter();
+            let first = param.next();
+            let second = param.next();
+            match (first, second) {
+                (Some(TokenTree::Ident(ident)), None) => Ok(ident),
+                (Some(TokenTree::Ident(ident)), Some(TokenTree::Punct(colon)))
+                    if colon.as_char() == ':' =>
+                {
+                    Ok(ident)
+                }
+                (Some(token), _) => Err(syn::Error::new(
+                    token.span(),
+                    "expected the name of a field that is generated before this one",
+                )),
+                (None, _) => unreachable!("empty parameters are filtered out"),
+            }
+        })
+        .collect()
+}
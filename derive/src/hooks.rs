@@ -0,0 +1,146 @@
+use crate::field_attributes::parse_assigned_value;
+use crate::ARBITRARY_ATTRIBUTE_NAME;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::quote;
+use syn::{spanned::Spanned, *};
+
+const HOOK_NAMES: [&str; 3] = ["filter", "fixup", "retries"];
+
+/// Checks and repairs for a generated value, given with the `filter`, `fixup`
+/// and `retries` attributes of a container or a field.
+///
+/// ```ignore
+/// #[arbitrary(fixup = |c: &mut Config| c.checksum = c.compute_checksum())]
+/// #[arbitrary(filter = |c: &Config| c.min <= c.max)]
+/// #[arbitrary(retries = 8)]
+/// ```
+///
+/// Each of them takes the rest of its attribute, so that closures need no
+/// quoting.
+#[derive(Default)]
+pub struct Hooks {
+    /// A `fn(&T) -> bool` that rejects the values it returns `false` for.
+    pub filter: Option<TokenStream>,
+
+    /// A `fn(&mut T)` that repairs values before they are filtered.
+    pub fixup: Option<TokenStream>,
+
+    /// How many more values to generate after one is rejected.
+    pub retries: Option<u32>,
+}
+
+/// Whether the attribute is one of the hooks, rather than another option.
+pub fn is_hook(attr: &Attribute) -> bool {
+    match &attr.meta {
+        Meta::List(meta_list) => match meta_list.tokens.clone().into_iter().next() {
+            Some(TokenTree::Ident(ident)) => HOOK_NAMES.iter().any(|name| ident == name),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl Hooks {
+    /// Collects the hooks among the given attributes.
+    pub fn from_attributes(attrs: &[Attribute]) -> Result<Self> {
+        let mut hooks = Hooks::default();
+        let mut retries_attr = None;
+        for attr in attrs {
+            if attr.path().is_ident(ARBITRARY_ATTRIBUTE_NAME)
+                && is_hook(attr)
+                && hooks.parse(attr)?
+            {
+                retries_attr = Some(attr);
+            }
+        }
+        if let (Some(attr), None) = (retries_attr, &hooks.filter) {
+            return Err(Error::new_spanned(
+                attr,
+                format!(
+                    "`retries` has no effect without a `#[{}(filter = ...)]` attribute",
+                    ARBITRARY_ATTRIBUTE_NAME
+                ),
+            ));
+        }
+        Ok(hooks)
+    }
+
+    /// Returns whether the attribute is `retries`.
+    fn parse(&mut self, attr: &Attribute) -> Result<bool> {
+        let meta_list = attr.meta.require_list()?;
+        let mut tokens = meta_list.tokens.clone().into_iter();
+        let name = tokens.next().unwrap().to_string();
+        let value = parse_assigned_value(&name, tokens, meta_list.span())?;
+        let slot_is_taken = match name.as_str() {
+            "filter" => self.filter.replace(value).is_some(),
+            "fixup" => self.fixup.replace(value).is_some(),
+            "retries" => {
+                let retries = parse2::<LitInt>(value)?.base10_parse()?;
+                self.retries.replace(retries).is_some()
+            }
+            _ => unreachable!("only hooks are parsed"),
+        };
+        if slot_is_taken {
+            return Err(Error::new_spanned(
+                attr,
+                format!("multiple `{}` attributes", name),
+            ));
+        }
+        Ok(name == "retries")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filter.is_none() && self.fixup.is_none()
+    }
+
+    /// Wraps an expression that generates a value, so that it is repaired and
+    /// checked. If `retry` is set, the expression is evaluated again for every
+    /// retry, and otherwise, rejecting the value returns
+    /// `Error::IncorrectFormat` right away.
+    pub fn apply(&self, value: TokenStream, retry: bool) -> TokenStream {
+        if self.is_empty() {
+            return value;
+        }
+
+        let fixup = self.fixup.as_ref().map(|fixup| {
+            quote! { (#fixup)(&mut __arbitrary_value); }
+        });
+        let mutability = fixup.as_ref().map(|_| quote!(mut));
+        let retries = self.retries.unwrap_or(0);
+        match &self.filter {
+            Some(filter) if retry && retries > 0 => quote! {
+                {
+                    let mut __arbitrary_retries: u32 = #retries;
+                    loop {
+                        let #mutability __arbitrary_value = #value;
+                        #fixup
+                        if (#filter)(&__arbitrary_value) {
+                            break __arbitrary_value;
+                        }
+                        if __arbitrary_retries == 0 {
+                            return Err(arbitrary::Error::IncorrectFormat);
+                        }
+                        __arbitrary_retries -= 1;
+                    }
+                }
+            },
+            Some(filter) => quote! {
+                {
+                    let #mutability __arbitrary_value = #value;
+                    #fixup
+                    if !(#filter)(&__arbitrary_value) {
+                        return Err(arbitrary::Error::IncorrectFormat);
+                    }
+                    __arbitrary_value
+                }
+            },
+            None => quote! {
+                {
+                    let mut __arbitrary_value = #value;
+                    #fixup
+                    __arbitrary_value
+                }
+            },
+        }
+    }
+}
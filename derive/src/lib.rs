@@ -1,16 +1,23 @@
 extern crate proc_macro;
 
-use proc_macro2::{Span, TokenStream};
-use quote::quote;
-use syn::*;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, *};
 
 mod container_attributes;
+mod dearbitrary;
 mod field_attributes;
+mod hooks;
+mod mutate;
+mod property_test;
+mod regex;
+mod shrink;
 mod variant_attributes;
 
-use container_attributes::ContainerAttributes;
-use field_attributes::{determine_field_constructor, FieldConstructor};
-use variant_attributes::not_skipped;
+use container_attributes::{ContainerAttributes, Conversion};
+use field_attributes::{determine_field_constructor, with_arguments, FieldConstructor};
+use hooks::Hooks;
+use variant_attributes::{not_skipped, variant_tag, variant_tags, variant_weight, variant_weights};
 
 const ARBITRARY_ATTRIBUTE_NAME: &str = "arbitrary";
 const ARBITRARY_LIFETIME_NAME: &str = "'arbitrary";
@@ -23,25 +30,87 @@ pub fn derive_arbitrary(tokens: proc_macro::TokenStream) -> proc_macro::TokenStr
         .into()
 }
 
+#[proc_macro_derive(Dearbitrary, attributes(arbitrary))]
+pub fn derive_dearbitrary(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(tokens as syn::DeriveInput);
+    dearbitrary::expand_derive_dearbitrary(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Shrink, attributes(arbitrary))]
+pub fn derive_shrink(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(tokens as syn::DeriveInput);
+    shrink::expand_derive_shrink(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Mutate, attributes(arbitrary))]
+pub fn derive_mutate(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(tokens as syn::DeriveInput);
+    mutate::expand_derive_mutate(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turn a function with `Arbitrary` arguments into a property test.
+///
+/// The test runs the function with many arbitrary arguments, generated from
+/// pseudo-random raw data with a seed that is derived from the name of the
+/// function, unless the `ARBITRARY_SEED` environment variable is set. If the
+/// function panics or returns an error, the test panics with the `Debug`
+/// representation of the arguments, the seed and the shrunk raw data.
+///
+/// The function may return `()` or a `Result<(), E>` where `E: Debug`, and
+/// its arguments must implement `Arbitrary` and `Debug`. Settings are given
+/// as `name = value` pairs, and set the `arbitrary::check::Config` method of
+/// the same name: `cases`, `sizes`, `seed`, `max_discards`, `regressions` and
+/// `shrink`. Other attributes, like `#[should_panic]`, apply to the test.
+///
+/// ```ignore
+/// #[arbitrary::test(cases = 1000, sizes = 0..=64)]
+/// fn reverse_twice(list: Vec<u8>) {
+///     let mut reversed = list.clone();
+///     reversed.reverse();
+///     reversed.reverse();
+///     assert_eq!(reversed, list);
+/// }
+/// ```
+///
+/// Since `use arbitrary::*;` imports this attribute too, it makes `#[test]`
+/// ambiguous; import the items that you need by name instead.
+///
+/// Requires the `"std"` cargo feature of `arbitrary`.
+#[proc_macro_attribute]
+pub fn test(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    property_test::expand_test(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// An alias of [`macro@test`], with a name that can't be mistaken for the
+/// built-in `#[test]`.
+#[proc_macro_attribute]
+pub fn property_test(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    test(attr, item)
+}
+
 fn expand_derive_arbitrary(input: syn::DeriveInput) -> Result<TokenStream> {
     let container_attrs = ContainerAttributes::from_derive_input(&input)?;
 
     let (lifetime_without_bounds, lifetime_with_bounds) =
         build_arbitrary_lifetime(input.generics.clone());
 
-    let recursive_count = syn::Ident::new(
-        &format!("RECURSIVE_COUNT_{}", input.ident),
-        Span::call_site(),
-    );
-
-    let arbitrary_method =
-        gen_arbitrary_method(&input, lifetime_without_bounds.clone(), &recursive_count)?;
-    let size_hint_method = gen_size_hint_method(&input)?;
-    let name = input.ident;
-
     // Apply user-supplied bounds or automatic `T: ArbitraryBounds`.
     let generics = apply_trait_bounds(
-        input.generics,
+        input.generics.clone(),
         lifetime_without_bounds.clone(),
         &container_attrs,
     )?;
@@ -56,22 +125,107 @@ fn expand_derive_arbitrary(input: syn::DeriveInput) -> Result<TokenStream> {
     // Build TypeGenerics and WhereClause without a lifetime
     let (_, ty_generics, where_clause) = generics.split_for_impl();
 
-    Ok(quote! {
-        const _: () = {
-            ::std::thread_local! {
-                #[allow(non_upper_case_globals)]
-                static #recursive_count: ::core::cell::Cell<u32> = ::core::cell::Cell::new(0);
-            }
+    let context = container_attrs.context.as_ref();
+    let name = &input.ident;
+    let signatures = match &container_attrs.remote {
+        Some(remote) => Signatures::new(
+            &lifetime_without_bounds,
+            context,
+            &quote!(#remote #ty_generics),
+            &input.vis,
+        ),
+        None => Signatures::new(
+            &lifetime_without_bounds,
+            context,
+            &quote!(Self),
+            &Visibility::Inherited,
+        ),
+    };
+    let arbitrary_method = gen_arbitrary_method(&input, &signatures, &container_attrs)?;
+    let size_hint_method = gen_size_hint_method(&input, &signatures, &container_attrs)?;
 
+    // A remote type gets inherent functions that generate the foreign type,
+    // since the orphan rules forbid implementing `Arbitrary` for it.
+    if container_attrs.remote.is_some() {
+        return Ok(quote! {
             #[automatically_derived]
-            impl #impl_generics arbitrary::Arbitrary<#lifetime_without_bounds> for #name #ty_generics #where_clause {
+            impl #impl_generics #name #ty_generics #where_clause {
                 #arbitrary_method
                 #size_hint_method
             }
-        };
+        });
+    }
+
+    let trait_path = match context {
+        Some(context) => quote!(arbitrary::ArbitraryWith<#lifetime_without_bounds, #context>),
+        None => quote!(arbitrary::Arbitrary<#lifetime_without_bounds>),
+    };
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #arbitrary_method
+            #size_hint_method
+        }
     })
 }
 
+/// The signatures of the generated methods, which are those of `Arbitrary`,
+/// or those of `ArbitraryWith` when the container takes a context. For a
+/// remote type, they are inherent functions with the visibility of the
+/// container that return the foreign `output` type instead of `Self`.
+struct Signatures {
+    arbitrary: TokenStream,
+    arbitrary_take_rest: TokenStream,
+    size_hint: TokenStream,
+    try_size_hint: TokenStream,
+    output: TokenStream,
+}
+
+impl Signatures {
+    fn new(
+        lifetime: &LifetimeParam,
+        context: Option<&Type>,
+        output: &TokenStream,
+        vis: &Visibility,
+    ) -> Self {
+        let hint = quote!((usize, ::core::option::Option<usize>));
+        match context {
+            Some(context) => Signatures {
+                arbitrary: quote! {
+                    #vis fn arbitrary_with(
+                        u: &mut arbitrary::Unstructured<#lifetime>,
+                        __arbitrary_context: &#context,
+                    ) -> arbitrary::Result<#output>
+                },
+                arbitrary_take_rest: quote! {
+                    #vis fn arbitrary_take_rest_with(
+                        u: arbitrary::Unstructured<#lifetime>,
+                        __arbitrary_context: &#context,
+                    ) -> arbitrary::Result<#output>
+                },
+                size_hint: quote!(#vis fn size_hint_with(depth: usize) -> #hint),
+                try_size_hint: quote! {
+                    #vis fn try_size_hint_with(depth: usize) -> Result<#hint, arbitrary::MaxRecursionReached>
+                },
+                output: output.clone(),
+            },
+            None => Signatures {
+                arbitrary: quote! {
+                    #vis fn arbitrary(u: &mut arbitrary::Unstructured<#lifetime>) -> arbitrary::Result<#output>
+                },
+                arbitrary_take_rest: quote! {
+                    #vis fn arbitrary_take_rest(u: arbitrary::Unstructured<#lifetime>) -> arbitrary::Result<#output>
+                },
+                size_hint: quote!(#vis fn size_hint(depth: usize) -> #hint),
+                try_size_hint: quote! {
+                    #vis fn try_size_hint(depth: usize) -> Result<#hint, arbitrary::MaxRecursionReached>
+                },
+                output: output.clone(),
+            },
+        }
+    }
+}
+
 // Returns: (lifetime without bounds, lifetime with bounds)
 // Example: ("'arbitrary", "'arbitrary: 'a + 'b")
 fn build_arbitrary_lifetime(generics: Generics) -> (LifetimeParam, LifetimeParam) {
@@ -131,113 +285,135 @@ fn apply_trait_bounds(
         Ok(generics)
     } else {
         // Otherwise, inject a `T: Arbitrary` bound for every parameter.
-        Ok(add_trait_bounds(generics, lifetime))
+        Ok(add_trait_bounds(
+            generics,
+            parse_quote!(arbitrary::Arbitrary<#lifetime>),
+        ))
     }
 }
 
-// Add a bound `T: Arbitrary` to every type parameter T.
-fn add_trait_bounds(mut generics: Generics, lifetime: LifetimeParam) -> Generics {
+// Add the given bound, e.g. `T: Arbitrary`, to every type parameter T.
+fn add_trait_bounds(mut generics: Generics, bound: TypeParamBound) -> Generics {
     for param in generics.params.iter_mut() {
         if let GenericParam::Type(type_param) = param {
-            type_param
-                .bounds
-                .push(parse_quote!(arbitrary::Arbitrary<#lifetime>));
+            type_param.bounds.push(bound.clone());
         }
     }
     generics
 }
 
-fn with_recursive_count_guard(
-    recursive_count: &syn::Ident,
-    expr: impl quote::ToTokens,
-) -> impl quote::ToTokens {
-    quote! {
-        let guard_against_recursion = u.is_empty();
-        if guard_against_recursion {
-            #recursive_count.with(|count| {
-                if count.get() > 0 {
-                    return Err(arbitrary::Error::NotEnoughData);
-                }
-                count.set(count.get() + 1);
-                Ok(())
-            })?;
-        }
-
-        let result = (|| { #expr })();
-
-        if guard_against_recursion {
-            #recursive_count.with(|count| {
-                count.set(count.get() - 1);
-            });
-        }
-
-        result
-    }
-}
-
 fn gen_arbitrary_method(
     input: &DeriveInput,
-    lifetime: LifetimeParam,
-    recursive_count: &syn::Ident,
+    signatures: &Signatures,
+    container_attrs: &ContainerAttributes,
 ) -> Result<TokenStream> {
+    // The container's hooks apply to the whole value, once it is generated.
+    // `arbitrary_take_rest` has no data left to retry with.
+    fn with_hooks(hooks: &Hooks, body: TokenStream, retry: bool) -> TokenStream {
+        if hooks.is_empty() {
+            return body;
+        }
+        let value = hooks.apply(quote!(#body?), retry);
+        quote!(Ok(#value))
+    }
+
     fn arbitrary_structlike(
         fields: &Fields,
+        path: &TokenStream,
         ident: &syn::Ident,
-        lifetime: LifetimeParam,
-        recursive_count: &syn::Ident,
+        signatures: &Signatures,
+        hooks: &Hooks,
     ) -> Result<TokenStream> {
-        let arbitrary = construct(fields, |_idx, field| gen_constructor_for_field(field))?;
-        let body = with_recursive_count_guard(recursive_count, quote! { Ok(#ident #arbitrary) });
-
-        let arbitrary_take_rest = construct_take_rest(fields)?;
-        let take_rest_body =
-            with_recursive_count_guard(recursive_count, quote! { Ok(#ident #arbitrary_take_rest) });
+        let arbitrary = construct(path, fields, gen_constructor_for_field)?;
+        let arbitrary_take_rest = construct_take_rest(path, fields)?;
+        let take_rest_binding = take_rest_binding(fields)?;
 
+        let segment = type_segment(ident);
+        let Signatures {
+            arbitrary: arbitrary_signature,
+            arbitrary_take_rest: arbitrary_take_rest_signature,
+            ..
+        } = signatures;
+        let arbitrary = with_hooks(
+            hooks,
+            quote!(u.nested(|u| u.trace_scope(#segment, |u| Ok(#arbitrary)))),
+            true,
+        );
+        let arbitrary_take_rest = with_hooks(
+            hooks,
+            quote! {
+                u.nested_take_rest(|u| {
+                    u.trace_scope_take_rest(#segment, |#take_rest_binding u| Ok(#arbitrary_take_rest))
+                })
+            },
+            false,
+        );
         Ok(quote! {
-            fn arbitrary(u: &mut arbitrary::Unstructured<#lifetime>) -> arbitrary::Result<Self> {
-                #body
+            #arbitrary_signature {
+                #arbitrary
             }
 
-            fn arbitrary_take_rest(mut u: arbitrary::Unstructured<#lifetime>) -> arbitrary::Result<Self> {
-                #take_rest_body
+            #arbitrary_take_rest_signature {
+                #arbitrary_take_rest
             }
         })
     }
 
     fn arbitrary_variant(
         index: u64,
-        enum_name: &Ident,
         variant_name: &Ident,
         ctor: TokenStream,
+        binding: TokenStream,
+    ) -> TokenStream {
+        let segment = variant_segment(variant_name);
+        quote! {
+            #index => u.trace_scope(#segment, |#binding| Ok(#ctor))?
+        }
+    }
+
+    fn arbitrary_variant_take_rest(
+        index: u64,
+        variant_name: &Ident,
+        ctor: TokenStream,
+        binding: TokenStream,
     ) -> TokenStream {
-        quote! { #index => #enum_name::#variant_name #ctor }
+        let segment = variant_segment(variant_name);
+        quote! {
+            #index => u.trace_scope_take_rest(#segment, |#binding| Ok(#ctor))?
+        }
     }
 
-    fn arbitrary_enum_method(
-        recursive_count: &syn::Ident,
+    fn choose_variant(
         unstructured: TokenStream,
-        variants: &[TokenStream],
-    ) -> impl quote::ToTokens {
-        let count = variants.len() as u64;
-        with_recursive_count_guard(
-            recursive_count,
-            quote! {
-                // Use a multiply + shift to generate a ranged random number
-                // with slight bias. For details, see:
-                // https://lemire.me/blog/2016/06/30/fast-random-shuffling
-                Ok(match (u64::from(<u32 as arbitrary::Arbitrary>::arbitrary(#unstructured)?) * #count) >> 32 {
-                    #(#variants,)*
-                    _ => unreachable!()
-                })
-            },
-        )
+        count: u64,
+        weights: &Option<Vec<u32>>,
+        tags: &Option<Vec<u32>>,
+    ) -> TokenStream {
+        if let Some(tags) = tags {
+            return quote! {
+                arbitrary::Unstructured::choose_tagged_index(#unstructured, &[#(#tags),*])? as u64
+            };
+        }
+        if let Some(weights) = weights {
+            return quote! {
+                arbitrary::Unstructured::choose_weighted_index(#unstructured, &[#(#weights),*])? as u64
+            };
+        }
+        quote! {
+            // Use a multiply + shift to generate a ranged random number
+            // with slight bias. For details, see:
+            // https://lemire.me/blog/2016/06/30/fast-random-shuffling
+            (u64::from(<u32 as arbitrary::Arbitrary>::arbitrary(#unstructured)?) * #count) >> 32
+        }
     }
 
     fn arbitrary_enum(
         DataEnum { variants, .. }: &DataEnum,
+        enum_path: &TokenStream,
         enum_name: &Ident,
-        lifetime: LifetimeParam,
-        recursive_count: &syn::Ident,
+        signatures: &Signatures,
+        hooks: &Hooks,
+        stable_tags: bool,
     ) -> Result<TokenStream> {
         let filtered_variants = variants.iter().filter(not_skipped);
 
@@ -245,6 +421,8 @@ fn gen_arbitrary_method(
         filtered_variants
             .clone()
             .try_for_each(check_variant_attrs)?;
+        let weights = variant_weights(filtered_variants.clone())?;
+        let tags = variant_tags(filtered_variants.clone(), stable_tags)?;
 
         // From here on, we can assume that the attributes of all variants were checked.
         let enumerated_variants = filtered_variants
@@ -255,16 +433,31 @@ fn gen_arbitrary_method(
         let variants = enumerated_variants
             .clone()
             .map(|(index, Variant { fields, ident, .. })| {
-                construct(fields, |_, field| gen_constructor_for_field(field))
-                    .map(|ctor| arbitrary_variant(index, enum_name, ident, ctor))
+                let binding = if uses_unstructured(fields)? {
+                    quote!(u)
+                } else {
+                    quote!(_)
+                };
+                construct(
+                    &quote!(#enum_path::#ident),
+                    fields,
+                    gen_constructor_for_field,
+                )
+                .map(|ctor| arbitrary_variant(index, ident, ctor, binding))
             })
             .collect::<Result<Vec<TokenStream>>>()?;
 
         // Construct `match`-arms for the `arbitrary_take_rest` method.
         let variants_take_rest = enumerated_variants
             .map(|(index, Variant { fields, ident, .. })| {
-                construct_take_rest(fields)
-                    .map(|ctor| arbitrary_variant(index, enum_name, ident, ctor))
+                let binding = if uses_unstructured(fields)? {
+                    let mutability = take_rest_binding(fields)?;
+                    quote!(#mutability u)
+                } else {
+                    quote!(_)
+                };
+                construct_take_rest(&quote!(#enum_path::#ident), fields)
+                    .map(|ctor| arbitrary_variant_take_rest(index, ident, ctor, binding))
             })
             .collect::<Result<Vec<TokenStream>>>()?;
 
@@ -274,95 +467,453 @@ fn gen_arbitrary_method(
         // If `variants` is empty, this will emit a compiler-error.
         (!variants.is_empty())
             .then(|| {
-                // TODO: Improve dealing with `u` vs. `&mut u`.
-                let arbitrary = arbitrary_enum_method(recursive_count, quote! { u }, &variants);
-                let arbitrary_take_rest = arbitrary_enum_method(recursive_count, quote! { &mut u }, &variants_take_rest);
+                let count = variants.len() as u64;
+                let choose = choose_variant(quote! { u }, count, &weights, &tags);
+                let choose_take_rest = choose_variant(quote! { &mut u }, count, &weights, &tags);
 
+                let segment = type_segment(enum_name);
+                let output = &signatures.output;
+                let select = quote! {
+                    Ok(match index {
+                        #(#variants,)*
+                        _ => unreachable!()
+                    })
+                };
+                let arbitrary = if count == 1 {
+                    quote! {
+                        let index = #choose;
+                        #select
+                    }
+                } else {
+                    quote! {
+                        let mut index = #choose;
+                        // Once the depth budget is spent, variants with nested
+                        // values fail, however deep inside of them the budget
+                        // runs out. Fall back to the next variant that fits.
+                        let mut fallbacks = #count - 1;
+                        loop {
+                            match (|| -> arbitrary::Result<#output> { #select })() {
+                                Err(arbitrary::Error::MaxDepthReached) if fallbacks > 0 => {
+                                    fallbacks -= 1;
+                                    index = (index + 1) % #count;
+                                }
+                                result => return result,
+                            }
+                        }
+                    }
+                };
+                let Signatures {
+                    arbitrary: arbitrary_signature,
+                    arbitrary_take_rest: arbitrary_take_rest_signature,
+                    ..
+                } = signatures;
+                let arbitrary_take_rest = with_hooks(
+                    hooks,
+                    quote! {
+                        u.nested_take_rest(|mut u| {
+                            // The variants to fall back to don't need the rest
+                            // of the data.
+                            if u.is_depth_exhausted() {
+                                return u.trace_scope(#segment, |u| { #arbitrary });
+                            }
+                            u.trace_scope_take_rest(#segment, |mut u| {
+                                Ok(match #choose_take_rest {
+                                    #(#variants_take_rest,)*
+                                    _ => unreachable!()
+                                })
+                            })
+                        })
+                    },
+                    false,
+                );
+                let arbitrary = with_hooks(
+                    hooks,
+                    quote!(u.nested(|u| u.trace_scope(#segment, |u| { #arbitrary }))),
+                    true,
+                );
                 quote! {
-                    fn arbitrary(u: &mut arbitrary::Unstructured<#lifetime>) -> arbitrary::Result<Self> {
+                    #arbitrary_signature {
                         #arbitrary
                     }
 
-                    fn arbitrary_take_rest(mut u: arbitrary::Unstructured<#lifetime>) -> arbitrary::Result<Self> {
+                    #arbitrary_take_rest_signature {
                         #arbitrary_take_rest
                     }
                 }
             })
-            .ok_or_else(|| Error::new_spanned(
-                enum_name,
-                "Enum must have at least one variant, that is not skipped"
-            ))
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    enum_name,
+                    "Enum must have at least one variant, that is not skipped",
+                )
+            })
     }
 
-    let ident = &input.ident;
+    fn arbitrary_converted(
+        conversion: &Conversion,
+        ident: &syn::Ident,
+        signatures: &Signatures,
+        container_attrs: &ContainerAttributes,
+    ) -> TokenStream {
+        let Signatures {
+            arbitrary: arbitrary_signature,
+            arbitrary_take_rest: arbitrary_take_rest_signature,
+            output,
+            ..
+        } = signatures;
+        let (raw, convert) = match conversion {
+            Conversion::From(raw) => (
+                raw,
+                quote!(Ok(<#output as ::core::convert::From<#raw>>::from(raw))),
+            ),
+            Conversion::TryFrom(raw) => (
+                raw,
+                quote! {
+                    <#output as ::core::convert::TryFrom<#raw>>::try_from(raw)
+                        .map_err(|_| arbitrary::Error::IncorrectFormat)
+                },
+            ),
+        };
+        let (arbitrary_raw, arbitrary_take_rest_raw) = match &container_attrs.context {
+            Some(context) => (
+                quote! {
+                    <#raw as arbitrary::ArbitraryWith<#context>>::arbitrary_with(u, __arbitrary_context)?
+                },
+                quote! {
+                    <#raw as arbitrary::ArbitraryWith<#context>>::arbitrary_take_rest_with(u, __arbitrary_context)?
+                },
+            ),
+            None => (
+                quote!(<#raw as arbitrary::Arbitrary>::arbitrary(u)?),
+                quote!(<#raw as arbitrary::Arbitrary>::arbitrary_take_rest(u)?),
+            ),
+        };
+
+        let segment = type_segment(ident);
+        let hooks = &container_attrs.hooks;
+        let arbitrary = with_hooks(
+            hooks,
+            quote! {
+                u.trace_scope(#segment, |u| {
+                    let raw = #arbitrary_raw;
+                    #convert
+                })
+            },
+            true,
+        );
+        let arbitrary_take_rest = with_hooks(
+            hooks,
+            quote! {
+                u.trace_scope_take_rest(#segment, |u| {
+                    let raw = #arbitrary_take_rest_raw;
+                    #convert
+                })
+            },
+            false,
+        );
+        quote! {
+            #arbitrary_signature {
+                #arbitrary
+            }
+
+            #arbitrary_take_rest_signature {
+                #arbitrary_take_rest
+            }
+        }
+    }
+
+    // Values of a remote type are constructed, and traced, as the foreign
+    // type.
+    let (path, ident) = match &container_attrs.remote {
+        Some(remote) => (quote!(#remote), &remote.segments.last().unwrap().ident),
+        None => {
+            let ident = &input.ident;
+            (quote!(#ident), ident)
+        }
+    };
+    if let Some(conversion) = &container_attrs.conversion {
+        return Ok(arbitrary_converted(
+            conversion,
+            ident,
+            signatures,
+            container_attrs,
+        ));
+    }
+    if container_attrs.stable_tags && !matches!(input.data, Data::Enum(_)) {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`stable_tags` only applies to enums",
+        ));
+    }
+    let hooks = &container_attrs.hooks;
     match &input.data {
-        Data::Struct(data) => arbitrary_structlike(&data.fields, ident, lifetime, recursive_count),
+        Data::Struct(data) => arbitrary_structlike(&data.fields, &path, ident, signatures, hooks),
         Data::Union(data) => arbitrary_structlike(
             &Fields::Named(data.fields.clone()),
+            &path,
             ident,
-            lifetime,
-            recursive_count,
+            signatures,
+            hooks,
+        ),
+        Data::Enum(data) => arbitrary_enum(
+            data,
+            &path,
+            ident,
+            signatures,
+            hooks,
+            container_attrs.stable_tags,
         ),
-        Data::Enum(data) => arbitrary_enum(data, ident, lifetime, recursive_count),
     }
 }
 
+/// Returns an expression that constructs `path` from the given fields, which
+/// are generated in order. The `with`, `value`, `len`, `range`, `filter` and
+/// `fixup` attributes of a field can refer to the named fields before it: those are bound by
+/// reference under their own names, except for a field named `u`, which would
+/// shadow the `Unstructured`.
 fn construct(
+    path: &TokenStream,
     fields: &Fields,
     ctor: impl Fn(usize, &Field) -> Result<TokenStream>,
 ) -> Result<TokenStream> {
-    let output = match fields {
-        Fields::Named(names) => {
-            let names: Vec<TokenStream> = names
-                .named
-                .iter()
-                .enumerate()
-                .map(|(i, f)| {
-                    let name = f.ident.as_ref().unwrap();
-                    ctor(i, f).map(|ctor| quote! { #name: #ctor })
-                })
-                .collect::<Result<_>>()?;
-            quote! { { #(#names,)* } }
+    if fields.is_empty() {
+        return Ok(match fields {
+            Fields::Named(_) => quote!(#path {}),
+            Fields::Unnamed(_) => quote!(#path ()),
+            Fields::Unit => quote!(#path),
+        });
+    }
+
+    let mut lets = Vec::with_capacity(fields.len());
+    let mut bindings = Vec::with_capacity(fields.len());
+    let mut earlier: Vec<(&Ident, Ident)> = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let binding = format_ident!("__arbitrary_field_{}", i, span = Span::call_site());
+        let value = ctor(i, field)?;
+        let refers_to_earlier = match determine_field_constructor(field)? {
+            FieldConstructor::With(function_or_closure) => {
+                for argument in with_arguments(&function_or_closure)? {
+                    if !earlier.iter().any(|(name, _)| **name == argument) {
+                        return Err(Error::new_spanned(
+                            &argument,
+                            format!(
+                                "`{}` is not a field that is generated before this one",
+                                argument
+                            ),
+                        ));
+                    }
+                }
+                true
+            }
+            FieldConstructor::Value(_) | FieldConstructor::Len(_) | FieldConstructor::Range(_) => {
+                true
+            }
+            FieldConstructor::Arbitrary
+            | FieldConstructor::Default
+            | FieldConstructor::Context
+            | FieldConstructor::Regex(_)
+            | FieldConstructor::Charset(_) => !Hooks::from_attributes(&field.attrs)?.is_empty(),
+        };
+        lets.push(if refers_to_earlier && !earlier.is_empty() {
+            let (names, earlier_bindings): (Vec<_>, Vec<_>) = earlier.iter().cloned().unzip();
+            quote! {
+                let #binding = {
+                    #(
+                        #[allow(unused_variables)]
+                        let #names = &#earlier_bindings;
+                    )*
+                    #value
+                };
+            }
+        } else {
+            quote!(let #binding = #value;)
+        });
+        if let Some(name) = &field.ident {
+            if name != "u" {
+                earlier.push((name, binding.clone()));
+            }
         }
-        Fields::Unnamed(names) => {
-            let names: Vec<TokenStream> = names
-                .unnamed
-                .iter()
-                .enumerate()
-                .map(|(i, f)| ctor(i, f).map(|ctor| quote! { #ctor }))
-                .collect::<Result<_>>()?;
-            quote! { ( #(#names),* ) }
+        bindings.push(binding);
+    }
+
+    let body = match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+            quote! { { #(#names: #bindings,)* } }
         }
-        Fields::Unit => quote!(),
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => unreachable!("unit fields are empty"),
     };
-    Ok(output)
+    Ok(quote! {
+        {
+            #(#lets)*
+            #path #body
+        }
+    })
+}
+
+/// Returns a pattern that binds the fields which are generated with
+/// `Arbitrary` by reference, along with those bindings and the fields they
+/// belong to. The other fields keep their value in derived `Shrink` and
+/// `Mutate` implementations.
+fn destructure(fields: &Fields) -> Result<(TokenStream, Vec<(Ident, Member)>)> {
+    let mut bound = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+        if let FieldConstructor::Arbitrary = determine_field_constructor(field)? {
+            let binding = format_ident!("__arbitrary_field_{}", i, span = Span::call_site());
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index::from(i)),
+            };
+            bound.push((binding, member));
+        }
+    }
+
+    let (bindings, members): (Vec<_>, Vec<_>) = bound.iter().cloned().unzip();
+    let pattern = quote! { { #(#members: #bindings,)* .. } };
+    Ok((pattern, bound))
 }
 
-fn construct_take_rest(fields: &Fields) -> Result<TokenStream> {
-    construct(fields, |idx, field| {
-        determine_field_constructor(field).map(|field_constructor| match field_constructor {
+fn construct_take_rest(path: &TokenStream, fields: &Fields) -> Result<TokenStream> {
+    construct(path, fields, |idx, field| {
+        let segment = field_segment(idx, field);
+        let field_constructor = determine_field_constructor(field)?;
+        // The last field takes the rest of the data, so it can't be retried.
+        let takes_rest = idx + 1 == fields.len()
+            && matches!(
+                field_constructor,
+                FieldConstructor::Arbitrary | FieldConstructor::Context
+            );
+        let ctor = match field_constructor {
             FieldConstructor::Default => quote!(::core::default::Default::default()),
             FieldConstructor::Arbitrary => {
                 if idx + 1 == fields.len() {
-                    quote! { arbitrary::Arbitrary::arbitrary_take_rest(u)? }
+                    quote! { u.trace_scope_take_rest(#segment, arbitrary::Arbitrary::arbitrary_take_rest)? }
                 } else {
-                    quote! { arbitrary::Arbitrary::arbitrary(&mut u)? }
+                    quote! { u.trace_scope(#segment, arbitrary::Arbitrary::arbitrary)? }
                 }
             }
-            FieldConstructor::With(function_or_closure) => quote!((#function_or_closure)(&mut u)?),
+            FieldConstructor::With(function_or_closure) => {
+                with_constructor(&segment, &function_or_closure)?
+            }
             FieldConstructor::Value(value) => quote!(#value),
-        })
+            FieldConstructor::Len(range) => len_constructor(&segment, &field.ty, &range),
+            FieldConstructor::Range(range) => range_constructor(&segment, &field.ty, &range),
+            FieldConstructor::Regex(pattern) => regex_constructor(&segment, &pattern),
+            FieldConstructor::Charset(string_ty) => charset_constructor(&segment, &string_ty),
+            FieldConstructor::Context => {
+                if idx + 1 == fields.len() {
+                    quote! {
+                        u.trace_scope_take_rest(#segment, |u| {
+                            arbitrary::ArbitraryWith::arbitrary_take_rest_with(u, __arbitrary_context)
+                        })?
+                    }
+                } else {
+                    context_constructor(&segment)
+                }
+            }
+        };
+        Ok(Hooks::from_attributes(&field.attrs)?.apply(ctor, !takes_rest))
     })
 }
 
-fn gen_size_hint_method(input: &DeriveInput) -> Result<TokenStream> {
+// Whether the constructors of the fields consume any data.
+fn uses_unstructured(fields: &Fields) -> Result<bool> {
+    for field in fields {
+        match determine_field_constructor(field)? {
+            FieldConstructor::Arbitrary
+            | FieldConstructor::With(_)
+            | FieldConstructor::Len(_)
+            | FieldConstructor::Range(_)
+            | FieldConstructor::Regex(_)
+            | FieldConstructor::Charset(_)
+            | FieldConstructor::Context => return Ok(true),
+            FieldConstructor::Default | FieldConstructor::Value(_) => {}
+        }
+    }
+    Ok(false)
+}
+
+// The mutability with which `construct_take_rest` needs to bind the owned
+// `Unstructured`: only the last field may take it by value.
+fn take_rest_binding(fields: &Fields) -> Result<TokenStream> {
+    let len = fields.len();
+    for (idx, field) in fields.iter().enumerate() {
+        match determine_field_constructor(field)? {
+            FieldConstructor::Arbitrary | FieldConstructor::Context if idx + 1 == len => {}
+            FieldConstructor::Arbitrary
+            | FieldConstructor::With(_)
+            | FieldConstructor::Len(_)
+            | FieldConstructor::Range(_)
+            | FieldConstructor::Regex(_)
+            | FieldConstructor::Charset(_)
+            | FieldConstructor::Context => return Ok(quote!(mut)),
+            FieldConstructor::Default | FieldConstructor::Value(_) => {}
+        }
+    }
+    Ok(quote!())
+}
+
+fn type_segment(ident: &Ident) -> TokenStream {
+    let name = ident.unraw().to_string();
+    quote!(arbitrary::trace::Segment::Type(#name))
+}
+
+fn variant_segment(ident: &Ident) -> TokenStream {
+    let name = ident.unraw().to_string();
+    quote!(arbitrary::trace::Segment::Variant(#name))
+}
+
+fn field_segment(idx: usize, field: &Field) -> TokenStream {
+    let name = match &field.ident {
+        Some(ident) => ident.unraw().to_string(),
+        None => idx.to_string(),
+    };
+    quote!(arbitrary::trace::Segment::Field(#name))
+}
+
+fn gen_size_hint_method(
+    input: &DeriveInput,
+    signatures: &Signatures,
+    container_attrs: &ContainerAttributes,
+) -> Result<TokenStream> {
+    let context = container_attrs.context.as_ref();
+    let Signatures {
+        size_hint: size_hint_signature,
+        try_size_hint: try_size_hint_signature,
+        ..
+    } = signatures;
+    let try_size_hint_method = match context {
+        Some(_) => quote!(try_size_hint_with),
+        None => quote!(try_size_hint),
+    };
+
+    // Converted types are as big as the type they are converted from.
+    if let Some(Conversion::From(raw) | Conversion::TryFrom(raw)) = &container_attrs.conversion {
+        let raw_trait = match context {
+            Some(context) => quote!(arbitrary::ArbitraryWith<#context>),
+            None => quote!(arbitrary::Arbitrary),
+        };
+        return Ok(quote! {
+            #[inline]
+            #size_hint_signature {
+                Self::#try_size_hint_method(depth).unwrap_or_default()
+            }
+
+            #[inline]
+            #try_size_hint_signature {
+                <#raw as #raw_trait>::#try_size_hint_method(depth)
+            }
+        });
+    }
+
     let size_hint_fields = |fields: &Fields| {
         fields
             .iter()
             .map(|f| {
                 let ty = &f.ty;
-                determine_field_constructor(f).map(|field_constructor| {
-                    match field_constructor {
+                determine_field_constructor(f).and_then(|field_constructor| {
+                    Ok(match field_constructor {
                         FieldConstructor::Default | FieldConstructor::Value(_) => {
                             quote!(Ok((0, Some(0))))
                         }
@@ -376,7 +927,33 @@ fn gen_size_hint_method(input: &DeriveInput) -> Result<TokenStream> {
                         FieldConstructor::With(_) => {
                             quote! { Ok((::core::mem::size_of::<#ty>(), None)) }
                         }
-                    }
+                        // Ranges that refer to other fields are unknown until
+                        // those are generated, so fall back to the full range.
+                        FieldConstructor::Len(range) => {
+                            let range = if mentions_field(&range, fields) { quote!(..) } else { range };
+                            quote! { <#ty as arbitrary::ArbitraryLen>::try_size_hint_with_len_in(depth, #range) }
+                        }
+                        FieldConstructor::Range(range) => {
+                            let range = if mentions_field(&range, fields) { quote!(..) } else { range };
+                            quote! { <#ty as arbitrary::ArbitraryInRange>::try_size_hint_in_range(depth, #range) }
+                        }
+                        // Every decision of the pattern may be made without
+                        // any data left, with its first option.
+                        FieldConstructor::Regex(_) => quote!(Ok((0, None))),
+                        FieldConstructor::Charset(string_ty) => {
+                            quote! { <#string_ty as arbitrary::Arbitrary>::try_size_hint(depth) }
+                        }
+                        FieldConstructor::Context => {
+                            let context = context.ok_or_else(|| Error::new_spanned(
+                                f,
+                                format!(
+                                    "the `context` field attribute requires a `#[{}(context = ...)]` container attribute",
+                                    ARBITRARY_ATTRIBUTE_NAME
+                                ),
+                            ))?;
+                            quote! { <#ty as arbitrary::ArbitraryWith<#context>>::try_size_hint_with(depth) }
+                        }
+                    })
                 })
             })
             .collect::<Result<Vec<TokenStream>>>()
@@ -392,12 +969,12 @@ fn gen_size_hint_method(input: &DeriveInput) -> Result<TokenStream> {
         size_hint_fields(fields).map(|hint| {
             quote! {
                 #[inline]
-                fn size_hint(depth: usize) -> (usize, ::core::option::Option<usize>) {
-                    Self::try_size_hint(depth).unwrap_or_default()
+                #size_hint_signature {
+                    Self::#try_size_hint_method(depth).unwrap_or_default()
                 }
 
                 #[inline]
-                fn try_size_hint(depth: usize) -> Result<(usize, ::core::option::Option<usize>), arbitrary::MaxRecursionReached> {
+                #try_size_hint_signature {
                     arbitrary::size_hint::try_recursion_guard(depth, |depth| #hint)
                 }
             }
@@ -418,11 +995,11 @@ fn gen_size_hint_method(input: &DeriveInput) -> Result<TokenStream> {
             .collect::<Result<Vec<TokenStream>>>()
             .map(|variants| {
                 quote! {
-                    fn size_hint(depth: usize) -> (usize, ::core::option::Option<usize>) {
-                        Self::try_size_hint(depth).unwrap_or_default()
+                    #size_hint_signature {
+                        Self::#try_size_hint_method(depth).unwrap_or_default()
                     }
                     #[inline]
-                    fn try_size_hint(depth: usize) -> Result<(usize, ::core::option::Option<usize>), arbitrary::MaxRecursionReached> {
+                    #try_size_hint_signature {
                         Ok(arbitrary::size_hint::and(
                             <u32 as arbitrary::Arbitrary>::try_size_hint(depth)?,
                             arbitrary::size_hint::try_recursion_guard(depth, |depth| {
@@ -435,27 +1012,91 @@ fn gen_size_hint_method(input: &DeriveInput) -> Result<TokenStream> {
     }
 }
 
-fn gen_constructor_for_field(field: &Field) -> Result<TokenStream> {
+fn gen_constructor_for_field(idx: usize, field: &Field) -> Result<TokenStream> {
+    let segment = field_segment(idx, field);
     let ctor = match determine_field_constructor(field)? {
         FieldConstructor::Default => quote!(::core::default::Default::default()),
-        FieldConstructor::Arbitrary => quote!(arbitrary::Arbitrary::arbitrary(u)?),
-        FieldConstructor::With(function_or_closure) => quote!((#function_or_closure)(u)?),
+        FieldConstructor::Arbitrary => {
+            quote!(u.trace_scope(#segment, arbitrary::Arbitrary::arbitrary)?)
+        }
+        FieldConstructor::With(function_or_closure) => {
+            with_constructor(&segment, &function_or_closure)?
+        }
         FieldConstructor::Value(value) => quote!(#value),
+        FieldConstructor::Len(range) => len_constructor(&segment, &field.ty, &range),
+        FieldConstructor::Range(range) => range_constructor(&segment, &field.ty, &range),
+        FieldConstructor::Regex(pattern) => regex_constructor(&segment, &pattern),
+        FieldConstructor::Charset(string_ty) => charset_constructor(&segment, &string_ty),
+        FieldConstructor::Context => context_constructor(&segment),
     };
-    Ok(ctor)
+    Ok(Hooks::from_attributes(&field.attrs)?.apply(ctor, true))
 }
 
-fn check_variant_attrs(variant: &Variant) -> Result<()> {
-    for attr in &variant.attrs {
-        if attr.path().is_ident(ARBITRARY_ATTRIBUTE_NAME) {
-            return Err(Error::new_spanned(
-                attr,
-                format!(
-                    "invalid `{}` attribute. it is unsupported on enum variants. try applying it to a field of the variant instead",
-                    ARBITRARY_ATTRIBUTE_NAME
-                ),
-            ));
-        }
+/// Whether the given tokens contain the name of one of the fields.
+fn mentions_field(tokens: &TokenStream, fields: &Fields) -> bool {
+    tokens.clone().into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => fields.iter().any(|f| f.ident.as_ref() == Some(&ident)),
+        TokenTree::Group(group) => mentions_field(&group.stream(), fields),
+        TokenTree::Punct(_) | TokenTree::Literal(_) => false,
+    })
+}
+
+fn with_constructor(
+    segment: &TokenStream,
+    function_or_closure: &TokenStream,
+) -> Result<TokenStream> {
+    let arguments = with_arguments(function_or_closure)?;
+    Ok(quote! {
+        u.trace_scope(#segment, |u| (#function_or_closure)(u, #(#arguments),*))?
+    })
+}
+
+fn len_constructor(segment: &TokenStream, ty: &Type, range: &TokenStream) -> TokenStream {
+    quote! {
+        u.trace_scope(#segment, |u| {
+            <#ty as arbitrary::ArbitraryLen>::arbitrary_with_len_in(u, #range)
+        })?
     }
-    Ok(())
+}
+
+fn range_constructor(segment: &TokenStream, ty: &Type, range: &TokenStream) -> TokenStream {
+    quote! {
+        u.trace_scope(#segment, |u| {
+            <#ty as arbitrary::ArbitraryInRange>::arbitrary_in_range(u, #range)
+        })?
+    }
+}
+
+// The pattern is checked by `FieldConstructor::Regex`, and compiled once, the
+// first time that the field is generated; on targets without atomic pointers,
+// `LazyRegex` compiles it every time.
+fn regex_constructor(segment: &TokenStream, pattern: &LitStr) -> TokenStream {
+    quote! {
+        u.trace_scope(#segment, |u| {
+            static PATTERN: arbitrary::regex::LazyRegex = arbitrary::regex::LazyRegex::new(#pattern);
+            PATTERN.generate(u).map(::core::convert::From::from)
+        })?
+    }
+}
+
+fn charset_constructor(segment: &TokenStream, string_ty: &TokenStream) -> TokenStream {
+    quote! {
+        u.trace_scope(#segment, |u| {
+            <#string_ty as arbitrary::Arbitrary>::arbitrary(u)
+                .map(|s| ::core::convert::From::from(s.into_string()))
+        })?
+    }
+}
+
+fn context_constructor(segment: &TokenStream) -> TokenStream {
+    quote! {
+        u.trace_scope(#segment, |u| {
+            arbitrary::ArbitraryWith::arbitrary_with(u, __arbitrary_context)
+        })?
+    }
+}
+
+fn check_variant_attrs(variant: &Variant) -> Result<()> {
+    variant_weight(variant)?;
+    variant_tag(variant).map(drop)
 }
@@ -0,0 +1,161 @@
+use crate::variant_attributes::not_skipped;
+use crate::{
+    add_trait_bounds, build_arbitrary_lifetime, check_variant_attrs, construct, destructure,
+    gen_constructor_for_field, uses_unstructured, variant_segment,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::*;
+
+pub fn expand_derive_mutate(input: syn::DeriveInput) -> Result<TokenStream> {
+    let (lifetime_without_bounds, lifetime_with_bounds) =
+        build_arbitrary_lifetime(input.generics.clone());
+
+    let body = gen_mutate_body(&input)?;
+    let name = input.ident;
+
+    // As with `Shrink`, the `bound` attribute does not apply here.
+    let mut generics = add_trait_bounds(
+        input.generics,
+        parse_quote!(arbitrary::Mutate<#lifetime_without_bounds>),
+    );
+    // Borrowed fields can only be replaced with data that lives exactly as
+    // long, so the lifetimes of the type have to match `'arbitrary`.
+    let lifetimes: Vec<_> = generics.lifetimes().map(|l| l.lifetime.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for lifetime in lifetimes {
+        where_clause
+            .predicates
+            .push(parse_quote!(#lifetime: #lifetime_without_bounds));
+    }
+    let mut generics_with_lifetime = generics.clone();
+    generics_with_lifetime
+        .params
+        .push(GenericParam::Lifetime(lifetime_with_bounds));
+    let (impl_generics, _, _) = generics_with_lifetime.split_for_impl();
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics arbitrary::Mutate<#lifetime_without_bounds> for #name #ty_generics #where_clause {
+            fn mutate(&mut self, u: &mut arbitrary::Unstructured<#lifetime_without_bounds>) -> arbitrary::Result<()> {
+                #body
+            }
+        }
+    })
+}
+
+fn gen_mutate_body(input: &DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    match &input.data {
+        Data::Struct(data) => {
+            let (pattern, fields) = destructure(&data.fields)?;
+            let choices = choose_mutation(&fields, None);
+            Ok(quote! {
+                let #ident #pattern = self;
+                #choices
+            })
+        }
+        Data::Enum(data) => mutate_enum(data, ident),
+        Data::Union(_) => Err(Error::new_spanned(
+            ident,
+            "`Mutate` cannot be derived for unions",
+        )),
+    }
+}
+
+fn mutate_enum(DataEnum { variants, .. }: &DataEnum, enum_name: &Ident) -> Result<TokenStream> {
+    variants
+        .iter()
+        .filter(not_skipped)
+        .try_for_each(check_variant_attrs)?;
+
+    if !variants.iter().any(|variant| not_skipped(&variant)) {
+        return Err(Error::new_spanned(
+            enum_name,
+            "Enum must have at least one variant, that is not skipped",
+        ));
+    }
+
+    // Build every variant that is not skipped, like `Arbitrary` does.
+    let mut constructors = Vec::with_capacity(variants.len());
+    for variant in variants.iter().filter(not_skipped) {
+        let variant_name = &variant.ident;
+        let binding = if uses_unstructured(&variant.fields)? {
+            quote!(u)
+        } else {
+            quote!(_)
+        };
+        let ctor = construct(
+            &quote!(#enum_name::#variant_name),
+            &variant.fields,
+            gen_constructor_for_field,
+        )?;
+        let segment = variant_segment(variant_name);
+        constructors.push((
+            variant_name,
+            quote! {
+                u.trace_scope(#segment, |#binding| Ok(#ctor))?
+            },
+        ));
+    }
+
+    let mut arms = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let variant_name = &variant.ident;
+        let (pattern, fields) = destructure(&variant.fields)?;
+
+        let others: Vec<_> = constructors
+            .iter()
+            .filter(|(name, _)| *name != variant_name)
+            .enumerate()
+            .map(|(i, (_, ctor))| quote!(#i => #ctor))
+            .collect();
+        let switch = (!others.is_empty()).then(|| {
+            let count = others.len();
+            quote! {
+                {
+                    *self = match u.choose_index(#count)? {
+                        #(#others,)*
+                        _ => unreachable!(),
+                    };
+                    Ok(())
+                }
+            }
+        });
+
+        let choices = choose_mutation(&fields, switch);
+        arms.push(quote! {
+            #enum_name::#variant_name #pattern => { #choices }
+        });
+    }
+
+    Ok(quote! {
+        match self {
+            #(#arms)*
+        }
+    })
+}
+
+/// Mutate one of the given fields, or run `alternative` instead, as chosen by
+/// the raw data.
+fn choose_mutation(fields: &[(Ident, Member)], alternative: Option<TokenStream>) -> TokenStream {
+    let mutations = fields
+        .iter()
+        .map(|(binding, _)| quote!(arbitrary::Mutate::mutate(#binding, u)));
+    let choices: Vec<_> = alternative.into_iter().chain(mutations).collect();
+    if choices.is_empty() {
+        return quote! {
+            let _ = u;
+            Ok(())
+        };
+    }
+    let count = choices.len();
+    let indices = 0..count;
+    quote! {
+        match u.choose_index(#count)? {
+            #(#indices => #choices,)*
+            _ => unreachable!(),
+        }
+    }
+}
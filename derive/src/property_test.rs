@@ -0,0 +1,248 @@
+use crate::field_attributes::parse_assigned_value;
+use proc_macro2::{Delimiter, Literal, Spacing, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::{Error, Ident, Result};
+
+/// The settings of `#[arbitrary::test(...)]`, which are the
+/// `check::Config` methods of the same name.
+const SETTINGS: &[&str] = &[
+    "cases",
+    "sizes",
+    "seed",
+    "max_discards",
+    "regressions",
+    "shrink",
+];
+
+/// A test function, split into the parts that the expansion rearranges.
+struct TestFn {
+    attrs: Vec<TokenStream>,
+    vis: TokenStream,
+    ident: Ident,
+    args: Vec<(TokenStream, TokenStream)>,
+    output: TokenStream,
+    body: TokenTree,
+}
+
+pub fn expand_test(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
+    let settings = parse_settings(attr)?;
+
+    let function = match parse_test_fn(item)? {
+        Some(function) => function,
+        None => {
+            return Err(Error::new(
+                Span::call_site(),
+                "#[arbitrary::test] can only be used on functions",
+            ))
+        }
+    };
+    if function.args.is_empty() {
+        return Err(Error::new(
+            function.ident.span(),
+            "#[arbitrary::test] requires a function with arguments",
+        ));
+    }
+
+    let TestFn {
+        attrs,
+        vis,
+        ident,
+        args,
+        output,
+        body,
+    } = function;
+    let params = args.iter().map(|(pat, ty)| quote!(#pat: #ty));
+    let types = args.iter().map(|(_, ty)| ty);
+    let names: Vec<Ident> = (0..args.len()).map(|i| format_ident!("arg{}", i)).collect();
+    let seed = Literal::u64_suffixed(fnv1a(ident.to_string().as_bytes()));
+    let settings = settings.iter().map(|(name, value)| quote!(.#name(#value)));
+    let call = if output.is_empty() {
+        quote! {
+            #ident(#(#names),*);
+            ::core::result::Result::Ok::<(), ::core::convert::Infallible>(())
+        }
+    } else {
+        quote!(#ident(#(#names),*))
+    };
+
+    Ok(quote! {
+        #[::core::prelude::v1::test]
+        #(#attrs)*
+        #vis fn #ident() {
+            fn #ident(#(#params),*) #output #body
+
+            ::arbitrary::check::Config::new()
+                .fallback_seed(#seed)
+                #(#settings)*
+                .run(|(#(#names,)*): (#(#types,)*)| { #call });
+        }
+    })
+}
+
+fn parse_settings(attr: TokenStream) -> Result<Vec<(Ident, TokenStream)>> {
+    let mut settings = Vec::new();
+    for setting in split_top_level(attr, ',') {
+        let mut tokens = setting.into_iter();
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(name)) if SETTINGS.contains(&name.to_string().as_str()) => name,
+            Some(other) => {
+                let msg = format!(
+                    "Unknown #[arbitrary::test] setting `{}`, expected one of: {}",
+                    other,
+                    SETTINGS.join(", ")
+                );
+                return Err(Error::new(other.span(), msg));
+            }
+            None => continue,
+        };
+        let value = parse_assigned_value(&name.to_string(), tokens, name.span())?;
+        if value.is_empty() {
+            let msg = format!("#[arbitrary::test] setting `{}` is missing a value", name);
+            return Err(Error::new(name.span(), msg));
+        }
+        settings.push((name, value));
+    }
+    Ok(settings)
+}
+
+/// Split a function into its parts, or return `None` for anything else.
+fn parse_test_fn(item: TokenStream) -> Result<Option<TestFn>> {
+    let mut tokens = item.into_iter().peekable();
+
+    let mut attrs = Vec::new();
+    while let Some(TokenTree::Punct(p)) = tokens.peek() {
+        if p.as_char() != '#' {
+            break;
+        }
+        let pound = tokens.next();
+        let group = tokens.next();
+        attrs.push(quote!(#pound #group));
+    }
+
+    let mut vis = TokenStream::new();
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Ident(ident)) if ident == "fn" => break,
+            Some(TokenTree::Ident(ident)) if ident == "async" || ident == "const" => {
+                return Err(Error::new(
+                    ident.span(),
+                    format!("#[arbitrary::test] does not support {} functions", ident),
+                ));
+            }
+            Some(token) => vis.extend(Some(token)),
+            None => return Ok(None),
+        }
+    }
+
+    let ident = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident,
+        _ => return Ok(None),
+    };
+    let args = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream()
+        }
+        Some(TokenTree::Punct(p)) if p.as_char() == '<' => {
+            return Err(Error::new(
+                p.span(),
+                "#[arbitrary::test] does not support generic functions",
+            ));
+        }
+        _ => return Ok(None),
+    };
+
+    let mut rest: Vec<TokenTree> = tokens.collect();
+    let body = match rest.pop() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+            TokenTree::Group(group)
+        }
+        _ => return Ok(None),
+    };
+    let output: TokenStream = rest.into_iter().collect();
+
+    let mut parsed_args = Vec::new();
+    for arg in split_top_level(args, ',') {
+        let mut parts = split_top_level(arg.clone(), ':').into_iter();
+        let (pat, ty) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(pat), Some(ty), None) if !pat.is_empty() && !ty.is_empty() => (pat, ty),
+            _ => {
+                return Err(Error::new_spanned(
+                    arg,
+                    "#[arbitrary::test] arguments must look like `name: Type`",
+                ))
+            }
+        };
+        if pat.to_string() == "self" {
+            return Err(Error::new_spanned(
+                pat,
+                "#[arbitrary::test] does not support methods",
+            ));
+        }
+        parsed_args.push((pat, ty));
+    }
+
+    Ok(Some(TestFn {
+        attrs,
+        vis,
+        ident,
+        args: parsed_args,
+        output,
+        body,
+    }))
+}
+
+/// Split the tokens at the given punctuation, unless it is nested in angle
+/// brackets or is part of `::`. Empty trailing parts are dropped.
+fn split_top_level(tokens: TokenStream, separator: char) -> Vec<TokenStream> {
+    let mut parts = Vec::new();
+    let mut current = TokenStream::new();
+    let mut depth = 0_usize;
+    let mut previous_joint: Option<char> = None;
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        if let TokenTree::Punct(p) = &token {
+            let c = p.as_char();
+            let joint_with_next = p.spacing() == Spacing::Joint;
+            let after_joint = previous_joint.take();
+            match c {
+                '<' => depth += 1,
+                // `->` and `=>` are not closing brackets.
+                '>' if after_joint != Some('-') && after_joint != Some('=') => {
+                    depth = depth.saturating_sub(1)
+                }
+                ':' if separator == ':' => {
+                    let next_is_colon =
+                        matches!(tokens.peek(), Some(TokenTree::Punct(n)) if n.as_char() == ':');
+                    if (joint_with_next && next_is_colon) || after_joint == Some(':') {
+                        // Part of a `::` path separator.
+                    } else if depth == 0 {
+                        parts.push(core::mem::take(&mut current));
+                        continue;
+                    }
+                }
+                _ if c == separator && depth == 0 => {
+                    parts.push(core::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            if joint_with_next {
+                previous_joint = Some(c);
+            }
+        } else {
+            previous_joint = None;
+        }
+        current.extend(Some(token));
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// The 64-bit FNV-1a hash, for a deterministic seed per test.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
@@ -0,0 +1,433 @@
+//! Checking `#[arbitrary(regex = "...")]` patterns while expanding the
+//! derive, so that an invalid one is a compile error rather than a panic the
+//! first time that the field is generated.
+//!
+//! This mirrors the parser in `arbitrary::regex`, which this crate can't
+//! depend on, without building anything from the pattern. Both are tested
+//! against the patterns in `tests/regex/patterns.rs`.
+
+/// How many more times than their minimum `*`, `+` and `{n,}` repeat at most.
+const UNBOUNDED_REPEAT_LIMIT: u32 = 16;
+
+/// How deeply groups may be nested.
+const MAX_NESTING: usize = 64;
+
+/// A pattern that `arbitrary::regex::Regex` rejects.
+pub struct RegexError {
+    pub offset: usize,
+    pub message: &'static str,
+}
+
+/// Check that `pattern` compiles with `arbitrary::regex::Regex::new`.
+pub fn validate(pattern: &str) -> Result<(), RegexError> {
+    Parser::new(pattern).parse()
+}
+
+/// A set of characters, as sorted, disjoint ranges without surrogates.
+struct Class {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl Class {
+    fn new(mut ranges: Vec<(u32, u32)>) -> Class {
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let mut ranges = Vec::with_capacity(merged.len() + 1);
+        for (start, end) in merged {
+            if start < 0xD800 {
+                ranges.push((start, end.min(0xD7FF)));
+            }
+            if end > 0xDFFF {
+                ranges.push((start.max(0xE000), end));
+            }
+        }
+        Class { ranges }
+    }
+
+    fn ascii(spec: &[(u8, u8)]) -> Class {
+        Class::new(
+            spec.iter()
+                .map(|&(start, end)| (u32::from(start), u32::from(end)))
+                .collect(),
+        )
+    }
+
+    fn negate(&self) -> Class {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 1);
+        let mut next = 0;
+        for &(start, end) in &self.ranges {
+            if start > next {
+                ranges.push((next, start - 1));
+            }
+            next = end + 1;
+        }
+        if next <= char::MAX as u32 {
+            ranges.push((next, char::MAX as u32));
+        }
+        Class::new(ranges)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+const DIGIT: &[(u8, u8)] = &[(b'0', b'9')];
+const WORD: &[(u8, u8)] = &[(b'0', b'9'), (b'A', b'Z'), (b'_', b'_'), (b'a', b'z')];
+const SPACE: &[(u8, u8)] = &[(b'\t', b'\r'), (b' ', b' ')];
+
+/// The ASCII classes, like `[:alpha:]`.
+const ASCII_CLASSES: &[(&str, &[(u8, u8)])] = &[
+    ("alnum", &[(b'0', b'9'), (b'A', b'Z'), (b'a', b'z')]),
+    ("alpha", &[(b'A', b'Z'), (b'a', b'z')]),
+    ("ascii", &[(0, 0x7F)]),
+    ("blank", &[(b'\t', b'\t'), (b' ', b' ')]),
+    ("cntrl", &[(0, 0x1F), (0x7F, 0x7F)]),
+    ("digit", DIGIT),
+    ("graph", &[(b'!', b'~')]),
+    ("lower", &[(b'a', b'z')]),
+    ("print", &[(b' ', b'~')]),
+    (
+        "punct",
+        &[(b'!', b'/'), (b':', b'@'), (b'[', b'`'), (b'{', b'~')],
+    ),
+    ("space", SPACE),
+    ("upper", &[(b'A', b'Z')]),
+    ("word", WORD),
+    ("xdigit", &[(b'0', b'9'), (b'A', b'F'), (b'a', b'f')]),
+];
+
+/// What an escape sequence stands for.
+enum Escape {
+    Char(char),
+    Class(Class),
+    Empty,
+}
+
+struct Parser<'p> {
+    pattern: &'p str,
+    offset: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn new(pattern: &'p str) -> Self {
+        Parser { pattern, offset: 0 }
+    }
+
+    fn parse(mut self) -> Result<(), RegexError> {
+        self.alternation(0)?;
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.error(self.offset, "unopened group")),
+        }
+    }
+
+    fn error(&self, offset: usize, message: &'static str) -> RegexError {
+        RegexError { offset, message }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.pattern[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.offset += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alternation(&mut self, depth: usize) -> Result<(), RegexError> {
+        loop {
+            self.concat(depth)?;
+            if !self.eat('|') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn concat(&mut self, depth: usize) -> Result<(), RegexError> {
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.atom(depth)?;
+            self.repetition()?;
+        }
+        Ok(())
+    }
+
+    fn atom(&mut self, depth: usize) -> Result<(), RegexError> {
+        let start = self.offset;
+        let c = self.next().expect("the caller peeked");
+        match c {
+            '(' => self.group(start, depth),
+            '[' => self.class(start).map(drop),
+            '*' | '+' | '?' => Err(self.error(start, "repetition operator missing expression")),
+            '\\' => self.escape(start, false).map(drop),
+            _ => Ok(()),
+        }
+    }
+
+    fn group(&mut self, start: usize, depth: usize) -> Result<(), RegexError> {
+        if depth >= MAX_NESTING {
+            return Err(self.error(start, "groups are nested too deeply"));
+        }
+        if self.eat('?') {
+            let named = if self.eat('P') {
+                if !self.eat('<') {
+                    return Err(self.error(start, "unsupported group flags"));
+                }
+                true
+            } else {
+                self.eat('<')
+            };
+            if named {
+                let name_start = self.offset;
+                let name = self.pattern[name_start..].split('>').next().unwrap();
+                let valid = !name.is_empty()
+                    && self.pattern[name_start + name.len()..].starts_with('>')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    return Err(self.error(name_start, "invalid capture group name"));
+                }
+                self.offset += name.len() + 1;
+            } else if !self.eat(':') {
+                return Err(self.error(start, "unsupported group flags"));
+            }
+        }
+        self.alternation(depth + 1)?;
+        if !self.eat(')') {
+            return Err(self.error(start, "unclosed group"));
+        }
+        Ok(())
+    }
+
+    fn repetition(&mut self) -> Result<(), RegexError> {
+        if self.eat('*') || self.eat('+') || self.eat('?') || self.counted_repetition()? {
+            self.eat('?');
+        }
+        Ok(())
+    }
+
+    /// Check `{n}`, `{n,}` or `{n,m}`, or return `false` and consume nothing
+    /// if there is none of those. A `{` that does not start one is a literal
+    /// character.
+    fn counted_repetition(&mut self) -> Result<bool, RegexError> {
+        let start = self.offset;
+        let rest = match self.pattern[start..].strip_prefix('{') {
+            Some(rest) => rest,
+            None => return Ok(false),
+        };
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => return Ok(false),
+        };
+        let body = &rest[..end];
+        if body.is_empty() || !body.chars().all(|c| c.is_ascii_digit() || c == ',') {
+            return Ok(false);
+        }
+        let number = |s: &str| -> Result<u32, RegexError> {
+            s.parse()
+                .map_err(|_| self.error(start, "invalid repetition count"))
+        };
+        let (min, max) = match body.split_once(',') {
+            None => {
+                let n = number(body)?;
+                (n, n)
+            }
+            Some((min, "")) => {
+                let min = number(min)?;
+                (min, min.saturating_add(UNBOUNDED_REPEAT_LIMIT))
+            }
+            Some((min, max)) => (number(min)?, number(max)?),
+        };
+        if min > max {
+            return Err(self.error(start, "invalid repetition range"));
+        }
+        self.offset = start + 1 + end + 1;
+        Ok(true)
+    }
+
+    /// Check the escape sequence after a `\` at `start`.
+    fn escape(&mut self, start: usize, in_class: bool) -> Result<Escape, RegexError> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err(self.error(start, "incomplete escape sequence")),
+        };
+        Ok(match c {
+            'd' => Escape::Class(Class::ascii(DIGIT)),
+            'D' => Escape::Class(Class::ascii(DIGIT).negate()),
+            'w' => Escape::Class(Class::ascii(WORD)),
+            'W' => Escape::Class(Class::ascii(WORD).negate()),
+            's' => Escape::Class(Class::ascii(SPACE)),
+            'S' => Escape::Class(Class::ascii(SPACE).negate()),
+            'n' => Escape::Char('\n'),
+            't' => Escape::Char('\t'),
+            'r' => Escape::Char('\r'),
+            'f' => Escape::Char('\x0C'),
+            'v' => Escape::Char('\x0B'),
+            'a' => Escape::Char('\x07'),
+            'e' => Escape::Char('\x1B'),
+            '0' => Escape::Char('\0'),
+            'x' => Escape::Char(self.hex(start, 2)?),
+            'u' => Escape::Char(self.hex(start, 4)?),
+            'A' | 'z' if !in_class => Escape::Empty,
+            'b' | 'B' => return Err(self.error(start, "word boundaries are not supported")),
+            'p' | 'P' => return Err(self.error(start, "Unicode classes are not supported")),
+            c if c.is_ascii_punctuation() || c == ' ' => Escape::Char(c),
+            _ => return Err(self.error(start, "unknown escape sequence")),
+        })
+    }
+
+    /// Check the hex digits of `\x` or `\u`: either exactly `digits` of
+    /// them, or any number in braces.
+    fn hex(&mut self, start: usize, digits: usize) -> Result<char, RegexError> {
+        let rest = &self.pattern[self.offset..];
+        let (hex, len) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => return Err(self.error(start, "unclosed hex escape")),
+            }
+        } else {
+            match rest.get(..digits) {
+                Some(hex) => (hex, digits),
+                None => return Err(self.error(start, "invalid hex escape")),
+            }
+        };
+        let valid = !hex.is_empty() && hex.len() <= 8 && hex.chars().all(|c| c.is_ascii_hexdigit());
+        match u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| valid)
+            .and_then(char::from_u32)
+        {
+            Some(c) => {
+                self.offset += len;
+                Ok(c)
+            }
+            None => Err(self.error(start, "invalid hex escape")),
+        }
+    }
+
+    /// Check a character class after the `[` at `start`.
+    fn class(&mut self, start: usize) -> Result<Class, RegexError> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let item_start = self.offset;
+            let c = match self.next() {
+                Some(c) => c,
+                None => return Err(self.error(start, "unclosed character class")),
+            };
+            let low = match c {
+                ']' if !first => break,
+                '[' if self.peek() == Some(':') => {
+                    ranges.extend(self.ascii_class(item_start)?.ranges);
+                    first = false;
+                    continue;
+                }
+                '\\' => match self.escape(item_start, true)? {
+                    Escape::Char(c) => c,
+                    Escape::Class(class) => {
+                        ranges.extend(class.ranges);
+                        first = false;
+                        continue;
+                    }
+                    Escape::Empty => unreachable!("anchors are not escapes in classes"),
+                },
+                c => c,
+            };
+            first = false;
+
+            // A `-` before the closing `]` is a literal character.
+            let high = if self.peek() == Some('-')
+                && !self.pattern[self.offset + 1..].starts_with(']')
+            {
+                self.next();
+                let high_start = self.offset;
+                match self.next() {
+                    Some('\\') => match self.escape(high_start, true)? {
+                        Escape::Char(c) => c,
+                        _ => return Err(self.error(item_start, "invalid character class range")),
+                    },
+                    Some(c) => c,
+                    None => return Err(self.error(start, "unclosed character class")),
+                }
+            } else {
+                low
+            };
+            if low > high {
+                return Err(self.error(item_start, "invalid character class range"));
+            }
+            ranges.push((u32::from(low), u32::from(high)));
+        }
+
+        let class = Class::new(ranges);
+        let class = if negated { class.negate() } else { class };
+        if class.is_empty() {
+            return Err(self.error(start, "empty character class"));
+        }
+        Ok(class)
+    }
+
+    /// Check an ASCII class like `[:alpha:]` after its `[` at `start`.
+    fn ascii_class(&mut self, start: usize) -> Result<Class, RegexError> {
+        let rest = &self.pattern[self.offset..];
+        let end = match rest.find(":]") {
+            Some(end) => end,
+            None => return Err(self.error(start, "unclosed ASCII class")),
+        };
+        let (negated, name) = match rest[1..end].strip_prefix('^') {
+            Some(name) => (true, name),
+            None => (false, &rest[1..end]),
+        };
+        let spec = match ASCII_CLASSES.iter().find(|(n, _)| *n == name) {
+            Some((_, spec)) => spec,
+            None => return Err(self.error(start, "unknown ASCII class")),
+        };
+        self.offset += end + 2;
+        let class = Class::ascii(spec);
+        Ok(if negated { class.negate() } else { class })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod patterns {
+        include!("../../tests/regex/patterns.rs");
+    }
+
+    #[test]
+    fn patterns_shared_with_the_runtime() {
+        for pattern in patterns::VALID {
+            assert!(validate(pattern).is_ok(), "pattern: {}", pattern);
+        }
+        for &(pattern, offset, message) in patterns::INVALID {
+            let error = validate(pattern).err().unwrap();
+            assert_eq!(
+                (error.offset, error.message),
+                (offset, message),
+                "pattern: {}",
+                pattern
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::variant_attributes::not_skipped;
+use crate::{
+    add_trait_bounds, check_variant_attrs, construct, destructure, gen_constructor_for_field,
+    uses_unstructured,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::*;
+
+pub fn expand_derive_shrink(input: syn::DeriveInput) -> Result<TokenStream> {
+    let (body, needs_arbitrary) = gen_shrink_body(&input)?;
+    let name = input.ident;
+
+    // As with `Dearbitrary`, the `bound` attribute does not apply here.
+    let mut generics = add_trait_bounds(input.generics, parse_quote!(arbitrary::Shrink));
+    if needs_arbitrary {
+        // Earlier enum variants are built from empty raw data.
+        generics = add_trait_bounds(
+            generics,
+            parse_quote!(for<'__shrink> arbitrary::Arbitrary<'__shrink>),
+        );
+    }
+    generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(Self: ::core::clone::Clone));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics arbitrary::Shrink for #name #ty_generics #where_clause {
+            // Enums with a single variant replace fields with an irrefutable
+            // `if let`.
+            #[allow(irrefutable_let_patterns)]
+            fn shrink(&self) -> arbitrary::__Box<dyn ::core::iter::Iterator<Item = Self> + '_> {
+                #body
+            }
+        }
+    })
+}
+
+/// Returns the body of the `shrink` method, and whether it builds values with
+/// `Arbitrary`.
+fn gen_shrink_body(input: &DeriveInput) -> Result<(TokenStream, bool)> {
+    let ident = &input.ident;
+    match &input.data {
+        Data::Struct(data) => {
+            let (pattern, fields) = destructure(&data.fields)?;
+            let shrink_fields = fields.iter().map(|(binding, member)| {
+                quote! {
+                    let __candidates = __candidates.chain(
+                        arbitrary::Shrink::shrink(#binding).map(move |__value| {
+                            let mut __candidate = ::core::clone::Clone::clone(self);
+                            __candidate.#member = __value;
+                            __candidate
+                        }),
+                    );
+                }
+            });
+            Ok((
+                quote! {
+                    let #ident #pattern = self;
+                    let __candidates = ::core::iter::empty();
+                    #(#shrink_fields)*
+                    arbitrary::__Box::new(__candidates)
+                },
+                false,
+            ))
+        }
+        Data::Enum(data) => shrink_enum(data, ident),
+        Data::Union(_) => Err(Error::new_spanned(
+            ident,
+            "`Shrink` cannot be derived for unions",
+        )),
+    }
+}
+
+fn shrink_enum(
+    DataEnum { variants, .. }: &DataEnum,
+    enum_name: &Ident,
+) -> Result<(TokenStream, bool)> {
+    variants
+        .iter()
+        .filter(not_skipped)
+        .try_for_each(check_variant_attrs)?;
+
+    if !variants.iter().any(|variant| not_skipped(&variant)) {
+        return Err(Error::new_spanned(
+            enum_name,
+            "Enum must have at least one variant, that is not skipped",
+        ));
+    }
+
+    let mut needs_arbitrary = false;
+    let mut earlier_variants_use_arbitrary = false;
+    let mut earlier_variants = Vec::new();
+    let mut arms = Vec::with_capacity(variants.len());
+    for variant in variants {
+        let variant_name = &variant.ident;
+        let (pattern, fields) = destructure(&variant.fields)?;
+
+        let shrink_fields = fields.iter().map(|(binding, member)| {
+            quote! {
+                let __candidates = __candidates.chain(
+                    arbitrary::Shrink::shrink(#binding).map(move |__value| {
+                        let mut __candidate = ::core::clone::Clone::clone(self);
+                        if let #enum_name::#variant_name { #member: __field, .. } = &mut __candidate {
+                            *__field = __value;
+                        }
+                        __candidate
+                    }),
+                );
+            }
+        });
+        needs_arbitrary |= earlier_variants_use_arbitrary;
+        arms.push(quote! {
+            #enum_name::#variant_name #pattern => {
+                let __candidates = ::core::iter::empty();
+                #(#earlier_variants)*
+                #(#shrink_fields)*
+                arbitrary::__Box::new(__candidates)
+            }
+        });
+
+        // Skipped variants are never produced.
+        if !not_skipped(&variant) {
+            continue;
+        }
+        let binding = if uses_unstructured(&variant.fields)? {
+            earlier_variants_use_arbitrary = true;
+            quote!(u)
+        } else {
+            quote!(_)
+        };
+        let ctor = construct(
+            &quote!(#enum_name::#variant_name),
+            &variant.fields,
+            gen_constructor_for_field,
+        )?;
+        earlier_variants.push(quote! {
+            let __candidates = __candidates.chain(
+                ::core::iter::once_with(|| {
+                    let #binding = &mut arbitrary::Unstructured::new(&[]).with_max_depth(0);
+                    arbitrary::Result::<Self>::Ok(#ctor)
+                })
+                .filter_map(::core::result::Result::ok),
+            );
+        });
+    }
+
+    Ok((
+        quote! {
+            match self {
+                #(#arms)*
+            }
+        },
+        needs_arbitrary,
+    ))
+}
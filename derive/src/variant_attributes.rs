@@ -1,10 +1,12 @@
 use crate::ARBITRARY_ATTRIBUTE_NAME;
-use syn::*;
+use syn::{ext::IdentExt, *};
 
 pub fn not_skipped(variant: &&Variant) -> bool {
     !should_skip(variant)
 }
 
+/// Variants marked with `#[arbitrary(skip)]` or `#[arbitrary(weight = 0)]` are
+/// never generated.
 fn should_skip(Variant { attrs, .. }: &Variant) -> bool {
     attrs
         .iter()
@@ -16,6 +18,143 @@ fn should_skip(Variant { attrs, .. }: &Variant) -> bool {
         })
         .any(|meta| match meta {
             Meta::Path(path) => path.is_ident("skip"),
+            Meta::NameValue(MetaNameValue { path, value, .. }) if path.is_ident("weight") => {
+                parse_u32("weight", &value).map_or(false, |weight| weight == 0)
+            }
             _ => false,
         })
 }
+
+/// Returns the weight given with `#[arbitrary(weight = N)]`, if any.
+///
+/// Any `arbitrary` attribute on a variant other than `weight`, `tag` and
+/// `skip` is an error.
+pub fn variant_weight(variant: &Variant) -> Result<Option<u32>> {
+    variant_option(variant, "weight")
+}
+
+/// Returns the tag given with `#[arbitrary(tag = N)]`, if any.
+pub fn variant_tag(variant: &Variant) -> Result<Option<u32>> {
+    variant_option(variant, "tag")
+}
+
+fn variant_option(variant: &Variant, name: &str) -> Result<Option<u32>> {
+    let mut option = None;
+    for attr in &variant.attrs {
+        if !attr.path().is_ident(ARBITRARY_ATTRIBUTE_NAME) {
+            continue;
+        }
+        match attr.parse_args::<Meta>() {
+            Ok(Meta::NameValue(MetaNameValue { path, value, .. })) if path.is_ident(name) => {
+                if option.is_some() {
+                    return Err(Error::new_spanned(
+                        attr,
+                        format!(
+                            "multiple `{}`s in `{}` attributes",
+                            name, ARBITRARY_ATTRIBUTE_NAME
+                        ),
+                    ));
+                }
+                option = Some(parse_u32(name, &value)?);
+            }
+            Ok(Meta::NameValue(MetaNameValue { path, .. }))
+                if path.is_ident("weight") || path.is_ident("tag") => {}
+            _ => {
+                return Err(Error::new_spanned(
+                    attr,
+                    format!(
+                        "invalid `{}` attribute. only `weight = N`, `tag = N` and `skip` are supported on enum variants. try applying it to a field of the variant instead",
+                        ARBITRARY_ATTRIBUTE_NAME
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(option)
+}
+
+fn parse_u32(name: &str, value: &Expr) -> Result<u32> {
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        _ => Err(Error::new_spanned(
+            value,
+            format!("`{}` must be an integer literal that fits in a `u32`", name),
+        )),
+    }
+}
+
+/// Returns the weights of the variants that are not skipped, in order, or
+/// `None` if none of them has a `weight` attribute.
+pub fn variant_weights<'a>(
+    variants: impl IntoIterator<Item = &'a Variant>,
+) -> Result<Option<Vec<u32>>> {
+    let weights = variants
+        .into_iter()
+        .map(variant_weight)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(weights
+        .iter()
+        .any(Option::is_some)
+        .then(|| weights.iter().map(|w| w.unwrap_or(1)).collect()))
+}
+
+/// Returns the tags of the variants that are not skipped, in order, or `None`
+/// if the enum does not have the `#[arbitrary(stable_tags)]` attribute.
+///
+/// Variants without a `tag` attribute are tagged with the 32-bit FNV-1a hash
+/// of their name, so that their tag does not depend on the other variants.
+pub fn variant_tags<'a>(
+    variants: impl IntoIterator<Item = &'a Variant>,
+    stable_tags: bool,
+) -> Result<Option<Vec<u32>>> {
+    let variants: Vec<&Variant> = variants.into_iter().collect();
+    if !stable_tags {
+        for variant in &variants {
+            if variant_tag(variant)?.is_some() {
+                return Err(Error::new_spanned(
+                    &variant.ident,
+                    format!(
+                        "`tag` requires the `#[{}(stable_tags)]` attribute on the enum",
+                        ARBITRARY_ATTRIBUTE_NAME
+                    ),
+                ));
+            }
+        }
+        return Ok(None);
+    }
+
+    let mut tags: Vec<u32> = Vec::with_capacity(variants.len());
+    for variant in &variants {
+        if variant_weight(variant)?.is_some() {
+            return Err(Error::new_spanned(
+                &variant.ident,
+                "`weight` cannot be combined with `stable_tags`",
+            ));
+        }
+        let tag = match variant_tag(variant)? {
+            Some(tag) => tag,
+            None => fnv1a(variant.ident.unraw().to_string().as_bytes()),
+        };
+        if let Some(other) = tags.iter().position(|&t| t == tag) {
+            return Err(Error::new_spanned(
+                &variant.ident,
+                format!(
+                    "`{}` has the same tag as `{}`: {}. give one of them another `tag = N`",
+                    variant.ident.unraw(),
+                    variants[other].ident.unraw(),
+                    tag
+                ),
+            ));
+        }
+        tags.push(tag);
+    }
+    Ok(Some(tags))
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash: u32, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
@@ -0,0 +1,837 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Strings made of a fixed set of characters.
+//!
+//! `String`'s `Arbitrary` implementation takes the valid UTF-8 prefix of the
+//! raw data, so its strings are often cut short, and seldom plain ASCII. The
+//! strings in this module map every character from the raw data instead:
+//! each one is an index into a [`Charset`], chosen with
+//! [`Unstructured::choose_index`], so no data is ever rejected and any raw
+//! data yields a string of the right characters.
+//!
+//! [`CharsetString<C>`] works with any `Charset`, and this module has
+//! aliases for the common ones: [`AsciiString`], [`Alphanumeric`] and
+//! [`Printable`]. [`Identifier`] generates identifiers, like `snake_case` or
+//! `_tmp1`.
+//!
+//! [`LosslessString`] is made of any characters at all. Instead of stopping
+//! at the first invalid UTF-8 byte, it decodes every group of bytes that
+//! starts with a UTF-8 leading byte into one character, so changing a byte of
+//! the raw data changes a single character rather than cutting the string
+//! short. [`Unstructured::arbitrary_string_lossless`] generates such a string
+//! in a single call.
+//!
+//! `#[arbitrary(charset = "...")]` generates a field of
+//! `#[derive(Arbitrary)]` with any type that implements `From<String>`,
+//! where the charset is one of `"ascii"`, `"ascii_graphic"`,
+//! `"alphanumeric"`, `"printable"` or `"identifier"`, or the path of a type
+//! that implements `Charset`.
+//!
+//! Requires the `"alloc"` cargo feature.
+//!
+//! # Example
+//!
+//! ```
+//! use arbitrary::{
+//!     charset::{Alphanumeric, Charset, CharsetString, Identifier},
+//!     Arbitrary, Unstructured,
+//! };
+//! use std::ops::RangeInclusive;
+//!
+//! struct Hex;
+//!
+//! impl Charset for Hex {
+//!     const RANGES: &'static [RangeInclusive<char>] = &['0'..='9', 'a'..='f'];
+//! }
+//!
+//! let mut u = Unstructured::new(&[0xc7, 0x12, 0xff, 0x80, 0x3e, 0x05, 4, 2]);
+//! let hex = CharsetString::<Hex>::arbitrary(&mut u).unwrap();
+//! assert!(hex.chars().all(|c| c.is_ascii_hexdigit()));
+//!
+//! let word = Alphanumeric::arbitrary(&mut u).unwrap();
+//! assert!(word.chars().all(|c| c.is_ascii_alphanumeric()));
+//!
+//! // Identifiers always have a first character, even without data.
+//! let name = Identifier::arbitrary(&mut Unstructured::new(&[])).unwrap();
+//! assert_eq!(name.as_str(), "a");
+//! ```
+
+use {
+    crate::{
+        shrink::ShrinkVec, Arbitrary, Dearbitrary, Dearbitrator, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    alloc::{boxed::Box, string::String, vec, vec::Vec},
+    core::{cmp, fmt, hash, marker::PhantomData, ops},
+};
+
+/// A set of characters, for [`CharsetString`].
+///
+/// The characters are numbered in the order of the ranges, so the first
+/// character of the first range is the one that strings shrink towards, and
+/// the one that is chosen once the raw data runs out. Surrogate code points
+/// within a range are skipped, and the ranges should not overlap, lest their
+/// common characters are chosen more often.
+///
+/// Requires the `"alloc"` cargo feature.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::charset::Charset;
+/// use std::ops::RangeInclusive;
+///
+/// /// Lowercase letters and digits, mostly for DNS labels.
+/// struct Label;
+///
+/// impl Charset for Label {
+///     const RANGES: &'static [RangeInclusive<char>] = &['a'..='z', '0'..='9', '-'..='-'];
+/// }
+/// ```
+pub trait Charset {
+    /// The ranges of characters in the set.
+    const RANGES: &'static [ops::RangeInclusive<char>];
+}
+
+/// All of ASCII, `'\0'` through `'\x7f'`.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ascii;
+
+impl Charset for Ascii {
+    const RANGES: &'static [ops::RangeInclusive<char>] = &['\0'..='\x7f'];
+}
+
+/// The visible ASCII characters, `'!'` through `'~'`.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsciiGraphic;
+
+impl Charset for AsciiGraphic {
+    const RANGES: &'static [ops::RangeInclusive<char>] = &['!'..='~'];
+}
+
+/// The visible ASCII characters and the space, `' '` through `'~'`.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsciiPrintable;
+
+impl Charset for AsciiPrintable {
+    const RANGES: &'static [ops::RangeInclusive<char>] = &[' '..='~'];
+}
+
+/// The ASCII letters and digits.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsciiAlphanumeric;
+
+impl Charset for AsciiAlphanumeric {
+    const RANGES: &'static [ops::RangeInclusive<char>] = &['a'..='z', 'A'..='Z', '0'..='9'];
+}
+
+/// The characters that may start an [`Identifier`]: the ASCII letters and
+/// `'_'`.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IdentifierStart;
+
+impl Charset for IdentifierStart {
+    const RANGES: &'static [ops::RangeInclusive<char>] = &['a'..='z', 'A'..='Z', '_'..='_'];
+}
+
+/// The characters that may follow the first one of an [`Identifier`]: the
+/// ASCII letters and digits, and `'_'`.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IdentifierContinue;
+
+// `IdentifierStart` comes first, so that its characters have the same
+// indices in both sets.
+impl Charset for IdentifierContinue {
+    const RANGES: &'static [ops::RangeInclusive<char>] =
+        &['a'..='z', 'A'..='Z', '_'..='_', '0'..='9'];
+}
+
+/// An ASCII string.
+///
+/// Requires the `"alloc"` cargo feature.
+pub type AsciiString = CharsetString<Ascii>;
+
+/// A string of ASCII letters and digits.
+///
+/// Requires the `"alloc"` cargo feature.
+pub type Alphanumeric = CharsetString<AsciiAlphanumeric>;
+
+/// A string of visible ASCII characters and spaces.
+///
+/// Requires the `"alloc"` cargo feature.
+pub type Printable = CharsetString<AsciiPrintable>;
+
+/// A string of the characters in the charset `C`.
+///
+/// It dereferences to `str`, and [`CharsetString::into_string`] unwraps the
+/// `String`.
+///
+/// Requires the `"alloc"` cargo feature.
+pub struct CharsetString<C> {
+    string: String,
+    charset: PhantomData<fn() -> C>,
+}
+
+impl<C: Charset> CharsetString<C> {
+    /// Wrap the given string, or return `None` if it has characters that are
+    /// not in the charset.
+    ///
+    /// ```
+    /// use arbitrary::charset::AsciiString;
+    ///
+    /// assert!(AsciiString::new("crab").is_some());
+    /// assert!(AsciiString::new("🦀").is_none());
+    /// ```
+    pub fn new(string: impl Into<String>) -> Option<Self> {
+        let string = string.into();
+        if string.chars().all(|c| position::<C>(c).is_some()) {
+            Some(CharsetString {
+                string,
+                charset: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The string.
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// Unwrap the string.
+    pub fn into_string(self) -> String {
+        self.string
+    }
+
+    fn from_indices(indices: impl IntoIterator<Item = usize>) -> Self {
+        CharsetString {
+            string: indices.into_iter().map(nth::<C>).collect(),
+            charset: PhantomData,
+        }
+    }
+
+    fn indices(&self) -> Vec<usize> {
+        self.string
+            .chars()
+            .map(|c| position::<C>(c).unwrap())
+            .collect()
+    }
+}
+
+impl<C> Clone for CharsetString<C> {
+    fn clone(&self) -> Self {
+        CharsetString {
+            string: self.string.clone(),
+            charset: PhantomData,
+        }
+    }
+}
+
+impl<C> fmt::Debug for CharsetString<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.string, f)
+    }
+}
+
+impl<C> fmt::Display for CharsetString<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.string, f)
+    }
+}
+
+impl<C> PartialEq for CharsetString<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.string == other.string
+    }
+}
+
+impl<C> Eq for CharsetString<C> {}
+
+impl<C> PartialOrd for CharsetString<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C> Ord for CharsetString<C> {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.string.cmp(&other.string)
+    }
+}
+
+impl<C> hash::Hash for CharsetString<C> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.string.hash(state);
+    }
+}
+
+impl<C> ops::Deref for CharsetString<C> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.string
+    }
+}
+
+impl<C> AsRef<str> for CharsetString<C> {
+    fn as_ref(&self) -> &str {
+        &self.string
+    }
+}
+
+impl<C> From<CharsetString<C>> for String {
+    fn from(string: CharsetString<C>) -> String {
+        string.string
+    }
+}
+
+impl<'a, C: Charset> Arbitrary<'a> for CharsetString<C> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = arbitrary_len::<C>(u)?;
+        let indices = (0..len)
+            .map(|_| u.choose_index(size::<C>()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_indices(indices))
+    }
+
+    fn arbitrary_take_rest(mut u: Unstructured<'a>) -> Result<Self> {
+        let len = take_rest_len::<C>(&mut u)?;
+        let indices = (0..len)
+            .map(|_| u.choose_index(size::<C>()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_indices(indices))
+    }
+
+    #[inline]
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl<C: Charset> Dearbitrary for CharsetString<C> {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        let indices = self.indices();
+        push_len::<C>(d, indices.len())?;
+        for index in indices {
+            d.push_choose_index(size::<C>(), index)?;
+        }
+        Ok(())
+    }
+}
+
+// Shrink the indices, so that characters shrink towards the first one of the
+// charset, and never leave it.
+impl<C: Charset> Shrink for CharsetString<C> {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(ShrinkVec::new(self.indices()).map(Self::from_indices))
+    }
+}
+
+impl<'a, C: Charset> Mutate<'a> for CharsetString<C> {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut indices = self.indices();
+        let result = indices.mutate(u);
+        *self = Self::from_indices(indices.into_iter().map(|i| i % size::<C>()));
+        result
+    }
+}
+
+/// An identifier: an [`IdentifierStart`] character, followed by any number
+/// of [`IdentifierContinue`] characters.
+///
+/// It dereferences to `str`, and [`Identifier::into_string`] unwraps the
+/// `String`.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Identifier(String);
+
+impl Identifier {
+    /// Wrap the given string, or return `None` if it is not an identifier.
+    ///
+    /// ```
+    /// use arbitrary::charset::Identifier;
+    ///
+    /// assert!(Identifier::new("_tmp1").is_some());
+    /// assert!(Identifier::new("1st").is_none());
+    /// assert!(Identifier::new("").is_none());
+    /// ```
+    pub fn new(string: impl Into<String>) -> Option<Self> {
+        let string = string.into();
+        let mut chars = string.chars();
+        let valid = chars
+            .next()
+            .map_or(false, |c| position::<IdentifierStart>(c).is_some())
+            && chars.all(|c| position::<IdentifierContinue>(c).is_some());
+        valid.then_some(Identifier(string))
+    }
+
+    /// The identifier.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Unwrap the identifier.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    fn from_indices(indices: impl IntoIterator<Item = usize>) -> Self {
+        Identifier(indices.into_iter().map(nth::<IdentifierContinue>).collect())
+    }
+
+    fn indices(&self) -> Vec<usize> {
+        self.0
+            .chars()
+            .map(|c| position::<IdentifierContinue>(c).unwrap())
+            .collect()
+    }
+
+    /// Whether the indices of `IdentifierContinue` characters make an
+    /// identifier.
+    fn valid_indices(indices: &[usize]) -> bool {
+        indices
+            .first()
+            .map_or(false, |i| *i < size::<IdentifierStart>())
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl ops::Deref for Identifier {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Identifier {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<Identifier> for String {
+    fn from(identifier: Identifier) -> String {
+        identifier.0
+    }
+}
+
+impl<'a> Arbitrary<'a> for Identifier {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = cmp::max(arbitrary_len::<IdentifierContinue>(u)?, 1);
+        let mut indices = Vec::with_capacity(len);
+        indices.push(u.choose_index(size::<IdentifierStart>())?);
+        for _ in 1..len {
+            indices.push(u.choose_index(size::<IdentifierContinue>())?);
+        }
+        Ok(Self::from_indices(indices))
+    }
+
+    fn arbitrary_take_rest(mut u: Unstructured<'a>) -> Result<Self> {
+        let mut indices = vec![u.choose_index(size::<IdentifierStart>())?];
+        for _ in 0..take_rest_len::<IdentifierContinue>(&mut u)? {
+            indices.push(u.choose_index(size::<IdentifierContinue>())?);
+        }
+        Ok(Self::from_indices(indices))
+    }
+
+    #[inline]
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+impl Dearbitrary for Identifier {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        let indices = self.indices();
+        push_len::<IdentifierContinue>(d, indices.len())?;
+        d.push_choose_index(size::<IdentifierStart>(), indices[0])?;
+        for index in &indices[1..] {
+            d.push_choose_index(size::<IdentifierContinue>(), *index)?;
+        }
+        Ok(())
+    }
+}
+
+impl Shrink for Identifier {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(
+            ShrinkVec::new(self.indices())
+                .filter(|indices| Self::valid_indices(indices))
+                .map(Self::from_indices),
+        )
+    }
+}
+
+impl<'a> Mutate<'a> for Identifier {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut indices = self.indices();
+        let result = indices.mutate(u);
+        for index in &mut indices {
+            *index %= size::<IdentifierContinue>();
+        }
+        match indices.first_mut() {
+            Some(first) => *first %= size::<IdentifierStart>(),
+            None => indices.push(0),
+        }
+        *self = Self::from_indices(indices);
+        result
+    }
+}
+
+/// A string of any characters, decoded from the raw data without losing any
+/// of it.
+///
+/// Valid UTF-8 decodes into the same string that `String`'s `Arbitrary`
+/// implementation would generate. Everywhere else, a UTF-8 leading byte and
+/// the bytes after it, whatever they are, make up a character: the payload
+/// bits of the group make a code point, and code points that are too small
+/// for the length of the group, surrogates and code points past `char::MAX`
+/// wrap into the valid range. A stray continuation byte, or a leading byte
+/// without enough bytes after it, is the Latin-1 character of the same value.
+///
+/// Requires the `"alloc"` cargo feature.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{charset::LosslessString, Arbitrary, Unstructured};
+///
+/// // A length of five bytes, read from the end of the data.
+/// let data = [b'h', 0xc3, b'i', 0xff, b'!', 5];
+/// let s = LosslessString::arbitrary(&mut Unstructured::new(&data)).unwrap();
+/// assert_eq!(s.as_str(), "héÿ!");
+///
+/// // The prefix that `String` takes is only the first byte.
+/// let s = String::arbitrary(&mut Unstructured::new(&data)).unwrap();
+/// assert_eq!(s, "h");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LosslessString(pub String);
+
+impl LosslessString {
+    /// The string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Unwrap the string.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for LosslessString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl ops::Deref for LosslessString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for LosslessString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for LosslessString {
+    fn from(string: String) -> LosslessString {
+        LosslessString(string)
+    }
+}
+
+impl From<LosslessString> for String {
+    fn from(string: LosslessString) -> String {
+        string.0
+    }
+}
+
+impl<'a> Arbitrary<'a> for LosslessString {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        arbitrary_lossless(u).map(LosslessString)
+    }
+
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        Ok(LosslessString(decode_lossless(u.take_rest())))
+    }
+
+    #[inline]
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (0, None)
+    }
+}
+
+// Valid UTF-8 decodes into itself, so the data of the `String` is the data of
+// the `LosslessString`.
+impl Dearbitrary for LosslessString {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        self.0.dearbitrary(d)
+    }
+}
+
+impl Shrink for LosslessString {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(self.0.shrink().map(LosslessString))
+    }
+}
+
+impl<'a> Mutate<'a> for LosslessString {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.0.mutate(u)
+    }
+}
+
+/// Generate a string from as many bytes as `String`'s `Arbitrary`
+/// implementation would take, decoding all of them.
+pub(crate) fn arbitrary_lossless(u: &mut Unstructured<'_>) -> Result<String> {
+    let size = u.arbitrary_len::<u8>()?;
+    if let Some(bytes) = u.peek_bytes(size) {
+        u.bytes(size)?;
+        return Ok(decode_lossless(bytes));
+    }
+
+    // The data comes from a `ByteSource` and cannot be borrowed.
+    let mut bytes = vec![0; size];
+    u.fill_buffer(&mut bytes)?;
+    Ok(decode_lossless(&bytes))
+}
+
+/// Decode every byte into a character, see [`LosslessString`].
+fn decode_lossless(mut bytes: &[u8]) -> String {
+    let mut string = String::with_capacity(bytes.len());
+    loop {
+        let invalid = match core::str::from_utf8(bytes) {
+            Ok(valid) => {
+                string.push_str(valid);
+                return string;
+            }
+            Err(e) => {
+                let (valid, invalid) = bytes.split_at(e.valid_up_to());
+                string.push_str(unsafe {
+                    debug_assert!(core::str::from_utf8(valid).is_ok());
+                    core::str::from_utf8_unchecked(valid)
+                });
+                invalid
+            }
+        };
+        let (c, len) = decode_group(invalid);
+        string.push(c);
+        bytes = &invalid[len..];
+    }
+}
+
+/// Decode the group of bytes at the start of `bytes` into a character, and
+/// return the number of bytes in the group.
+fn decode_group(bytes: &[u8]) -> (char, usize) {
+    let lead = bytes[0];
+    let (len, payload) = match lead {
+        0xc0..=0xdf => (2, lead & 0x1f),
+        0xe0..=0xef => (3, lead & 0x0f),
+        0xf0..=0xff => (4, lead & 0x07),
+        _ => (1, lead),
+    };
+    if len == 1 || bytes.len() < len {
+        return (char::from(lead), 1);
+    }
+    let code_point = bytes[1..len]
+        .iter()
+        .fold(u32::from(payload), |c, b| c << 6 | u32::from(b & 0x3f));
+    let code_point = match (len, code_point) {
+        // Overlong encodings.
+        (2, c @ 0..=0x7f) => c + 0x80,
+        (3, c @ 0..=0x7ff) => c + 0x800,
+        (4, c @ 0..=0xffff) => c + 0x1_0000,
+        // Surrogates wrap into the private use area after them.
+        (3, c) if SURROGATES.contains(&c) => c + 0x800,
+        // Past `char::MAX`.
+        (4, c @ 0x11_0000..) => c - 0x10_0000,
+        (_, c) => c,
+    };
+    let c = char::from_u32(code_point)
+        .expect("Decoded character should be valid! This is a bug in arbitrary-rs");
+    (c, len)
+}
+
+/// Surrogates are not characters, so ranges that span them skip them.
+const SURROGATES: ops::RangeInclusive<u32> = 0xd800..=0xdfff;
+
+fn spans_surrogates(range: &ops::RangeInclusive<char>) -> bool {
+    u32::from(*range.start()) < *SURROGATES.start() && u32::from(*range.end()) > *SURROGATES.end()
+}
+
+fn range_size(range: &ops::RangeInclusive<char>) -> usize {
+    let (start, end) = (u32::from(*range.start()), u32::from(*range.end()));
+    if start > end {
+        return 0;
+    }
+    let mut size = end - start + 1;
+    if spans_surrogates(range) {
+        size -= SURROGATES.end() - SURROGATES.start() + 1;
+    }
+    size as usize
+}
+
+/// The number of characters in the charset.
+fn size<C: Charset>() -> usize {
+    C::RANGES.iter().map(range_size).sum()
+}
+
+/// The `index`th character of the charset.
+fn nth<C: Charset>(mut index: usize) -> char {
+    for range in C::RANGES {
+        let size = range_size(range);
+        if index < size {
+            let mut c = u32::from(*range.start()) + index as u32;
+            if spans_surrogates(range) && c >= *SURROGATES.start() {
+                c += SURROGATES.end() - SURROGATES.start() + 1;
+            }
+            return char::from_u32(c).unwrap();
+        }
+        index -= size;
+    }
+    panic!("character index out of bounds of the charset")
+}
+
+/// The index of the character in the charset.
+fn position<C: Charset>(c: char) -> Option<usize> {
+    let mut index = 0;
+    for range in C::RANGES {
+        if range.contains(&c) {
+            let mut offset = u32::from(c) - u32::from(*range.start());
+            if spans_surrogates(range) && u32::from(c) > *SURROGATES.end() {
+                offset -= SURROGATES.end() - SURROGATES.start() + 1;
+            }
+            return Some(index + offset as usize);
+        }
+        index += range_size(range);
+    }
+    None
+}
+
+/// Choose the number of characters of a string, with the number of bytes
+/// that `choose_index` takes per character as the element size.
+fn arbitrary_len<C: Charset>(u: &mut Unstructured<'_>) -> Result<usize> {
+    match size::<C>() {
+        0..=0x100 => u.arbitrary_len::<u8>(),
+        0x101..=0x1_0000 => u.arbitrary_len::<u16>(),
+        _ => u.arbitrary_len::<u32>(),
+    }
+}
+
+/// Choose the number of characters of a string that takes the rest of the
+/// data: as many as the remaining bytes cover, or, for a source that may
+/// never run out, a length like [`arbitrary_len`] chooses.
+fn take_rest_len<C: Charset>(u: &mut Unstructured<'_>) -> Result<usize> {
+    match u.peek_bytes(u.len()) {
+        Some(bytes) => {
+            // The number of bytes that `choose_index` takes per character,
+            // and at least one, for charsets with a single character.
+            let mut per_char = 1;
+            while size::<C>().saturating_sub(1) >> (8 * per_char) > 0 {
+                per_char += 1;
+            }
+            Ok((bytes.len() + per_char - 1) / per_char)
+        }
+        None => arbitrary_len::<C>(u),
+    }
+}
+
+fn push_len<C: Charset>(d: &mut Dearbitrator, len: usize) -> Result<()> {
+    match size::<C>() {
+        0..=0x100 => d.push_len::<u8>(len),
+        0x101..=0x1_0000 => d.push_len::<u16>(len),
+        _ => d.push_len::<u32>(len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Wide;
+
+    impl Charset for Wide {
+        const RANGES: &'static [ops::RangeInclusive<char>] = &['\u{d000}'..='\u{e001}', 'a'..='b'];
+    }
+
+    #[test]
+    fn indices_skip_surrogates() {
+        assert_eq!(size::<Wide>(), 0x1002 - 0x800 + 2);
+        assert_eq!(nth::<Wide>(0x7ff), '\u{d7ff}');
+        assert_eq!(nth::<Wide>(0x800), '\u{e000}');
+        assert_eq!(nth::<Wide>(0x803), 'b');
+        for index in 0..size::<Wide>() {
+            assert_eq!(position::<Wide>(nth::<Wide>(index)), Some(index));
+        }
+        assert_eq!(position::<Wide>('c'), None);
+    }
+
+    #[test]
+    fn decode_groups() {
+        for (bytes, decoded) in [
+            (&b"plain"[..], "plain"),
+            (&[0xc3, 0xa9], "é"),
+            (&[0xc0, 0x81], "\u{81}"),
+            (&[0xed, 0xa0, 0x80], "\u{e000}"),
+            (&[0xf7, 0xbf, 0xbf, 0xbf], "\u{fffff}"),
+            (&[0xf4, 0x90, 0x80, 0x80], "\u{10000}"),
+            (&[0x80, b'a', 0xe2, 0x82], "\u{80}a\u{e2}\u{82}"),
+            (&[0xe2, b'a', b'b', b'c'], "\u{2862}c"),
+        ] {
+            assert_eq!(decode_lossless(bytes), decoded, "bytes: {:x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn decode_every_byte_value() {
+        for lead in 0..=0xff_u8 {
+            for continuation in 0..=0xff_u8 {
+                let bytes = [lead, continuation, continuation, continuation];
+                let decoded = decode_lossless(&bytes);
+                assert!(!decoded.is_empty() && decoded.chars().count() <= 4);
+            }
+        }
+    }
+
+    #[test]
+    fn identifier_charsets_share_indices() {
+        for index in 0..size::<IdentifierStart>() {
+            assert_eq!(
+                nth::<IdentifierStart>(index),
+                nth::<IdentifierContinue>(index)
+            );
+        }
+    }
+}
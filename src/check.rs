@@ -0,0 +1,572 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Checking properties of `Arbitrary` values in ordinary tests.
+//!
+//! [`check`] feeds a property, a function that returns an error for values
+//! that it does not hold for, with many values generated from pseudo-random
+//! raw data, and panics with the seed and the raw data of the first value it
+//! fails for. The raw data is first shrunk with
+//! [`choices::minimize`][crate::choices::minimize].
+//!
+//! The raw data is generated from a seed, which is random unless it is set
+//! with [`Config::seed`], the `ARBITRARY_SEED` environment variable or
+//! [`Config::fallback_seed`], and grows from case to case, so that early
+//! cases try small values. Values that can't be generated from the raw data,
+//! because `Arbitrary` returns [`Error::NotEnoughData`],
+//! [`Error::IncorrectFormat`] or [`Error::MaxDepthReached`], are discarded,
+//! and a check gives up after too many discards.
+//!
+//! With [`Config::regressions`], the raw data of failures is saved to a file,
+//! and replayed before any new case on the next run, so that a failure
+//! reproduces until it is fixed, whatever the seed. The file has one input per
+//! line, in hex, and lines starting with `#` are comments.
+//!
+//! Requires the `"std"` cargo feature.
+//!
+//! ```
+//! use arbitrary::check::Config;
+//!
+//! arbitrary::check(|(a, b): (u32, u32)| {
+//!     if a.wrapping_add(b) == b.wrapping_add(a) {
+//!         Ok(())
+//!     } else {
+//!         Err("addition is not commutative")
+//!     }
+//! });
+//!
+//! Config::new()
+//!     .cases(1000)
+//!     .sizes(0..=64)
+//!     .seed(42)
+//!     .run(|s: String| if s.len() <= 256 { Ok(()) } else { Err(s.len()) });
+//! ```
+
+use crate::{choices, source::ByteSource, source::Prng, Arbitrary, Error, Unstructured};
+use std::{
+    any::Any,
+    collections::hash_map::RandomState,
+    env, fmt, fs,
+    hash::{BuildHasher, Hasher},
+    io::{self, Write},
+    ops::RangeInclusive,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Check that `property` holds for many arbitrary values, with the default
+/// [`Config`].
+///
+/// # Panics
+///
+/// Panics with a [`Failure`] if `property` returns an error or panics for
+/// any value, or if too many values were discarded.
+pub fn check<T, E>(property: impl Fn(T) -> Result<(), E>)
+where
+    T: for<'a> Arbitrary<'a> + fmt::Debug,
+    E: fmt::Debug,
+{
+    Config::new().run(property)
+}
+
+/// The settings of a check.
+#[derive(Clone, Debug)]
+pub struct Config {
+    cases: u32,
+    sizes: RangeInclusive<usize>,
+    seed: Option<u64>,
+    fallback_seed: Option<u64>,
+    max_discards: u32,
+    regressions: Option<PathBuf>,
+    shrink: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            cases: Self::DEFAULT_CASES,
+            sizes: 0..=Self::DEFAULT_MAX_SIZE,
+            seed: None,
+            fallback_seed: None,
+            max_discards: Self::DEFAULT_CASES * 4,
+            regressions: None,
+            shrink: true,
+        }
+    }
+}
+
+impl Config {
+    /// The number of cases of a new `Config`, see [`Config::cases`].
+    pub const DEFAULT_CASES: u32 = 256;
+
+    /// The size of the raw data of the last case of a new `Config`, see
+    /// [`Config::sizes`].
+    pub const DEFAULT_MAX_SIZE: usize = 1024;
+
+    /// The environment variable that sets the seed, unless
+    /// [`Config::seed`] does.
+    pub const SEED_VAR: &'static str = "ARBITRARY_SEED";
+
+    /// Create a new `Config` with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of values to check, not counting discarded ones.
+    ///
+    /// This also sets the maximum number of discards to four times as many.
+    pub fn cases(mut self, cases: u32) -> Self {
+        self.cases = cases;
+        self.max_discards = cases.saturating_mul(4);
+        self
+    }
+
+    /// Set the sizes of the raw data: the first case gets as many bytes as
+    /// the start of the range, and the size grows linearly up to the end of
+    /// the range for the last case.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn sizes(mut self, sizes: RangeInclusive<usize>) -> Self {
+        assert!(
+            sizes.start() <= sizes.end(),
+            "`arbitrary::check::Config::sizes` requires a non-empty range"
+        );
+        self.sizes = sizes;
+        self
+    }
+
+    /// Set the seed of the pseudo-random raw data.
+    ///
+    /// Without a seed, the seed is taken from the `ARBITRARY_SEED`
+    /// environment variable, or else the fallback seed (see
+    /// [`Config::fallback_seed`]), or chosen at random.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the seed to use when neither [`Config::seed`] nor the
+    /// `ARBITRARY_SEED` environment variable set one, instead of a random
+    /// one.
+    ///
+    /// This makes a check deterministic while still letting the environment
+    /// variable try other seeds.
+    pub fn fallback_seed(mut self, seed: u64) -> Self {
+        self.fallback_seed = Some(seed);
+        self
+    }
+
+    /// Set how many values may be discarded before the check gives up.
+    pub fn max_discards(mut self, max_discards: u32) -> Self {
+        self.max_discards = max_discards;
+        self
+    }
+
+    /// Save the raw data of failures to the given file, and replay the raw
+    /// data in it before generating any new cases.
+    ///
+    /// Missing directories are created when a failure is saved.
+    pub fn regressions(mut self, path: impl Into<PathBuf>) -> Self {
+        self.regressions = Some(path.into());
+        self
+    }
+
+    /// Set whether to shrink the raw data of failures. This is on by default.
+    pub fn shrink(mut self, shrink: bool) -> Self {
+        self.shrink = shrink;
+        self
+    }
+
+    /// Check that `property` holds for many arbitrary values.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the [`Failure`] that [`Config::try_run`] returns.
+    pub fn run<T, E>(&self, property: impl Fn(T) -> Result<(), E>)
+    where
+        T: for<'a> Arbitrary<'a> + fmt::Debug,
+        E: fmt::Debug,
+    {
+        if let Err(failure) = self.try_run(property) {
+            panic!("{}", failure);
+        }
+    }
+
+    /// Check that `property` holds for many arbitrary values, and return
+    /// the first failure instead of panicking.
+    ///
+    /// This first replays the regressions, if any, and then checks the
+    /// configured number of new cases. A panic in `property` counts as a
+    /// failure.
+    pub fn try_run<T, E>(&self, property: impl Fn(T) -> Result<(), E>) -> Result<(), Failure>
+    where
+        T: for<'a> Arbitrary<'a> + fmt::Debug,
+        E: fmt::Debug,
+    {
+        self.try_run_with(|u| T::arbitrary_take_rest(u), property)
+    }
+
+    /// Like [`Config::try_run`], but generate the values with `generate`
+    /// instead of `Arbitrary`.
+    pub(crate) fn try_run_with<T, E>(
+        &self,
+        generate: impl Fn(Unstructured<'_>) -> crate::Result<T>,
+        property: impl Fn(T) -> Result<(), E>,
+    ) -> Result<(), Failure>
+    where
+        T: fmt::Debug,
+        E: fmt::Debug,
+    {
+        let property = Property { generate, property };
+        if let Some(path) = &self.regressions {
+            for input in read_regressions(path) {
+                if let Outcome::Failed(_) | Outcome::Broken(_) = evaluate(&input, &property) {
+                    return Err(self.fail(None, input, &property));
+                }
+            }
+        }
+
+        let seed = self
+            .seed
+            .unwrap_or_else(|| seed_from_env(self.fallback_seed));
+        let mut prng = Prng::new(seed);
+        let mut passed = 0;
+        let mut discards = 0;
+        while passed < self.cases {
+            let mut input = vec![0; self.size(passed)];
+            prng.fill(&mut input);
+            match evaluate(&input, &property) {
+                Outcome::Passed => passed += 1,
+                Outcome::Discarded => {
+                    discards += 1;
+                    if discards > self.max_discards {
+                        return Err(Failure::GaveUp {
+                            seed,
+                            passed,
+                            discards,
+                        });
+                    }
+                }
+                Outcome::Failed(_) | Outcome::Broken(_) => {
+                    return Err(self.fail(Some(seed), input, &property));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The size of the raw data for the case after `passed` cases.
+    fn size(&self, passed: u32) -> usize {
+        let (min, max) = (*self.sizes.start(), *self.sizes.end());
+        if self.cases <= 1 {
+            return max;
+        }
+        let step = (max - min) as u128 * u128::from(passed) / u128::from(self.cases - 1);
+        min + step as usize
+    }
+
+    /// Shrink and save a failing input.
+    fn fail<T, E>(
+        &self,
+        seed: Option<u64>,
+        input: Vec<u8>,
+        property: &Property<
+            impl Fn(Unstructured<'_>) -> crate::Result<T>,
+            impl Fn(T) -> Result<(), E>,
+        >,
+    ) -> Failure
+    where
+        T: fmt::Debug,
+        E: fmt::Debug,
+    {
+        let input = if self.shrink {
+            choices::minimize(&input, |u| {
+                matches!(
+                    evaluate_with(u, property),
+                    Outcome::Failed(_) | Outcome::Broken(_)
+                )
+            })
+        } else {
+            input
+        };
+
+        let mut regressions = None;
+        if let Some(path) = &self.regressions {
+            // Failing to save the input should not hide the failure itself.
+            let _ = save_regression(path, &input);
+            regressions = Some(path.clone());
+        }
+
+        match evaluate(&input, property) {
+            Outcome::Broken(error) => Failure::Broken { seed, input, error },
+            Outcome::Failed(Failed { value, error }) => Failure::Failed {
+                seed,
+                input,
+                value,
+                error,
+                regressions,
+            },
+            // Only a flaky property gets here.
+            Outcome::Passed | Outcome::Discarded => Failure::Failed {
+                seed,
+                input,
+                value: String::new(),
+                error: "the property failed once, but not again".to_string(),
+                regressions,
+            },
+        }
+    }
+}
+
+/// Why a check did not pass.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Failure {
+    /// The property returned an error or panicked.
+    Failed {
+        /// The seed of the raw data, or `None` if the raw data was replayed
+        /// from the regressions.
+        seed: Option<u64>,
+        /// The raw data of the failing value.
+        input: Vec<u8>,
+        /// The `Debug` representation of the failing value.
+        value: String,
+        /// The `Debug` representation of the error, or the panic message.
+        error: String,
+        /// The file that the raw data was saved to.
+        regressions: Option<PathBuf>,
+    },
+    /// `Arbitrary` returned an error that is neither
+    /// [`Error::NotEnoughData`], [`Error::IncorrectFormat`] nor
+    /// [`Error::MaxDepthReached`], which usually means that its
+    /// implementation is broken.
+    Broken {
+        /// The seed of the raw data, or `None` if the raw data was replayed
+        /// from the regressions.
+        seed: Option<u64>,
+        /// The raw data that `Arbitrary` failed for.
+        input: Vec<u8>,
+        /// The error.
+        error: Error,
+    },
+    /// Too many values were discarded.
+    GaveUp {
+        /// The seed of the raw data.
+        seed: u64,
+        /// How many values passed.
+        passed: u32,
+        /// How many values were discarded.
+        discards: u32,
+    },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Failed {
+                seed,
+                input,
+                value,
+                error,
+                regressions,
+            } => {
+                writeln!(f, "property failed: {}", error)?;
+                writeln!(f, "value: {}", value)?;
+                write_origin(f, *seed, input)?;
+                if let Some(path) = regressions {
+                    write!(f, "\nsaved to {}", path.display())?;
+                }
+                Ok(())
+            }
+            Failure::Broken { seed, input, error } => {
+                writeln!(f, "`Arbitrary` failed: {}", error)?;
+                write_origin(f, *seed, input)
+            }
+            Failure::GaveUp {
+                seed,
+                passed,
+                discards,
+            } => write!(
+                f,
+                "gave up after {} discarded values and {} passing values \
+                 (seed {}, set {}={} to reproduce)",
+                discards,
+                passed,
+                seed,
+                Config::SEED_VAR,
+                seed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+fn write_origin(f: &mut fmt::Formatter<'_>, seed: Option<u64>, input: &[u8]) -> fmt::Result {
+    write!(f, "input: {}", to_hex(input))?;
+    match seed {
+        Some(seed) => write!(
+            f,
+            "\nseed: {} (set {}={} to reproduce)",
+            seed,
+            Config::SEED_VAR,
+            seed
+        ),
+        None => write!(f, "\nreplayed from the regressions"),
+    }
+}
+
+struct Failed {
+    value: String,
+    error: String,
+}
+
+enum Outcome {
+    Passed,
+    Discarded,
+    Failed(Failed),
+    Broken(Error),
+}
+
+/// A function that generates values, and a property of the values.
+struct Property<G, P> {
+    generate: G,
+    property: P,
+}
+
+fn evaluate<T, E>(
+    input: &[u8],
+    property: &Property<impl Fn(Unstructured<'_>) -> crate::Result<T>, impl Fn(T) -> Result<(), E>>,
+) -> Outcome
+where
+    T: fmt::Debug,
+    E: fmt::Debug,
+{
+    evaluate_with(Unstructured::new(input), property)
+}
+
+fn evaluate_with<T, E>(
+    u: Unstructured<'_>,
+    Property { generate, property }: &Property<
+        impl Fn(Unstructured<'_>) -> crate::Result<T>,
+        impl Fn(T) -> Result<(), E>,
+    >,
+) -> Outcome
+where
+    T: fmt::Debug,
+    E: fmt::Debug,
+{
+    let value = match generate(u) {
+        Ok(value) => value,
+        Err(Error::NotEnoughData | Error::IncorrectFormat | Error::MaxDepthReached) => {
+            return Outcome::Discarded
+        }
+        Err(error) => return Outcome::Broken(error),
+    };
+    let debug = format!("{:?}", value);
+    match panic::catch_unwind(AssertUnwindSafe(|| property(value))) {
+        Ok(Ok(())) => Outcome::Passed,
+        Ok(Err(error)) => Outcome::Failed(Failed {
+            value: debug,
+            error: format!("{:?}", error),
+        }),
+        Err(payload) => Outcome::Failed(Failed {
+            value: debug,
+            error: panic_message(&*payload),
+        }),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
+
+fn seed_from_env(fallback: Option<u64>) -> u64 {
+    match env::var(Config::SEED_VAR) {
+        Ok(seed) => seed
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a `u64`, not {:?}", Config::SEED_VAR, seed)),
+        Err(_) => fallback.unwrap_or_else(|| RandomState::new().build_hasher().finish()),
+    }
+}
+
+fn read_regressions(path: &Path) -> Vec<Vec<u8>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(from_hex)
+        .collect()
+}
+
+fn save_regression(path: &Path, input: &[u8]) -> io::Result<()> {
+    if read_regressions(path).iter().any(|saved| saved == input) {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", to_hex(input))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse a line of hex, which is empty for an empty input.
+fn from_hex(line: &str) -> Option<Vec<u8>> {
+    if line.len() % 2 != 0 {
+        return None;
+    }
+    (0..line.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(line.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes = [0, 1, 0x7f, 0xab, 0xff];
+        assert_eq!(to_hex(&bytes), "00017fabff");
+        assert_eq!(from_hex("00017fabff").unwrap(), bytes);
+        assert_eq!(from_hex("").unwrap(), []);
+        assert_eq!(from_hex("0"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+
+    #[test]
+    fn sizes_ramp_up() {
+        let config = Config::new().cases(5).sizes(10..=50);
+        let sizes: Vec<_> = (0..5).map(|passed| config.size(passed)).collect();
+        assert_eq!(sizes, [10, 20, 30, 40, 50]);
+        assert_eq!(Config::new().cases(1).sizes(3..=7).size(0), 7);
+    }
+}
@@ -0,0 +1,642 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recording the decisions behind a value, and shrinking raw data through
+//! them.
+//!
+//! Create an [`Unstructured`] with [`Unstructured::with_recorder`] to record
+//! every decision it makes into a [`Recorder`]: every integer in a range,
+//! index, ratio, collection length, "one more element?" flag and run of raw
+//! bytes. The resulting [`ChoiceSequence`] can be edited and turned back into
+//! raw data with [`ChoiceSequence::to_bytes`], which makes the same decisions
+//! for everything that was not edited.
+//!
+//! Editing decisions instead of bytes is what makes [`minimize`] work for any
+//! `Arbitrary` implementation: deleting the decisions of a collection element
+//! removes exactly that element, while deleting the bytes of its encoding
+//! would usually garble everything after it.
+//!
+//! Requires the `"std"` cargo feature.
+//!
+//! ```
+//! # fn foo() -> arbitrary::Result<()> {
+//! use arbitrary::{choices::Recorder, Arbitrary, Unstructured};
+//!
+//! let data = [1, 7, 1, 8, 0];
+//! let recorder = Recorder::new();
+//! let mut u = Unstructured::with_recorder(&data, &recorder);
+//! assert_eq!(Vec::<u8>::arbitrary(&mut u)?, [7, 8]);
+//!
+//! // Drop the decisions of the first element: its "one more element?" flag
+//! // and its byte.
+//! let mut sequence = recorder.sequence();
+//! sequence.choices.drain(..2);
+//! let data = sequence.to_bytes();
+//! assert_eq!(Vec::<u8>::arbitrary(&mut Unstructured::new(&data))?, [8]);
+//! # Ok(()) }
+//! ```
+
+use crate::{Dearbitrator, Unstructured};
+use std::{
+    ops::Range,
+    sync::{Mutex, PoisonError},
+    vec::Vec,
+};
+
+/// A single decision that an [`Unstructured`] made.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Choice {
+    /// [`Unstructured::int_in_range`] picked the integer `value` places after
+    /// the start of a range that spans `max + 1` integers.
+    IntInRange {
+        /// The offset of the integer from the start of the range.
+        value: u128,
+        /// The offset of the end of the range.
+        max: u128,
+    },
+    /// [`Unstructured::choose_index`] picked index `value` of `max + 1`
+    /// choices.
+    ChooseIndex {
+        /// The chosen index.
+        value: u128,
+        /// The largest index.
+        max: u128,
+    },
+    /// [`Unstructured::ratio`] rolled `value + 1` of `max + 1`.
+    Ratio {
+        /// The roll, minus one.
+        value: u128,
+        /// The denominator, minus one.
+        max: u128,
+    },
+    /// [`Unstructured::arbitrary_len`] took a byte size, which it divides by
+    /// the size of an element.
+    Len {
+        /// The byte size.
+        byte_size: usize,
+    },
+    /// A collection decided whether to generate another element.
+    Continue(bool),
+    /// [`Unstructured::bytes`], [`Unstructured::fill_buffer`] or
+    /// [`Unstructured::take_rest`] consumed raw bytes. `fill_buffer` pads
+    /// what is missing with zeros, which are not part of the choice, and
+    /// consuming no bytes at all is not a choice.
+    Bytes(Vec<u8>),
+}
+
+impl Choice {
+    /// Is this the smallest choice of its kind?
+    fn is_zero(&self) -> bool {
+        match self {
+            Choice::IntInRange { value, .. }
+            | Choice::ChooseIndex { value, .. }
+            | Choice::Ratio { value, .. } => *value == 0,
+            Choice::Len { byte_size } => *byte_size == 0,
+            Choice::Continue(keep_going) => !keep_going,
+            Choice::Bytes(bytes) => bytes.iter().all(|b| *b == 0),
+        }
+    }
+
+    /// Make this the smallest choice of its kind.
+    fn zero(&mut self) {
+        match self {
+            Choice::IntInRange { value, .. }
+            | Choice::ChooseIndex { value, .. }
+            | Choice::Ratio { value, .. } => *value = 0,
+            Choice::Len { byte_size } => *byte_size = 0,
+            Choice::Continue(keep_going) => *keep_going = false,
+            Choice::Bytes(bytes) => bytes.iter_mut().for_each(|b| *b = 0),
+        }
+    }
+}
+
+/// The choices that were made within one
+/// [`Unstructured::trace_scope`], like a field or a collection element.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Span {
+    /// The indices of the choices within [`ChoiceSequence::choices`].
+    pub range: Range<usize>,
+    /// How many scopes this span is nested in.
+    pub depth: usize,
+}
+
+/// The decisions that an [`Unstructured`] made, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChoiceSequence {
+    /// The decisions.
+    pub choices: Vec<Choice>,
+    /// The scopes that the decisions were made in, in the order in which the
+    /// scopes were entered.
+    pub spans: Vec<Span>,
+}
+
+impl ChoiceSequence {
+    /// Lay out the raw data that makes these decisions.
+    ///
+    /// Decoding the data with the same `Arbitrary` implementation makes the
+    /// same decisions, as long as they fit: a choice that is out of its
+    /// range is clamped to the largest value in it, and a `Bytes` choice is
+    /// consumed by whatever the decoder asks for next, so changing how many
+    /// bytes an earlier choice asks for shifts the bytes of later choices.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{choices::Recorder, Arbitrary, Unstructured};
+    ///
+    /// // Unused trailing bytes are not recorded.
+    /// let recorder = Recorder::new();
+    /// let mut u = Unstructured::with_recorder(&[1, 2, 3, 4, 5], &recorder);
+    /// let value = <(u16, bool)>::arbitrary(&mut u).unwrap();
+    ///
+    /// let data = recorder.sequence().to_bytes();
+    /// assert_eq!(data, [1, 2, 3]);
+    /// assert_eq!(<(u16, bool)>::arbitrary(&mut Unstructured::new(&data)).unwrap(), value);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut d = Dearbitrator::new();
+        for choice in &self.choices {
+            let result = match choice {
+                Choice::IntInRange { value, max }
+                | Choice::ChooseIndex { value, max }
+                | Choice::Ratio { value, max } => {
+                    d.push_int_in_range(0..=*max, core::cmp::min(*value, *max))
+                }
+                Choice::Len { byte_size } => {
+                    d.push_byte_size(*byte_size);
+                    Ok(())
+                }
+                Choice::Continue(keep_going) => d.push(keep_going),
+                Choice::Bytes(bytes) => {
+                    d.push_bytes(bytes);
+                    Ok(())
+                }
+            };
+            debug_assert!(result.is_ok());
+        }
+        d.finish()
+    }
+}
+
+/// A log of the decisions that an [`Unstructured`] made.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sequence: ChoiceSequence,
+    /// The indices of the spans of the scopes that are currently entered.
+    open: Vec<usize>,
+    /// While positive, decisions are made on behalf of a higher-level
+    /// decision, which records itself.
+    muted: usize,
+}
+
+impl Recorder {
+    /// Create a new, empty `Recorder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the decisions that were recorded so far.
+    pub fn sequence(&self) -> ChoiceSequence {
+        self.state().sequence.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn reset(&self) {
+        *self.state() = State::default();
+    }
+
+    pub(crate) fn record(&self, choice: Choice) {
+        let mut state = self.state();
+        if state.muted == 0 {
+            state.sequence.choices.push(choice);
+        }
+    }
+
+    /// Record the decision that `f` makes as `choice`, instead of the
+    /// lower-level decisions that it is made of.
+    pub(crate) fn muted<T>(&self, f: impl FnOnce() -> T, choice: impl FnOnce(&T) -> Choice) -> T {
+        self.state().muted += 1;
+        let result = f();
+        self.state().muted -= 1;
+        self.record(choice(&result));
+        result
+    }
+
+    pub(crate) fn push_span(&self) {
+        let mut state = self.state();
+        let start = state.sequence.choices.len();
+        let depth = state.open.len();
+        let index = state.sequence.spans.len();
+        state.sequence.spans.push(Span {
+            range: start..start,
+            depth,
+        });
+        state.open.push(index);
+    }
+
+    pub(crate) fn pop_span(&self) {
+        let mut state = self.state();
+        if let Some(index) = state.open.pop() {
+            let end = state.sequence.choices.len();
+            state.sequence.spans[index].range.end = end;
+        }
+    }
+}
+
+/// Shrink the raw data `input` for as long as `fails` returns `true` for the
+/// data, and return the smallest failing data that was found.
+///
+/// Instead of editing bytes, this records the decisions that `fails` makes
+/// with the data (see [`Recorder`]), edits those, and lays them out again
+/// with [`ChoiceSequence::to_bytes`]. It deletes the decisions of whole
+/// scopes, like fields and collection elements, then deletes runs of
+/// decisions, drops trailing raw bytes, zeros runs of decisions, and finally
+/// lowers single decisions, for as long as any of it helps. Data is smaller
+/// when it is shorter, or as long but lexicographically smaller, so this
+/// always terminates.
+///
+/// `fails` gets an [`Unstructured`] for the data, which it should use exactly
+/// like the code under test does, so that it works with any `Arbitrary`
+/// implementation. If `fails` returns `false` for `input`, `input` is
+/// returned unchanged.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{choices, Arbitrary, Dearbitrary, Unstructured};
+///
+/// // Fails for any list of at least two numbers.
+/// let fails = |mut u: Unstructured<'_>| match Vec::<u32>::arbitrary(&mut u) {
+///     Ok(list) => list.len() >= 2,
+///     Err(_) => false,
+/// };
+///
+/// let input = vec![5_u32, 200, 9999].dearbitrary_to_vec().unwrap();
+/// let minimal = choices::minimize(&input, fails);
+///
+/// let list = Vec::<u32>::arbitrary(&mut Unstructured::new(&minimal)).unwrap();
+/// assert_eq!(list, [0, 0]);
+/// ```
+pub fn minimize(input: &[u8], fails: impl FnMut(Unstructured<'_>) -> bool) -> Vec<u8> {
+    let mut minimizer = Minimizer {
+        fails,
+        recorder: Recorder::new(),
+        data: input.to_vec(),
+        sequence: ChoiceSequence::default(),
+    };
+    match minimizer.run(input) {
+        Some(sequence) => minimizer.sequence = sequence,
+        None => return minimizer.data,
+    }
+    let normal = minimizer.sequence.to_bytes();
+    if is_smaller(&normal, &minimizer.data) {
+        if let Some(sequence) = minimizer.run(&normal) {
+            minimizer.data = normal;
+            minimizer.sequence = sequence;
+        }
+    }
+
+    loop {
+        let before = minimizer.data.clone();
+        minimizer.delete_spans();
+        for chunk in [8, 4, 2, 1] {
+            minimizer.delete_chunks(chunk);
+        }
+        minimizer.shorten_bytes();
+        for chunk in [8, 4, 2, 1] {
+            minimizer.zero_chunks(chunk);
+        }
+        minimizer.lower_choices();
+        if minimizer.data == before {
+            return minimizer.data;
+        }
+    }
+}
+
+/// Is `a` shorter than `b`, or as long but lexicographically smaller?
+fn is_smaller(a: &[u8], b: &[u8]) -> bool {
+    (a.len(), a) < (b.len(), b)
+}
+
+struct Minimizer<F> {
+    fails: F,
+    recorder: Recorder,
+    /// The smallest failing data so far.
+    data: Vec<u8>,
+    /// The decisions that `fails` made with `data`.
+    sequence: ChoiceSequence,
+}
+
+impl<F> Minimizer<F>
+where
+    F: FnMut(Unstructured<'_>) -> bool,
+{
+    /// Record the decisions for `data`, if it fails.
+    fn run(&mut self, data: &[u8]) -> Option<ChoiceSequence> {
+        self.recorder.reset();
+        let u = Unstructured::with_recorder(data, &self.recorder);
+        if (self.fails)(u) {
+            Some(self.recorder.sequence())
+        } else {
+            None
+        }
+    }
+
+    /// Keep the given decisions if their data is smaller and still fails.
+    fn attempt(&mut self, choices: Vec<Choice>) -> bool {
+        let candidate = ChoiceSequence {
+            choices,
+            spans: Vec::new(),
+        };
+        let data = candidate.to_bytes();
+        if !is_smaller(&data, &self.data) {
+            return false;
+        }
+        let sequence = match self.run(&data) {
+            Some(sequence) => sequence,
+            None => return false,
+        };
+        self.data = data;
+        self.sequence = sequence;
+
+        // Edited decisions may leave bytes unused, which recording drops.
+        let normal = self.sequence.to_bytes();
+        if is_smaller(&normal, &self.data) {
+            if let Some(sequence) = self.run(&normal) {
+                self.data = normal;
+                self.sequence = sequence;
+            }
+        }
+        true
+    }
+
+    fn without(&self, range: Range<usize>) -> Vec<Choice> {
+        let mut choices = self.sequence.choices.clone();
+        choices.drain(range);
+        choices
+    }
+
+    /// Delete the decisions of whole scopes, largest first, with and without
+    /// the decision right before them, which is usually the flag that asked
+    /// for another collection element.
+    fn delete_spans(&mut self) {
+        let mut i = 0;
+        loop {
+            let mut ranges: Vec<Range<usize>> = self
+                .sequence
+                .spans
+                .iter()
+                .map(|span| span.range.clone())
+                .filter(|range| !range.is_empty())
+                .collect();
+            ranges.sort_by_key(|range| core::cmp::Reverse(range.len()));
+            ranges.dedup();
+            let range = match ranges.get(i) {
+                Some(range) => range.clone(),
+                None => return,
+            };
+            let deleted = (range.start > 0
+                && self.attempt(self.without(range.start - 1..range.end)))
+                || self.attempt(self.without(range));
+            if !deleted {
+                i += 1;
+            }
+        }
+    }
+
+    fn delete_chunks(&mut self, chunk: usize) {
+        let mut start = 0;
+        while start + chunk <= self.sequence.choices.len() {
+            if !self.attempt(self.without(start..start + chunk)) {
+                start += 1;
+            }
+        }
+    }
+
+    /// Drop trailing bytes of raw byte decisions, which matters for the last
+    /// decisions before the data runs out, and for byte strings.
+    fn shorten_bytes(&mut self) {
+        let mut index = 0;
+        while index < self.sequence.choices.len() {
+            let len = match &self.sequence.choices[index] {
+                Choice::Bytes(bytes) => bytes.len(),
+                _ => 0,
+            };
+            if len > 0 {
+                let mut choices = self.sequence.choices.clone();
+                if let Choice::Bytes(bytes) = &mut choices[index] {
+                    bytes.pop();
+                }
+                if self.attempt(choices) {
+                    continue;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    fn zero_chunks(&mut self, chunk: usize) {
+        let mut start = 0;
+        while start + chunk <= self.sequence.choices.len() {
+            let range = start..start + chunk;
+            if self.sequence.choices[range.clone()]
+                .iter()
+                .any(|c| !c.is_zero())
+            {
+                let mut choices = self.sequence.choices.clone();
+                choices[range].iter_mut().for_each(Choice::zero);
+                self.attempt(choices);
+            }
+            start += 1;
+        }
+    }
+
+    /// Lower every integer, length and byte to the smallest value that still
+    /// fails, with a binary search.
+    fn lower_choices(&mut self) {
+        let mut index = 0;
+        while index < self.sequence.choices.len() {
+            let parts = match &self.sequence.choices[index] {
+                Choice::Bytes(bytes) => bytes.len(),
+                Choice::Continue(_) => 0,
+                _ => 1,
+            };
+            for part in 0..parts {
+                self.lower(index, part);
+                if index >= self.sequence.choices.len() {
+                    return;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    fn lower(&mut self, index: usize, part: usize) {
+        let current = match self.sequence.choices.get(index).and_then(|c| get(c, part)) {
+            Some(current) => current,
+            None => return,
+        };
+        let mut lo = 0;
+        let mut hi = current;
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mut choices = self.sequence.choices.clone();
+            set(&mut choices[index], part, mid);
+            let before = self.sequence.choices.len();
+            if self.attempt(choices) {
+                // The decisions after this one may have changed shape, so
+                // stop lowering this one if it is not where it was.
+                if self.sequence.choices.len() != before
+                    || self.sequence.choices.get(index).and_then(|c| get(c, part)) != Some(mid)
+                {
+                    return;
+                }
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+    }
+}
+
+/// Get the numeric value of a part of a choice.
+fn get(choice: &Choice, part: usize) -> Option<u128> {
+    match choice {
+        Choice::IntInRange { value, .. }
+        | Choice::ChooseIndex { value, .. }
+        | Choice::Ratio { value, .. } => Some(*value),
+        Choice::Len { byte_size } => Some(*byte_size as u128),
+        Choice::Continue(_) => None,
+        Choice::Bytes(bytes) => bytes.get(part).map(|b| u128::from(*b)),
+    }
+}
+
+/// Set the numeric value of a part of a choice, which is at most what
+/// [`get`] returned.
+fn set(choice: &mut Choice, part: usize, new: u128) {
+    match choice {
+        Choice::IntInRange { value, .. }
+        | Choice::ChooseIndex { value, .. }
+        | Choice::Ratio { value, .. } => *value = new,
+        Choice::Len { byte_size } => *byte_size = new as usize,
+        Choice::Continue(_) => {}
+        Choice::Bytes(bytes) => bytes[part] = new as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Arbitrary, Dearbitrary};
+
+    fn record<T>(data: &[u8]) -> (T, ChoiceSequence)
+    where
+        T: for<'a> Arbitrary<'a>,
+    {
+        let recorder = Recorder::new();
+        let mut u = Unstructured::with_recorder(data, &recorder);
+        let value = T::arbitrary(&mut u).unwrap();
+        (value, recorder.sequence())
+    }
+
+    #[test]
+    fn records_decisions() {
+        let data = [1, 7, 1, 8, 0, 2, 3];
+        let (value, sequence) = record::<(Vec<u8>, u16)>(&data);
+        assert_eq!(value, (vec![7, 8], 0x0302));
+        assert_eq!(
+            sequence.choices,
+            [
+                Choice::Continue(true),
+                Choice::Bytes(vec![7]),
+                Choice::Continue(true),
+                Choice::Bytes(vec![8]),
+                Choice::Continue(false),
+                Choice::Bytes(vec![2, 3]),
+            ]
+        );
+        assert_eq!(
+            sequence.spans,
+            [
+                Span {
+                    range: 1..2,
+                    depth: 0
+                },
+                Span {
+                    range: 3..4,
+                    depth: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn records_ranges_and_lengths() {
+        let recorder = Recorder::new();
+        let mut u = Unstructured::with_recorder(&[3, 5, 0, 1, 2, 1], &recorder);
+        assert_eq!(u.int_in_range(10..=20_u32).unwrap(), 13);
+        assert_eq!(u.choose_index(4).unwrap(), 1);
+        assert!(u.ratio(1, 2_u8).unwrap());
+        assert_eq!(u.arbitrary_len::<u8>().unwrap(), 1);
+        assert_eq!(
+            recorder.sequence().choices,
+            [
+                Choice::IntInRange { value: 3, max: 10 },
+                Choice::ChooseIndex { value: 1, max: 3 },
+                Choice::Ratio { value: 0, max: 1 },
+                Choice::Len { byte_size: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn to_bytes_round_trips() {
+        let data = [
+            1, 200, 1, 0, 0, 1, 3, 104, 105, 33, 0, 9, 8, 7, 6, 5, 4, 3, 2, 1,
+        ];
+        let (value, sequence) = record::<(Vec<u8>, String, Option<u32>)>(&data);
+        let (again, resequence) = record::<(Vec<u8>, String, Option<u32>)>(&sequence.to_bytes());
+        assert_eq!(value, again);
+        assert_eq!(sequence.choices, resequence.choices);
+    }
+
+    #[test]
+    fn minimize_returns_passing_input_unchanged() {
+        let input = [1, 2, 3];
+        assert_eq!(minimize(&input, |_| false), input);
+    }
+
+    #[test]
+    fn minimize_keeps_only_what_fails() {
+        let fails = |mut u: Unstructured<'_>| match <(Vec<u16>, String)>::arbitrary(&mut u) {
+            Ok((list, s)) => list.contains(&0x300) && s.len() >= 2,
+            Err(_) => false,
+        };
+        let mut input = (vec![0xffff_u16, 0x300, 0x707], "hijkl")
+            .dearbitrary_to_vec()
+            .unwrap();
+        input.extend_from_slice(&[1, 2, 3]);
+        assert!(fails(Unstructured::new(&input)));
+
+        let minimal = minimize(&input, fails);
+        assert!(minimal.len() < input.len());
+        let (list, s) = <(Vec<u16>, String)>::arbitrary(&mut Unstructured::new(&minimal)).unwrap();
+        assert_eq!(list, [0x300]);
+        assert_eq!(s, "\0\0");
+    }
+}
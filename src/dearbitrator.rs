@@ -0,0 +1,502 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Turning structured values back into raw, unstructured bytes.
+
+use crate::{
+    unstructured::{len_bounds, Int},
+    Arbitrary, Dearbitrary, Error, Result, Unstructured,
+};
+use alloc::{collections::VecDeque, vec::Vec};
+use core::{mem, ops};
+
+/// A sink for the raw data that an [`Unstructured`][crate::Unstructured]
+/// would turn into a given value.
+///
+/// A `Dearbitrator` is the inverse of an `Unstructured`: for every method
+/// that consumes data from an `Unstructured`, there is a `push_*` method here
+/// that records the data for it. Calling the `push_*` methods in the same
+/// order in which an [`Arbitrary`] implementation calls the corresponding
+/// `Unstructured` methods yields raw data that this implementation decodes
+/// back into the original value.
+///
+/// `Unstructured` takes some of its data, like the lengths of byte slices,
+/// from the end of its buffer, and how it interprets those bytes depends on
+/// how much data is left. Therefore a `Dearbitrator` only records the calls
+/// and lays out the final bytes in [`Dearbitrator::finish`].
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{Arbitrary, Dearbitrator, Unstructured};
+///
+/// let mut d = Dearbitrator::new();
+/// d.push(&42_u32).unwrap();
+/// d.push_int_in_range(-10..=10, -3_i8).unwrap();
+/// d.push(&"hello").unwrap();
+/// let data = d.finish();
+///
+/// let mut u = Unstructured::new(&data);
+/// assert_eq!(u32::arbitrary(&mut u).unwrap(), 42);
+/// assert_eq!(u.int_in_range(-10..=10_i8).unwrap(), -3);
+/// assert_eq!(<&str>::arbitrary(&mut u).unwrap(), "hello");
+/// ```
+#[derive(Debug)]
+pub struct Dearbitrator {
+    front: Vec<u8>,
+    ops: Vec<Op>,
+    /// How many calls to `nested` are currently running.
+    depth: usize,
+    max_depth: usize,
+}
+
+impl Default for Dearbitrator {
+    fn default() -> Self {
+        Dearbitrator {
+            front: Vec::new(),
+            ops: Vec::new(),
+            depth: 0,
+            max_depth: Unstructured::DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Op {
+    /// The next `n` bytes of `front`, consumed from the front of the data.
+    Front(usize),
+    /// A byte size taken from the back of the data by
+    /// `Unstructured::arbitrary_byte_size`.
+    ByteSize(usize),
+}
+
+impl Dearbitrator {
+    /// Create a new, empty `Dearbitrator`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum depth of the `Unstructured` that is going to decode the
+    /// data, see
+    /// [`Unstructured::with_max_depth`][crate::Unstructured::with_max_depth].
+    ///
+    /// The default is [`Unstructured::DEFAULT_MAX_DEPTH`].
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Record the data for a value one level deeper.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::nested`][crate::Unstructured::nested]. Returns
+    /// [`Error::Unrepresentable`] without calling the function if the value
+    /// would nest deeper than the maximum depth, since decoding it would fail
+    /// or fall back to a different value. Derived `Dearbitrary`
+    /// implementations record every value this way.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "derive")] fn foo() {
+    /// use arbitrary::{Arbitrary, Dearbitrary, Dearbitrator, Error};
+    ///
+    /// #[derive(Arbitrary, Dearbitrary)]
+    /// struct List(Option<Box<List>>);
+    ///
+    /// let list = List(Some(Box::new(List(Some(Box::new(List(None)))))));
+    /// let mut d = Dearbitrator::new().with_max_depth(2);
+    /// assert_eq!(d.push(&list), Err(Error::Unrepresentable));
+    /// # }
+    /// ```
+    pub fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.depth >= self.max_depth {
+            return Err(Error::Unrepresentable);
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Record the raw data for `value`.
+    ///
+    /// This is simply a helper method that is equivalent to `<A as
+    /// Dearbitrary>::dearbitrary(value, self)`, and is the inverse of
+    /// [`Unstructured::arbitrary`][crate::Unstructured::arbitrary].
+    pub fn push<A>(&mut self, value: &A) -> Result<()>
+    where
+        A: Dearbitrary + ?Sized,
+    {
+        value.dearbitrary(self)
+    }
+
+    /// Record raw bytes.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::bytes`][crate::Unstructured::bytes] and
+    /// [`Unstructured::fill_buffer`][crate::Unstructured::fill_buffer].
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        self.front.extend_from_slice(bytes);
+        match self.ops.last_mut() {
+            Some(Op::Front(n)) => *n += bytes.len(),
+            _ => self.ops.push(Op::Front(bytes.len())),
+        }
+    }
+
+    /// Record the data for the length of a collection of `len` elements.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::arbitrary_len`][crate::Unstructured::arbitrary_len].
+    pub fn push_len<'a, ElementType>(&mut self, len: usize) -> Result<()>
+    where
+        ElementType: Arbitrary<'a>,
+    {
+        let (lower, upper) = <ElementType as Arbitrary>::size_hint(0);
+        let elem_size = upper.unwrap_or(lower * 2);
+        let elem_size = core::cmp::max(1, elem_size);
+        let byte_size = len.checked_mul(elem_size).ok_or(Error::Unrepresentable)?;
+        self.ops.push(Op::ByteSize(byte_size));
+        Ok(())
+    }
+
+    /// Record the data for the byte size behind a collection length, as
+    /// recorded by [`Choice::Len`][crate::choices::Choice::Len].
+    #[cfg(feature = "std")]
+    pub(crate) fn push_byte_size(&mut self, byte_size: usize) {
+        self.ops.push(Op::ByteSize(byte_size));
+    }
+
+    /// Record the data for the length of a collection of `len` elements,
+    /// within the given range.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::arbitrary_len_in`][crate::Unstructured::arbitrary_len_in].
+    ///
+    /// Returns [`Error::Unrepresentable`] if `len` is not within `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty, just like `arbitrary_len_in`.
+    pub fn push_len_in<'a, ElementType>(
+        &mut self,
+        range: impl ops::RangeBounds<usize>,
+        len: usize,
+    ) -> Result<()>
+    where
+        ElementType: Arbitrary<'a>,
+    {
+        let (min, max) = len_bounds(&range);
+        if len < min || len > max {
+            return Err(Error::Unrepresentable);
+        }
+        if min == max {
+            return Ok(());
+        }
+        // `len - min` is smaller than the size of the range, so
+        // `arbitrary_len_in` doesn't wrap it around.
+        self.push_len::<ElementType>(len - min)
+    }
+
+    /// Record the data for `value`, generated within the given range.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::int_in_range`][crate::Unstructured::int_in_range].
+    ///
+    /// Returns [`Error::Unrepresentable`] if `value` is not within `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`, just like `int_in_range`.
+    pub fn push_int_in_range<T>(&mut self, range: ops::RangeInclusive<T>, value: T) -> Result<()>
+    where
+        T: Int,
+    {
+        let start = *range.start();
+        let end = *range.end();
+        assert!(
+            start <= end,
+            "`arbitrary::Dearbitrator::push_int_in_range` requires a non-empty range"
+        );
+        if value < start || end < value {
+            return Err(Error::Unrepresentable);
+        }
+
+        let start = start.to_unsigned();
+        let delta = end.to_unsigned().wrapping_sub(start);
+        let offset = value.to_unsigned().wrapping_sub(start);
+
+        // `int_in_range` consumes only as many bytes as necessary to cover the
+        // range, and combines them into an integer most significant byte
+        // first.
+        let mut bytes_needed: usize = 0;
+        while (bytes_needed < mem::size_of::<T>())
+            && (delta >> T::Unsigned::from_usize(bytes_needed * 8)) > T::Unsigned::ZERO
+        {
+            bytes_needed += 1;
+        }
+
+        let mut buf = [0; 16];
+        for (i, byte) in buf[..bytes_needed].iter_mut().rev().enumerate() {
+            *byte = (offset >> T::Unsigned::from_usize(i * 8)).to_u8();
+        }
+        self.push_bytes(&buf[..bytes_needed]);
+        Ok(())
+    }
+
+    /// Record the data for choosing the `index`th of `len` choices.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::choose_index`][crate::Unstructured::choose_index],
+    /// [`Unstructured::choose`][crate::Unstructured::choose] and
+    /// [`Unstructured::choose_iter`][crate::Unstructured::choose_iter].
+    pub fn push_choose_index(&mut self, len: usize, index: usize) -> Result<()> {
+        if len == 0 {
+            return Err(Error::EmptyChoose);
+        }
+        self.push_int_in_range(0..=len - 1, index)
+    }
+
+    /// Record the data for a boolean generated with the given ratio.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::ratio`][crate::Unstructured::ratio].
+    ///
+    /// # Panics
+    ///
+    /// Panics when the numerator and denominator do not meet these constraints:
+    ///
+    /// * `0 < numerator <= denominator`
+    pub fn push_ratio<T>(&mut self, numerator: T, denominator: T, value: bool) -> Result<()>
+    where
+        T: Int,
+    {
+        assert!(T::ZERO < numerator);
+        assert!(numerator <= denominator);
+        if value {
+            self.push_int_in_range(T::ONE..=denominator, T::ONE)
+        } else if numerator < denominator {
+            self.push_int_in_range(T::ONE..=denominator, denominator)
+        } else {
+            Err(Error::Unrepresentable)
+        }
+    }
+
+    /// Record the data for the elements of a collection.
+    ///
+    /// This is the inverse of
+    /// [`Unstructured::arbitrary_iter`][crate::Unstructured::arbitrary_iter],
+    /// which is how all collections in this crate are generated.
+    pub fn push_iter<I>(&mut self, elements: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Dearbitrary,
+    {
+        for element in elements {
+            self.push(&true)?;
+            self.push(&element)?;
+        }
+        self.push(&false)
+    }
+
+    /// Lay out the recorded data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{Arbitrary, Dearbitrator, Unstructured};
+    ///
+    /// let mut d = Dearbitrator::new();
+    /// d.push(&vec![1_u8, 2, 3]).unwrap();
+    /// let data = d.finish();
+    ///
+    /// let value = Vec::<u8>::arbitrary(&mut Unstructured::new(&data)).unwrap();
+    /// assert_eq!(value, [1, 2, 3]);
+    /// ```
+    pub fn finish(self) -> Vec<u8> {
+        // Byte sizes are bounded by the amount of data that is left when they
+        // are decoded. If a size does not fit, we leave enough unused bytes
+        // after everything else, which only makes the data after the size
+        // longer, and try again.
+        let mut padding = 0;
+        loop {
+            match self.assemble(padding) {
+                Ok(data) => return data,
+                Err(missing) => padding += missing,
+            }
+        }
+    }
+
+    /// Lay out the recorded data back to front, so that the amount of data
+    /// left over for every byte size is known by the time we encode it.
+    ///
+    /// Returns how many bytes are missing if a byte size does not fit.
+    fn assemble(&self, padding: usize) -> core::result::Result<Vec<u8>, usize> {
+        let mut data = VecDeque::with_capacity(self.front.len() + padding);
+        data.resize(padding, 0);
+
+        let mut front_end = self.front.len();
+        for op in self.ops.iter().rev() {
+            match *op {
+                Op::Front(n) => {
+                    let front_start = front_end - n;
+                    for byte in self.front[front_start..front_end].iter().rev() {
+                        data.push_front(*byte);
+                    }
+                    front_end = front_start;
+                }
+                Op::ByteSize(size) => push_byte_size(&mut data, size)?,
+            }
+        }
+
+        Ok(data.into())
+    }
+}
+
+/// Append the bytes from which `Unstructured::arbitrary_byte_size` decodes
+/// `size`, given that `data` is what is left after it.
+fn push_byte_size(data: &mut VecDeque<u8>, size: usize) -> core::result::Result<(), usize> {
+    let rest = data.len();
+    if size > rest {
+        return Err(size - rest);
+    }
+    if rest == 0 {
+        // An empty buffer has a byte size of zero.
+        return Ok(());
+    }
+
+    // Find the width that `arbitrary_byte_size` picks for this much data.
+    let rest = rest as u64;
+    let bytes = if rest <= u8::MAX as u64 {
+        1
+    } else if rest <= u16::MAX as u64 {
+        2
+    } else if rest <= u32::MAX as u64 {
+        4
+    } else {
+        8
+    };
+
+    // The size is then decoded as `int_in_range(0..=rest)`.
+    let mut bytes_needed = 0;
+    while bytes_needed < bytes && (rest >> (bytes_needed * 8)) > 0 {
+        bytes_needed += 1;
+    }
+    for i in (0..bytes_needed).rev() {
+        data.push_back((size as u64 >> (i * 8)) as u8);
+    }
+    for _ in bytes_needed..bytes {
+        data.push_back(0);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Unstructured;
+    use alloc::vec;
+
+    #[test]
+    fn byte_size_round_trips() {
+        for rest in [
+            0_usize, 1, 2, 100, 254, 255, 256, 257, 1000, 65534, 65535, 65536,
+        ] {
+            for size in [0, 1, rest / 2, rest.saturating_sub(1), rest] {
+                if size > rest {
+                    continue;
+                }
+                let mut data = VecDeque::from(vec![0; rest]);
+                push_byte_size(&mut data, size).unwrap();
+                let data = Vec::from(data);
+
+                let mut u = Unstructured::new(&data);
+                assert_eq!(u.arbitrary_byte_size().unwrap(), size);
+                assert_eq!(u.len(), rest);
+            }
+        }
+    }
+
+    #[test]
+    fn byte_size_needs_enough_data() {
+        let mut data = VecDeque::from(vec![0; 3]);
+        assert_eq!(push_byte_size(&mut data, 5), Err(2));
+    }
+
+    #[test]
+    fn int_in_range_round_trips() {
+        fn check<T: Int>(range: ops::RangeInclusive<T>, value: T) {
+            let mut d = Dearbitrator::new();
+            d.push_int_in_range(range.clone(), value).unwrap();
+            let data = d.finish();
+            let mut u = Unstructured::new(&data);
+            assert_eq!(u.int_in_range(range).unwrap(), value);
+            assert!(u.is_empty());
+        }
+
+        for value in 0..=u8::MAX {
+            check(0..=u8::MAX, value);
+            check(value..=u8::MAX, value);
+            check(0..=value, value);
+        }
+        for value in i8::MIN..=i8::MAX {
+            check(i8::MIN..=i8::MAX, value);
+            check(-5..=i8::MAX, value.max(-5));
+        }
+        check(0..=u32::MAX, 0x1234_5678);
+        check(1_000..=1_000_000, 999_999_u64);
+        check(i128::MIN..=i128::MAX, -1);
+        check(-5_000..=-1_000, -3_000_i32);
+    }
+
+    #[test]
+    fn int_out_of_range() {
+        let mut d = Dearbitrator::new();
+        assert_eq!(
+            d.push_int_in_range(1..=3, 4_u8),
+            Err(Error::Unrepresentable)
+        );
+    }
+
+    #[test]
+    fn len_in_round_trips() {
+        for (range, len) in [(2..=10, 2), (2..=10, 7), (2..=10, 10), (4..=4, 4)] {
+            let mut d = Dearbitrator::new();
+            d.push_len_in::<u16>(range.clone(), len).unwrap();
+            for i in 0..len {
+                d.push(&(i as u16)).unwrap();
+            }
+            let data = d.finish();
+            let mut u = Unstructured::new(&data);
+            assert_eq!(u.arbitrary_len_in::<u16>(range).unwrap(), len);
+            for i in 0..len {
+                assert_eq!(u.arbitrary::<u16>().unwrap(), i as u16);
+            }
+        }
+
+        let mut d = Dearbitrator::new();
+        assert_eq!(
+            d.push_len_in::<u16>(2..=10, 11),
+            Err(Error::Unrepresentable)
+        );
+    }
+
+    #[test]
+    fn lengths_get_enough_data() {
+        // The length of the byte slice is taken from the end of the data, and
+        // is bounded by the data that is left. Put the slice behind a bunch of
+        // other lengths so that there is too little data in front of it.
+        let value: (&[u8], &[u8], &[u8]) = (&[], &[], &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let data = value.dearbitrary_to_vec().unwrap();
+        let mut u = Unstructured::new(&data);
+        assert_eq!(u.arbitrary::<(&[u8], &[u8], &[u8])>().unwrap(), value);
+    }
+}
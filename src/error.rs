@@ -1,4 +1,4 @@
-use std::{error, fmt};
+use core::{error, fmt};
 
 /// An enumeration of buffer creation errors
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -11,6 +11,12 @@ pub enum Error {
     NotEnoughData,
     /// The input bytes were not of the right format
     IncorrectFormat,
+    /// The value cannot be turned back into raw data that `Arbitrary` would
+    /// decode into an equal value
+    Unrepresentable,
+    /// Generating the value would nest deeper than the maximum depth of the
+    /// `Unstructured`
+    MaxDepthReached,
 }
 
 impl fmt::Display for Error {
@@ -28,6 +34,14 @@ impl fmt::Display for Error {
                 f,
                 "The raw data is not of the correct format to construct this type"
             ),
+            Error::Unrepresentable => write!(
+                f,
+                "There is no raw data that `Arbitrary` would decode into this value"
+            ),
+            Error::MaxDepthReached => write!(
+                f,
+                "Generating this value would nest deeper than the maximum depth of the `arbitrary::Unstructured`"
+            ),
         }
     }
 }
@@ -37,9 +51,9 @@ impl error::Error for Error {}
 /// A `Result` with the error type fixed as `arbitrary::Error`.
 ///
 /// Either an `Ok(T)` or `Err(arbitrary::Error)`.
-pub type Result<T, E = Error> = std::result::Result<T, E>;
+pub type Result<T, E = Error> = core::result::Result<T, E>;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     // Often people will import our custom `Result` type because 99.9% of
     // results in a file will be `arbitrary::Result` but then have that one last
@@ -1,6 +1,12 @@
 use {
-    crate::{size_hint, Arbitrary, Result, Unstructured},
-    std::borrow::{Cow, ToOwned},
+    crate::{
+        size_hint, Arbitrary, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
+    alloc::{
+        borrow::{Cow, ToOwned},
+        boxed::Box,
+        vec::Vec,
+    },
 };
 
 impl<'a, A> Arbitrary<'a> for Cow<'a, A>
@@ -24,3 +30,38 @@ where
         })
     }
 }
+
+impl<'a, A> Dearbitrary for Cow<'a, A>
+where
+    A: ToOwned + ?Sized,
+    <A as ToOwned>::Owned: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        match self {
+            Cow::Borrowed(borrowed) => d.push(&(*borrowed).to_owned()),
+            Cow::Owned(owned) => d.push(owned),
+        }
+    }
+}
+
+impl<'a, A> Shrink for Cow<'a, A>
+where
+    A: ToOwned + ?Sized,
+    <A as ToOwned>::Owned: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let owned = (**self).to_owned();
+        let candidates: Vec<Self> = owned.shrink().map(Cow::Owned).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl<'a, A> Mutate<'a> for Cow<'a, A>
+where
+    A: ToOwned + ?Sized,
+    <A as ToOwned>::Owned: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.to_mut().mutate(u)
+    }
+}
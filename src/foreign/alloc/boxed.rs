@@ -1,6 +1,11 @@
 use {
-    crate::{size_hint, Arbitrary, Result, Unstructured},
-    std::boxed::Box,
+    crate::{
+        shrink::{shrink_str, ShrinkVec},
+        size_hint, Arbitrary, ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    alloc::{boxed::Box, string::String, vec::Vec},
+    core::mem,
 };
 
 impl<'a, A> Arbitrary<'a> for Box<A>
@@ -22,6 +27,32 @@ where
     }
 }
 
+impl<'a, A, Ctx> ArbitraryWith<'a, Ctx> for Box<A>
+where
+    A: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        A::arbitrary_with(u, ctx).map(Self::new)
+    }
+
+    fn arbitrary_take_rest_with(u: Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        A::arbitrary_take_rest_with(u, ctx).map(Self::new)
+    }
+
+    #[inline]
+    fn size_hint_with(depth: usize) -> (usize, Option<usize>) {
+        Self::try_size_hint_with(depth).unwrap_or_default()
+    }
+
+    #[inline]
+    fn try_size_hint_with(
+        depth: usize,
+    ) -> Result<(usize, Option<usize>), crate::MaxRecursionReached> {
+        size_hint::try_recursion_guard(depth, A::try_size_hint_with)
+    }
+}
+
 impl<'a, A> Arbitrary<'a> for Box<[A]>
 where
     A: Arbitrary<'a>,
@@ -50,3 +81,81 @@ impl<'a> Arbitrary<'a> for Box<str> {
         <String as Arbitrary>::size_hint(depth)
     }
 }
+
+impl<A> Dearbitrary for Box<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&**self)
+    }
+}
+
+impl<A> Dearbitrary for Box<[A]>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self.iter())
+    }
+}
+
+impl Dearbitrary for Box<str> {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&&**self)
+    }
+}
+
+impl<A> Shrink for Box<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new((**self).shrink().map(Box::new))
+    }
+}
+
+impl<A> Shrink for Box<[A]>
+where
+    A: Shrink + Clone,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(ShrinkVec::new(self.to_vec()).map(Vec::into_boxed_slice))
+    }
+}
+
+impl Shrink for Box<str> {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(shrink_str(self).map(String::into_boxed_str))
+    }
+}
+
+impl<'a, A> Mutate<'a> for Box<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        (**self).mutate(u)
+    }
+}
+
+impl<'a, A> Mutate<'a> for Box<[A]>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut elements = mem::take(self).into_vec();
+        let result = elements.mutate(u);
+        *self = elements.into_boxed_slice();
+        result
+    }
+}
+
+impl<'a> Mutate<'a> for Box<str> {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut s = String::from(mem::take(self));
+        let result = s.mutate(u);
+        *self = s.into_boxed_str();
+        result
+    }
+}
@@ -1,6 +1,9 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::collections::binary_heap::BinaryHeap,
+    crate::{
+        foreign::alloc::vec::mutate_as_vec, shrink::ShrinkVec, Arbitrary, ArbitraryLen,
+        ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
+    alloc::{boxed::Box, collections::binary_heap::BinaryHeap},
 };
 
 impl<'a, A> Arbitrary<'a> for BinaryHeap<A>
@@ -20,3 +23,51 @@ where
         (0, None)
     }
 }
+
+impl<'a, A> ArbitraryLen<'a> for BinaryHeap<A>
+where
+    A: Arbitrary<'a> + Ord,
+{
+    type Element = A;
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements(len)
+    }
+}
+
+impl<'a, A, Ctx> ArbitraryWith<'a, Ctx> for BinaryHeap<A>
+where
+    A: ArbitraryWith<'a, Ctx> + Ord,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        u.arbitrary_iter_with(ctx)?.collect()
+    }
+}
+
+impl<A> Dearbitrary for BinaryHeap<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self)
+    }
+}
+
+impl<A> Shrink for BinaryHeap<A>
+where
+    A: Shrink + Clone + Ord,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(ShrinkVec::new(self.iter().cloned().collect()).map(Self::from))
+    }
+}
+
+impl<'a, A> Mutate<'a> for BinaryHeap<A>
+where
+    A: Mutate<'a> + Ord,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        mutate_as_vec(self, u)
+    }
+}
@@ -1,6 +1,9 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::collections::btree_map::BTreeMap,
+    crate::{
+        foreign::alloc::vec::mutate_as_vec, shrink::ShrinkVec, Arbitrary, ArbitraryLen,
+        ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
+    alloc::{boxed::Box, collections::btree_map::BTreeMap},
 };
 
 impl<'a, K, V> Arbitrary<'a> for BTreeMap<K, V>
@@ -21,3 +24,57 @@ where
         (0, None)
     }
 }
+
+impl<'a, K, V> ArbitraryLen<'a> for BTreeMap<K, V>
+where
+    K: Arbitrary<'a> + Ord,
+    V: Arbitrary<'a>,
+{
+    type Element = (K, V);
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements(len)
+    }
+}
+
+impl<'a, K, V, Ctx> ArbitraryWith<'a, Ctx> for BTreeMap<K, V>
+where
+    K: ArbitraryWith<'a, Ctx> + Ord,
+    V: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        u.arbitrary_iter_with(ctx)?.collect()
+    }
+}
+
+impl<K, V> Dearbitrary for BTreeMap<K, V>
+where
+    K: Dearbitrary,
+    V: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self)
+    }
+}
+
+impl<K, V> Shrink for BTreeMap<K, V>
+where
+    K: Shrink + Clone + Ord,
+    V: Shrink + Clone,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let entries = self.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        Box::new(ShrinkVec::new(entries).map(|entries| entries.into_iter().collect()))
+    }
+}
+
+impl<'a, K, V> Mutate<'a> for BTreeMap<K, V>
+where
+    K: Mutate<'a> + Ord,
+    V: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        mutate_as_vec(self, u)
+    }
+}
@@ -1,6 +1,9 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::collections::btree_set::BTreeSet,
+    crate::{
+        foreign::alloc::vec::mutate_as_vec, shrink::ShrinkVec, Arbitrary, ArbitraryLen,
+        ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
+    alloc::{boxed::Box, collections::btree_set::BTreeSet},
 };
 
 impl<'a, A> Arbitrary<'a> for BTreeSet<A>
@@ -20,3 +23,53 @@ where
         (0, None)
     }
 }
+
+impl<'a, A> ArbitraryLen<'a> for BTreeSet<A>
+where
+    A: Arbitrary<'a> + Ord,
+{
+    type Element = A;
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements(len)
+    }
+}
+
+impl<'a, A, Ctx> ArbitraryWith<'a, Ctx> for BTreeSet<A>
+where
+    A: ArbitraryWith<'a, Ctx> + Ord,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        u.arbitrary_iter_with(ctx)?.collect()
+    }
+}
+
+impl<A> Dearbitrary for BTreeSet<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self)
+    }
+}
+
+impl<A> Shrink for BTreeSet<A>
+where
+    A: Shrink + Clone + Ord,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(
+            ShrinkVec::new(self.iter().cloned().collect()).map(|items| items.into_iter().collect()),
+        )
+    }
+}
+
+impl<'a, A> Mutate<'a> for BTreeSet<A>
+where
+    A: Mutate<'a> + Ord,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        mutate_as_vec(self, u)
+    }
+}
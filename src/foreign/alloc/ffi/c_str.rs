@@ -1,6 +1,9 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::ffi::CString,
+    crate::{
+        shrink::ShrinkVec, Arbitrary, Dearbitrary, Dearbitrator, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    alloc::{boxed::Box, ffi::CString, vec::Vec},
 };
 
 impl<'a> Arbitrary<'a> for CString {
@@ -17,3 +20,20 @@ impl<'a> Arbitrary<'a> for CString {
         <Vec<u8> as Arbitrary>::size_hint(depth)
     }
 }
+
+impl Dearbitrary for CString {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self.as_bytes())
+    }
+}
+
+impl Shrink for CString {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        // Shrinking the bytes can produce zeros, which a `CString` can't hold.
+        Box::new(
+            ShrinkVec::new(self.as_bytes().to_vec()).filter_map(|bytes| CString::new(bytes).ok()),
+        )
+    }
+}
+
+impl<'a> Mutate<'a> for CString {}
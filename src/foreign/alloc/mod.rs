@@ -10,4 +10,4 @@ mod ffi;
 mod rc;
 mod string;
 mod sync;
-mod vec;
+pub(super) mod vec;
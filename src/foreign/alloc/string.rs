@@ -1,11 +1,33 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::string::String,
+    crate::{
+        foreign::core::str::arbitrary_str, shrink::shrink_str, Arbitrary, ArbitraryLen,
+        Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
+    alloc::{boxed::Box, string::String, vec, vec::Vec},
 };
 
 impl<'a> Arbitrary<'a> for String {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        <&str as Arbitrary>::arbitrary(u).map(Into::into)
+        let size = u.arbitrary_len::<u8>()?;
+        if u.peek_bytes(size).is_some() {
+            return arbitrary_str(u, size).map(Into::into);
+        }
+
+        // The data comes from a `ByteSource` and cannot be borrowed. Like the
+        // slice path above, leave the bytes after the valid UTF-8 prefix for
+        // the next value.
+        let mut bytes = vec![0; size];
+        u.fill_buffer(&mut bytes)?;
+        match String::from_utf8(bytes) {
+            Ok(s) => Ok(s),
+            Err(e) => {
+                let valid = e.utf8_error().valid_up_to();
+                let mut bytes = e.into_bytes();
+                u.unread(&bytes[valid..]);
+                bytes.truncate(valid);
+                Ok(unsafe { String::from_utf8_unchecked(bytes) })
+            }
+        }
     }
 
     fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
@@ -17,3 +39,32 @@ impl<'a> Arbitrary<'a> for String {
         <&str as Arbitrary>::size_hint(depth)
     }
 }
+
+impl<'a> ArbitraryLen<'a> for String {
+    type Element = char;
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements::<char, _>(len)
+    }
+}
+
+impl Dearbitrary for String {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.as_str())
+    }
+}
+
+impl Shrink for String {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(shrink_str(self))
+    }
+}
+
+impl<'a> Mutate<'a> for String {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut chars: Vec<char> = self.chars().collect();
+        let result = chars.mutate(u);
+        *self = chars.into_iter().collect();
+        result
+    }
+}
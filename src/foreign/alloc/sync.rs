@@ -1,6 +1,10 @@
 use {
-    crate::{size_hint, Arbitrary, Result, Unstructured},
-    std::sync::Arc,
+    crate::{
+        shrink::{shrink_str, ShrinkVec},
+        size_hint, Arbitrary, ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    alloc::{boxed::Box, string::String, sync::Arc},
 };
 
 impl<'a, A> Arbitrary<'a> for Arc<A>
@@ -22,6 +26,32 @@ where
     }
 }
 
+impl<'a, A, Ctx> ArbitraryWith<'a, Ctx> for Arc<A>
+where
+    A: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        A::arbitrary_with(u, ctx).map(Self::new)
+    }
+
+    fn arbitrary_take_rest_with(u: Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        A::arbitrary_take_rest_with(u, ctx).map(Self::new)
+    }
+
+    #[inline]
+    fn size_hint_with(depth: usize) -> (usize, Option<usize>) {
+        Self::try_size_hint_with(depth).unwrap_or_default()
+    }
+
+    #[inline]
+    fn try_size_hint_with(
+        depth: usize,
+    ) -> Result<(usize, Option<usize>), crate::MaxRecursionReached> {
+        size_hint::try_recursion_guard(depth, A::try_size_hint_with)
+    }
+}
+
 impl<'a, A> Arbitrary<'a> for Arc<[A]>
 where
     A: Arbitrary<'a>,
@@ -42,7 +72,7 @@ where
 
 impl<'a> Arbitrary<'a> for Arc<str> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        <&str as Arbitrary>::arbitrary(u).map(Into::into)
+        <String as Arbitrary>::arbitrary(u).map(Into::into)
     }
 
     #[inline]
@@ -50,3 +80,57 @@ impl<'a> Arbitrary<'a> for Arc<str> {
         <&str as Arbitrary>::size_hint(depth)
     }
 }
+
+impl<A> Dearbitrary for Arc<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&**self)
+    }
+}
+
+impl<A> Dearbitrary for Arc<[A]>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self.iter())
+    }
+}
+
+impl Dearbitrary for Arc<str> {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&&**self)
+    }
+}
+
+impl<A> Shrink for Arc<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new((**self).shrink().map(Arc::new))
+    }
+}
+
+impl<A> Shrink for Arc<[A]>
+where
+    A: Shrink + Clone,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(ShrinkVec::new(self.to_vec()).map(Into::into))
+    }
+}
+
+impl Shrink for Arc<str> {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(shrink_str(self).map(Into::into))
+    }
+}
+
+impl<'a, A> Mutate<'a> for Arc<A> where A: Arbitrary<'a> {}
+
+impl<'a, A> Mutate<'a> for Arc<[A]> where A: Arbitrary<'a> {}
+
+impl<'a> Mutate<'a> for Arc<str> {}
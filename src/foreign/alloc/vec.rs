@@ -1,6 +1,10 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::vec::Vec,
+    crate::{
+        shrink::ShrinkVec, Arbitrary, ArbitraryLen, ArbitraryWith, Dearbitrary, Dearbitrator,
+        Mutate, Result, Shrink, Unstructured,
+    },
+    alloc::{boxed::Box, vec::Vec},
+    core::mem,
 };
 
 impl<'a, A> Arbitrary<'a> for Vec<A>
@@ -20,3 +24,92 @@ where
         (0, None)
     }
 }
+
+impl<'a, A> ArbitraryLen<'a> for Vec<A>
+where
+    A: Arbitrary<'a>,
+{
+    type Element = A;
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements(len)
+    }
+}
+
+impl<'a, A, Ctx> ArbitraryWith<'a, Ctx> for Vec<A>
+where
+    A: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        u.arbitrary_iter_with(ctx)?.collect()
+    }
+}
+
+impl<A> Dearbitrary for Vec<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self)
+    }
+}
+
+impl<A> Shrink for Vec<A>
+where
+    A: Shrink + Clone,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(ShrinkVec::new(self.clone()))
+    }
+}
+
+impl<'a, A> Mutate<'a> for Vec<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        // An empty vector can only grow.
+        let len = self.len();
+        if len == 0 {
+            self.push(A::arbitrary(u)?);
+            return Ok(());
+        }
+        // Mutate, insert, remove, replace or swap elements.
+        match u.choose_index(5)? {
+            0 => {
+                let index = u.choose_index(len)?;
+                self[index].mutate(u)?;
+            }
+            1 => {
+                let index = u.choose_index(len + 1)?;
+                self.insert(index, A::arbitrary(u)?);
+            }
+            2 => {
+                self.remove(u.choose_index(len)?);
+            }
+            3 => {
+                let index = u.choose_index(len)?;
+                self[index] = A::arbitrary(u)?;
+            }
+            _ => {
+                let a = u.choose_index(len)?;
+                let b = u.choose_index(len)?;
+                self.swap(a, b);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mutate a collection like a `Vec` of its elements.
+pub(crate) fn mutate_as_vec<'a, C, A>(collection: &mut C, u: &mut Unstructured<'a>) -> Result<()>
+where
+    C: Default + IntoIterator<Item = A> + FromIterator<A>,
+    A: Mutate<'a>,
+{
+    let mut elements: Vec<A> = mem::take(collection).into_iter().collect();
+    let result = elements.mutate(u);
+    *collection = elements.into_iter().collect();
+    result
+}
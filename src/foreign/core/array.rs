@@ -1,5 +1,5 @@
 use {
-    crate::{size_hint, Arbitrary, Result, Unstructured},
+    crate::{size_hint, Arbitrary, ArbitraryWith, Mutate, Result, Unstructured},
     core::{
         array,
         mem::{self, MaybeUninit},
@@ -7,6 +7,12 @@ use {
     },
 };
 
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {crate::Shrink, alloc::boxed::Box};
+
 /// Helper to safely create arrays since the standard library doesn't
 /// provide one yet. Shouldn't be necessary in the future.
 struct ArrayGuard<T, const N: usize> {
@@ -74,3 +80,75 @@ where
         Ok(size_hint::and_all(&array::from_fn::<_, N, _>(|_| hint)))
     }
 }
+
+impl<'a, T, Ctx, const N: usize> ArbitraryWith<'a, Ctx> for [T; N]
+where
+    T: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    #[inline]
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        try_create_array(|_| T::arbitrary_with(u, ctx))
+    }
+
+    #[inline]
+    fn arbitrary_take_rest_with(mut u: Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        let mut array = Self::arbitrary_with(&mut u, ctx)?;
+        if let Some(last) = array.last_mut() {
+            *last = T::arbitrary_take_rest_with(u, ctx)?;
+        }
+        Ok(array)
+    }
+
+    #[inline]
+    fn size_hint_with(depth: usize) -> (usize, Option<usize>) {
+        Self::try_size_hint_with(depth).unwrap_or_default()
+    }
+
+    #[inline]
+    fn try_size_hint_with(
+        depth: usize,
+    ) -> Result<(usize, Option<usize>), crate::MaxRecursionReached> {
+        let hint = T::try_size_hint_with(depth)?;
+        Ok(size_hint::and_all(&array::from_fn::<_, N, _>(|_| hint)))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize> Dearbitrary for [T; N]
+where
+    T: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        self.iter().try_for_each(|element| d.push(element))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, const N: usize> Shrink for [T; N]
+where
+    T: Shrink + Clone,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new((0..N).flat_map(move |i| {
+            self[i].shrink().map(move |element| {
+                let mut array = self.clone();
+                array[i] = element;
+                array
+            })
+        }))
+    }
+}
+
+impl<'a, T, const N: usize> Mutate<'a> for [T; N]
+where
+    T: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        if N == 0 {
+            return Ok(());
+        }
+        let index = u.choose_index(N)?;
+        self[index].mutate(u)
+    }
+}
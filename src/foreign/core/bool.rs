@@ -1,4 +1,10 @@
-use crate::{Arbitrary, Result, Unstructured};
+use crate::{Arbitrary, Mutate, Result, Unstructured};
+
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {crate::Shrink, alloc::boxed::Box};
 
 impl<'a> Arbitrary<'a> for bool {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
@@ -10,3 +16,24 @@ impl<'a> Arbitrary<'a> for bool {
         <u8 as Arbitrary<'a>>::size_hint(depth)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for bool {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&(*self as u8))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for bool {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(self.then_some(false).into_iter())
+    }
+}
+
+impl<'a> Mutate<'a> for bool {
+    fn mutate(&mut self, _: &mut Unstructured<'a>) -> Result<()> {
+        *self = !*self;
+        Ok(())
+    }
+}
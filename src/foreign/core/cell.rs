@@ -1,8 +1,17 @@
 use {
-    crate::{Arbitrary, MaxRecursionReached, Result, Unstructured},
+    crate::{Arbitrary, MaxRecursionReached, Mutate, Result, Unstructured},
     core::cell::{Cell, RefCell, UnsafeCell},
 };
 
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::Shrink,
+    alloc::{boxed::Box, vec::Vec},
+};
+
 impl<'a, A> Arbitrary<'a> for Cell<A>
 where
     A: Arbitrary<'a>,
@@ -59,3 +68,72 @@ where
         <A as Arbitrary<'a>>::try_size_hint(depth)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for Cell<A>
+where
+    A: Dearbitrary + Copy,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.get())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for RefCell<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&*self.borrow())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for Cell<A>
+where
+    A: Shrink + Copy,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = self.get().shrink().map(Cell::new).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for RefCell<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = self.borrow().shrink().map(RefCell::new).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl<'a, A> Mutate<'a> for Cell<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut().mutate(u)
+    }
+}
+
+impl<'a, A> Mutate<'a> for RefCell<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut().mutate(u)
+    }
+}
+
+impl<'a, A> Mutate<'a> for UnsafeCell<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut().mutate(u)
+    }
+}
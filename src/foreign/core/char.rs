@@ -1,4 +1,19 @@
-use crate::{Arbitrary, Result, Unstructured};
+use {
+    crate::{
+        unstructured::{index_bounds, int_in_range_size},
+        Arbitrary, ArbitraryInRange, MaxRecursionReached, Mutate, Result, Unstructured,
+    },
+    core::ops::RangeBounds,
+};
+
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::Shrink,
+    alloc::{boxed::Box, vec::Vec},
+};
 
 impl<'a> Arbitrary<'a> for char {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
@@ -22,3 +37,62 @@ impl<'a> Arbitrary<'a> for char {
         <u32 as Arbitrary<'a>>::size_hint(depth)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for char {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&(*self as u32))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for char {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = (*self as u32).shrink().filter_map(char::from_u32).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl<'a> Mutate<'a> for char {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut c = *self as u32;
+        c.mutate(u)?;
+        // Keep the current value rather than fail when the mutation hits a
+        // surrogate or goes past the highest code point.
+        if let Some(c) = char::from_u32(c) {
+            *self = c;
+        }
+        Ok(())
+    }
+}
+
+/// Map a `char` to its code point, without the gap of the surrogates.
+fn char_index(c: &char) -> u32 {
+    match *c as u32 {
+        c @ 0..=0xD7FF => c,
+        c => c - 0x800,
+    }
+}
+
+impl<'a> ArbitraryInRange<'a> for char {
+    fn arbitrary_in_range(u: &mut Unstructured<'a>, range: impl RangeBounds<Self>) -> Result<Self> {
+        let full = 0..=char_index(&char::MAX);
+        let index = u.int_in_range(index_bounds(&range, char_index, full))?;
+        let c = match index {
+            0..=0xD7FF => index,
+            _ => index + 0x800,
+        };
+        Ok(char::from_u32(c)
+            .expect("Generated character should be valid! This is a bug in arbitrary-rs"))
+    }
+
+    #[inline]
+    fn try_size_hint_in_range(
+        _depth: usize,
+        range: impl RangeBounds<Self>,
+    ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        let full = 0..=char_index(&char::MAX);
+        let n = int_in_range_size(&index_bounds(&range, char_index, full));
+        Ok((n, Some(n)))
+    }
+}
@@ -1,8 +1,11 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
+    crate::{Arbitrary, Mutate, Result, Unstructured},
     core::iter::{empty, Empty},
 };
 
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator, Shrink};
+
 impl<'a, A> Arbitrary<'a> for Empty<A>
 where
     A: Arbitrary<'a>,
@@ -16,3 +19,15 @@ where
         (0, Some(0))
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for Empty<A> {
+    fn dearbitrary(&self, _: &mut Dearbitrator) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for Empty<A> {}
+
+impl<'a, A> Mutate<'a> for Empty<A> where A: Arbitrary<'a> {}
@@ -1,8 +1,11 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
+    crate::{Arbitrary, Mutate, Result, Unstructured},
     core::marker::{PhantomData, PhantomPinned},
 };
 
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator, Shrink};
+
 impl<'a, A> Arbitrary<'a> for PhantomData<A>
 where
     A: ?Sized,
@@ -27,3 +30,30 @@ impl<'a> Arbitrary<'a> for PhantomPinned {
         (0, Some(0))
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for PhantomData<A>
+where
+    A: ?Sized,
+{
+    fn dearbitrary(&self, _: &mut Dearbitrator) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for PhantomPinned {
+    fn dearbitrary(&self, _: &mut Dearbitrator) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for PhantomData<A> where A: ?Sized {}
+
+#[cfg(feature = "alloc")]
+impl Shrink for PhantomPinned {}
+
+impl<'a, A> Mutate<'a> for PhantomData<A> where A: ?Sized {}
+
+impl<'a> Mutate<'a> for PhantomPinned {}
@@ -13,7 +13,7 @@ mod ops;
 mod option;
 mod result;
 mod slice;
-mod str;
+pub(super) mod str;
 mod sync;
 mod time;
 mod tuple;
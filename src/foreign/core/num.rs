@@ -1,14 +1,24 @@
 use {
-    crate::{Arbitrary, Error, MaxRecursionReached, Result, Unstructured},
+    crate::{
+        unstructured::{index_bounds, int_in_range_size},
+        Arbitrary, ArbitraryInRange, Error, MaxRecursionReached, Mutate, Result, Unstructured,
+    },
     core::{
         mem,
         num::{
             NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
             NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, Wrapping,
         },
+        ops::RangeBounds,
     },
 };
 
+#[cfg(feature = "alloc")]
+use {
+    crate::{Dearbitrary, Dearbitrator, Shrink},
+    alloc::{boxed::Box, vec::Vec},
+};
+
 macro_rules! impl_arbitrary_for_integers {
     ( $( $ty:ty; )* ) => {
         $(
@@ -26,6 +36,14 @@ macro_rules! impl_arbitrary_for_integers {
                 }
 
             }
+
+            #[cfg(feature = "alloc")]
+            impl Dearbitrary for $ty {
+                fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+                    d.push_bytes(&self.to_le_bytes());
+                    Ok(())
+                }
+            }
         )*
     }
 }
@@ -57,6 +75,13 @@ impl<'a> Arbitrary<'a> for usize {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl Dearbitrary for usize {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&(*self as u64))
+    }
+}
+
 impl<'a> Arbitrary<'a> for isize {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         u.arbitrary::<i64>().map(|x| x as isize)
@@ -68,6 +93,13 @@ impl<'a> Arbitrary<'a> for isize {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl Dearbitrary for isize {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&(*self as i64))
+    }
+}
+
 macro_rules! impl_arbitrary_for_floats {
     ( $( $ty:ident : $unsigned:ty; )* ) => {
         $(
@@ -81,6 +113,13 @@ macro_rules! impl_arbitrary_for_floats {
                     <$unsigned as Arbitrary<'a>>::size_hint(depth)
                 }
             }
+
+            #[cfg(feature = "alloc")]
+            impl Dearbitrary for $ty {
+                fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+                    d.push(&self.to_bits())
+                }
+            }
         )*
     }
 }
@@ -105,6 +144,13 @@ macro_rules! implement_nonzero_int {
                 <$int as Arbitrary<'a>>::size_hint(depth)
             }
         }
+
+        #[cfg(feature = "alloc")]
+        impl Dearbitrary for $nonzero {
+            fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+                d.push(&self.get())
+            }
+        }
     };
 }
 
@@ -139,3 +185,399 @@ where
         <A as Arbitrary<'a>>::try_size_hint(depth)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for Wrapping<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.0)
+    }
+}
+
+impl<'a, A> Mutate<'a> for Wrapping<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.0.mutate(u)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for Wrapping<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(self.0.shrink().map(Wrapping))
+    }
+}
+
+#[cfg(feature = "alloc")]
+macro_rules! impl_shrink_for_integers {
+    ( $( $ty:ty; )* ) => {
+        $(
+            impl Shrink for $ty {
+                fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+                    let x = *self;
+                    // Try zero, then the absolute value of negative numbers,
+                    // then values ever closer to `x`.
+                    let mut delta = x / 2;
+                    Box::new(
+                        (x != 0)
+                            .then_some(0)
+                            .into_iter()
+                            .chain(x.checked_neg().filter(|neg| *neg > x))
+                            .chain(core::iter::from_fn(move || {
+                                if delta == 0 {
+                                    return None;
+                                }
+                                let candidate = x - delta;
+                                delta /= 2;
+                                Some(candidate)
+                            })),
+                    )
+                }
+            }
+        )*
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl_shrink_for_integers! {
+    u8;
+    u16;
+    u32;
+    u64;
+    u128;
+    usize;
+    i8;
+    i16;
+    i32;
+    i64;
+    i128;
+    isize;
+}
+
+#[cfg(feature = "alloc")]
+macro_rules! impl_shrink_for_floats {
+    ( $( $ty:ident; )* ) => {
+        $(
+            impl Shrink for $ty {
+                fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+                    // Round toward zero without `std`. Floats this large have
+                    // no fractional part anyway.
+                    fn trunc(x: $ty) -> $ty {
+                        if -1e18 < x && x < 1e18 {
+                            x as i64 as $ty
+                        } else {
+                            x
+                        }
+                    }
+
+                    let x = *self;
+                    let finite = x.is_finite();
+                    let candidates = [
+                        (x != 0.0).then_some(0.0),
+                        (x < 0.0).then_some(-x),
+                        Some(trunc(x)).filter(|t| finite && *t != x),
+                        Some(trunc(x / 2.0)).filter(|_| finite && x != 0.0),
+                    ];
+                    Box::new(candidates.into_iter().flatten())
+                }
+            }
+        )*
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl_shrink_for_floats! {
+    f32;
+    f64;
+}
+
+#[cfg(feature = "alloc")]
+macro_rules! impl_shrink_for_nonzero_integers {
+    ( $( $nonzero:ty; )* ) => {
+        $(
+            impl Shrink for $nonzero {
+                fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+                    let candidates: Vec<Self> =
+                        self.get().shrink().filter_map(Self::new).collect();
+                    Box::new(candidates.into_iter())
+                }
+            }
+        )*
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl_shrink_for_nonzero_integers! {
+    NonZeroI8;
+    NonZeroI16;
+    NonZeroI32;
+    NonZeroI64;
+    NonZeroI128;
+    NonZeroIsize;
+    NonZeroU8;
+    NonZeroU16;
+    NonZeroU32;
+    NonZeroU64;
+    NonZeroU128;
+    NonZeroUsize;
+}
+
+macro_rules! impl_mutate_for_integers {
+    ( $( $ty:ident; )* ) => {
+        $(
+            impl<'a> Mutate<'a> for $ty {
+                fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+                    // Flip a bit, jump to a boundary value, nudge the value
+                    // up or down, or generate a new one.
+                    *self = match u.choose_index(4)? {
+                        0 => *self ^ (1 << u.int_in_range(0..=$ty::BITS - 1)?),
+                        1 => *u.choose(&[0, 1, !0, $ty::MIN, $ty::MAX])?,
+                        2 => {
+                            let delta = u.int_in_range(1..=16)?;
+                            if u.arbitrary()? {
+                                self.wrapping_add(delta)
+                            } else {
+                                self.wrapping_sub(delta)
+                            }
+                        }
+                        _ => Self::arbitrary(u)?,
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_mutate_for_integers! {
+    u8;
+    u16;
+    u32;
+    u64;
+    u128;
+    usize;
+    i8;
+    i16;
+    i32;
+    i64;
+    i128;
+    isize;
+}
+
+macro_rules! impl_mutate_for_floats {
+    ( $( $ty:ident : $unsigned:ident; )* ) => {
+        $(
+            impl<'a> Mutate<'a> for $ty {
+                fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+                    *self = match u.choose_index(4)? {
+                        0 => {
+                            let bit = u.int_in_range(0..=$unsigned::BITS - 1)?;
+                            Self::from_bits(self.to_bits() ^ (1 << bit))
+                        }
+                        1 => *u.choose(&[
+                            0.0,
+                            -0.0,
+                            1.0,
+                            -1.0,
+                            $ty::MIN,
+                            $ty::MAX,
+                            $ty::MIN_POSITIVE,
+                            $ty::EPSILON,
+                            $ty::INFINITY,
+                            $ty::NEG_INFINITY,
+                            $ty::NAN,
+                        ])?,
+                        2 => -*self,
+                        _ => Self::arbitrary(u)?,
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_mutate_for_floats! {
+    f32: u32;
+    f64: u64;
+}
+
+macro_rules! impl_mutate_for_nonzero_integers {
+    ( $( $nonzero:ty; )* ) => {
+        $(
+            impl<'a> Mutate<'a> for $nonzero {
+                fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+                    let mut int = self.get();
+                    int.mutate(u)?;
+                    // Keep the current value rather than fail when the
+                    // mutation hits zero.
+                    if let Some(nonzero) = Self::new(int) {
+                        *self = nonzero;
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    }
+}
+
+impl_mutate_for_nonzero_integers! {
+    NonZeroI8;
+    NonZeroI16;
+    NonZeroI32;
+    NonZeroI64;
+    NonZeroI128;
+    NonZeroIsize;
+    NonZeroU8;
+    NonZeroU16;
+    NonZeroU32;
+    NonZeroU64;
+    NonZeroU128;
+    NonZeroUsize;
+}
+
+macro_rules! impl_arbitrary_in_range_for_integers {
+    ( $( $ty:ty; )* ) => {
+        $(
+            impl<'a> ArbitraryInRange<'a> for $ty {
+                fn arbitrary_in_range(
+                    u: &mut Unstructured<'a>,
+                    range: impl RangeBounds<Self>,
+                ) -> Result<Self> {
+                    u.int_in_range(index_bounds(&range, |x| *x, Self::MIN..=Self::MAX))
+                }
+
+                #[inline]
+                fn try_size_hint_in_range(
+                    _depth: usize,
+                    range: impl RangeBounds<Self>,
+                ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+                    let n = int_in_range_size(&index_bounds(&range, |x| *x, Self::MIN..=Self::MAX));
+                    Ok((n, Some(n)))
+                }
+            }
+        )*
+    }
+}
+
+impl_arbitrary_in_range_for_integers! {
+    u8;
+    u16;
+    u32;
+    u64;
+    u128;
+    usize;
+    i8;
+    i16;
+    i32;
+    i64;
+    i128;
+    isize;
+}
+
+macro_rules! impl_arbitrary_in_range_for_floats {
+    ( $( $ty:ident : $unsigned:ident, $key:ident, $from_key:ident; )* ) => {
+        $(
+            /// Map a float to an integer, such that the integers are in the
+            /// same order as the floats, and NaNs are outside of the
+            /// infinities.
+            fn $key(x: &$ty) -> $unsigned {
+                assert!(
+                    !x.is_nan(),
+                    "`arbitrary::ArbitraryInRange::arbitrary_in_range` requires bounds that are not NaN"
+                );
+                let bits = x.to_bits();
+                let sign = 1 << ($unsigned::BITS - 1);
+                if bits & sign == 0 {
+                    bits | sign
+                } else {
+                    !bits
+                }
+            }
+
+            fn $from_key(key: $unsigned) -> $ty {
+                let sign = 1 << ($unsigned::BITS - 1);
+                $ty::from_bits(if key & sign == 0 { !key } else { key & !sign })
+            }
+
+            impl<'a> ArbitraryInRange<'a> for $ty {
+                fn arbitrary_in_range(
+                    u: &mut Unstructured<'a>,
+                    range: impl RangeBounds<Self>,
+                ) -> Result<Self> {
+                    let full = $key(&$ty::NEG_INFINITY)..=$key(&$ty::INFINITY);
+                    u.int_in_range(index_bounds(&range, $key, full)).map($from_key)
+                }
+
+                #[inline]
+                fn try_size_hint_in_range(
+                    _depth: usize,
+                    range: impl RangeBounds<Self>,
+                ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+                    let full = $key(&$ty::NEG_INFINITY)..=$key(&$ty::INFINITY);
+                    let n = int_in_range_size(&index_bounds(&range, $key, full));
+                    Ok((n, Some(n)))
+                }
+            }
+        )*
+    }
+}
+
+impl_arbitrary_in_range_for_floats! {
+    f32: u32, f32_key, f32_from_key;
+    f64: u64, f64_key, f64_from_key;
+}
+
+macro_rules! impl_arbitrary_in_range_for_nonzero_integers {
+    ( $( $nonzero:ty: $int:ty; )* ) => {
+        $(
+            impl<'a> ArbitraryInRange<'a> for $nonzero {
+                fn arbitrary_in_range(
+                    u: &mut Unstructured<'a>,
+                    range: impl RangeBounds<Self>,
+                ) -> Result<Self> {
+                    // Skip zero by shifting the positive values down by one.
+                    let index = |x: &Self| if x.get() > 0 { x.get() - 1 } else { x.get() };
+                    let index = u.int_in_range(index_bounds(&range, index, <$int>::MIN..=<$int>::MAX - 1))?;
+                    let value = match index.checked_add(1) {
+                        Some(value) if value > 0 => value,
+                        _ => index,
+                    };
+                    Ok(Self::new(value).expect("Skipped zero! This is a bug in arbitrary-rs"))
+                }
+
+                #[inline]
+                fn try_size_hint_in_range(
+                    _depth: usize,
+                    range: impl RangeBounds<Self>,
+                ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+                    let index = |x: &Self| if x.get() > 0 { x.get() - 1 } else { x.get() };
+                    let n = int_in_range_size(&index_bounds(&range, index, <$int>::MIN..=<$int>::MAX - 1));
+                    Ok((n, Some(n)))
+                }
+            }
+        )*
+    }
+}
+
+impl_arbitrary_in_range_for_nonzero_integers! {
+    NonZeroI8: i8;
+    NonZeroI16: i16;
+    NonZeroI32: i32;
+    NonZeroI64: i64;
+    NonZeroI128: i128;
+    NonZeroIsize: isize;
+    NonZeroU8: u8;
+    NonZeroU16: u16;
+    NonZeroU32: u32;
+    NonZeroU64: u64;
+    NonZeroU128: u128;
+    NonZeroUsize: usize;
+}
@@ -1,11 +1,17 @@
 use {
-    crate::{size_hint, Arbitrary, MaxRecursionReached, Result, Unstructured},
+    crate::{size_hint, Arbitrary, MaxRecursionReached, Mutate, Result, Unstructured},
     core::{
         mem,
         ops::{Bound, Range, RangeBounds, RangeFrom, RangeInclusive, RangeTo, RangeToInclusive},
     },
 };
 
+#[cfg(feature = "alloc")]
+use {
+    crate::{Dearbitrary, Dearbitrator, Error, Shrink},
+    alloc::boxed::Box,
+};
+
 macro_rules! impl_range {
     (
         $range:ty,
@@ -78,6 +84,65 @@ impl_range!(
     |depth| <A as Arbitrary>::try_size_hint(depth)
 );
 
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for Range<A>
+where
+    A: Dearbitrary + PartialOrd,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        // `Arbitrary` swaps reversed bounds, so it never produces them.
+        if self.start > self.end {
+            return Err(Error::Unrepresentable);
+        }
+        d.push(&self.start)?;
+        d.push(&self.end)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for RangeInclusive<A>
+where
+    A: Dearbitrary + PartialOrd,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        if self.start() > self.end() {
+            return Err(Error::Unrepresentable);
+        }
+        d.push(self.start())?;
+        d.push(self.end())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for RangeFrom<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.start)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for RangeTo<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.end)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for RangeToInclusive<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.end)
+    }
+}
+
 pub(crate) fn bounded_range<CB, I, R>(bounds: (I, I), cb: CB) -> R
 where
     CB: Fn((I, I)) -> R,
@@ -125,3 +190,176 @@ where
         ))
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for Bound<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        match self {
+            Bound::Included(value) => {
+                d.push_int_in_range::<u8>(0..=2, 0)?;
+                d.push(value)
+            }
+            Bound::Excluded(value) => {
+                d.push_int_in_range::<u8>(0..=2, 1)?;
+                d.push(value)
+            }
+            Bound::Unbounded => d.push_int_in_range::<u8>(0..=2, 2),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for Range<A>
+where
+    A: Shrink + Clone + PartialOrd,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let Range { start, end } = self;
+        Box::new(
+            start
+                .shrink()
+                .map(move |start| start..end.clone())
+                .chain(end.shrink().map(move |end| start.clone()..end))
+                .filter(|range| range.start <= range.end),
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for RangeInclusive<A>
+where
+    A: Shrink + Clone + PartialOrd,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let (start, end) = (self.start(), self.end());
+        Box::new(
+            start
+                .shrink()
+                .map(move |start| start..=end.clone())
+                .chain(end.shrink().map(move |end| start.clone()..=end))
+                .filter(|range| range.start() <= range.end()),
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for RangeFrom<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(self.start.shrink().map(|start| start..))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for RangeTo<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(self.end.shrink().map(|end| ..end))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for RangeToInclusive<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(self.end.shrink().map(|end| ..=end))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for Bound<A>
+where
+    A: Shrink + Clone,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        match self {
+            Bound::Included(value) => Box::new(value.shrink().map(Bound::Included)),
+            Bound::Excluded(value) => Box::new(
+                core::iter::once(Bound::Included(value.clone()))
+                    .chain(value.shrink().map(Bound::Excluded)),
+            ),
+            Bound::Unbounded => Box::new(core::iter::empty()),
+        }
+    }
+}
+
+impl<'a, A> Mutate<'a> for Range<A>
+where
+    A: Mutate<'a> + Clone + PartialOrd,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        if u.arbitrary()? {
+            self.start.mutate(u)?;
+        } else {
+            self.end.mutate(u)?;
+        }
+        // Keep the bounds in order, like `Arbitrary` does.
+        if self.start > self.end {
+            mem::swap(&mut self.start, &mut self.end);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, A> Mutate<'a> for RangeInclusive<A>
+where
+    A: Mutate<'a> + Clone + PartialOrd,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut range = self.start().clone()..self.end().clone();
+        range.mutate(u)?;
+        *self = range.start..=range.end;
+        Ok(())
+    }
+}
+
+impl<'a, A> Mutate<'a> for RangeFrom<A>
+where
+    A: Mutate<'a> + Clone + PartialOrd,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.start.mutate(u)
+    }
+}
+
+impl<'a, A> Mutate<'a> for RangeTo<A>
+where
+    A: Mutate<'a> + Clone + PartialOrd,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.end.mutate(u)
+    }
+}
+
+impl<'a, A> Mutate<'a> for RangeToInclusive<A>
+where
+    A: Mutate<'a> + Clone + PartialOrd,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.end.mutate(u)
+    }
+}
+
+impl<'a, A> Mutate<'a> for Bound<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        match self {
+            Bound::Included(value) | Bound::Excluded(value) if u.ratio(3, 4)? => value.mutate(u),
+            _ => {
+                *self = Self::arbitrary(u)?;
+                Ok(())
+            }
+        }
+    }
+}
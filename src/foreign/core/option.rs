@@ -1,15 +1,26 @@
-use crate::{size_hint, Arbitrary, MaxRecursionReached, Result, Unstructured};
+use crate::{
+    size_hint, Arbitrary, ArbitraryWith, Error, MaxRecursionReached, Mutate, Result, Unstructured,
+};
+
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {crate::Shrink, alloc::boxed::Box};
 
 impl<'a, A> Arbitrary<'a> for Option<A>
 where
     A: Arbitrary<'a>,
 {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        Ok(if <bool as Arbitrary<'a>>::arbitrary(u)? {
-            Some(Arbitrary::arbitrary(u)?)
-        } else {
-            None
-        })
+        if !<bool as Arbitrary<'a>>::arbitrary(u)? {
+            return Ok(None);
+        }
+        // A value that would nest too deeply falls back to `None`.
+        match Arbitrary::arbitrary(u) {
+            Err(Error::MaxDepthReached) => Ok(None),
+            value => value.map(Some),
+        }
     }
 
     #[inline]
@@ -25,3 +36,78 @@ where
         ))
     }
 }
+
+impl<'a, A, Ctx> ArbitraryWith<'a, Ctx> for Option<A>
+where
+    A: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        if !<bool as Arbitrary<'a>>::arbitrary(u)? {
+            return Ok(None);
+        }
+        match A::arbitrary_with(u, ctx) {
+            Err(Error::MaxDepthReached) => Ok(None),
+            value => value.map(Some),
+        }
+    }
+
+    #[inline]
+    fn size_hint_with(depth: usize) -> (usize, Option<usize>) {
+        Self::try_size_hint_with(depth).unwrap_or_default()
+    }
+
+    #[inline]
+    fn try_size_hint_with(depth: usize) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        Ok(size_hint::and(
+            <bool as Arbitrary>::try_size_hint(depth)?,
+            size_hint::or((0, Some(0)), A::try_size_hint_with(depth)?),
+        ))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Dearbitrary for Option<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.is_some())?;
+        match self {
+            Some(value) => d.push(value),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A> Shrink for Option<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        match self {
+            Some(value) => Box::new(core::iter::once(None).chain(value.shrink().map(Some))),
+            None => Box::new(core::iter::empty()),
+        }
+    }
+}
+
+impl<'a, A> Mutate<'a> for Option<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        match self {
+            Some(value) if u.ratio(3, 4)? => value.mutate(u),
+            Some(_) => {
+                *self = None;
+                Ok(())
+            }
+            None => {
+                *self = Some(A::arbitrary(u)?);
+                Ok(())
+            }
+        }
+    }
+}
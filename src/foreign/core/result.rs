@@ -1,4 +1,10 @@
-use crate::{size_hint, Arbitrary, Error, MaxRecursionReached, Unstructured};
+use crate::{size_hint, Arbitrary, Error, MaxRecursionReached, Mutate, Unstructured};
+
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {crate::Shrink, alloc::boxed::Box};
 
 impl<'a, T, E> Arbitrary<'a> for Result<T, E>
 where
@@ -29,3 +35,53 @@ where
         ))
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<T, E> Dearbitrary for Result<T, E>
+where
+    T: Dearbitrary,
+    E: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<(), Error> {
+        d.push(&self.is_ok())?;
+        match self {
+            Ok(value) => d.push(value),
+            Err(error) => d.push(error),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, E> Shrink for Result<T, E>
+where
+    T: Shrink,
+    E: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        match self {
+            Ok(value) => Box::new(value.shrink().map(Ok)),
+            Err(error) => Box::new(error.shrink().map(Err)),
+        }
+    }
+}
+
+impl<'a, T, E> Mutate<'a> for Result<T, E>
+where
+    T: Mutate<'a>,
+    E: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<(), Error> {
+        match self {
+            Ok(value) if u.ratio(3, 4)? => value.mutate(u),
+            Err(error) if u.ratio(3, 4)? => error.mutate(u),
+            Ok(_) => {
+                *self = Err(E::arbitrary(u)?);
+                Ok(())
+            }
+            Err(_) => {
+                *self = Ok(T::arbitrary(u)?);
+                Ok(())
+            }
+        }
+    }
+}
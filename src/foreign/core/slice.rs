@@ -1,4 +1,10 @@
-use crate::{Arbitrary, Result, Unstructured};
+use crate::{Arbitrary, Mutate, Result, Unstructured};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::{Dearbitrary, Dearbitrator, Shrink},
+    alloc::boxed::Box,
+};
 
 impl<'a> Arbitrary<'a> for &'a [u8] {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
@@ -15,3 +21,33 @@ impl<'a> Arbitrary<'a> for &'a [u8] {
         (0, None)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for &[u8] {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_len::<u8>(self.len())?;
+        d.push_bytes(self);
+        Ok(())
+    }
+}
+
+/// The lengths to cut off either end of a borrowed sequence of length `len`,
+/// from all of it down to one element.
+#[cfg(feature = "alloc")]
+pub(crate) fn cut_lengths(len: usize) -> impl Iterator<Item = usize> {
+    core::iter::successors(Some(len), |n| Some(n / 2)).take_while(|n| *n > 0)
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for &[u8] {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        // A borrowed slice can only shrink to its subslices.
+        let bytes = *self;
+        Box::new(cut_lengths(bytes.len()).flat_map(move |n| {
+            let (front, back) = (&bytes[n..], &bytes[..bytes.len() - n]);
+            core::iter::once(front).chain((n < bytes.len()).then_some(back))
+        }))
+    }
+}
+
+impl<'a> Mutate<'a> for &'a [u8] {}
@@ -1,10 +1,18 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
+    crate::{Arbitrary, Error, Mutate, Result, Unstructured},
     core::str,
 };
 
-fn arbitrary_str<'a>(u: &mut Unstructured<'a>, size: usize) -> Result<&'a str> {
-    match str::from_utf8(u.peek_bytes(size).unwrap()) {
+#[cfg(feature = "alloc")]
+use {
+    super::slice::cut_lengths,
+    crate::{Dearbitrary, Dearbitrator, Shrink},
+    alloc::boxed::Box,
+};
+
+pub(crate) fn arbitrary_str<'a>(u: &mut Unstructured<'a>, size: usize) -> Result<&'a str> {
+    let bytes = u.peek_bytes(size).ok_or(Error::NotEnoughData)?;
+    match str::from_utf8(bytes) {
         Ok(s) => {
             u.bytes(size).unwrap();
             Ok(s)
@@ -27,9 +35,16 @@ impl<'a> Arbitrary<'a> for &'a str {
         arbitrary_str(u, size)
     }
 
-    fn arbitrary_take_rest(mut u: Unstructured<'a>) -> Result<Self> {
-        let size = u.len();
-        arbitrary_str(&mut u, size)
+    fn arbitrary_take_rest(u: Unstructured<'a>) -> Result<Self> {
+        let bytes = u.take_rest();
+        let valid = match str::from_utf8(bytes) {
+            Ok(s) => return Ok(s),
+            Err(e) => &bytes[..e.valid_up_to()],
+        };
+        Ok(unsafe {
+            debug_assert!(str::from_utf8(valid).is_ok());
+            str::from_utf8_unchecked(valid)
+        })
     }
 
     #[inline]
@@ -37,3 +52,25 @@ impl<'a> Arbitrary<'a> for &'a str {
         (0, None)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for &str {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.as_bytes())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for &str {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        // A borrowed string can only shrink to its substrings.
+        let s = *self;
+        Box::new(cut_lengths(s.len()).flat_map(move |n| {
+            let front = s.get(n..);
+            let back = s.get(..s.len() - n).filter(|_| n < s.len());
+            front.into_iter().chain(back)
+        }))
+    }
+}
+
+impl<'a> Mutate<'a> for &'a str {}
@@ -1,8 +1,15 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
+    crate::{Arbitrary, Mutate, Result, Unstructured},
     core::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize},
 };
 
+#[cfg(feature = "alloc")]
+use {
+    crate::{Dearbitrary, Dearbitrator, Shrink},
+    alloc::{boxed::Box, vec::Vec},
+    core::sync::atomic::Ordering,
+};
+
 impl<'a> Arbitrary<'a> for AtomicBool {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         Arbitrary::arbitrary(u).map(Self::new)
@@ -35,3 +42,78 @@ impl<'a> Arbitrary<'a> for AtomicUsize {
         <usize as Arbitrary<'a>>::size_hint(depth)
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for AtomicBool {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for AtomicIsize {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for AtomicUsize {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for AtomicBool {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = self
+            .load(Ordering::SeqCst)
+            .shrink()
+            .map(Self::new)
+            .collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for AtomicIsize {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = self
+            .load(Ordering::SeqCst)
+            .shrink()
+            .map(Self::new)
+            .collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for AtomicUsize {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = self
+            .load(Ordering::SeqCst)
+            .shrink()
+            .map(Self::new)
+            .collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl<'a> Mutate<'a> for AtomicBool {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut().mutate(u)
+    }
+}
+
+impl<'a> Mutate<'a> for AtomicIsize {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut().mutate(u)
+    }
+}
+
+impl<'a> Mutate<'a> for AtomicUsize {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut().mutate(u)
+    }
+}
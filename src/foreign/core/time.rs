@@ -1,6 +1,19 @@
 use {
-    crate::{size_hint, Arbitrary, Result, Unstructured},
-    core::time::Duration,
+    crate::{
+        size_hint,
+        unstructured::{index_bounds, int_in_range_size},
+        Arbitrary, ArbitraryInRange, MaxRecursionReached, Mutate, Result, Unstructured,
+    },
+    core::{ops::RangeBounds, time::Duration},
+};
+
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::Shrink,
+    alloc::{boxed::Box, vec::Vec},
 };
 
 impl<'a> Arbitrary<'a> for Duration {
@@ -19,3 +32,47 @@ impl<'a> Arbitrary<'a> for Duration {
         )
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for Duration {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.as_secs())?;
+        d.push_int_in_range(0..=999_999_999, self.subsec_nanos())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for Duration {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let (secs, nanos) = (self.as_secs(), self.subsec_nanos());
+        let candidates: Vec<Self> = secs
+            .shrink()
+            .map(|secs| Duration::new(secs, nanos))
+            .chain(nanos.shrink().map(|nanos| Duration::new(secs, nanos)))
+            .collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl<'a> Mutate<'a> for Duration {}
+
+impl<'a> ArbitraryInRange<'a> for Duration {
+    fn arbitrary_in_range(u: &mut Unstructured<'a>, range: impl RangeBounds<Self>) -> Result<Self> {
+        let full = 0..=Duration::MAX.as_nanos();
+        let nanos = u.int_in_range(index_bounds(&range, Duration::as_nanos, full))?;
+        Ok(Self::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        ))
+    }
+
+    #[inline]
+    fn try_size_hint_in_range(
+        _depth: usize,
+        range: impl RangeBounds<Self>,
+    ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        let full = 0..=Duration::MAX.as_nanos();
+        let n = int_in_range_size(&index_bounds(&range, Duration::as_nanos, full));
+        Ok((n, Some(n)))
+    }
+}
@@ -1,4 +1,42 @@
-use crate::{size_hint, Arbitrary, MaxRecursionReached, Result, Unstructured};
+use crate::{
+    size_hint, Arbitrary, ArbitraryWith, MaxRecursionReached, Mutate, Result, Unstructured,
+};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::{Dearbitrary, Dearbitrator, Shrink},
+    alloc::boxed::Box,
+};
+
+/// Chain the candidates for shrinking each element of a tuple in turn onto
+/// `$candidates`, keeping the other elements.
+#[cfg(feature = "alloc")]
+macro_rules! shrink_tuple_elements {
+    ($tuple:ident $candidates:ident [$($done:ident)*]) => {};
+    ($tuple:ident $candidates:ident [$($done:ident)*] $current:ident $($rest:ident)*) => {
+        let $candidates = $candidates.chain({
+            let ($($done,)* $current, $($rest,)*) = $tuple;
+            $current
+                .shrink()
+                .map(move |$current| ($($done.clone(),)* $current, $($rest.clone(),)*))
+        });
+        shrink_tuple_elements!($tuple $candidates [$($done)* $current] $($rest)*);
+    };
+}
+
+/// Mutate the element of a tuple at `$index`, given bindings to all elements.
+macro_rules! mutate_tuple_element {
+    ($u:ident $index:ident $i:expr; $last:ident) => {
+        $last.mutate($u)
+    };
+    ($u:ident $index:ident $i:expr; $current:ident $($rest:ident)+) => {
+        if $index == $i {
+            $current.mutate($u)
+        } else {
+            mutate_tuple_element!($u $index $i + 1; $($rest)+)
+        }
+    };
+}
 
 macro_rules! arbitrary_tuple {
     () => {};
@@ -33,6 +71,79 @@ macro_rules! arbitrary_tuple {
                 ]))
             }
         }
+
+        impl<'a, Ctx, $($xs,)* $last> ArbitraryWith<'a, Ctx> for ($($xs,)* $last,)
+        where
+            Ctx: ?Sized,
+            $($xs: ArbitraryWith<'a, Ctx>,)*
+            $last: ArbitraryWith<'a, Ctx>,
+        {
+            fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+                Ok(($($xs::arbitrary_with(u, ctx)?,)* $last::arbitrary_with(u, ctx)?,))
+            }
+
+            #[allow(unused_mut, non_snake_case)]
+            fn arbitrary_take_rest_with(mut u: Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+                $(let $xs = $xs::arbitrary_with(&mut u, ctx)?;)*
+                let $last = $last::arbitrary_take_rest_with(u, ctx)?;
+                Ok(($($xs,)* $last,))
+            }
+
+            #[inline]
+            fn size_hint_with(depth: usize) -> (usize, Option<usize>) {
+                Self::try_size_hint_with(depth).unwrap_or_default()
+            }
+            #[inline]
+            fn try_size_hint_with(depth: usize) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+                Ok(size_hint::and_all(&[
+                    $last::try_size_hint_with(depth)?,
+                    $( $xs::try_size_hint_with(depth)?),*
+                ]))
+            }
+        }
+
+        impl<'a, $($xs,)* $last> Mutate<'a> for ($($xs,)* $last,)
+        where
+            $($xs: Mutate<'a>,)*
+            $last: Mutate<'a>,
+        {
+            // One-element tuples don't look at the index.
+            #[allow(non_snake_case, unused_variables)]
+            fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+                let ($($xs,)* $last,) = self;
+                let index = u.choose_index([$(stringify!($xs),)* stringify!($last)].len())?;
+                mutate_tuple_element!(u index 0; $($xs)* $last)
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<$($xs,)* $last> Dearbitrary for ($($xs,)* $last,)
+        where
+            $($xs: Dearbitrary,)*
+            $last: Dearbitrary,
+        {
+            #[allow(non_snake_case)]
+            fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+                let ($($xs,)* $last,) = self;
+                $(d.push($xs)?;)*
+                d.push($last)
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl<$($xs,)* $last> Shrink for ($($xs,)* $last,)
+        where
+            $($xs: Shrink + Clone,)*
+            $last: Shrink + Clone,
+        {
+            #[allow(non_snake_case)]
+            fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+                let tuple = self;
+                let candidates = core::iter::empty();
+                shrink_tuple_elements!(tuple candidates [] $($xs)* $last);
+                Box::new(candidates)
+            }
+        }
     };
 }
 arbitrary_tuple!(A B C D E F G H I J K L M N O P Q R S T U V W X Y Z);
@@ -1,4 +1,7 @@
-use crate::{Arbitrary, Result, Unstructured};
+use crate::{Arbitrary, Mutate, Result, Unstructured};
+
+#[cfg(feature = "alloc")]
+use crate::{Dearbitrary, Dearbitrator, Shrink};
 
 impl<'a> Arbitrary<'a> for () {
     fn arbitrary(_: &mut Unstructured<'a>) -> Result<Self> {
@@ -10,3 +13,15 @@ impl<'a> Arbitrary<'a> for () {
         (0, Some(0))
     }
 }
+
+#[cfg(feature = "alloc")]
+impl Dearbitrary for () {
+    fn dearbitrary(&self, _: &mut Dearbitrator) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Shrink for () {}
+
+impl<'a> Mutate<'a> for () {}
@@ -1,6 +1,10 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
+    crate::{
+        foreign::alloc::vec::mutate_as_vec, shrink::ShrinkVec, Arbitrary, ArbitraryLen,
+        ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
     std::{
+        boxed::Box,
         collections::hash_map::HashMap,
         hash::{BuildHasher, Hash},
     },
@@ -25,3 +29,61 @@ where
         (0, None)
     }
 }
+
+impl<'a, K, V, S> ArbitraryLen<'a> for HashMap<K, V, S>
+where
+    K: Arbitrary<'a> + Eq + Hash,
+    V: Arbitrary<'a>,
+    S: BuildHasher + Default,
+{
+    type Element = (K, V);
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements(len)
+    }
+}
+
+impl<'a, K, V, S, Ctx> ArbitraryWith<'a, Ctx> for HashMap<K, V, S>
+where
+    K: ArbitraryWith<'a, Ctx> + Eq + Hash,
+    V: ArbitraryWith<'a, Ctx>,
+    S: BuildHasher + Default,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        u.arbitrary_iter_with(ctx)?.collect()
+    }
+}
+
+impl<K, V, S> Dearbitrary for HashMap<K, V, S>
+where
+    K: Dearbitrary,
+    V: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self)
+    }
+}
+
+impl<K, V, S> Shrink for HashMap<K, V, S>
+where
+    K: Shrink + Clone + Eq + Hash,
+    V: Shrink + Clone,
+    S: BuildHasher + Default,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let entries = self.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        Box::new(ShrinkVec::new(entries).map(|entries| entries.into_iter().collect()))
+    }
+}
+
+impl<'a, K, V, S> Mutate<'a> for HashMap<K, V, S>
+where
+    K: Mutate<'a> + Eq + Hash,
+    V: Mutate<'a>,
+    S: BuildHasher + Default,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        mutate_as_vec(self, u)
+    }
+}
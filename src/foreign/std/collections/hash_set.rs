@@ -1,6 +1,10 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
+    crate::{
+        foreign::alloc::vec::mutate_as_vec, shrink::ShrinkVec, Arbitrary, ArbitraryLen,
+        ArbitraryWith, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
     std::{
+        boxed::Box,
         collections::hash_set::HashSet,
         hash::{BuildHasher, Hash},
     },
@@ -24,3 +28,57 @@ where
         (0, None)
     }
 }
+
+impl<'a, A, S> ArbitraryLen<'a> for HashSet<A, S>
+where
+    A: Arbitrary<'a> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    type Element = A;
+
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self> {
+        u.arbitrary_elements(len)
+    }
+}
+
+impl<'a, A, S, Ctx> ArbitraryWith<'a, Ctx> for HashSet<A, S>
+where
+    A: ArbitraryWith<'a, Ctx> + Eq + Hash,
+    S: BuildHasher + Default,
+    Ctx: ?Sized,
+{
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        u.arbitrary_iter_with(ctx)?.collect()
+    }
+}
+
+impl<A, S> Dearbitrary for HashSet<A, S>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push_iter(self)
+    }
+}
+
+impl<A, S> Shrink for HashSet<A, S>
+where
+    A: Shrink + Clone + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        Box::new(
+            ShrinkVec::new(self.iter().cloned().collect()).map(|items| items.into_iter().collect()),
+        )
+    }
+}
+
+impl<'a, A, S> Mutate<'a> for HashSet<A, S>
+where
+    A: Mutate<'a> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        mutate_as_vec(self, u)
+    }
+}
@@ -1,6 +1,9 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::ffi::OsString,
+    crate::{
+        shrink::shrink_str, Arbitrary, Dearbitrary, Dearbitrator, Error, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    std::{boxed::Box, ffi::OsString},
 };
 
 impl<'a> Arbitrary<'a> for OsString {
@@ -14,9 +17,28 @@ impl<'a> Arbitrary<'a> for OsString {
     }
 }
 
+impl Dearbitrary for OsString {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        // `Arbitrary` only generates valid UTF-8.
+        d.push(&self.to_str().ok_or(Error::Unrepresentable)?)
+    }
+}
+
 // impl Arbitrary for Box<OsStr> {
 //     fn arbitrary(u: &mut Unstructured<'_>) -> Result<Self> {
 //         <OsString as Arbitrary>::arbitrary(u).map(|x| x.into_boxed_osstr())
 //
 //     }
 // }
+
+impl Shrink for OsString {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        // `Arbitrary` only generates valid UTF-8.
+        match self.to_str() {
+            Some(s) => Box::new(shrink_str(s).map(OsString::from)),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+impl<'a> Mutate<'a> for OsString {}
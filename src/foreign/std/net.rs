@@ -1,6 +1,12 @@
 use {
-    crate::{size_hint, Arbitrary, Result, Unstructured},
-    std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    crate::{
+        size_hint, Arbitrary, Dearbitrary, Dearbitrator, Mutate, Result, Shrink, Unstructured,
+    },
+    std::{
+        boxed::Box,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+        vec::Vec,
+    },
 };
 
 impl<'a> Arbitrary<'a> for Ipv4Addr {
@@ -94,3 +100,214 @@ impl<'a> Arbitrary<'a> for SocketAddr {
         )
     }
 }
+
+impl Dearbitrary for Ipv4Addr {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&u32::from(*self))
+    }
+}
+
+impl Dearbitrary for Ipv6Addr {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&u128::from(*self))
+    }
+}
+
+impl Dearbitrary for IpAddr {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        match self {
+            IpAddr::V4(addr) => {
+                d.push(&true)?;
+                d.push(addr)
+            }
+            IpAddr::V6(addr) => {
+                d.push(&false)?;
+                d.push(addr)
+            }
+        }
+    }
+}
+
+impl Dearbitrary for SocketAddrV4 {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(self.ip())?;
+        d.push(&self.port())
+    }
+}
+
+impl Dearbitrary for SocketAddrV6 {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(self.ip())?;
+        d.push(&self.port())?;
+        d.push(&self.flowinfo())?;
+        d.push(&self.scope_id())
+    }
+}
+
+impl Dearbitrary for SocketAddr {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        match self {
+            SocketAddr::V4(addr) => {
+                d.push(&true)?;
+                d.push(addr)
+            }
+            SocketAddr::V6(addr) => {
+                d.push(&false)?;
+                d.push(addr)
+            }
+        }
+    }
+}
+
+impl Shrink for Ipv4Addr {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = u32::from(*self).shrink().map(Ipv4Addr::from).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl Shrink for Ipv6Addr {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let candidates: Vec<Self> = u128::from(*self).shrink().map(Ipv6Addr::from).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl Shrink for IpAddr {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        match self {
+            IpAddr::V4(ip) => Box::new(ip.shrink().map(IpAddr::V4)),
+            IpAddr::V6(ip) => Box::new(
+                std::iter::once(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+                    .chain(ip.shrink().map(IpAddr::V6)),
+            ),
+        }
+    }
+}
+
+impl Shrink for SocketAddrV4 {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let (ip, port) = (*self.ip(), self.port());
+        let candidates: Vec<Self> = ip
+            .shrink()
+            .map(|ip| SocketAddrV4::new(ip, port))
+            .chain(port.shrink().map(|port| SocketAddrV4::new(ip, port)))
+            .collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl Shrink for SocketAddrV6 {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let (ip, port) = (*self.ip(), self.port());
+        let (flowinfo, scope_id) = (self.flowinfo(), self.scope_id());
+        let candidates: Vec<Self> = ip
+            .shrink()
+            .map(|ip| SocketAddrV6::new(ip, port, flowinfo, scope_id))
+            .chain(
+                port.shrink()
+                    .map(|port| SocketAddrV6::new(ip, port, flowinfo, scope_id)),
+            )
+            .chain(
+                flowinfo
+                    .shrink()
+                    .map(|flowinfo| SocketAddrV6::new(ip, port, flowinfo, scope_id)),
+            )
+            .chain(
+                scope_id
+                    .shrink()
+                    .map(|scope_id| SocketAddrV6::new(ip, port, flowinfo, scope_id)),
+            )
+            .collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl Shrink for SocketAddr {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        match self {
+            SocketAddr::V4(addr) => Box::new(addr.shrink().map(SocketAddr::V4)),
+            SocketAddr::V6(addr) => Box::new(
+                std::iter::once(SocketAddr::V4(SocketAddrV4::new(
+                    Ipv4Addr::UNSPECIFIED,
+                    addr.port(),
+                )))
+                .chain(addr.shrink().map(SocketAddr::V6)),
+            ),
+        }
+    }
+}
+
+impl<'a> Mutate<'a> for Ipv4Addr {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut bits = u32::from(*self);
+        bits.mutate(u)?;
+        *self = Ipv4Addr::from(bits);
+        Ok(())
+    }
+}
+
+impl<'a> Mutate<'a> for Ipv6Addr {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        let mut bits = u128::from(*self);
+        bits.mutate(u)?;
+        *self = Ipv6Addr::from(bits);
+        Ok(())
+    }
+}
+
+impl<'a> Mutate<'a> for IpAddr {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        match self {
+            IpAddr::V4(ip) if u.ratio(3, 4)? => ip.mutate(u),
+            IpAddr::V6(ip) if u.ratio(3, 4)? => ip.mutate(u),
+            _ => {
+                *self = Self::arbitrary(u)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a> Mutate<'a> for SocketAddrV4 {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        if u.arbitrary()? {
+            let mut ip = *self.ip();
+            ip.mutate(u)?;
+            self.set_ip(ip);
+        } else {
+            let mut port = self.port();
+            port.mutate(u)?;
+            self.set_port(port);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Mutate<'a> for SocketAddrV6 {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        if u.arbitrary()? {
+            let mut ip = *self.ip();
+            ip.mutate(u)?;
+            self.set_ip(ip);
+        } else {
+            let mut port = self.port();
+            port.mutate(u)?;
+            self.set_port(port);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Mutate<'a> for SocketAddr {
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        match self {
+            SocketAddr::V4(addr) if u.ratio(3, 4)? => addr.mutate(u),
+            SocketAddr::V6(addr) if u.ratio(3, 4)? => addr.mutate(u),
+            _ => {
+                *self = Self::arbitrary(u)?;
+                Ok(())
+            }
+        }
+    }
+}
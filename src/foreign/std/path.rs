@@ -1,6 +1,9 @@
 use {
-    crate::{Arbitrary, Result, Unstructured},
-    std::{ffi::OsString, path::PathBuf},
+    crate::{
+        shrink::shrink_str, Arbitrary, Dearbitrary, Dearbitrator, Error, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    std::{boxed::Box, ffi::OsString, path::PathBuf},
 };
 
 impl<'a> Arbitrary<'a> for PathBuf {
@@ -13,3 +16,21 @@ impl<'a> Arbitrary<'a> for PathBuf {
         <OsString as Arbitrary>::size_hint(depth)
     }
 }
+
+impl Dearbitrary for PathBuf {
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        d.push(&self.to_str().ok_or(Error::Unrepresentable)?)
+    }
+}
+
+impl Shrink for PathBuf {
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        // `Arbitrary` only generates valid UTF-8.
+        match self.to_str() {
+            Some(s) => Box::new(shrink_str(s).map(PathBuf::from)),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+impl<'a> Mutate<'a> for PathBuf {}
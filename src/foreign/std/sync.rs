@@ -1,6 +1,13 @@
 use {
-    crate::{Arbitrary, MaxRecursionReached, Result, Unstructured},
-    std::sync::Mutex,
+    crate::{
+        Arbitrary, Dearbitrary, Dearbitrator, MaxRecursionReached, Mutate, Result, Shrink,
+        Unstructured,
+    },
+    std::{
+        boxed::Box,
+        sync::{Mutex, PoisonError},
+        vec::Vec,
+    },
 };
 
 impl<'a, A> Arbitrary<'a> for Mutex<A>
@@ -21,3 +28,35 @@ where
         A::try_size_hint(depth)
     }
 }
+
+impl<A> Dearbitrary for Mutex<A>
+where
+    A: Dearbitrary,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        let guard = self.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        d.push(&*guard)
+    }
+}
+
+impl<A> Shrink for Mutex<A>
+where
+    A: Shrink,
+{
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+        let value = self.lock().unwrap_or_else(PoisonError::into_inner);
+        let candidates: Vec<Self> = value.shrink().map(Mutex::new).collect();
+        Box::new(candidates.into_iter())
+    }
+}
+
+impl<'a, A> Mutate<'a> for Mutex<A>
+where
+    A: Mutate<'a>,
+{
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        self.get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .mutate(u)
+    }
+}
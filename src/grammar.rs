@@ -0,0 +1,962 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generating sentences of a context-free grammar.
+//!
+//! A [`Grammar`] is a set of named rules, either written in EBNF and loaded
+//! with [`Grammar::from_ebnf`], or built from [`Expr`]s with
+//! [`Grammar::new`]. The first rule is the start symbol. [`Grammar::generate`]
+//! expands it into a string, and [`Grammar::generate_tree`] into a parse tree
+//! of [`Node`]s.
+//!
+//! Every choice between alternatives is made with
+//! [`Unstructured::choose_index`], and every repetition count with
+//! [`Unstructured::int_in_range`]. Each expansion of a rule is one level of
+//! [`Unstructured::nested`], so the depth budget of the `Unstructured` bounds
+//! the recursion. Alternatives and repetitions that cannot finish within the
+//! remaining budget are left out, and once the data runs out, every choice
+//! takes the alternative that finishes soonest. So any raw data generates a
+//! sentence of the grammar, unless the budget is too small for even the
+//! shortest one, which is [`Error::MaxDepthReached`][crate::Error::MaxDepthReached].
+//!
+//! # EBNF
+//!
+//! Rules look like `name = expression ;`, where `::=` may stand in for `=` and
+//! the `;` is optional. Expressions are made of:
+//!
+//! * Terminals in double or single quotes, like `"while"` or `'"'`, with the
+//!   escapes `\\`, `\"`, `\'`, `\n`, `\r` and `\t`.
+//! * Regular expressions between slashes, like `/[a-z_][a-z0-9_]*/`, which
+//!   generate a terminal with a [`Regex`]. `\/` stands for a slash.
+//! * Rule names, like `expr` or `<expr>`.
+//! * Sequences, separated by whitespace or commas, and alternatives,
+//!   separated by `|`.
+//! * Groups `( ... )`, options `[ ... ]` and repetitions `{ ... }`, and the
+//!   postfix operators `?`, `*` and `+`. Unbounded repetitions repeat at most
+//!   [`UNBOUNDED_REPEAT_LIMIT`] more times than their minimum.
+//! * Comments `(* ... *)`.
+//!
+//! Requires the `"alloc"` cargo feature.
+//!
+//! # Example
+//!
+//! ```
+//! use arbitrary::{grammar::Grammar, Unstructured};
+//!
+//! let grammar = Grammar::from_ebnf(r#"
+//!     expr   = term { ("+" | "-") term } ;
+//!     term   = factor { ("*" | "/") factor } ;
+//!     factor = number | "(" expr ")" ;
+//!     number = /0|[1-9][0-9]{0,3}/ ;
+//! "#).unwrap();
+//!
+//! let mut u = Unstructured::new(&[1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8, 9, 7, 9]);
+//! let program = grammar.generate(&mut u).unwrap();
+//! assert!(program.chars().all(|c| c.is_ascii_digit() || "+-*/()".contains(c)));
+//!
+//! // Without any data, the shortest sentence.
+//! assert_eq!(grammar.generate(&mut Unstructured::new(&[])).unwrap(), "0");
+//! ```
+
+use {
+    crate::{regex::Regex, Result, Unstructured},
+    alloc::{
+        borrow::ToOwned, boxed::Box, collections::BTreeMap, format, string::String, vec, vec::Vec,
+    },
+    core::{fmt, str::FromStr},
+};
+
+pub use crate::regex::UNBOUNDED_REPEAT_LIMIT;
+
+/// An expression on the right-hand side of a grammar rule.
+///
+/// Requires the `"alloc"` cargo feature.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{grammar::{Expr, Grammar}, Unstructured};
+///
+/// // list = "[" [ item { "," item } ] "]" ;
+/// // item = "x" | list ;
+/// let grammar = Grammar::new([
+///     (
+///         "list",
+///         Expr::seq([
+///             Expr::terminal("["),
+///             Expr::seq([
+///                 Expr::rule("item"),
+///                 Expr::seq([Expr::terminal(","), Expr::rule("item")]).zero_or_more(),
+///             ])
+///             .optional(),
+///             Expr::terminal("]"),
+///         ]),
+///     ),
+///     ("item", Expr::choice([Expr::terminal("x"), Expr::rule("list")])),
+/// ])
+/// .unwrap();
+///
+/// let mut u = Unstructured::new(&[1, 2, 1, 0, 0, 1, 0]);
+/// let list = grammar.generate(&mut u).unwrap();
+/// assert!(list.starts_with('[') && list.ends_with(']'));
+/// ```
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Expr {
+    /// A literal string.
+    Terminal(String),
+    /// A string that matches a regular expression.
+    Pattern(Regex),
+    /// An expansion of the named rule.
+    Rule(String),
+    /// Each of the expressions, one after the other.
+    Sequence(Vec<Expr>),
+    /// One of the expressions.
+    Choice(Vec<Expr>),
+    /// The expression, repeated between `min` and `max` times, or at most
+    /// [`UNBOUNDED_REPEAT_LIMIT`] more times than `min` if `max` is `None`.
+    Repeat {
+        /// The expression to repeat.
+        expr: Box<Expr>,
+        /// The minimum number of repetitions.
+        min: u32,
+        /// The maximum number of repetitions.
+        max: Option<u32>,
+    },
+}
+
+impl Expr {
+    /// A literal string.
+    pub fn terminal(text: impl Into<String>) -> Expr {
+        Expr::Terminal(text.into())
+    }
+
+    /// A string that matches the regular expression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is not a valid regular expression. Use
+    /// `Expr::Pattern(Regex::new(pattern)?)` to handle that instead.
+    pub fn pattern(pattern: &str) -> Expr {
+        Expr::Pattern(crate::regex::compile(pattern))
+    }
+
+    /// An expansion of the named rule.
+    pub fn rule(name: impl Into<String>) -> Expr {
+        Expr::Rule(name.into())
+    }
+
+    /// Each of the expressions, one after the other.
+    pub fn seq(exprs: impl IntoIterator<Item = Expr>) -> Expr {
+        Expr::Sequence(exprs.into_iter().collect())
+    }
+
+    /// One of the expressions.
+    pub fn choice(exprs: impl IntoIterator<Item = Expr>) -> Expr {
+        Expr::Choice(exprs.into_iter().collect())
+    }
+
+    /// This expression or nothing, like `[ ... ]` or `?` in EBNF.
+    pub fn optional(self) -> Expr {
+        self.repeat(0, Some(1))
+    }
+
+    /// This expression any number of times, like `{ ... }` or `*` in EBNF.
+    pub fn zero_or_more(self) -> Expr {
+        self.repeat(0, None)
+    }
+
+    /// This expression at least once, like `+` in EBNF.
+    pub fn one_or_more(self) -> Expr {
+        self.repeat(1, None)
+    }
+
+    /// This expression, repeated between `min` and `max` times.
+    pub fn repeat(self, min: u32, max: Option<u32>) -> Expr {
+        Expr::Repeat {
+            expr: Box::new(self),
+            min,
+            max,
+        }
+    }
+}
+
+/// A node of the parse tree of a generated sentence.
+///
+/// Its [`Display`][fmt::Display] implementation writes the text of the
+/// sentence.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Node {
+    /// Text from a terminal or a regular expression.
+    Terminal(String),
+    /// An expansion of the named rule.
+    Rule {
+        /// The name of the rule.
+        name: String,
+        /// The nodes that the rule expanded into.
+        children: Vec<Node>,
+    },
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Terminal(text) => f.write_str(text),
+            Node::Rule { children, .. } => children.iter().try_for_each(|child| child.fmt(f)),
+        }
+    }
+}
+
+/// A context-free grammar, which generates sentences.
+///
+/// See the [module documentation][self] for how sentences are generated, and
+/// for the EBNF syntax.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Debug)]
+pub struct Grammar {
+    names: Vec<String>,
+    rules: Vec<Compiled>,
+}
+
+impl Grammar {
+    /// Create a grammar from named rules. The first rule is the start symbol.
+    pub fn new<N>(
+        rules: impl IntoIterator<Item = (N, Expr)>,
+    ) -> core::result::Result<Grammar, GrammarError>
+    where
+        N: Into<String>,
+    {
+        let rules: Vec<(String, Expr)> = rules
+            .into_iter()
+            .map(|(name, expr)| (name.into(), expr))
+            .collect();
+        if rules.is_empty() {
+            return Err(GrammarError::new(
+                None,
+                "the grammar has no rules".to_owned(),
+            ));
+        }
+
+        let mut indices = BTreeMap::new();
+        for (index, (name, _)) in rules.iter().enumerate() {
+            if indices.insert(name.as_str(), index).is_some() {
+                return Err(GrammarError::new(
+                    None,
+                    format!("rule `{}` is defined more than once", name),
+                ));
+            }
+        }
+        let mut compiled = rules
+            .iter()
+            .map(|(_, expr)| compile(expr, &indices))
+            .collect::<core::result::Result<Vec<_>, _>>()?;
+
+        // The height of a rule is the fewest levels of nesting that it can
+        // be expanded in. Rules that never finish keep `u32::MAX`.
+        let mut heights = vec![u32::MAX; compiled.len()];
+        loop {
+            let mut changed = false;
+            for (rule, expr) in compiled.iter().enumerate() {
+                let height = height(expr, &heights);
+                if height < heights[rule] {
+                    heights[rule] = height;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        if let Some(rule) = heights.iter().position(|h| *h == u32::MAX) {
+            return Err(GrammarError::new(
+                None,
+                format!("rule `{}` never finishes", rules[rule].0),
+            ));
+        }
+        for expr in &mut compiled {
+            annotate(expr, &heights);
+        }
+
+        Ok(Grammar {
+            names: rules.into_iter().map(|(name, _)| name).collect(),
+            rules: compiled,
+        })
+    }
+
+    /// Load a grammar from EBNF text. The first rule is the start symbol.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::grammar::Grammar;
+    ///
+    /// let grammar = Grammar::from_ebnf(r#"
+    ///     json   = value ;
+    ///     value  = "null" | "true" | "false" | number | string | array | object ;
+    ///     number = /-?(0|[1-9][0-9]*)(\.[0-9]+)?/ ;
+    ///     string = '"' /[a-z ]*/ '"' ;
+    ///     array  = "[" [ value { "," value } ] "]" ;
+    ///     object = "{" [ member { "," member } ] "}" ;
+    ///     member = string ":" value ;
+    /// "#).unwrap();
+    /// assert_eq!(grammar.start(), "json");
+    ///
+    /// let error = Grammar::from_ebnf("a = b ;").unwrap_err();
+    /// assert_eq!(error.to_string(), "rule `b` is not defined");
+    /// ```
+    pub fn from_ebnf(ebnf: &str) -> core::result::Result<Grammar, GrammarError> {
+        Grammar::new(Parser::new(ebnf).grammar()?)
+    }
+
+    /// The name of the start symbol.
+    pub fn start(&self) -> &str {
+        &self.names[0]
+    }
+
+    /// Generate a sentence of the grammar.
+    pub fn generate(&self, u: &mut Unstructured<'_>) -> Result<String> {
+        let mut text = String::new();
+        self.expand_rule(0, u, &mut text)?;
+        Ok(text)
+    }
+
+    /// Generate a sentence of the grammar as a parse tree, whose root is an
+    /// expansion of the start symbol.
+    ///
+    /// This makes the same choices as [`Grammar::generate`] for the same
+    /// data, so the text of the tree is the same as the generated string.
+    pub fn generate_tree(&self, u: &mut Unstructured<'_>) -> Result<Node> {
+        let mut tree = Tree {
+            stack: vec![Vec::new()],
+        };
+        self.expand_rule(0, u, &mut tree)?;
+        Ok(tree.stack.pop().and_then(|mut root| root.pop()).unwrap())
+    }
+
+    fn expand_rule(
+        &self,
+        rule: usize,
+        u: &mut Unstructured<'_>,
+        out: &mut impl Output,
+    ) -> Result<()> {
+        u.nested(|u| {
+            out.enter();
+            self.expand(&self.rules[rule], u, out)?;
+            out.exit(&self.names[rule]);
+            Ok(())
+        })
+    }
+
+    fn expand(
+        &self,
+        expr: &Compiled,
+        u: &mut Unstructured<'_>,
+        out: &mut impl Output,
+    ) -> Result<()> {
+        match expr {
+            Compiled::Terminal(text) => out.terminal(text),
+            Compiled::Pattern(regex) => out.terminal(&regex.generate(u)?),
+            Compiled::Rule(rule) => self.expand_rule(*rule, u, out)?,
+            Compiled::Sequence(exprs) => {
+                for expr in exprs {
+                    self.expand(expr, u, out)?;
+                }
+            }
+            Compiled::Choice(alternatives) => {
+                let budget = budget(u);
+                let fitting = alternatives.iter().filter(|(h, _)| *h <= budget).count();
+                let (_, expr) = if u.is_empty() || fitting == 0 {
+                    // The first of the alternatives that finish soonest.
+                    alternatives
+                        .iter()
+                        .min_by_key(|(h, _)| *h)
+                        .expect("choices are not empty")
+                } else {
+                    let index = u.choose_index(fitting)?;
+                    alternatives
+                        .iter()
+                        .filter(|(h, _)| *h <= budget)
+                        .nth(index)
+                        .unwrap()
+                };
+                self.expand(expr, u, out)?;
+            }
+            Compiled::Repeat {
+                expr,
+                min,
+                max,
+                height,
+            } => {
+                let count = if *height > budget(u) {
+                    *min
+                } else {
+                    u.int_in_range(*min..=*max)?
+                };
+                for _ in 0..count {
+                    self.expand(expr, u, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Grammar {
+    type Err = GrammarError;
+
+    fn from_str(ebnf: &str) -> core::result::Result<Grammar, GrammarError> {
+        Grammar::from_ebnf(ebnf)
+    }
+}
+
+/// How many more levels of nesting the `Unstructured` allows.
+fn budget(u: &Unstructured<'_>) -> u32 {
+    u32::try_from(u.max_depth().saturating_sub(u.depth())).unwrap_or(u32::MAX)
+}
+
+/// A grammar that is not valid EBNF, refers to rules that do not exist, or
+/// has rules that can never finish expanding.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GrammarError {
+    /// The byte offset in the EBNF text where the error is, if it is a
+    /// syntax error.
+    pub offset: Option<usize>,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl GrammarError {
+    fn new(offset: Option<usize>, message: String) -> Self {
+        GrammarError { offset, message }
+    }
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "{} at byte {} of the grammar", self.message, offset),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl core::error::Error for GrammarError {}
+
+/// An `Expr` with rule indices instead of names, and the height of every
+/// alternative and repeated expression.
+#[derive(Clone, Debug)]
+enum Compiled {
+    Terminal(String),
+    Pattern(Regex),
+    Rule(usize),
+    Sequence(Vec<Compiled>),
+    Choice(Vec<(u32, Compiled)>),
+    Repeat {
+        expr: Box<Compiled>,
+        min: u32,
+        max: u32,
+        height: u32,
+    },
+}
+
+fn compile(
+    expr: &Expr,
+    indices: &BTreeMap<&str, usize>,
+) -> core::result::Result<Compiled, GrammarError> {
+    Ok(match expr {
+        Expr::Terminal(text) => Compiled::Terminal(text.clone()),
+        Expr::Pattern(regex) => Compiled::Pattern(regex.clone()),
+        Expr::Rule(name) => match indices.get(name.as_str()) {
+            Some(index) => Compiled::Rule(*index),
+            None => {
+                return Err(GrammarError::new(
+                    None,
+                    format!("rule `{}` is not defined", name),
+                ))
+            }
+        },
+        Expr::Sequence(exprs) => Compiled::Sequence(
+            exprs
+                .iter()
+                .map(|expr| compile(expr, indices))
+                .collect::<core::result::Result<_, _>>()?,
+        ),
+        Expr::Choice(exprs) => {
+            if exprs.is_empty() {
+                return Err(GrammarError::new(
+                    None,
+                    "a choice needs at least one alternative".to_owned(),
+                ));
+            }
+            Compiled::Choice(
+                exprs
+                    .iter()
+                    .map(|expr| Ok((u32::MAX, compile(expr, indices)?)))
+                    .collect::<core::result::Result<_, _>>()?,
+            )
+        }
+        Expr::Repeat { expr, min, max } => {
+            let max = max.unwrap_or_else(|| min.saturating_add(UNBOUNDED_REPEAT_LIMIT));
+            if *min > max {
+                return Err(GrammarError::new(
+                    None,
+                    format!(
+                        "a repetition has a minimum of {} and a maximum of {}",
+                        min, max
+                    ),
+                ));
+            }
+            Compiled::Repeat {
+                expr: Box::new(compile(expr, indices)?),
+                min: *min,
+                max,
+                height: u32::MAX,
+            }
+        }
+    })
+}
+
+/// The fewest levels of nesting that the expression can be expanded in,
+/// given the heights of the rules.
+fn height(expr: &Compiled, heights: &[u32]) -> u32 {
+    match expr {
+        Compiled::Terminal(_) | Compiled::Pattern(_) => 0,
+        Compiled::Rule(rule) => heights[*rule].saturating_add(1),
+        Compiled::Sequence(exprs) => exprs.iter().map(|e| height(e, heights)).max().unwrap_or(0),
+        Compiled::Choice(alternatives) => alternatives
+            .iter()
+            .map(|(_, e)| height(e, heights))
+            .min()
+            .unwrap(),
+        Compiled::Repeat { min: 0, .. } => 0,
+        Compiled::Repeat { expr, .. } => height(expr, heights),
+    }
+}
+
+/// Record the heights of alternatives and repeated expressions.
+fn annotate(expr: &mut Compiled, heights: &[u32]) {
+    match expr {
+        Compiled::Terminal(_) | Compiled::Pattern(_) | Compiled::Rule(_) => {}
+        Compiled::Sequence(exprs) => exprs.iter_mut().for_each(|e| annotate(e, heights)),
+        Compiled::Choice(alternatives) => {
+            for (h, e) in alternatives {
+                annotate(e, heights);
+                *h = height(e, heights);
+            }
+        }
+        Compiled::Repeat {
+            expr, height: h, ..
+        } => {
+            annotate(expr, heights);
+            *h = height(expr, heights);
+        }
+    }
+}
+
+/// Where generated sentences go.
+trait Output {
+    fn terminal(&mut self, text: &str);
+    fn enter(&mut self);
+    fn exit(&mut self, rule: &str);
+}
+
+impl Output for String {
+    fn terminal(&mut self, text: &str) {
+        self.push_str(text);
+    }
+
+    fn enter(&mut self) {}
+
+    fn exit(&mut self, _rule: &str) {}
+}
+
+/// The children of the rules that are being expanded.
+struct Tree {
+    stack: Vec<Vec<Node>>,
+}
+
+impl Output for Tree {
+    fn terminal(&mut self, text: &str) {
+        let children = self.stack.last_mut().unwrap();
+        children.push(Node::Terminal(text.to_owned()));
+    }
+
+    fn enter(&mut self) {
+        self.stack.push(Vec::new());
+    }
+
+    fn exit(&mut self, rule: &str) {
+        let children = self.stack.pop().unwrap();
+        self.stack.last_mut().unwrap().push(Node::Rule {
+            name: rule.to_owned(),
+            children,
+        });
+    }
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    offset: usize,
+}
+
+type ParseResult<T> = core::result::Result<T, GrammarError>;
+
+impl<'t> Parser<'t> {
+    fn new(text: &'t str) -> Self {
+        Parser { text, offset: 0 }
+    }
+
+    fn error<T>(&self, offset: usize, message: &str) -> ParseResult<T> {
+        Err(GrammarError::new(Some(offset), message.to_owned()))
+    }
+
+    fn rest(&self) -> &'t str {
+        &self.text[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        if self.rest().starts_with(expected) {
+            self.offset += expected.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_trivia(&mut self) -> ParseResult<()> {
+        loop {
+            let trimmed = self.rest().trim_start();
+            self.offset = self.text.len() - trimmed.len();
+            if !trimmed.starts_with("(*") {
+                return Ok(());
+            }
+            match trimmed[2..].find("*)") {
+                Some(end) => self.offset += 2 + end + 2,
+                None => return self.error(self.offset, "unclosed comment"),
+            }
+        }
+    }
+
+    fn grammar(mut self) -> ParseResult<Vec<(String, Expr)>> {
+        let mut rules = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek().is_none() {
+                return Ok(rules);
+            }
+            let start = self.offset;
+            let name = match self.name()? {
+                Some(name) => name,
+                None => return self.error(start, "expected a rule name"),
+            };
+            self.skip_trivia()?;
+            if !self.definition() {
+                return self.error(self.offset, "expected `=` after the rule name");
+            }
+            let expr = self.alternatives()?;
+            self.skip_trivia()?;
+            if !self.eat(";") && self.peek().is_some() && !self.at_rule() {
+                return self.error(self.offset, "expected `;` or another rule");
+            }
+            rules.push((name, expr));
+        }
+    }
+
+    fn definition(&mut self) -> bool {
+        self.eat("::=") || self.eat("=")
+    }
+
+    /// Whether a rule definition starts here.
+    fn at_rule(&mut self) -> bool {
+        let offset = self.offset;
+        let at_rule =
+            matches!(self.name(), Ok(Some(_))) && self.skip_trivia().is_ok() && self.definition();
+        self.offset = offset;
+        at_rule
+    }
+
+    /// Parse a rule name, like `expr` or `<expr>`.
+    fn name(&mut self) -> ParseResult<Option<String>> {
+        let start = self.offset;
+        if self.eat("<") {
+            return match self.rest().find('>') {
+                Some(end) if end > 0 => {
+                    let name = self.rest()[..end].to_owned();
+                    self.offset += end + 1;
+                    Ok(Some(name))
+                }
+                _ => self.error(start, "invalid rule name"),
+            };
+        }
+        let is_start = |c: char| c.is_alphabetic() || c == '_';
+        if !self.peek().map_or(false, is_start) {
+            return Ok(None);
+        }
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(self.rest().len());
+        let name = self.rest()[..len].to_owned();
+        self.offset += len;
+        Ok(Some(name))
+    }
+
+    fn alternatives(&mut self) -> ParseResult<Expr> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat("|") {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Expr::Choice(alternatives)
+        })
+    }
+
+    fn sequence(&mut self) -> ParseResult<Expr> {
+        let mut exprs = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                None | Some('|' | ')' | ']' | '}' | ';') => break,
+                Some(',') => {
+                    self.offset += 1;
+                    continue;
+                }
+                Some(_) if self.at_rule() => break,
+                Some(_) => {}
+            }
+            let factor = self.factor()?;
+            exprs.push(self.postfix(factor)?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::Sequence(exprs)
+        })
+    }
+
+    fn postfix(&mut self, mut expr: Expr) -> ParseResult<Expr> {
+        loop {
+            self.skip_trivia()?;
+            expr = if self.eat("?") {
+                expr.optional()
+            } else if self.eat("*") {
+                expr.zero_or_more()
+            } else if self.eat("+") {
+                expr.one_or_more()
+            } else {
+                return Ok(expr);
+            };
+        }
+    }
+
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let start = self.offset;
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.offset += 1;
+                self.string(start, quote).map(Expr::Terminal)
+            }
+            Some('/') => {
+                self.offset += 1;
+                self.pattern(start).map(Expr::Pattern)
+            }
+            Some('(') => {
+                self.offset += 1;
+                self.group(start, ")", "unclosed group")
+            }
+            Some('[') => {
+                self.offset += 1;
+                Ok(self.group(start, "]", "unclosed option")?.optional())
+            }
+            Some('{') => {
+                self.offset += 1;
+                Ok(self
+                    .group(start, "}", "unclosed repetition")?
+                    .zero_or_more())
+            }
+            _ => match self.name()? {
+                Some(name) => Ok(Expr::Rule(name)),
+                None => self.error(start, "expected a terminal, a rule name or a group"),
+            },
+        }
+    }
+
+    fn group(&mut self, start: usize, close: &str, unclosed: &str) -> ParseResult<Expr> {
+        let expr = self.alternatives()?;
+        self.skip_trivia()?;
+        if !self.eat(close) {
+            return self.error(start, unclosed);
+        }
+        Ok(expr)
+    }
+
+    /// Parse a quoted terminal after its opening quote.
+    fn string(&mut self, start: usize, quote: char) -> ParseResult<String> {
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.offset += i + 1;
+                    return Ok(text);
+                }
+                '\n' => break,
+                '\\' => text.push(match chars.next() {
+                    Some((_, '\\')) => '\\',
+                    Some((_, '"')) => '"',
+                    Some((_, '\'')) => '\'',
+                    Some((_, 'n')) => '\n',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 't')) => '\t',
+                    _ => return self.error(self.offset + i, "unknown escape sequence"),
+                }),
+                c => text.push(c),
+            }
+        }
+        self.error(start, "unclosed terminal")
+    }
+
+    /// Parse a regular expression after its opening slash.
+    fn pattern(&mut self, start: usize) -> ParseResult<Regex> {
+        let mut pattern = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '/' => {
+                    let regex = Regex::new(&pattern).map_err(|e| {
+                        GrammarError::new(
+                            Some(self.offset + e.offset),
+                            format!("invalid regular expression: {}", e.message),
+                        )
+                    })?;
+                    self.offset += i + 1;
+                    return Ok(regex);
+                }
+                '\n' => break,
+                '\\' => match chars.next() {
+                    Some((_, '/')) => pattern.push('/'),
+                    Some((_, c)) => {
+                        pattern.push('\\');
+                        pattern.push(c);
+                    }
+                    None => break,
+                },
+                c => pattern.push(c),
+            }
+        }
+        self.error(start, "unclosed regular expression")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ebnf() {
+        let grammar = Grammar::from_ebnf(
+            r#"
+            (* A comment. *)
+            <start> ::= "a" , b? c* 'd'+ | [ b ] { c }
+            b = /[0-9]/ (* Another comment. *)
+            c = ( "x" | "y\"" ) ;
+            "#,
+        )
+        .unwrap();
+        assert_eq!(grammar.names, ["start", "b", "c"]);
+        assert_eq!(grammar.start(), "start");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for (ebnf, offset, message) in [
+            ("= a", 0, "expected a rule name"),
+            ("a b", 2, "expected `=` after the rule name"),
+            ("a = (b", 4, "unclosed group"),
+            ("a = [b", 4, "unclosed option"),
+            ("a = {b", 4, "unclosed repetition"),
+            ("a = \"b", 4, "unclosed terminal"),
+            ("a = /b", 4, "unclosed regular expression"),
+            (
+                "a = /[b/",
+                5,
+                "invalid regular expression: unclosed character class",
+            ),
+            ("a = \"\\q\"", 5, "unknown escape sequence"),
+            ("a = (* b", 4, "unclosed comment"),
+            ("a = ) ;", 4, "expected `;` or another rule"),
+            ("a = <>", 4, "invalid rule name"),
+        ] {
+            let error = Grammar::from_ebnf(ebnf).unwrap_err();
+            assert_eq!(
+                (error.offset, error.message.as_str()),
+                (Some(offset), message),
+                "grammar: {}",
+                ebnf
+            );
+        }
+    }
+
+    #[test]
+    fn grammar_errors() {
+        for (ebnf, message) in [
+            ("", "the grammar has no rules"),
+            ("a = b", "rule `b` is not defined"),
+            ("a = 'x' ; a = 'y'", "rule `a` is defined more than once"),
+            ("a = 'x' b ; b = 'y' b", "rule `a` never finishes"),
+        ] {
+            let error = Grammar::from_ebnf(ebnf).unwrap_err();
+            assert_eq!((error.offset, error.message.as_str()), (None, message));
+        }
+
+        let error = Grammar::new([("a", Expr::choice([]))]).unwrap_err();
+        assert_eq!(error.message, "a choice needs at least one alternative");
+        let error = Grammar::new([("a", Expr::terminal("x").repeat(2, Some(1)))]).unwrap_err();
+        assert_eq!(
+            error.message,
+            "a repetition has a minimum of 2 and a maximum of 1"
+        );
+    }
+
+    #[test]
+    fn heights() {
+        let grammar = Grammar::from_ebnf(
+            "
+            a = b | 'x' c ;
+            b = a a ;
+            c = { a } ;
+            ",
+        )
+        .unwrap();
+        match &grammar.rules[0] {
+            Compiled::Choice(alternatives) => {
+                let heights: Vec<u32> = alternatives.iter().map(|(h, _)| *h).collect();
+                assert_eq!(heights, [3, 1]);
+            }
+            other => panic!("unexpected rule: {:?}", other),
+        }
+        match &grammar.rules[2] {
+            Compiled::Repeat { height, .. } => assert_eq!(*height, 2),
+            other => panic!("unexpected rule: {:?}", other),
+        }
+    }
+}
@@ -13,7 +13,26 @@
 //! generally intended to be used with fuzzers like AFL or libFuzzer. See the
 //! [`Arbitrary`] trait's documentation for details on
 //! automatically deriving, implementing, and/or using the trait.
+//!
+//! # `no_std` Support
+//!
+//! The `std` cargo feature is enabled by default. Disabling it makes this crate
+//! `#![no_std]`: [`Arbitrary`], [`Unstructured`] and the custom derive keep
+//! working, and only the implementations for types from [`std`] that are not
+//! re-exports of [`core`] or [`alloc`] types go away. The `alloc` feature, which
+//! `std` implies, gates the implementations for [`alloc`] types like `Vec`,
+//! `String` and `Box`, so that targets without an allocator can still use the
+//! rest of the crate:
+//!
+//! ```toml
+//! [dependencies]
+//! arbitrary = { version = "1", default-features = false, features = ["alloc"] }
+//! ```
+//!
+//! [`std`]: https://doc.rust-lang.org/std/
+//! [`alloc`]: https://doc.rust-lang.org/alloc/
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(bad_style)]
 #![deny(missing_docs)]
 #![deny(future_incompatible)]
@@ -22,12 +41,36 @@
 #![deny(rust_2018_idioms)]
 #![deny(unused)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+pub mod charset;
+#[cfg(feature = "std")]
+pub mod check;
+#[cfg(feature = "std")]
+pub mod choices;
+#[cfg(feature = "alloc")]
+pub mod dearbitrator;
 mod error;
 mod foreign;
+#[cfg(feature = "alloc")]
+pub mod grammar;
+#[cfg(feature = "alloc")]
+pub mod regex;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "alloc")]
+pub mod shrink;
 pub mod size_hint;
+#[cfg(feature = "alloc")]
+pub mod source;
+#[cfg(feature = "alloc")]
+pub mod stateful;
+pub mod trace;
 pub mod unstructured;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests;
 
 pub use error::*;
@@ -38,6 +81,24 @@ pub use derive_arbitrary::*;
 #[doc(inline)]
 pub use unstructured::Unstructured;
 
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use trace::Trace;
+
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use check::check;
+
+#[cfg(feature = "alloc")]
+#[doc(inline)]
+pub use dearbitrator::Dearbitrator;
+
+// The return type of derived `Shrink` implementations, which can't name
+// `alloc` themselves.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub use alloc::boxed::Box as __Box;
+
 /// Error indicating that the maximum recursion depth has been reached while calculating [`Arbitrary::size_hint`]()
 #[derive(Debug, Clone)]
 #[non_exhaustive]
@@ -74,7 +135,7 @@ impl core::error::Error for MaxRecursionReached {}
 /// `enum` type definition:
 ///
 /// ```
-/// # #[cfg(feature = "derive")] mod foo {
+/// # #[cfg(all(feature = "derive", feature = "std"))] mod foo {
 /// use arbitrary::Arbitrary;
 /// use std::collections::HashSet;
 ///
@@ -177,7 +238,9 @@ pub trait Arbitrary<'a>: Sized {
     /// interesting runtime behavior or new code coverage if only we had just a
     /// few more bytes. However, it also requires that implementations for
     /// recursive types (e.g. `struct Foo(Option<Box<Foo>>)`) avoid infinite
-    /// recursion when the underlying data is exhausted.
+    /// recursion when the underlying data is exhausted. Derived
+    /// implementations bound their depth with [`Unstructured::nested`], no
+    /// matter how much data there is.
     ///
     /// ```
     /// # #[cfg(feature = "derive")] fn foo() {
@@ -418,6 +481,588 @@ pub trait Arbitrary<'a>: Sized {
     }
 }
 
+/// Turn a value back into raw data that [`Arbitrary`] decodes into it.
+///
+/// `Dearbitrary` is the inverse of [`Arbitrary::arbitrary`]: for a value `x`,
+/// `T::arbitrary(&mut Unstructured::new(&x.dearbitrary_to_vec()?))` yields a
+/// value equal to `x`. This is useful for seeding fuzzing corpora with
+/// hand-written values, and for turning crash reproducers into readable test
+/// cases and back again.
+///
+/// Requires the `"alloc"` cargo feature.
+///
+/// # Deriving `Dearbitrary`
+///
+/// `Dearbitrary` can be derived for every type that derives `Arbitrary`, and
+/// the derived implementation follows the same `#[arbitrary(...)]`
+/// attributes:
+///
+/// ```
+/// # #[cfg(feature = "derive")] fn foo() -> arbitrary::Result<()> {
+/// use arbitrary::{Arbitrary, Dearbitrary, Unstructured};
+///
+/// #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+/// pub enum Shape {
+///     Circle { radius: u32 },
+///     Polygon(Vec<(i16, i16)>),
+/// }
+///
+/// let shape = Shape::Polygon(vec![(0, 0), (3, 0), (0, 4)]);
+/// let data = shape.dearbitrary_to_vec()?;
+/// assert_eq!(Shape::arbitrary(&mut Unstructured::new(&data))?, shape);
+/// # Ok(()) }
+/// ```
+///
+/// Fields marked `#[arbitrary(default)]` or `#[arbitrary(value = ...)]` are
+/// not part of the raw data, so they are decoded as whatever those attributes
+/// produce, regardless of their original value. Fields that are generated
+/// `#[arbitrary(with = ...)]`, `#[arbitrary(len = ...)]`,
+/// `#[arbitrary(range = ...)]`, `#[arbitrary(regex = ...)]`,
+/// `#[arbitrary(charset = ...)]` or `#[arbitrary(context)]` cannot be turned
+/// back into raw data, so neither can values that contain them. The same goes
+/// for `#[arbitrary(skip)]`ped enum variants, and for weighted variants that
+/// no `u32` selects, which only happens when the weights add up to more than
+/// `u32::MAX`, and for values that nest deeper than
+/// [`Unstructured::DEFAULT_MAX_DEPTH`], or the depth set with
+/// [`Dearbitrator::with_max_depth`], which the derived implementations check
+/// with [`Dearbitrator::nested`]. Those values make `dearbitrary` return
+/// [`Error::Unrepresentable`].
+///
+/// The `#[arbitrary(filter = ...)]` and `#[arbitrary(fixup = ...)]` attributes
+/// are not checked: a value decodes into itself as long as its filter accepts
+/// it and its fixup leaves it unchanged.
+///
+/// The `#[arbitrary(bound = ...)]` container attribute only applies to the
+/// derived `Arbitrary` implementation; the derived `Dearbitrary`
+/// implementation requires `T: Dearbitrary` for every type parameter `T`.
+///
+/// `Dearbitrary` cannot be derived together with the
+/// `#[arbitrary(from = ...)]`, `#[arbitrary(try_from = ...)]` or
+/// `#[arbitrary(remote = ...)]` container attributes, since the raw data of
+/// those types is that of another type. They need to implement `Dearbitrary`
+/// by hand, by turning the value back into the other type and dearbitrary-ing
+/// that.
+///
+/// # Implementing `Dearbitrary` By Hand
+///
+/// An implementation has to mirror the corresponding `Arbitrary`
+/// implementation: for every call on the [`Unstructured`] there, it calls the
+/// matching method of the [`Dearbitrator`], in the same order.
+///
+/// ```
+/// use arbitrary::{Arbitrary, Dearbitrary, Dearbitrator, Result, Unstructured};
+///
+/// #[derive(Debug, PartialEq)]
+/// pub struct Percentage(u8);
+///
+/// impl<'a> Arbitrary<'a> for Percentage {
+///     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+///         u.int_in_range(0..=100).map(Percentage)
+///     }
+/// }
+///
+/// impl Dearbitrary for Percentage {
+///     fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+///         d.push_int_in_range(0..=100, self.0)
+///     }
+/// }
+///
+/// let data = Percentage(42).dearbitrary_to_vec().unwrap();
+/// assert_eq!(
+///     Percentage::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+///     Percentage(42),
+/// );
+/// ```
+#[cfg(feature = "alloc")]
+pub trait Dearbitrary {
+    /// Record the raw data for `self` in the given `Dearbitrator`.
+    ///
+    /// Returns [`Error::Unrepresentable`] if there is no raw data that
+    /// [`Arbitrary::arbitrary`] would decode into a value equal to `self`.
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()>;
+
+    /// Get the raw data that [`Arbitrary::arbitrary`] decodes into a value
+    /// equal to `self`.
+    ///
+    /// ```
+    /// use arbitrary::{Arbitrary, Dearbitrary, Unstructured};
+    ///
+    /// let value = (true, Some(String::from("🦀")), vec![1_u32, 2, 3]);
+    /// let data = value.dearbitrary_to_vec().unwrap();
+    ///
+    /// let mut u = Unstructured::new(&data);
+    /// assert_eq!(u.arbitrary::<(bool, Option<String>, Vec<u32>)>().unwrap(), value);
+    /// ```
+    fn dearbitrary_to_vec(&self) -> Result<alloc::vec::Vec<u8>> {
+        let mut d = Dearbitrator::new();
+        self.dearbitrary(&mut d)?;
+        Ok(d.finish())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Dearbitrary for &T
+where
+    T: Dearbitrary + ?Sized,
+{
+    fn dearbitrary(&self, d: &mut Dearbitrator) -> Result<()> {
+        (**self).dearbitrary(d)
+    }
+}
+
+/// Produce "smaller" versions of a value, to minimize failing test cases.
+///
+/// When a property fails for a large generated value, shrinking finds a
+/// smaller value that still makes it fail: [`shrink::minimize`] repeatedly
+/// replaces the value with the first candidate from [`Shrink::shrink`] for
+/// which the property still fails, until none of the candidates does.
+///
+/// ```
+/// use arbitrary::shrink;
+///
+/// // Find the smallest counterexample to "no vector sums up to more than 100".
+/// let failing = vec![77_u32, 3, 99, 1024, 0, 8];
+/// let minimal = shrink::minimize(failing, |v| v.iter().sum::<u32>() > 100);
+/// assert_eq!(minimal, [101]);
+/// ```
+///
+/// The implementations in this crate shrink integers and floats toward zero,
+/// `bool`s toward `false`, collections by removing elements and then by
+/// shrinking their elements, `Option`s toward `None`, and other values by
+/// shrinking their components.
+///
+/// Requires the `"alloc"` cargo feature.
+///
+/// # Deriving `Shrink`
+///
+/// The derived implementation shrinks enums toward earlier variants, and then
+/// shrinks every field in turn. The earlier variants are built from empty raw
+/// data with [`Arbitrary`], so their fields have to implement `Arbitrary`,
+/// just like with `#[derive(Arbitrary)]`. The type has to implement `Clone`.
+/// The maximum depth is zero while building them, so variants that need a
+/// nested derived value, like `Neg` below, are not candidates; otherwise a
+/// recursive variant could "shrink" into an ever larger value.
+///
+/// ```
+/// # #[cfg(feature = "derive")] fn foo() {
+/// use arbitrary::{shrink, Arbitrary, Shrink};
+///
+/// #[derive(Arbitrary, Shrink, Clone, Debug, PartialEq)]
+/// enum Expr {
+///     Lit(u8),
+///     Neg(Box<Expr>),
+///     Add(Box<Expr>, Box<Expr>),
+/// }
+///
+/// let expr = Expr::Add(Box::new(Expr::Lit(7)), Box::new(Expr::Lit(200)));
+/// let minimal = shrink::minimize(expr, |e| matches!(e, Expr::Add(..)));
+/// assert_eq!(minimal, Expr::Add(Box::new(Expr::Lit(0)), Box::new(Expr::Lit(0))));
+/// # }
+/// ```
+///
+/// Fields marked `#[arbitrary(default)]`, `#[arbitrary(value = ...)]`,
+/// `#[arbitrary(with = ...)]`, `#[arbitrary(len = ...)]`,
+/// `#[arbitrary(range = ...)]`, `#[arbitrary(regex = ...)]`,
+/// `#[arbitrary(charset = ...)]` or `#[arbitrary(context)]` keep their value, since shrinking could break whatever invariant those attributes uphold.
+/// Other fields shrink without regard for `#[arbitrary(filter = ...)]` and
+/// `#[arbitrary(fixup = ...)]`, so the candidates may not pass the filter.
+/// Values never shrink toward `#[arbitrary(skip)]`ped enum variants or
+/// variants with `#[arbitrary(weight = 0)]`. The
+/// `#[arbitrary(bound = ...)]` container attribute does not apply; the derived
+/// implementation requires `T: Shrink` for every type parameter `T`, and
+/// `T: Arbitrary` for enums.
+///
+/// # Implementing `Shrink` By Hand
+///
+/// Every candidate should be strictly smaller than `self` by some measure, so
+/// that shrinking terminates. Yield the candidates that simplify the value the
+/// most first.
+///
+/// ```
+/// use arbitrary::Shrink;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Percentage(u8);
+///
+/// impl Shrink for Percentage {
+///     fn shrink(&self) -> Box<dyn Iterator<Item = Self> + '_> {
+///         Box::new(self.0.shrink().map(Percentage))
+///     }
+/// }
+///
+/// assert_eq!(Percentage(4).shrink().next(), Some(Percentage(0)));
+/// ```
+#[cfg(feature = "alloc")]
+pub trait Shrink: Sized {
+    /// Get the candidates for a smaller version of `self`, simplest first.
+    ///
+    /// The default implementation yields no candidates.
+    fn shrink(&self) -> alloc::boxed::Box<dyn Iterator<Item = Self> + '_> {
+        alloc::boxed::Box::new(core::iter::empty())
+    }
+}
+
+/// Mutate a value in place, driven by raw data from an [`Unstructured`].
+///
+/// Fuzzers like libFuzzer and AFL++ accept custom mutators. Mutating the
+/// structured value that an input decodes into, instead of the raw input,
+/// keeps the invariants that the `Arbitrary` implementations uphold, e.g.
+/// that a length matches its payload:
+///
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use arbitrary::{source::Prng, Arbitrary, Dearbitrary, Mutate, Unstructured};
+///
+/// // Decode an input, mutate it and encode it again, like a custom mutator.
+/// fn mutate_input(data: &[u8], seed: u64) -> arbitrary::Result<Vec<u8>> {
+///     let mut value = Vec::<(u8, String)>::arbitrary(&mut Unstructured::new(data))?;
+///     value.mutate(&mut Unstructured::from_source(Prng::new(seed)))?;
+///     value.dearbitrary_to_vec()
+/// }
+///
+/// let data = vec![(1_u8, String::from("one"))].dearbitrary_to_vec().unwrap();
+/// let mutated = mutate_input(&data, 42).unwrap();
+/// # let _ = mutated;
+/// # }
+/// ```
+///
+/// The implementations in this crate flip bits of numbers, replace them with
+/// boundary values like `0` and `MAX`, or nudge them up and down. They insert,
+/// remove, replace, mutate and swap the elements of collections, and mutate
+/// one element of tuples and arrays. Everything else is replaced with a freshly
+/// generated value, which is also what the default implementation does.
+///
+/// # Deriving `Mutate`
+///
+/// The derived implementation either switches an enum to another variant,
+/// generated with [`Arbitrary`], or mutates one of the fields of the current
+/// variant.
+///
+/// ```
+/// # #[cfg(feature = "derive")] fn foo() {
+/// use arbitrary::{Arbitrary, Mutate, Unstructured};
+///
+/// #[derive(Arbitrary, Mutate, Debug)]
+/// enum Message {
+///     Ping,
+///     Data { id: u32, payload: Vec<u8> },
+/// }
+///
+/// let mut message = Message::Data { id: 1, payload: vec![1, 2, 3] };
+/// message.mutate(&mut Unstructured::new(&[1, 2, 3, 4, 5, 6])).unwrap();
+/// # }
+/// ```
+///
+/// Fields marked `#[arbitrary(default)]`, `#[arbitrary(value = ...)]`,
+/// `#[arbitrary(with = ...)]`, `#[arbitrary(len = ...)]`,
+/// `#[arbitrary(range = ...)]`, `#[arbitrary(regex = ...)]` or
+/// `#[arbitrary(charset = ...)]` keep their value. Other fields are mutated
+/// without applying `#[arbitrary(filter = ...)]` or
+/// `#[arbitrary(fixup = ...)]`. Enums never switch to `#[arbitrary(skip)]`ped variants or variants with
+/// `#[arbitrary(weight = 0)]`, and switch between the others uniformly. The
+/// `#[arbitrary(bound = ...)]` container attribute does not apply; the derived
+/// implementation requires `T: Mutate<'a>` for every type parameter `T`.
+/// Types with lifetime parameters only implement `Mutate<'a>` for those
+/// lifetimes being `'a`, so that borrowed fields can be replaced with data
+/// from `u`.
+///
+/// # Implementing `Mutate` By Hand
+///
+/// ```
+/// use arbitrary::{Arbitrary, Mutate, Result, Unstructured};
+///
+/// #[derive(Debug)]
+/// pub struct Percentage(u8);
+///
+/// impl<'a> Arbitrary<'a> for Percentage {
+///     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+///         Ok(Percentage(u.int_in_range(0..=100)?))
+///     }
+/// }
+///
+/// impl<'a> Mutate<'a> for Percentage {
+///     fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+///         // Stay close to the current value.
+///         let low = self.0.saturating_sub(5);
+///         let high = core::cmp::min(self.0 + 5, 100);
+///         self.0 = u.int_in_range(low..=high)?;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut percentage = Percentage(100);
+/// percentage.mutate(&mut Unstructured::new(&[3])).unwrap();
+/// assert_eq!(percentage.0, 98);
+/// ```
+pub trait Mutate<'a>: Arbitrary<'a> {
+    /// Change `self` in place, using the raw data in `u` to decide how.
+    ///
+    /// The default implementation replaces `self` with a value generated by
+    /// [`Arbitrary::arbitrary`].
+    fn mutate(&mut self, u: &mut Unstructured<'a>) -> Result<()> {
+        *self = Self::arbitrary(u)?;
+        Ok(())
+    }
+}
+
+/// Collections that can be generated with a given number of elements.
+///
+/// This is what the `#[arbitrary(len = ...)]` field attribute of
+/// `#[derive(Arbitrary)]` uses to generate collections whose length is within
+/// the given range. It is implemented for `Vec`, `String` (counting `char`s)
+/// and all collections in `std::collections`. Sets and maps collapse duplicate
+/// elements, so they may end up with fewer elements than requested.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(all(feature = "derive", feature = "alloc"))] fn foo() {
+/// use arbitrary::{Arbitrary, ArbitraryLen, Unstructured};
+///
+/// #[derive(Arbitrary, Debug)]
+/// struct Batch {
+///     #[arbitrary(len = 1..=16)]
+///     items: Vec<u32>,
+///     #[arbitrary(len = ..4)]
+///     tags: Vec<String>,
+/// }
+///
+/// let batch = Batch::arbitrary(&mut Unstructured::new(&[])).unwrap();
+/// assert_eq!(batch.items.len(), 1);
+/// assert!(batch.tags.is_empty());
+///
+/// let mut u = Unstructured::new(&[0xff; 100]);
+/// let items = Vec::<u8>::arbitrary_with_len_in(&mut u, 3..=5).unwrap();
+/// assert_eq!(items.len(), 5);
+///
+/// // The size hint reflects the bounds, too.
+/// assert_eq!(Vec::<u32>::size_hint_with_len_in(0, 1..=16).0, 4);
+/// # }
+/// ```
+pub trait ArbitraryLen<'a>: Arbitrary<'a> {
+    /// The type of the elements, which determines how many elements the raw
+    /// data can cover, see [`Unstructured::arbitrary_len_in`].
+    type Element: Arbitrary<'a>;
+
+    /// Generate a collection of `len` arbitrary elements.
+    fn arbitrary_with_len(u: &mut Unstructured<'a>, len: usize) -> Result<Self>;
+
+    /// Generate a collection with a number of elements within the given
+    /// range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty, just like
+    /// [`Unstructured::arbitrary_len_in`].
+    fn arbitrary_with_len_in(
+        u: &mut Unstructured<'a>,
+        range: impl core::ops::RangeBounds<usize>,
+    ) -> Result<Self> {
+        let len = u.arbitrary_len_in::<Self::Element>(range)?;
+        Self::arbitrary_with_len(u, len)
+    }
+
+    /// Get a size hint for [`ArbitraryLen::arbitrary_with_len_in`], like
+    /// [`Arbitrary::size_hint`].
+    fn size_hint_with_len_in(
+        depth: usize,
+        range: impl core::ops::RangeBounds<usize>,
+    ) -> (usize, Option<usize>) {
+        Self::try_size_hint_with_len_in(depth, range).unwrap_or_default()
+    }
+
+    /// Get a size hint for [`ArbitraryLen::arbitrary_with_len_in`], like
+    /// [`Arbitrary::try_size_hint`].
+    fn try_size_hint_with_len_in(
+        depth: usize,
+        range: impl core::ops::RangeBounds<usize>,
+    ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        let (min, max) = unstructured::len_bounds(&range);
+        let (lower, upper) = size_hint::try_recursion_guard(depth, Self::Element::try_size_hint)?;
+        let elements = (
+            min.saturating_mul(lower),
+            upper.and_then(|upper| max.checked_mul(upper)),
+        );
+        // The length takes up to eight bytes, unless it is fixed.
+        let len = if min == max {
+            (0, Some(0))
+        } else {
+            (0, Some(core::mem::size_of::<u64>()))
+        };
+        Ok(size_hint::and(len, elements))
+    }
+}
+
+/// Types that can be generated within a range of values.
+///
+/// This is what the `#[arbitrary(range = ...)]` field attribute of
+/// `#[derive(Arbitrary)]` uses to generate values within the given range. It
+/// is implemented for all integers, floats, `char`, `Duration` and the
+/// `NonZero*` integers, and accepts inclusive, exclusive and half-open ranges,
+/// like `1024..=65535`, `0.0..1.0` or `'a'..`.
+///
+/// The implementations choose values with [`Unstructured::int_in_range`], so
+/// there is no rejection sampling. Floats are chosen by their bit patterns,
+/// just like `f64::arbitrary` interprets the raw data, and are never NaN.
+/// Ranges of `char`s skip the surrogate code points, and ranges of `NonZero*`
+/// integers skip zero.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "derive")] fn foo() {
+/// use arbitrary::{Arbitrary, ArbitraryInRange, Unstructured};
+/// use std::time::Duration;
+///
+/// #[derive(Arbitrary, Debug)]
+/// struct Listener {
+///     #[arbitrary(range = 1024..=65535)]
+///     port: u16,
+///     #[arbitrary(range = 0.0..1.0)]
+///     load: f64,
+///     #[arbitrary(range = ..Duration::from_secs(30))]
+///     timeout: Duration,
+/// }
+///
+/// let listener = Listener::arbitrary(&mut Unstructured::new(&[])).unwrap();
+/// assert_eq!(listener.port, 1024);
+/// assert_eq!(listener.load, 0.0);
+/// assert_eq!(listener.timeout, Duration::ZERO);
+///
+/// let mut u = Unstructured::new(&[0xff; 8]);
+/// assert_eq!(char::arbitrary_in_range(&mut u, 'a'..='z').unwrap(), 'v');
+///
+/// // Only one byte is needed to choose a value among 11.
+/// assert_eq!(i32::size_hint_in_range(0, -5..=5), (1, Some(1)));
+/// # }
+/// ```
+pub trait ArbitraryInRange<'a>: Arbitrary<'a> {
+    /// Generate a value within the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty, or if one of its bounds is NaN.
+    fn arbitrary_in_range(
+        u: &mut Unstructured<'a>,
+        range: impl core::ops::RangeBounds<Self>,
+    ) -> Result<Self>;
+
+    /// Get a size hint for [`ArbitraryInRange::arbitrary_in_range`], like
+    /// [`Arbitrary::size_hint`].
+    fn size_hint_in_range(
+        depth: usize,
+        range: impl core::ops::RangeBounds<Self>,
+    ) -> (usize, Option<usize>) {
+        Self::try_size_hint_in_range(depth, range).unwrap_or_default()
+    }
+
+    /// Get a size hint for [`ArbitraryInRange::arbitrary_in_range`], like
+    /// [`Arbitrary::try_size_hint`].
+    ///
+    /// The default implementation returns the size hint for the whole type.
+    fn try_size_hint_in_range(
+        depth: usize,
+        range: impl core::ops::RangeBounds<Self>,
+    ) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        let _ = range;
+        Self::try_size_hint(depth)
+    }
+}
+
+/// Generate a value that is only valid relative to some outside state.
+///
+/// This is the contextual counterpart to [`Arbitrary`]: the generator gets a
+/// shared reference to a context of type `Ctx`, such as the registers an
+/// instruction may refer to, or the keys an edit may target. It is
+/// implemented for `Option`, arrays, `Box`, `Rc`, `Arc` and the collections
+/// whose elements implement `ArbitraryWith<'a, Ctx>`, passing the context on
+/// to every element.
+///
+/// # Deriving `ArbitraryWith`
+///
+/// Putting the `#[arbitrary(context = Type)]` container attribute on a type
+/// makes `#[derive(Arbitrary)]` implement `ArbitraryWith<'a, Type>` instead of
+/// `Arbitrary`. Fields marked `#[arbitrary(context)]` are generated with
+/// `ArbitraryWith` and the same context, and all other fields as usual. Use a
+/// string for types that are not plain paths, as in
+/// `#[arbitrary(context = "[Reg]")]`.
+///
+/// Such types do not implement `Arbitrary`, so they cannot derive `Mutate`,
+/// and derived `Shrink` implementations only work for structs. Derived
+/// `Dearbitrary` implementations treat fields marked `#[arbitrary(context)]`
+/// as unrepresentable.
+///
+/// ```
+/// # #[cfg(feature = "derive")] fn foo() -> arbitrary::Result<()> {
+/// use arbitrary::{Arbitrary, ArbitraryWith, Result, Unstructured};
+///
+/// struct Registers {
+///     count: u8,
+/// }
+///
+/// #[derive(Debug)]
+/// struct Reg(u8);
+///
+/// impl<'a> ArbitraryWith<'a, Registers> for Reg {
+///     fn arbitrary_with(u: &mut Unstructured<'a>, regs: &Registers) -> Result<Self> {
+///         Ok(Reg(u.int_in_range(0..=regs.count - 1)?))
+///     }
+/// }
+///
+/// #[derive(Arbitrary, Debug)]
+/// #[arbitrary(context = Registers)]
+/// enum Instr {
+///     Load(#[arbitrary(context)] Reg, u32),
+///     Add {
+///         #[arbitrary(context)]
+///         dst: Reg,
+///         #[arbitrary(context)]
+///         src: [Reg; 2],
+///     },
+///     Nop,
+/// }
+///
+/// #[derive(Arbitrary, Debug)]
+/// #[arbitrary(context = Registers)]
+/// struct Program {
+///     #[arbitrary(context)]
+///     instrs: Vec<Instr>,
+/// }
+///
+/// let regs = Registers { count: 4 };
+/// let mut u = Unstructured::new(&[1, 0x40, 0, 0, 0, 7, 9]);
+/// let program = Program::arbitrary_with(&mut u, &regs)?;
+/// # let _ = program;
+/// # Ok(()) }
+/// ```
+pub trait ArbitraryWith<'a, Ctx: ?Sized>: Sized {
+    /// Generate an arbitrary value of `Self` that is valid relative to `ctx`,
+    /// like [`Arbitrary::arbitrary`].
+    fn arbitrary_with(u: &mut Unstructured<'a>, ctx: &Ctx) -> Result<Self>;
+
+    /// Generate an arbitrary value of `Self` that is valid relative to `ctx`
+    /// from the entirety of the given unstructured data, like
+    /// [`Arbitrary::arbitrary_take_rest`].
+    fn arbitrary_take_rest_with(mut u: Unstructured<'a>, ctx: &Ctx) -> Result<Self> {
+        Self::arbitrary_with(&mut u, ctx)
+    }
+
+    /// Get a size hint for [`ArbitraryWith::arbitrary_with`], like
+    /// [`Arbitrary::size_hint`].
+    #[inline]
+    fn size_hint_with(depth: usize) -> (usize, Option<usize>) {
+        let _ = depth;
+        (0, None)
+    }
+
+    /// Get a size hint for [`ArbitraryWith::arbitrary_with`], like
+    /// [`Arbitrary::try_size_hint`].
+    #[inline]
+    fn try_size_hint_with(depth: usize) -> Result<(usize, Option<usize>), MaxRecursionReached> {
+        Ok(Self::size_hint_with(depth))
+    }
+}
+
 /// Multiple conflicting arbitrary attributes are used on the same field:
 /// ```compile_fail
 /// #[derive(::arbitrary::Arbitrary)]
@@ -515,6 +1160,92 @@ pub trait Arbitrary<'a>: Sized {
 /// }
 /// ```
 ///
+/// An attribute that refers to a field that is generated later:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Message {
+///     #[arbitrary(value = *len as u32)]
+///     size: u32,
+///     len: u8,
+/// }
+/// ```
+///
+/// A `with` closure that takes a field that is generated later:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Message {
+///     #[arbitrary(with = |u: &mut ::arbitrary::Unstructured, len: &u8| u.int_in_range(0..=*len))]
+///     size: u8,
+///     len: u8,
+/// }
+/// ```
+///
+/// `retries` without a `filter`:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// #[arbitrary(retries = 4)]
+/// struct Point {
+///     x: i32,
+/// }
+/// ```
+///
+/// A field `filter` that refers to a field that is generated later:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Span {
+///     #[arbitrary(filter = |start: &u8| *start <= *end)]
+///     start: u8,
+///     end: u8,
+/// }
+/// ```
+///
+/// A remote mirror with a field that the foreign type does not have:
+/// ```compile_fail
+/// mod other {
+///     pub struct Point {
+///         pub x: i32,
+///     }
+/// }
+///
+/// #[derive(::arbitrary::Arbitrary)]
+/// #[arbitrary(remote = "other::Point")]
+/// struct PointDef {
+///     x: i32,
+///     y: i32,
+/// }
+/// ```
+///
+/// A variant `tag` without `stable_tags`:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// enum Enum {
+///     #[arbitrary(tag = 1)]
+///     A,
+///     B,
+/// }
+/// ```
+///
+/// Two variants with the same tag:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// #[arbitrary(stable_tags)]
+/// enum Enum {
+///     #[arbitrary(tag = 1)]
+///     A,
+///     #[arbitrary(tag = 1)]
+///     B,
+/// }
+/// ```
+///
+/// A field that takes the context of a container without one:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Point {
+///     #[arbitrary(context)]
+///     x: i32,
+/// }
+/// ```
+///
 /// Attempt to use the derive attribute on an enum variant:
 /// ```compile_fail
 /// #[derive(::arbitrary::Arbitrary)]
@@ -523,5 +1254,66 @@ pub trait Arbitrary<'a>: Sized {
 ///     Variant(T),
 /// }
 /// ```
+///
+/// An invalid regular expression:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Name {
+///     #[arbitrary(regex = "[a-z")]
+///     name: String,
+/// }
+/// ```
+///
+/// A regular expression that is not a string literal:
+/// ```compile_fail
+/// const PATTERN: &str = "[a-z]+";
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Name {
+///     #[arbitrary(regex = PATTERN)]
+///     name: String,
+/// }
+/// ```
+///
+/// Deriving `Dearbitrary` for a type that is converted from another one:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary, ::arbitrary::Dearbitrary)]
+/// #[arbitrary(from = u8)]
+/// struct Doubled {
+///     x: u16,
+/// }
+///
+/// impl From<u8> for Doubled {
+///     fn from(x: u8) -> Self {
+///         Doubled { x: u16::from(x) * 2 }
+///     }
+/// }
+/// ```
+///
+/// An unknown charset:
+/// ```compile_fail
+/// #[derive(::arbitrary::Arbitrary)]
+/// struct Name {
+///     #[arbitrary(charset = "emoji")]
+///     name: String,
+/// }
+/// ```
+///
+/// An unknown property test setting:
+/// ```compile_fail
+/// #[arbitrary::test(iterations = 10)]
+/// fn prop(x: u8) {}
+/// ```
+///
+/// A generic property test:
+/// ```compile_fail
+/// #[arbitrary::test]
+/// fn prop<T: std::fmt::Debug>(x: T) {}
+/// ```
+///
+/// A property test without arguments:
+/// ```compile_fail
+/// #[arbitrary::test]
+/// fn prop() {}
+/// ```
 #[cfg(all(doctest, feature = "derive"))]
 pub struct CompileFailTests;
@@ -0,0 +1,833 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generating strings that match a regular expression.
+//!
+//! A [`Regex`] compiles a pattern once, and then generates any number of
+//! matching strings from [`Unstructured`] data. Every decision is made with
+//! [`Unstructured::choose_index`] or [`Unstructured::int_in_range`]: which
+//! branch of an alternation, how many repetitions, and which character of a
+//! class. So any raw data generates a matching string, and changing a byte
+//! of the data changes a single decision, and a small part of the string.
+//! Once the data runs out, every decision takes its first option, which makes
+//! strings as short as the pattern allows.
+//!
+//! [`Unstructured::string_matching`] compiles a pattern and generates a
+//! single string, [`LazyRegex`] compiles it the first time that it is used,
+//! and `#[arbitrary(regex = "...")]` generates a field of
+//! `#[derive(Arbitrary)]` with a `LazyRegex`.
+//!
+//! # Syntax
+//!
+//! The syntax is a subset of the `regex` crate's:
+//!
+//! * Literal characters, and `\` followed by any ASCII punctuation for that
+//!   character. `\n`, `\t`, `\r`, `\f`, `\v`, `\a`, `\e`, `\0`, `\xHH`,
+//!   `\x{H...}`, `\uHHHH` and `\u{H...}` are characters too.
+//! * `.` for any character except `\n`.
+//! * Character classes like `[a-z_]` and `[^0-9]`, with ASCII classes like
+//!   `[[:alpha:]]`, and the Perl classes `\d`, `\w` and `\s` and their
+//!   negations `\D`, `\W` and `\S`, which are ASCII-only.
+//! * Alternations `a|b`, and groups `(a)`, `(?:a)`, `(?P<name>a)` and
+//!   `(?<name>a)`.
+//! * Repetitions `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}`, optionally
+//!   followed by `?`, which makes no difference when generating strings. `*`,
+//!   `+` and `{n,}` repeat at most [`UNBOUNDED_REPEAT_LIMIT`] more times than
+//!   their minimum.
+//! * Anchors `^`, `$`, `\A` and `\z`, which match the empty string, since the
+//!   generated string is the whole match.
+//!
+//! Flags like `(?i)`, word boundaries and Unicode classes like `\pL` are not
+//! supported. Negated classes and `.` pick from all of Unicode.
+//!
+//! Requires the `"alloc"` cargo feature.
+//!
+//! # Example
+//!
+//! ```
+//! use arbitrary::{regex::Regex, Unstructured};
+//!
+//! let hostname = Regex::new(r"[a-z][a-z0-9-]{0,10}(\.[a-z]{2,5}){1,3}").unwrap();
+//!
+//! let mut u = Unstructured::new(&[7, 3, 99, 42, 12, 250, 1, 17, 0, 4]);
+//! let host = hostname.generate(&mut u).unwrap();
+//! assert!(host.contains('.'));
+//! ```
+
+use {
+    crate::{Result, Unstructured},
+    alloc::{boxed::Box, string::String, vec, vec::Vec},
+    core::fmt,
+};
+
+#[cfg(target_has_atomic = "ptr")]
+use core::{
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// How many more times than their minimum `*`, `+` and `{n,}` repeat at most.
+pub const UNBOUNDED_REPEAT_LIMIT: u32 = 16;
+
+/// How deeply groups may be nested.
+const MAX_NESTING: usize = 64;
+
+/// A compiled regular expression, which generates matching strings.
+///
+/// See the [module documentation][self] for the supported syntax.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone)]
+pub struct Regex {
+    pattern: String,
+    hir: Hir,
+}
+
+impl Regex {
+    /// Compile the given pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::regex::Regex;
+    ///
+    /// assert!(Regex::new("[a-z_][a-z0-9_]*").is_ok());
+    ///
+    /// let error = Regex::new("(ab").unwrap_err();
+    /// assert_eq!(error.to_string(), "unclosed group at byte 0 of the pattern");
+    /// ```
+    pub fn new(pattern: &str) -> core::result::Result<Regex, RegexError> {
+        let hir = Parser::new(pattern).parse()?;
+        Ok(Regex {
+            pattern: pattern.into(),
+            hir,
+        })
+    }
+
+    /// The pattern that this was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Generate a string that matches the pattern.
+    ///
+    /// This only fails if the data comes from a
+    /// [`ByteSource`][crate::source::ByteSource] that fails; a byte slice
+    /// that runs out generates the shortest matches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{regex::Regex, Unstructured};
+    ///
+    /// let version = Regex::new(r"v?[0-9]{1,3}\.[0-9]{1,3}\.[0-9]{1,3}").unwrap();
+    ///
+    /// let mut u = Unstructured::new(&[]);
+    /// assert_eq!(version.generate(&mut u).unwrap(), "0.0.0");
+    /// ```
+    pub fn generate(&self, u: &mut Unstructured<'_>) -> Result<String> {
+        let mut output = String::new();
+        self.hir.generate(u, &mut output)?;
+        Ok(output)
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.pattern).finish()
+    }
+}
+
+/// A [`Regex`] that is compiled the first time it generates a string, for use
+/// in a `static`.
+///
+/// `#[arbitrary(regex = "...")]` generates fields with one of these. The
+/// compiled pattern is kept in an atomic pointer, so generating strings from
+/// several threads at once doesn't contend on a lock. On targets without
+/// atomic pointers, there is nowhere to keep it, and it is compiled every
+/// time.
+///
+/// Requires the `"alloc"` cargo feature.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{regex::LazyRegex, Arbitrary, Result, Unstructured};
+///
+/// struct Identifier(String);
+///
+/// impl<'a> Arbitrary<'a> for Identifier {
+///     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+///         static PATTERN: LazyRegex = LazyRegex::new("[a-zA-Z_][a-zA-Z0-9_]{0,15}");
+///         PATTERN.generate(u).map(Identifier)
+///     }
+/// }
+/// ```
+pub struct LazyRegex {
+    pattern: &'static str,
+    // Null until the pattern is compiled, and then a leaked `Box<Regex>`
+    // that is freed when this is dropped.
+    #[cfg(target_has_atomic = "ptr")]
+    compiled: AtomicPtr<Regex>,
+}
+
+impl LazyRegex {
+    /// Create a `LazyRegex` for the given pattern, without compiling it yet.
+    pub const fn new(pattern: &'static str) -> LazyRegex {
+        LazyRegex {
+            pattern,
+            #[cfg(target_has_atomic = "ptr")]
+            compiled: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Generate a string that matches the pattern, like
+    /// [`Regex::generate`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is not a valid regular expression.
+    pub fn generate(&self, u: &mut Unstructured<'_>) -> Result<String> {
+        #[cfg(target_has_atomic = "ptr")]
+        return self.get().generate(u);
+
+        #[cfg(not(target_has_atomic = "ptr"))]
+        compile(self.pattern).generate(u)
+    }
+
+    #[cfg(target_has_atomic = "ptr")]
+    fn get(&self) -> &Regex {
+        let compiled = self.compiled.load(Ordering::Acquire);
+        if !compiled.is_null() {
+            // SAFETY: a non-null pointer comes from `Box::into_raw` below,
+            // and is only freed by `drop`, which has `&mut self`.
+            return unsafe { &*compiled };
+        }
+
+        let new = Box::into_raw(Box::new(compile(self.pattern)));
+        match self.compiled.compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            // SAFETY: as above, `new` now belongs to `self`.
+            Ok(_) => unsafe { &*new },
+            Err(existing) => {
+                // Another thread compiled the pattern first, so use its
+                // `Regex`, and free ours, which nothing else has seen.
+                // SAFETY: `new` comes from `Box::into_raw` and was never
+                // shared, and `existing` is as above.
+                unsafe {
+                    drop(Box::from_raw(new));
+                    &*existing
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl Drop for LazyRegex {
+    fn drop(&mut self) {
+        let compiled = *self.compiled.get_mut();
+        if !compiled.is_null() {
+            // SAFETY: the pointer comes from `Box::into_raw` in `get`, and
+            // nothing borrows `self` anymore.
+            drop(unsafe { Box::from_raw(compiled) });
+        }
+    }
+}
+
+impl fmt::Debug for LazyRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LazyRegex").field(&self.pattern).finish()
+    }
+}
+
+/// Compile a pattern that is part of the program, and so must be valid.
+pub(crate) fn compile(pattern: &str) -> Regex {
+    match Regex::new(pattern) {
+        Ok(regex) => regex,
+        Err(e) => panic!("invalid regex `{}`: {}", pattern, e),
+    }
+}
+
+/// A pattern that is not a valid regular expression, or uses syntax that
+/// [`Regex`] does not support.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RegexError {
+    /// The byte offset in the pattern where the error is.
+    pub offset: usize,
+    /// What is wrong.
+    pub message: &'static str,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {} of the pattern", self.message, self.offset)
+    }
+}
+
+impl core::error::Error for RegexError {}
+
+/// A compiled pattern.
+#[derive(Clone, Debug)]
+enum Hir {
+    Char(char),
+    Class(Class),
+    Concat(Vec<Hir>),
+    Alternation(Vec<Hir>),
+    Repeat { hir: Box<Hir>, min: u32, max: u32 },
+}
+
+impl Hir {
+    fn empty() -> Hir {
+        Hir::Concat(Vec::new())
+    }
+
+    fn generate(&self, u: &mut Unstructured<'_>, output: &mut String) -> Result<()> {
+        match self {
+            Hir::Char(c) => output.push(*c),
+            Hir::Class(class) => output.push(class.generate(u)?),
+            Hir::Concat(hirs) => {
+                for hir in hirs {
+                    hir.generate(u, output)?;
+                }
+            }
+            Hir::Alternation(hirs) => {
+                let index = u.choose_index(hirs.len())?;
+                hirs[index].generate(u, output)?;
+            }
+            Hir::Repeat { hir, min, max } => {
+                for _ in 0..u.int_in_range(*min..=*max)? {
+                    hir.generate(u, output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A set of characters, as sorted, disjoint ranges without surrogates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Class {
+    ranges: Vec<(u32, u32)>,
+    len: u32,
+}
+
+impl Class {
+    fn new(mut ranges: Vec<(u32, u32)>) -> Class {
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        // Cut the surrogates out, since they are not `char`s.
+        let mut ranges = Vec::with_capacity(merged.len() + 1);
+        for (start, end) in merged {
+            if start < 0xD800 {
+                ranges.push((start, end.min(0xD7FF)));
+            }
+            if end > 0xDFFF {
+                ranges.push((start.max(0xE000), end));
+            }
+        }
+        let len = ranges.iter().map(|(start, end)| end - start + 1).sum();
+        Class { ranges, len }
+    }
+
+    fn ascii(spec: &[(u8, u8)]) -> Class {
+        Class::new(
+            spec.iter()
+                .map(|&(start, end)| (u32::from(start), u32::from(end)))
+                .collect(),
+        )
+    }
+
+    fn negate(&self) -> Class {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 1);
+        let mut next = 0;
+        for &(start, end) in &self.ranges {
+            if start > next {
+                ranges.push((next, start - 1));
+            }
+            next = end + 1;
+        }
+        if next <= char::MAX as u32 {
+            ranges.push((next, char::MAX as u32));
+        }
+        Class::new(ranges)
+    }
+
+    fn generate(&self, u: &mut Unstructured<'_>) -> Result<char> {
+        let mut index = u.int_in_range(0..=self.len - 1)?;
+        for &(start, end) in &self.ranges {
+            let size = end - start + 1;
+            if index < size {
+                return Ok(char::from_u32(start + index).expect("surrogates are cut out"));
+            }
+            index -= size;
+        }
+        unreachable!("the index is less than the number of characters")
+    }
+}
+
+const DIGIT: &[(u8, u8)] = &[(b'0', b'9')];
+const WORD: &[(u8, u8)] = &[(b'0', b'9'), (b'A', b'Z'), (b'_', b'_'), (b'a', b'z')];
+const SPACE: &[(u8, u8)] = &[(b'\t', b'\r'), (b' ', b' ')];
+
+/// The ASCII classes, like `[:alpha:]`.
+const ASCII_CLASSES: &[(&str, &[(u8, u8)])] = &[
+    ("alnum", &[(b'0', b'9'), (b'A', b'Z'), (b'a', b'z')]),
+    ("alpha", &[(b'A', b'Z'), (b'a', b'z')]),
+    ("ascii", &[(0, 0x7F)]),
+    ("blank", &[(b'\t', b'\t'), (b' ', b' ')]),
+    ("cntrl", &[(0, 0x1F), (0x7F, 0x7F)]),
+    ("digit", DIGIT),
+    ("graph", &[(b'!', b'~')]),
+    ("lower", &[(b'a', b'z')]),
+    ("print", &[(b' ', b'~')]),
+    (
+        "punct",
+        &[(b'!', b'/'), (b':', b'@'), (b'[', b'`'), (b'{', b'~')],
+    ),
+    ("space", SPACE),
+    ("upper", &[(b'A', b'Z')]),
+    ("word", WORD),
+    ("xdigit", &[(b'0', b'9'), (b'A', b'F'), (b'a', b'f')]),
+];
+
+/// What an escape sequence stands for.
+enum Escape {
+    Char(char),
+    Class(Class),
+    Empty,
+}
+
+struct Parser<'p> {
+    pattern: &'p str,
+    offset: usize,
+}
+
+impl<'p> Parser<'p> {
+    fn new(pattern: &'p str) -> Self {
+        Parser { pattern, offset: 0 }
+    }
+
+    fn parse(mut self) -> core::result::Result<Hir, RegexError> {
+        let hir = self.alternation(0)?;
+        match self.peek() {
+            None => Ok(hir),
+            Some(_) => Err(self.error(self.offset, "unopened group")),
+        }
+    }
+
+    fn error(&self, offset: usize, message: &'static str) -> RegexError {
+        RegexError { offset, message }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.pattern[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.offset += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alternation(&mut self, depth: usize) -> core::result::Result<Hir, RegexError> {
+        let mut branches = Vec::new();
+        loop {
+            branches.push(self.concat(depth)?);
+            if !self.eat('|') {
+                break;
+            }
+        }
+        Ok(if branches.len() == 1 {
+            branches.pop().unwrap()
+        } else {
+            Hir::Alternation(branches)
+        })
+    }
+
+    fn concat(&mut self, depth: usize) -> core::result::Result<Hir, RegexError> {
+        let mut hirs = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom(depth)?;
+            hirs.push(self.repetition(atom)?);
+        }
+        Ok(if hirs.len() == 1 {
+            hirs.pop().unwrap()
+        } else {
+            Hir::Concat(hirs)
+        })
+    }
+
+    fn atom(&mut self, depth: usize) -> core::result::Result<Hir, RegexError> {
+        let start = self.offset;
+        let c = self.next().expect("the caller peeked");
+        Ok(match c {
+            '(' => self.group(start, depth)?,
+            '[' => Hir::Class(self.class(start)?),
+            '.' => Hir::Class(Class::new(vec![(u32::from('\n'), u32::from('\n'))]).negate()),
+            '^' | '$' => Hir::empty(),
+            '*' | '+' | '?' => {
+                return Err(self.error(start, "repetition operator missing expression"))
+            }
+            '\\' => match self.escape(start, false)? {
+                Escape::Char(c) => Hir::Char(c),
+                Escape::Class(class) => Hir::Class(class),
+                Escape::Empty => Hir::empty(),
+            },
+            c => Hir::Char(c),
+        })
+    }
+
+    fn group(&mut self, start: usize, depth: usize) -> core::result::Result<Hir, RegexError> {
+        if depth >= MAX_NESTING {
+            return Err(self.error(start, "groups are nested too deeply"));
+        }
+        if self.eat('?') {
+            let named = if self.eat('P') {
+                if !self.eat('<') {
+                    return Err(self.error(start, "unsupported group flags"));
+                }
+                true
+            } else {
+                self.eat('<')
+            };
+            if named {
+                let name_start = self.offset;
+                let name = self.pattern[name_start..].split('>').next().unwrap();
+                let valid = !name.is_empty()
+                    && self.pattern[name_start + name.len()..].starts_with('>')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    return Err(self.error(name_start, "invalid capture group name"));
+                }
+                self.offset += name.len() + 1;
+            } else if !self.eat(':') {
+                return Err(self.error(start, "unsupported group flags"));
+            }
+        }
+        let hir = self.alternation(depth + 1)?;
+        if !self.eat(')') {
+            return Err(self.error(start, "unclosed group"));
+        }
+        Ok(hir)
+    }
+
+    fn repetition(&mut self, hir: Hir) -> core::result::Result<Hir, RegexError> {
+        let (min, max) = if self.eat('*') {
+            (0, UNBOUNDED_REPEAT_LIMIT)
+        } else if self.eat('+') {
+            (1, 1 + UNBOUNDED_REPEAT_LIMIT)
+        } else if self.eat('?') {
+            (0, 1)
+        } else if let Some(bounds) = self.counted_repetition()? {
+            bounds
+        } else {
+            return Ok(hir);
+        };
+        // Lazy repetitions generate the same strings as greedy ones.
+        self.eat('?');
+        Ok(Hir::Repeat {
+            hir: Box::new(hir),
+            min,
+            max,
+        })
+    }
+
+    /// Parse `{n}`, `{n,}` or `{n,m}`, or return `None` and consume nothing
+    /// if there is none of those. A `{` that does not start one is a literal
+    /// character.
+    fn counted_repetition(&mut self) -> core::result::Result<Option<(u32, u32)>, RegexError> {
+        let start = self.offset;
+        let rest = match self.pattern[start..].strip_prefix('{') {
+            Some(rest) => rest,
+            None => return Ok(None),
+        };
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let body = &rest[..end];
+        if body.is_empty() || !body.chars().all(|c| c.is_ascii_digit() || c == ',') {
+            return Ok(None);
+        }
+        let number = |s: &str| -> core::result::Result<u32, RegexError> {
+            s.parse()
+                .map_err(|_| self.error(start, "invalid repetition count"))
+        };
+        let (min, max) = match body.split_once(',') {
+            None => {
+                let n = number(body)?;
+                (n, n)
+            }
+            Some((min, "")) => {
+                let min = number(min)?;
+                (min, min.saturating_add(UNBOUNDED_REPEAT_LIMIT))
+            }
+            Some((min, max)) => (number(min)?, number(max)?),
+        };
+        if min > max {
+            return Err(self.error(start, "invalid repetition range"));
+        }
+        self.offset = start + 1 + end + 1;
+        Ok(Some((min, max)))
+    }
+
+    /// Parse the escape sequence after a `\` at `start`.
+    fn escape(&mut self, start: usize, in_class: bool) -> core::result::Result<Escape, RegexError> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err(self.error(start, "incomplete escape sequence")),
+        };
+        Ok(match c {
+            'd' => Escape::Class(Class::ascii(DIGIT)),
+            'D' => Escape::Class(Class::ascii(DIGIT).negate()),
+            'w' => Escape::Class(Class::ascii(WORD)),
+            'W' => Escape::Class(Class::ascii(WORD).negate()),
+            's' => Escape::Class(Class::ascii(SPACE)),
+            'S' => Escape::Class(Class::ascii(SPACE).negate()),
+            'n' => Escape::Char('\n'),
+            't' => Escape::Char('\t'),
+            'r' => Escape::Char('\r'),
+            'f' => Escape::Char('\x0C'),
+            'v' => Escape::Char('\x0B'),
+            'a' => Escape::Char('\x07'),
+            'e' => Escape::Char('\x1B'),
+            '0' => Escape::Char('\0'),
+            'x' => Escape::Char(self.hex(start, 2)?),
+            'u' => Escape::Char(self.hex(start, 4)?),
+            'A' | 'z' if !in_class => Escape::Empty,
+            'b' | 'B' => return Err(self.error(start, "word boundaries are not supported")),
+            'p' | 'P' => return Err(self.error(start, "Unicode classes are not supported")),
+            c if c.is_ascii_punctuation() || c == ' ' => Escape::Char(c),
+            _ => return Err(self.error(start, "unknown escape sequence")),
+        })
+    }
+
+    /// Parse the hex digits of `\x` or `\u`: either exactly `digits` of them,
+    /// or any number in braces.
+    fn hex(&mut self, start: usize, digits: usize) -> core::result::Result<char, RegexError> {
+        let rest = &self.pattern[self.offset..];
+        let (hex, len) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => return Err(self.error(start, "unclosed hex escape")),
+            }
+        } else {
+            match rest.get(..digits) {
+                Some(hex) => (hex, digits),
+                None => return Err(self.error(start, "invalid hex escape")),
+            }
+        };
+        let valid = !hex.is_empty() && hex.len() <= 8 && hex.chars().all(|c| c.is_ascii_hexdigit());
+        match u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| valid)
+            .and_then(char::from_u32)
+        {
+            Some(c) => {
+                self.offset += len;
+                Ok(c)
+            }
+            None => Err(self.error(start, "invalid hex escape")),
+        }
+    }
+
+    /// Parse a character class after the `[` at `start`.
+    fn class(&mut self, start: usize) -> core::result::Result<Class, RegexError> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let item_start = self.offset;
+            let c = match self.next() {
+                Some(c) => c,
+                None => return Err(self.error(start, "unclosed character class")),
+            };
+            let low = match c {
+                ']' if !first => break,
+                '[' if self.peek() == Some(':') => {
+                    ranges.extend(self.ascii_class(item_start)?.ranges);
+                    first = false;
+                    continue;
+                }
+                '\\' => match self.escape(item_start, true)? {
+                    Escape::Char(c) => c,
+                    Escape::Class(class) => {
+                        ranges.extend(class.ranges);
+                        first = false;
+                        continue;
+                    }
+                    Escape::Empty => unreachable!("anchors are not escapes in classes"),
+                },
+                c => c,
+            };
+            first = false;
+
+            // A `-` before the closing `]` is a literal character.
+            let high = if self.peek() == Some('-')
+                && !self.pattern[self.offset + 1..].starts_with(']')
+            {
+                self.next();
+                let high_start = self.offset;
+                match self.next() {
+                    Some('\\') => match self.escape(high_start, true)? {
+                        Escape::Char(c) => c,
+                        _ => return Err(self.error(item_start, "invalid character class range")),
+                    },
+                    Some(c) => c,
+                    None => return Err(self.error(start, "unclosed character class")),
+                }
+            } else {
+                low
+            };
+            if low > high {
+                return Err(self.error(item_start, "invalid character class range"));
+            }
+            ranges.push((u32::from(low), u32::from(high)));
+        }
+
+        let class = Class::new(ranges);
+        let class = if negated { class.negate() } else { class };
+        if class.len == 0 {
+            return Err(self.error(start, "empty character class"));
+        }
+        Ok(class)
+    }
+
+    /// Parse an ASCII class like `[:alpha:]` after its `[` at `start`.
+    fn ascii_class(&mut self, start: usize) -> core::result::Result<Class, RegexError> {
+        let rest = &self.pattern[self.offset..];
+        let end = match rest.find(":]") {
+            Some(end) => end,
+            None => return Err(self.error(start, "unclosed ASCII class")),
+        };
+        let (negated, name) = match rest[1..end].strip_prefix('^') {
+            Some(name) => (true, name),
+            None => (false, &rest[1..end]),
+        };
+        let spec = match ASCII_CLASSES.iter().find(|(n, _)| *n == name) {
+            Some((_, spec)) => spec,
+            None => return Err(self.error(start, "unknown ASCII class")),
+        };
+        self.offset += end + 2;
+        let class = Class::ascii(spec);
+        Ok(if negated { class.negate() } else { class })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(pattern: &str, data: &[u8]) -> String {
+        Regex::new(pattern)
+            .unwrap()
+            .generate(&mut Unstructured::new(data))
+            .unwrap()
+    }
+
+    #[test]
+    fn shortest_matches_without_data() {
+        for (pattern, expected) in [
+            ("abc", "abc"),
+            ("a|bc", "a"),
+            ("x*y+z?", "y"),
+            ("[b-d]{2,}", "bb"),
+            (r"\d{3}-\w", "000-0"),
+            ("(?:ab|cd){2}", "abab"),
+            ("(?P<year>[0-9]{4})", "0000"),
+            ("^a$", "a"),
+            ("[^\0]", "\u{1}"),
+            (r"\x41\u{1F600}", "A\u{1F600}"),
+            ("a{,", "a{,"),
+            ("[]a]", "]"),
+            ("[a-]", "-"),
+            ("[[:upper:]]", "A"),
+        ] {
+            assert_eq!(generate(pattern, &[]), expected, "pattern: {}", pattern);
+        }
+    }
+
+    #[test]
+    fn decisions_follow_data() {
+        assert_eq!(generate("[a-z]", &[2]), "c");
+        assert_eq!(generate("a|b|c", &[1]), "b");
+        assert_eq!(generate("x{1,3}", &[2]), "xxx");
+        assert_eq!(generate("(ab|cd)[0-9]", &[1, 5]), "cd5");
+    }
+
+    #[test]
+    fn classes() {
+        let class = Class::new(vec![
+            (u32::from('a'), u32::from('c')),
+            (u32::from('b'), u32::from('e')),
+        ]);
+        assert_eq!(class.ranges, [(u32::from('a'), u32::from('e'))]);
+        assert_eq!(class.len, 5);
+
+        // Surrogates are not characters.
+        let all = Class::new(vec![(0, char::MAX as u32)]);
+        assert_eq!(all.len, char::MAX as u32 + 1 - 0x800);
+        assert_eq!(all.negate().len, 0);
+        assert_eq!(Class::ascii(DIGIT).negate().negate(), Class::ascii(DIGIT));
+    }
+
+    #[test]
+    fn errors() {
+        for (pattern, offset, message) in [
+            ("(ab", 0, "unclosed group"),
+            ("ab)", 2, "unopened group"),
+            ("a**", 2, "repetition operator missing expression"),
+            ("[ab", 0, "unclosed character class"),
+            ("[z-a]", 1, "invalid character class range"),
+            ("[^\0-\u{10FFFF}]", 0, "empty character class"),
+            ("a{3,2}", 1, "invalid repetition range"),
+            (r"\bword", 0, "word boundaries are not supported"),
+            (r"\pL", 0, "Unicode classes are not supported"),
+            (r"\q", 0, "unknown escape sequence"),
+            (r"\x{D800}", 0, "invalid hex escape"),
+            ("(?i)a", 0, "unsupported group flags"),
+            ("[[:nope:]]", 1, "unknown ASCII class"),
+            ("a\\", 1, "incomplete escape sequence"),
+        ] {
+            let error = Regex::new(pattern).unwrap_err();
+            assert_eq!(
+                (error.offset, error.message),
+                (offset, message),
+                "pattern: {}",
+                pattern
+            );
+        }
+    }
+}
@@ -0,0 +1,504 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generating any type that implements `serde::Deserialize`.
+//!
+//! [`Deserializer`] is a `serde::Deserializer` that answers every request from
+//! an [`Unstructured`], and [`from_unstructured`] generates a value with it.
+//! This covers types from other crates that implement `Deserialize` but not
+//! `Arbitrary`, for example in a field with
+//! `#[arbitrary(with = arbitrary::serde::from_unstructured)]`.
+//!
+//! Values are decoded like the `Arbitrary` implementations of the matching
+//! Rust types: a sequence like `Vec<T>`, a map like `HashMap<K, V>`, an
+//! option like `Option<T>`, a struct field by field like
+//! `#[derive(Arbitrary)]`, and so on. Strings and byte slices are borrowed
+//! from the data, like `&'a str` and `&'a [u8]`, unless the type asks for an
+//! owned `String` or `Vec<u8>`; borrowing requires an `Unstructured` over a
+//! byte slice rather than a [`ByteSource`][crate::source::ByteSource].
+//!
+//! Types that describe their own format with `deserialize_any`, like
+//! `#[serde(untagged)]` enums, get an arbitrary kind of value: a unit, a
+//! `bool`, an integer, a float, a `char`, a string, bytes, an option, a
+//! sequence or a map. The deserializer is not human-readable, so types with
+//! both a textual and a compact form, like `IpAddr`, use the compact one.
+//!
+//! A value that `Deserialize` rejects, such as a variant index that is out of
+//! range, is [`Error::IncorrectFormat`].
+//!
+//! Requires the `"serde"` cargo feature.
+//!
+//! ```
+//! # #[cfg(feature = "derive")] fn foo() {
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use std::collections::BTreeMap;
+//!
+//! // Imagine that this type is from another crate, and only implements
+//! // `serde::Deserialize`.
+//! #[derive(Debug, serde::Deserialize)]
+//! pub struct Config<'a> {
+//!     name: &'a str,
+//!     retries: Option<u8>,
+//!     labels: BTreeMap<String, String>,
+//! }
+//!
+//! #[derive(Debug, Arbitrary)]
+//! struct Input<'a> {
+//!     #[arbitrary(with = arbitrary::serde::from_unstructured)]
+//!     config: Config<'a>,
+//!     payload: Vec<u8>,
+//! }
+//!
+//! let data = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8, 9, 7, 9, 3, 2, 3, 8, 4];
+//! let input = Input::arbitrary(&mut Unstructured::new(&data)).unwrap();
+//! println!("{:?}", input);
+//! # }
+//! ```
+
+use {
+    crate::{trace::Segment, Arbitrary, Error, Result, Unstructured},
+    ::serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, Visitor},
+    alloc::{string::String, vec::Vec},
+    core::fmt,
+};
+
+/// Generate a value of any type that implements `serde::Deserialize`.
+///
+/// This is the same as `T::deserialize(Deserializer::new(u))`, and has the
+/// signature that `#[arbitrary(with = ...)]` expects.
+///
+/// Requires the `"serde"` cargo feature.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::Unstructured;
+///
+/// let mut u = Unstructured::new(&[1, 2, 3, 4, 5]);
+/// let value: (u16, Option<u8>) = arbitrary::serde::from_unstructured(&mut u).unwrap();
+/// assert_eq!(value, (0x0201, Some(4)));
+/// ```
+pub fn from_unstructured<'a, T>(u: &mut Unstructured<'a>) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    T::deserialize(Deserializer::new(u))
+}
+
+/// A `serde::Deserializer` that generates values from an [`Unstructured`].
+///
+/// See the [module documentation][self] for how values are decoded.
+///
+/// Requires the `"serde"` cargo feature.
+pub struct Deserializer<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+}
+
+impl<'u, 'a> Deserializer<'u, 'a> {
+    /// Create a deserializer that takes its raw data from the given
+    /// `Unstructured`.
+    pub fn new(u: &'u mut Unstructured<'a>) -> Self {
+        Deserializer { u }
+    }
+}
+
+impl fmt::Debug for Deserializer<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deserializer").finish_non_exhaustive()
+    }
+}
+
+/// The kinds of value that `deserialize_any` chooses from. Kinds that contain
+/// other values come last, so that they can be left out once the depth
+/// budget is spent.
+#[derive(Clone, Copy)]
+enum Kind {
+    Unit,
+    Bool,
+    I64,
+    U64,
+    F64,
+    Char,
+    Str,
+    Bytes,
+    Option,
+    Seq,
+    Map,
+}
+
+const KINDS: &[Kind] = &[
+    Kind::Unit,
+    Kind::Bool,
+    Kind::I64,
+    Kind::U64,
+    Kind::F64,
+    Kind::Char,
+    Kind::Str,
+    Kind::Bytes,
+    Kind::Option,
+    Kind::Seq,
+    Kind::Map,
+];
+
+/// The number of kinds at the start of `KINDS` that contain no other values.
+const SCALAR_KINDS: usize = 8;
+
+macro_rules! deserialize_arbitrary {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'a>,
+            {
+                visitor.$visit(<$ty as Arbitrary<'a>>::arbitrary(self.u)?)
+            }
+        )*
+    };
+}
+
+impl<'u, 'a> de::Deserializer<'a> for Deserializer<'u, 'a> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        let kinds = if self.u.is_depth_exhausted() {
+            &KINDS[..SCALAR_KINDS]
+        } else {
+            KINDS
+        };
+        match *self.u.choose(kinds)? {
+            Kind::Unit => self.deserialize_unit(visitor),
+            Kind::Bool => self.deserialize_bool(visitor),
+            Kind::I64 => self.deserialize_i64(visitor),
+            Kind::U64 => self.deserialize_u64(visitor),
+            Kind::F64 => self.deserialize_f64(visitor),
+            Kind::Char => self.deserialize_char(visitor),
+            Kind::Str => self.deserialize_str(visitor),
+            Kind::Bytes => self.deserialize_bytes(visitor),
+            Kind::Option => self.deserialize_option(visitor),
+            Kind::Seq => self.deserialize_seq(visitor),
+            Kind::Map => self.deserialize_map(visitor),
+        }
+    }
+
+    deserialize_arbitrary! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+        deserialize_char => visit_char(char),
+        deserialize_str => visit_borrowed_str(&'a str),
+        deserialize_string => visit_string(String),
+        deserialize_bytes => visit_borrowed_bytes(&'a [u8]),
+        deserialize_byte_buf => visit_byte_buf(Vec<u8>),
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        if <bool as Arbitrary<'a>>::arbitrary(self.u)? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u.nested(|u| {
+            u.trace_scope(Segment::Type(name), |u| {
+                visitor.visit_newtype_struct(Deserializer::new(u))
+            })
+        })
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u
+            .nested(|u| visitor.visit_seq(Elements::new(u, Fields::Unknown)))
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        visitor.visit_seq(Elements::new(self.u, Fields::Indices(len)))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u.nested(|u| {
+            u.trace_scope(Segment::Type(name), |u| {
+                visitor.visit_seq(Elements::new(u, Fields::Indices(len)))
+            })
+        })
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u
+            .nested(|u| visitor.visit_map(Entries { u, index: 0 }))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u.nested(|u| {
+            u.trace_scope(Segment::Type(name), |u| {
+                visitor.visit_seq(Elements::new(u, Fields::Names(fields)))
+            })
+        })
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u.nested(|u| {
+            u.trace_scope(Segment::Type(name), |u| {
+                if variants.is_empty() {
+                    return Err(Error::EmptyChoose);
+                }
+                // Choose the variant like `#[derive(Arbitrary)]` does.
+                let index = (u64::from(<u32 as Arbitrary<'a>>::arbitrary(u)?)
+                    * variants.len() as u64)
+                    >> 32;
+                visitor.visit_enum(Variant {
+                    u,
+                    index: index as u32,
+                    name: variants[index as usize],
+                })
+            })
+        })
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        // The value is thrown away, so don't spend any data on it.
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// The elements of a sequence, tuple or struct.
+#[derive(Clone, Copy)]
+enum Fields {
+    /// As many elements as the data says, like a `Vec`.
+    Unknown,
+    /// The given number of elements, like a tuple.
+    Indices(usize),
+    /// The named fields of a struct.
+    Names(&'static [&'static str]),
+}
+
+struct Elements<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    fields: Fields,
+    index: usize,
+}
+
+impl<'u, 'a> Elements<'u, 'a> {
+    fn new(u: &'u mut Unstructured<'a>, fields: Fields) -> Self {
+        Elements {
+            u,
+            fields,
+            index: 0,
+        }
+    }
+}
+
+impl<'a> de::SeqAccess<'a> for Elements<'_, 'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'a>,
+    {
+        let segment = match self.fields {
+            Fields::Unknown if self.u.arbitrary_continue() => Segment::Index(self.index),
+            Fields::Indices(len) if self.index < len => Segment::Index(self.index),
+            Fields::Names(names) if self.index < names.len() => Segment::Field(names[self.index]),
+            _ => return Ok(None),
+        };
+        self.index += 1;
+        self.u
+            .trace_scope(segment, |u| seed.deserialize(Deserializer::new(u)))
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self.fields {
+            Fields::Unknown => None,
+            Fields::Indices(len) => Some(len - self.index),
+            Fields::Names(names) => Some(names.len() - self.index),
+        }
+    }
+}
+
+/// The entries of a map, decoded like the `(K, V)` elements of a `Vec`.
+struct Entries<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    index: usize,
+}
+
+impl<'a> de::MapAccess<'a> for Entries<'_, 'a> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'a>,
+    {
+        if !self.u.arbitrary_continue() {
+            return Ok(None);
+        }
+        self.u
+            .trace_scope(Segment::Index(self.index), |u| {
+                seed.deserialize(Deserializer::new(u))
+            })
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'a>,
+    {
+        let segment = Segment::Index(self.index);
+        self.index += 1;
+        self.u
+            .trace_scope(segment, |u| seed.deserialize(Deserializer::new(u)))
+    }
+}
+
+/// The chosen variant of an enum.
+struct Variant<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    index: u32,
+    name: &'static str,
+}
+
+impl<'u, 'a> de::EnumAccess<'a> for Variant<'u, 'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'a>,
+    {
+        let deserializer: de::value::U32Deserializer<Error> = self.index.into_deserializer();
+        let variant = seed.deserialize(deserializer)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'u, 'a> de::VariantAccess<'a> for Variant<'u, 'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'a>,
+    {
+        self.u.trace_scope(Segment::Variant(self.name), |u| {
+            seed.deserialize(Deserializer::new(u))
+        })
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u.trace_scope(Segment::Variant(self.name), |u| {
+            visitor.visit_seq(Elements::new(u, Fields::Indices(len)))
+        })
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'a>,
+    {
+        self.u.trace_scope(Segment::Variant(self.name), |u| {
+            visitor.visit_seq(Elements::new(u, Fields::Names(fields)))
+        })
+    }
+}
+
+/// Errors from `Deserialize` implementations, like an unknown variant or a
+/// value out of range, mean that the raw data is not of the right format.
+impl de::Error for Error {
+    fn custom<T>(_msg: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        Error::IncorrectFormat
+    }
+}
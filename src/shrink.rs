@@ -0,0 +1,127 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Minimizing failing values with [`Shrink`].
+//!
+//! Requires the `"alloc"` cargo feature.
+
+use {
+    crate::Shrink,
+    alloc::{
+        string::String,
+        vec::{self, Vec},
+    },
+};
+
+/// Shrink `value` for as long as `fails` returns `true` for a smaller
+/// candidate, and return the smallest failing value that was found.
+///
+/// `fails` should return `true` for `value` itself; it is only ever called
+/// with the candidates that [`Shrink::shrink`] yields.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::shrink;
+///
+/// let minimal = shrink::minimize(1_000_000_u64, |x| *x >= 1234);
+/// assert_eq!(minimal, 1234);
+/// ```
+pub fn minimize<T: Shrink>(mut value: T, mut fails: impl FnMut(&T) -> bool) -> T {
+    loop {
+        let smaller = value.shrink().find(|candidate| fails(candidate));
+        match smaller {
+            Some(smaller) => value = smaller,
+            None => return value,
+        }
+    }
+}
+
+/// The candidates for shrinking a sequence of items: first without ever
+/// smaller chunks of items, down to single items, and then with each item
+/// shrunk in turn.
+pub(crate) struct ShrinkVec<T> {
+    items: Vec<T>,
+    chunk: usize,
+    start: usize,
+    index: usize,
+    pending: vec::IntoIter<T>,
+}
+
+impl<T> ShrinkVec<T> {
+    pub(crate) fn new(items: Vec<T>) -> Self {
+        ShrinkVec {
+            chunk: items.len(),
+            items,
+            start: 0,
+            index: 0,
+            pending: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<T: Shrink + Clone> Iterator for ShrinkVec<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let len = self.items.len();
+        while self.chunk > 0 {
+            if self.start < len {
+                let end = core::cmp::min(self.start + self.chunk, len);
+                let mut candidate = Vec::with_capacity(len - (end - self.start));
+                candidate.extend_from_slice(&self.items[..self.start]);
+                candidate.extend_from_slice(&self.items[end..]);
+                self.start = end;
+                return Some(candidate);
+            }
+            self.chunk /= 2;
+            self.start = 0;
+        }
+
+        loop {
+            if let Some(item) = self.pending.next() {
+                let mut candidate = self.items.clone();
+                candidate[self.index - 1] = item;
+                return Some(candidate);
+            }
+            // Collect the candidates for one item at a time, since they borrow
+            // the item.
+            let item = self.items.get(self.index)?;
+            self.pending = item.shrink().collect::<Vec<_>>().into_iter();
+            self.index += 1;
+        }
+    }
+}
+
+/// The candidates for shrinking a string, by its characters.
+pub(crate) fn shrink_str(s: &str) -> impl Iterator<Item = String> {
+    ShrinkVec::new(s.chars().collect()).map(|chars| chars.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn shrink_vec_removes_then_shrinks_items() {
+        let candidates: Vec<Vec<u8>> = ShrinkVec::new(vec![1, 2]).collect();
+        assert_eq!(
+            candidates,
+            [vec![], vec![2], vec![1], vec![0, 2], vec![1, 0], vec![1, 1]]
+        );
+    }
+
+    #[test]
+    fn minimize_finds_local_minimum() {
+        let minimal = minimize(vec![5_i32, -300, 12, 7000], |v| {
+            v.iter().any(|x| *x < -100) && v.len() > 1
+        });
+        assert_eq!(minimal, [0, -101]);
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Sources of raw data other than a byte slice.
+//!
+//! An [`Unstructured`][crate::Unstructured] usually wraps a byte slice, e.g.
+//! the input a fuzzer provides. With
+//! [`Unstructured::from_source`][crate::Unstructured::from_source] it can
+//! instead pull its data from any [`ByteSource`], like a seeded
+//! pseudo-random number generator ([`Prng`]) or a reader ([`Reader`]).
+//!
+//! ```
+//! use arbitrary::{source::Prng, Arbitrary, Unstructured};
+//!
+//! let mut u = Unstructured::from_source(Prng::new(42));
+//! let xs = Vec::<(u32, String)>::arbitrary(&mut u).unwrap();
+//! # let _ = xs;
+//! ```
+//!
+//! Data from a `ByteSource` is consumed as it is read, so it cannot be
+//! borrowed: generating a `&[u8]` or a `&str` of non-zero length fails with
+//! [`Error::NotEnoughData`][crate::Error::NotEnoughData], and
+//! [`Unstructured::take_rest`][crate::Unstructured::take_rest] returns an empty
+//! slice. Owned types like `Vec<u8>` and `String` work with any source.
+//!
+//! Requires the `"alloc"` cargo feature.
+
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::io;
+
+/// A source of raw data for an [`Unstructured`][crate::Unstructured].
+pub trait ByteSource {
+    /// Write the next bytes of data to a prefix of `buf` and return how many
+    /// bytes were written.
+    ///
+    /// Writing fewer than `buf.len()` bytes means that the source is
+    /// exhausted.
+    fn fill(&mut self, buf: &mut [u8]) -> usize;
+
+    /// The number of bytes that are left in this source, or `None` if that is
+    /// unknown or the source is endless.
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<S: ByteSource + ?Sized> ByteSource for &mut S {
+    fn fill(&mut self, buf: &mut [u8]) -> usize {
+        (**self).fill(buf)
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        (**self).remaining()
+    }
+}
+
+/// An endless source of pseudo-random bytes, determined by a seed.
+///
+/// The same seed always produces the same bytes, on every platform and in
+/// every version of this crate with the same major version.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{source::Prng, Arbitrary, Unstructured};
+///
+/// let a = u64::arbitrary(&mut Unstructured::from_source(Prng::new(7))).unwrap();
+/// let b = u64::arbitrary(&mut Unstructured::from_source(Prng::new(7))).unwrap();
+/// assert_eq!(a, b);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Prng {
+    state: u64,
+}
+
+impl Prng {
+    /// Create a new `Prng` from the given seed.
+    pub fn new(seed: u64) -> Self {
+        Prng { state: seed }
+    }
+
+    // SplitMix64, see https://prng.di.unimi.it/splitmix64.c
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl ByteSource for Prng {
+    fn fill(&mut self, buf: &mut [u8]) -> usize {
+        for chunk in buf.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+        buf.len()
+    }
+}
+
+/// A source that reads its data from an [`io::Read`].
+///
+/// The source is exhausted when the reader reaches the end of its data or
+/// fails with an error other than [`io::ErrorKind::Interrupted`].
+///
+/// Requires the `"std"` cargo feature.
+///
+/// # Example
+///
+/// ```
+/// use arbitrary::{source::Reader, Arbitrary, Unstructured};
+///
+/// let data: &[u8] = &[1, 2, 3, 4];
+/// let mut u = Unstructured::from_source(Reader::new(data));
+/// assert_eq!(u32::arbitrary(&mut u).unwrap(), 0x04030201);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> Reader<R> {
+    /// Create a new `Reader` that reads from the given reader.
+    pub fn new(reader: R) -> Self {
+        Reader {
+            reader,
+            done: false,
+        }
+    }
+
+    /// Get back the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read> ByteSource for Reader<R> {
+    fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let mut filled = 0;
+        while !self.done && filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => self.done = true,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => self.done = true,
+            }
+        }
+        filled
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        if self.done {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+/// The source of an [`Unstructured`][crate::Unstructured], with the bytes that
+/// were read from it but not used put back in front of it.
+pub(crate) struct Unread<'a> {
+    source: Box<dyn ByteSource + Send + Sync + 'a>,
+    /// The put back bytes, last one first.
+    unread: Vec<u8>,
+}
+
+impl<'a> Unread<'a> {
+    pub(crate) fn new(source: Box<dyn ByteSource + Send + Sync + 'a>) -> Self {
+        Unread {
+            source,
+            unread: Vec::new(),
+        }
+    }
+
+    /// Put `bytes` back, so that the next read starts with them.
+    pub(crate) fn unread(&mut self, bytes: &[u8]) {
+        self.unread.extend(bytes.iter().rev());
+    }
+}
+
+impl ByteSource for Unread<'_> {
+    fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let n = core::cmp::min(buf.len(), self.unread.len());
+        for byte in buf[..n].iter_mut() {
+            *byte = self.unread.pop().unwrap();
+        }
+        n + self.source.fill(&mut buf[n..])
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.source
+            .remaining()
+            .map(|remaining| remaining + self.unread.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prng_is_deterministic() {
+        let mut a = [0; 20];
+        let mut b = [0; 20];
+        assert_eq!(Prng::new(1).fill(&mut a), 20);
+        assert_eq!(Prng::new(1).fill(&mut b), 20);
+        assert_eq!(a, b);
+
+        let mut c = [0; 20];
+        Prng::new(2).fill(&mut c);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn prng_matches_splitmix64() {
+        // The first output of SplitMix64 seeded with 0.
+        let mut buf = [0; 8];
+        Prng::new(0).fill(&mut buf);
+        assert_eq!(u64::from_le_bytes(buf), 0xe220_a839_7b1d_cdaf);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader_is_exhausted_at_end_of_data() {
+        let data: &[u8] = &[1, 2, 3];
+        let mut reader = Reader::new(data);
+        assert_eq!(reader.remaining(), None);
+
+        let mut buf = [0; 2];
+        assert_eq!(reader.fill(&mut buf), 2);
+        assert_eq!(buf, [1, 2]);
+        assert_eq!(reader.fill(&mut buf), 1);
+        assert_eq!(buf[0], 3);
+        assert_eq!(reader.remaining(), Some(0));
+        assert_eq!(reader.fill(&mut buf), 0);
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Testing a stateful system against a model, with arbitrary sequences of
+//! operations.
+//!
+//! A [`StateMachine`] describes a system under test, a simpler model of it,
+//! and the operations on both. [`arbitrary_ops`] generates a sequence of
+//! operations that are valid in the model state that they are applied to, and
+//! [`run_ops`] applies them to both the system and the model, checking after
+//! every operation that the system's output matches the model's expectations.
+//!
+//! Both work in any `Arbitrary` implementation or fuzz target. In ordinary
+//! tests, [`check`] runs many generated sequences with a
+//! [`check::Config`][crate::check::Config], and panics with a minimal
+//! sequence of operations that makes the system diverge from the model.
+//!
+//! Requires the `"alloc"` cargo feature, and [`check`] and [`try_check`]
+//! require the `"std"` cargo feature.
+//!
+//! ```
+//! # #[cfg(feature = "std")] fn foo() {
+//! use arbitrary::{check::Config, stateful::{self, StateMachine}, Result, Unstructured};
+//! use std::collections::VecDeque;
+//!
+//! #[derive(Clone, Debug)]
+//! enum Op {
+//!     Push(u8),
+//!     Pop,
+//! }
+//!
+//! // Test `VecDeque` as a stack, against a `Vec` as the model.
+//! struct Stack;
+//!
+//! impl StateMachine for Stack {
+//!     type State = Vec<u8>;
+//!     type Sut = VecDeque<u8>;
+//!     type Op = Op;
+//!     type Output = Option<u8>;
+//!
+//!     fn init_state(&self) -> Vec<u8> {
+//!         Vec::new()
+//!     }
+//!
+//!     fn init_sut(&self, _: &Vec<u8>) -> VecDeque<u8> {
+//!         VecDeque::new()
+//!     }
+//!
+//!     fn arbitrary_op(&self, u: &mut Unstructured<'_>, state: &Vec<u8>) -> Result<Op> {
+//!         // Only pop from non-empty stacks.
+//!         if state.is_empty() || u.arbitrary()? {
+//!             Ok(Op::Push(u.arbitrary()?))
+//!         } else {
+//!             Ok(Op::Pop)
+//!         }
+//!     }
+//!
+//!     fn precondition(&self, state: &Vec<u8>, op: &Op) -> bool {
+//!         !matches!(op, Op::Pop) || !state.is_empty()
+//!     }
+//!
+//!     fn apply(&self, sut: &mut VecDeque<u8>, op: &Op) -> Option<u8> {
+//!         match op {
+//!             Op::Push(x) => {
+//!                 sut.push_back(*x);
+//!                 None
+//!             }
+//!             Op::Pop => sut.pop_back(),
+//!         }
+//!     }
+//!
+//!     fn postcondition(&self, state: &Vec<u8>, op: &Op, output: &Option<u8>) -> bool {
+//!         match op {
+//!             Op::Push(_) => output.is_none(),
+//!             Op::Pop => output.as_ref() == state.last(),
+//!         }
+//!     }
+//!
+//!     fn next_state(&self, state: &mut Vec<u8>, op: &Op) {
+//!         match op {
+//!             Op::Push(x) => state.push(*x),
+//!             Op::Pop => {
+//!                 state.pop();
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! stateful::check(&Stack, &Config::new());
+//! # }
+//! ```
+
+use {
+    crate::{trace::Segment, Result, Unstructured},
+    alloc::{format, string::String, vec::Vec},
+    core::{fmt, ops::ControlFlow},
+};
+
+#[cfg(feature = "std")]
+use crate::check::{Config, Failure};
+
+/// A system under test, a model of it, and the operations on both.
+///
+/// Requires the `"alloc"` cargo feature.
+pub trait StateMachine {
+    /// The state of the model.
+    type State: fmt::Debug;
+
+    /// The system under test.
+    type Sut;
+
+    /// An operation on the system and the model.
+    type Op: fmt::Debug;
+
+    /// What the system returns for an operation.
+    type Output: fmt::Debug;
+
+    /// The maximum number of operations that [`arbitrary_ops`] generates.
+    const MAX_OPS: u32 = 64;
+
+    /// The initial state of the model.
+    fn init_state(&self) -> Self::State;
+
+    /// A new system under test, in the given initial state of the model.
+    fn init_sut(&self, state: &Self::State) -> Self::Sut;
+
+    /// Generate an operation that is valid in the given state of the model.
+    fn arbitrary_op(&self, u: &mut Unstructured<'_>, state: &Self::State) -> Result<Self::Op>;
+
+    /// Is the operation valid in the given state of the model?
+    ///
+    /// Operations for which this returns `false` are skipped, both when they
+    /// are generated and when they are run. This matters when a sequence of
+    /// operations is shrunk or written by hand, since removing an operation
+    /// may make later operations invalid.
+    fn precondition(&self, state: &Self::State, op: &Self::Op) -> bool {
+        let _ = (state, op);
+        true
+    }
+
+    /// Apply the operation to the system under test.
+    fn apply(&self, sut: &mut Self::Sut, op: &Self::Op) -> Self::Output;
+
+    /// Is the system's output for the operation right, given the state of
+    /// the model before the operation?
+    fn postcondition(&self, state: &Self::State, op: &Self::Op, output: &Self::Output) -> bool;
+
+    /// Apply the operation to the model.
+    fn next_state(&self, state: &mut Self::State, op: &Self::Op);
+}
+
+/// Generate up to [`StateMachine::MAX_OPS`] operations, each of which is
+/// valid in the model state that the operations before it lead to.
+///
+/// This generates each operation in a [`Unstructured::trace_scope`], so that
+/// the operations show up in traces and can be shrunk one by one.
+///
+/// Requires the `"alloc"` cargo feature.
+pub fn arbitrary_ops<M>(machine: &M, u: &mut Unstructured<'_>) -> Result<Vec<M::Op>>
+where
+    M: StateMachine + ?Sized,
+{
+    let mut state = machine.init_state();
+    let mut ops = Vec::new();
+    let mut index = 0;
+    u.arbitrary_loop(None, Some(M::MAX_OPS), |u| {
+        let op = u.trace_scope(Segment::Index(index), |u| machine.arbitrary_op(u, &state))?;
+        index += 1;
+        if machine.precondition(&state, &op) {
+            machine.next_state(&mut state, &op);
+            ops.push(op);
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    Ok(ops)
+}
+
+/// Apply the operations to a new system under test and to the model, and
+/// check the postcondition of every operation.
+///
+/// Operations that are not valid in the model state are skipped.
+///
+/// Requires the `"alloc"` cargo feature.
+pub fn run_ops<M>(machine: &M, ops: &[M::Op]) -> core::result::Result<(), Divergence>
+where
+    M: StateMachine + ?Sized,
+{
+    let mut state = machine.init_state();
+    let mut sut = machine.init_sut(&state);
+    for (step, op) in ops.iter().enumerate() {
+        if !machine.precondition(&state, op) {
+            continue;
+        }
+        let output = machine.apply(&mut sut, op);
+        if !machine.postcondition(&state, op, &output) {
+            return Err(Divergence {
+                step,
+                message: format!(
+                    "unexpected output {:?} for {:?} in state {:?}",
+                    output, op, state
+                ),
+            });
+        }
+        machine.next_state(&mut state, op);
+    }
+    Ok(())
+}
+
+/// Check many arbitrary sequences of operations with [`run_ops`].
+///
+/// # Panics
+///
+/// Panics with the [`Failure`] that [`try_check`] returns, whose value is
+/// the shrunk sequence of operations.
+///
+/// Requires the `"std"` cargo feature.
+#[cfg(feature = "std")]
+pub fn check<M>(machine: &M, config: &Config)
+where
+    M: StateMachine + ?Sized,
+{
+    if let Err(failure) = try_check(machine, config) {
+        panic!("{}", failure);
+    }
+}
+
+/// Check many arbitrary sequences of operations with [`run_ops`], and
+/// return the first failure instead of panicking.
+///
+/// A panic in the system under test counts as a failure.
+///
+/// Requires the `"std"` cargo feature.
+#[cfg(feature = "std")]
+pub fn try_check<M>(machine: &M, config: &Config) -> core::result::Result<(), Failure>
+where
+    M: StateMachine + ?Sized,
+{
+    config.try_run_with(
+        |mut u| arbitrary_ops(machine, &mut u),
+        |ops| run_ops(machine, &ops),
+    )
+}
+
+/// The system under test did not behave like the model.
+///
+/// Requires the `"alloc"` cargo feature.
+#[derive(Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Divergence {
+    /// The index of the operation whose postcondition failed.
+    pub step: usize,
+    /// What went wrong.
+    pub message: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "step {}: {}", self.step, self.message)
+    }
+}
+
+impl fmt::Debug for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Divergence {}
@@ -1,5 +1,8 @@
 use {
-    super::{Arbitrary, Result, Unstructured},
+    super::{
+        source::{ByteSource, Prng},
+        Arbitrary, Dearbitrary, Result, Unstructured,
+    },
     std::{collections::HashSet, fmt::Debug, hash::Hash, rc::Rc, sync::Arc},
 };
 
@@ -314,3 +317,550 @@ fn size_hint_for_tuples() {
     );
     assert_eq!((1, None), <(u8, Vec<u8>) as Arbitrary>::size_hint(0));
 }
+
+/// Call `f` with every buffer up to length 6 containing the bytes `0x00`,
+/// `0x01`, `0x61` and `0xff`, and with some longer pseudo-random buffers.
+fn for_each_buffer(mut f: impl FnMut(&[u8])) {
+    let bytes = [0, 1, b'a', 0xff];
+    let max_len = 6;
+
+    let mut buf = Vec::with_capacity(max_len);
+
+    let mut g = exhaustigen::Gen::new();
+    while !g.done() {
+        let len = g.gen(max_len);
+        buf.clear();
+        buf.extend(std::iter::repeat_with(|| bytes[g.gen(bytes.len() - 1)]).take(len));
+        f(&buf);
+    }
+
+    for len in (7..70_000).step_by(997) {
+        buf.resize(len, 0);
+        Prng::new(len as u64).fill(&mut buf);
+        f(&buf);
+    }
+}
+
+/// Assert that turning every generated value of the given type back into raw
+/// data and generating a value from that again yields the same value, as
+/// compared by the optional key function.
+macro_rules! assert_round_trips {
+    ($ty:ty) => {
+        for_each_buffer(|buf| {
+            let value = match <$ty as Arbitrary>::arbitrary(&mut Unstructured::new(buf)) {
+                Ok(value) => value,
+                // Some types, like `NonZeroU32`, reject some inputs.
+                Err(_) => return,
+            };
+            let data = value.dearbitrary_to_vec().unwrap();
+            let round_tripped = <$ty as Arbitrary>::arbitrary(&mut Unstructured::new(&data))
+                .unwrap_or_else(|e| panic!("failed to round trip {value:?} from {buf:?}: {e}"));
+            assert_eq!(
+                round_tripped, value,
+                "failed to round trip {value:?} from {buf:?} via {data:?}",
+            );
+        })
+    };
+    ($ty:ty, $key:expr) => {
+        for_each_buffer(|buf| {
+            let value = match <$ty as Arbitrary>::arbitrary(&mut Unstructured::new(buf)) {
+                Ok(value) => value,
+                // Some types, like `NonZeroU32`, reject some inputs.
+                Err(_) => return,
+            };
+            let data = value.dearbitrary_to_vec().unwrap();
+            let round_tripped = <$ty as Arbitrary>::arbitrary(&mut Unstructured::new(&data))
+                .unwrap_or_else(|e| panic!("failed to round trip {value:?} from {buf:?}: {e}"));
+            assert_eq!(
+                $key(&round_tripped),
+                $key(&value),
+                "failed to round trip {value:?} from {buf:?} via {data:?}",
+            );
+        })
+    };
+}
+
+#[test]
+fn dearbitrary_round_trips_for_primitives() {
+    assert_round_trips!(u8);
+    assert_round_trips!(u16);
+    assert_round_trips!(u32);
+    assert_round_trips!(u64);
+    assert_round_trips!(u128);
+    assert_round_trips!(usize);
+    assert_round_trips!(i8);
+    assert_round_trips!(i16);
+    assert_round_trips!(i32);
+    assert_round_trips!(i64);
+    assert_round_trips!(i128);
+    assert_round_trips!(isize);
+    assert_round_trips!(f32, |x: &f32| x.to_bits());
+    assert_round_trips!(f64, |x: &f64| x.to_bits());
+    assert_round_trips!(bool);
+    assert_round_trips!(char);
+    assert_round_trips!(());
+    assert_round_trips!(std::num::NonZeroU32);
+    assert_round_trips!(std::num::NonZeroI64);
+    assert_round_trips!(std::num::Wrapping<i16>);
+    assert_round_trips!(std::time::Duration);
+    assert_round_trips!(std::marker::PhantomData<String>);
+    assert_round_trips!(std::iter::Empty<u8>, |_: &std::iter::Empty<u8>| ());
+    assert_round_trips!(
+        std::marker::PhantomPinned,
+        |_: &std::marker::PhantomPinned| ()
+    );
+    assert_round_trips!(
+        std::sync::atomic::AtomicBool,
+        |x: &std::sync::atomic::AtomicBool| { x.load(std::sync::atomic::Ordering::SeqCst) }
+    );
+    assert_round_trips!(
+        std::sync::atomic::AtomicUsize,
+        |x: &std::sync::atomic::AtomicUsize| { x.load(std::sync::atomic::Ordering::SeqCst) }
+    );
+    assert_round_trips!(
+        std::sync::atomic::AtomicIsize,
+        |x: &std::sync::atomic::AtomicIsize| { x.load(std::sync::atomic::Ordering::SeqCst) }
+    );
+}
+
+#[test]
+fn dearbitrary_round_trips_for_compound_types() {
+    assert_round_trips!(Option<u8>);
+    assert_round_trips!(Result<u16, bool>);
+    assert_round_trips!((u8, bool, i32));
+    assert_round_trips!([u16; 3]);
+    assert_round_trips!(std::ops::Range<u8>);
+    assert_round_trips!(std::ops::RangeInclusive<i32>);
+    assert_round_trips!(std::ops::RangeFrom<u8>);
+    assert_round_trips!(std::ops::RangeTo<u8>);
+    assert_round_trips!(std::ops::RangeToInclusive<u8>);
+    assert_round_trips!(std::ops::Bound<u8>);
+    assert_round_trips!(std::cell::Cell<u16>);
+    assert_round_trips!(std::cell::RefCell<Vec<u8>>);
+    assert_round_trips!(std::sync::Mutex<u8>, |x: &std::sync::Mutex<u8>| *x
+        .lock()
+        .unwrap());
+    assert_round_trips!(Box<u8>);
+    assert_round_trips!(Rc<u8>);
+    assert_round_trips!(Arc<u8>);
+    assert_round_trips!(Box<[u16]>);
+    assert_round_trips!(Rc<[u16]>);
+    assert_round_trips!(Arc<[u16]>);
+}
+
+#[test]
+fn dearbitrary_round_trips_for_strings_and_bytes() {
+    assert_round_trips!(&[u8]);
+    assert_round_trips!(&str);
+    assert_round_trips!(String);
+    assert_round_trips!(Box<str>);
+    assert_round_trips!(Rc<str>);
+    assert_round_trips!(Arc<str>);
+    assert_round_trips!(std::borrow::Cow<'_, str>);
+    assert_round_trips!(std::ffi::CString);
+    assert_round_trips!(std::ffi::OsString);
+    assert_round_trips!(std::path::PathBuf);
+    assert_round_trips!((&[u8], &str, &[u8]));
+}
+
+#[test]
+fn dearbitrary_round_trips_for_collections() {
+    use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, LinkedList, VecDeque};
+
+    assert_round_trips!(Vec<u8>);
+    assert_round_trips!(Vec<Vec<u8>>);
+    assert_round_trips!(Vec<String>);
+    assert_round_trips!(Vec<(u32, &str)>);
+    assert_round_trips!(VecDeque<u16>);
+    assert_round_trips!(LinkedList<u16>);
+    assert_round_trips!(BTreeSet<u16>);
+    assert_round_trips!(BTreeMap<u8, String>);
+    assert_round_trips!(HashSet<u16>);
+    assert_round_trips!(HashMap<u8, Vec<u8>>);
+    assert_round_trips!(BinaryHeap<u16>, |x: &BinaryHeap<u16>| x
+        .clone()
+        .into_sorted_vec());
+}
+
+#[test]
+fn dearbitrary_round_trips_for_net() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+    assert_round_trips!(Ipv4Addr);
+    assert_round_trips!(Ipv6Addr);
+    assert_round_trips!(IpAddr);
+    assert_round_trips!(SocketAddrV4);
+    assert_round_trips!(SocketAddrV6);
+    assert_round_trips!(SocketAddr);
+}
+
+#[test]
+fn dearbitrary_unrepresentable() {
+    use crate::Error;
+
+    let (start, end) = (5_u8, 3);
+    assert_eq!(
+        (start..end).dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+    assert_eq!(
+        (start..=end).dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+}
+
+#[test]
+fn arbitrary_from_prng_source() {
+    use crate::source::Prng;
+    use std::collections::{BTreeMap, HashMap, VecDeque};
+
+    fn generate_all(seed: u64) -> Result<impl Debug + PartialEq> {
+        let mut u = Unstructured::from_source(Prng::new(seed));
+        Ok((
+            <(u8, i64, u128, f64, bool, char)>::arbitrary(&mut u)?,
+            <Option<[u16; 4]>>::arbitrary(&mut u)?,
+            <Vec<Vec<u8>>>::arbitrary(&mut u)?,
+            String::arbitrary(&mut u)?,
+            <Box<str>>::arbitrary(&mut u)?,
+            <Rc<str>>::arbitrary(&mut u)?,
+            <Arc<str>>::arbitrary(&mut u)?,
+            <HashMap<u8, String>>::arbitrary(&mut u)?,
+            <BTreeMap<i32, Vec<bool>>>::arbitrary(&mut u)?,
+            <VecDeque<std::time::Duration>>::arbitrary(&mut u)?,
+            std::path::PathBuf::arbitrary(&mut u)?,
+        ))
+    }
+
+    let mut saw_long_string = false;
+    for seed in 0..100 {
+        let values = generate_all(seed).unwrap();
+        assert_eq!(values, generate_all(seed).unwrap());
+        saw_long_string |= format!("{:?}", values).len() > 200;
+    }
+    assert!(saw_long_string);
+}
+
+#[test]
+fn arbitrary_from_source_cannot_borrow() {
+    use crate::{source::Prng, Error};
+
+    let mut u = Unstructured::from_source(Prng::new(0));
+    assert!(!u.is_empty());
+    assert_eq!(u.len(), usize::MAX);
+    assert_eq!(u.bytes(0), Ok(&[][..]));
+    assert_eq!(u.bytes(1), Err(Error::NotEnoughData));
+    assert_eq!(u.peek_bytes(1), None);
+    while <&[u8]>::arbitrary(&mut u) == Ok(&[]) {}
+    assert_eq!(<&[u8]>::arbitrary(&mut u), Err(Error::NotEnoughData));
+    assert_eq!(u.take_rest(), &[]);
+}
+
+#[test]
+fn arbitrary_from_reader_source() {
+    use crate::source::Reader;
+
+    // Types that don't need a length read the same data from a reader as
+    // from a slice.
+    for_each_buffer(|data| {
+        type T = (u32, bool, char, Option<u16>, [i8; 3]);
+        let from_slice = T::arbitrary(&mut Unstructured::new(data));
+        let mut u = Unstructured::from_source(Reader::new(data));
+        assert_eq!(T::arbitrary(&mut u), from_slice);
+    });
+
+    let data: &[u8] = &[2, b'h', b'i', 1, 7, 0];
+    let mut u = Unstructured::from_source(Reader::new(data));
+    assert_eq!(String::arbitrary(&mut u).unwrap(), "hi");
+    assert_eq!(<Vec<u8>>::arbitrary(&mut u).unwrap(), [7]);
+    assert!(!u.is_empty());
+    assert_eq!(u32::arbitrary(&mut u).unwrap(), 0);
+    assert!(u.is_empty());
+
+    // A string leaves the bytes after the valid UTF-8 prefix of its length
+    // for the next value, like it does in a byte slice.
+    let data: &[u8] = &[4, b'h', 0xe2, 0x82, b'i'];
+    let mut u = Unstructured::from_source(Reader::new(data));
+    assert_eq!(String::arbitrary(&mut u).unwrap(), "h");
+    assert_eq!(u8::arbitrary(&mut u).unwrap(), 0xe2);
+    assert_eq!(u8::arbitrary(&mut u).unwrap(), 0x82);
+    assert_eq!(u8::arbitrary(&mut u).unwrap(), b'i');
+
+    let mut u = Unstructured::new(&[b'h', 0xe2, 0x82, b'i', 3]);
+    assert_eq!(String::arbitrary(&mut u).unwrap(), "h");
+    assert_eq!(u.peek_bytes(3), Some(&[0xe2, 0x82, b'i'][..]));
+}
+
+#[test]
+fn shrink_integers_and_floats() {
+    use crate::Shrink;
+
+    assert_eq!(0_u32.shrink().count(), 0);
+    assert_eq!(
+        100_u8.shrink().collect::<Vec<_>>(),
+        [0, 50, 75, 88, 94, 97, 99]
+    );
+    assert_eq!((-8_i32).shrink().collect::<Vec<_>>(), [0, 8, -4, -6, -7]);
+    assert_eq!(i64::MIN.shrink().next(), Some(0));
+    assert_eq!(true.shrink().collect::<Vec<_>>(), [false]);
+    assert_eq!(2.5_f64.shrink().collect::<Vec<_>>(), [0.0, 2.0, 1.0]);
+    assert_eq!(f32::NAN.shrink().collect::<Vec<_>>(), [0.0]);
+    assert_eq!(
+        f64::NEG_INFINITY.shrink().collect::<Vec<_>>(),
+        [0.0, f64::INFINITY]
+    );
+    assert_eq!((-0.0_f64).shrink().count(), 0);
+}
+
+#[test]
+fn shrink_compound_types() {
+    use crate::{shrink::minimize, Shrink};
+    use std::collections::{BTreeMap, HashSet};
+
+    assert_eq!(
+        Some(3_u8).shrink().collect::<Vec<_>>(),
+        [None, Some(0), Some(2)]
+    );
+    assert_eq!(
+        (1_u8, false, 2_u8).shrink().collect::<Vec<_>>(),
+        [(0, false, 2), (1, false, 0), (1, false, 1)]
+    );
+    assert_eq!(
+        minimize(String::from("hello, world"), |s| s.contains('w')),
+        "w"
+    );
+    assert_eq!(minimize(&b"hello, world"[..], |s| s.contains(&b'w')), b"w");
+    assert_eq!(minimize("héllo", |s| s.contains('é')), "é");
+    assert_eq!(
+        minimize((0..50).collect::<HashSet<u32>>(), |s| s.contains(&42)),
+        [42].into_iter().collect()
+    );
+    assert_eq!(
+        minimize(
+            (0..50_i64).map(|k| (k, -k)).collect::<BTreeMap<_, _>>(),
+            |m| { m.values().any(|v| *v < -30) }
+        ),
+        [(0, -31)].into_iter().collect()
+    );
+    assert_eq!(
+        minimize(Box::<[u8]>::from(&[9, 9, 9][..]), |b| b.len() > 1),
+        Box::<[u8]>::from(&[0, 0][..])
+    );
+}
+
+#[test]
+fn shrink_always_terminates() {
+    use crate::shrink::minimize;
+
+    // Every value still fails, so shrinking only stops once there are no
+    // candidates left.
+    for_each_buffer(|data| {
+        if let Ok(value) =
+            <(Vec<i16>, Option<String>, f32, char)>::arbitrary(&mut Unstructured::new(data))
+        {
+            let minimal = minimize(value, |_| true);
+            assert_eq!(minimal.0, []);
+            assert_eq!(minimal.1, None);
+            assert_eq!(minimal.2, 0.0);
+            assert_eq!(minimal.3, '\0');
+        }
+    });
+}
+
+#[test]
+fn mutate_numbers() {
+    use crate::Mutate;
+
+    let mutated = |mut x: i8, data: &[u8]| {
+        x.mutate(&mut Unstructured::new(data)).unwrap();
+        x
+    };
+    // Flip the lowest bit.
+    assert_eq!(mutated(0b1010, &[0, 0]), 0b1011);
+    // Jump to a boundary value.
+    assert_eq!(mutated(10, &[1, 3]), i8::MIN);
+    assert_eq!(mutated(10, &[1, 4]), i8::MAX);
+    // Nudge the value up or down.
+    assert_eq!(mutated(10, &[2, 4, 1]), 15);
+    assert_eq!(mutated(i8::MIN, &[2, 0, 0]), i8::MAX);
+    // Generate a new value.
+    assert_eq!(mutated(10, &[3, 42]), 42);
+
+    let mut x = 1.5_f64;
+    x.mutate(&mut Unstructured::new(&[2])).unwrap();
+    assert_eq!(x, -1.5);
+
+    let mut nonzero = std::num::NonZeroU8::new(1).unwrap();
+    nonzero.mutate(&mut Unstructured::new(&[0, 0])).unwrap();
+    assert_eq!(nonzero.get(), 1);
+}
+
+#[test]
+fn mutate_collections() {
+    use crate::Mutate;
+
+    let mutated = |mut v: Vec<u8>, data: &[u8]| {
+        v.mutate(&mut Unstructured::new(data)).unwrap();
+        v
+    };
+    assert_eq!(mutated(vec![], &[]), [0]);
+    assert_eq!(mutated(vec![1, 2, 3], &[0, 1, 2, 7, 1]), [1, 10, 3]);
+    assert_eq!(mutated(vec![1, 2, 3], &[1, 3, 7]), [1, 2, 3, 7]);
+    assert_eq!(mutated(vec![1, 2, 3], &[2, 1]), [1, 3]);
+    assert_eq!(mutated(vec![1, 2, 3], &[3, 0, 7]), [7, 2, 3]);
+    assert_eq!(mutated(vec![1, 2, 3], &[4, 0, 2]), [3, 2, 1]);
+
+    let mut s = String::from("héllo");
+    s.mutate(&mut Unstructured::new(&[2, 1])).unwrap();
+    assert_eq!(s, "hllo");
+
+    let mut tuple = (1_u8, Some(2_u8), false);
+    tuple.mutate(&mut Unstructured::new(&[2])).unwrap();
+    assert_eq!(tuple, (1, Some(2), true));
+}
+
+#[test]
+fn mutate_keeps_invariants() {
+    use crate::Mutate;
+    use std::{collections::BTreeMap, num::NonZeroU16, ops::Range};
+
+    // Keep mutating the same value with every buffer.
+    let mut value: (Vec<u8>, Range<i32>, NonZeroU16, BTreeMap<u8, String>, char) = (
+        vec![1, 2, 3],
+        -5..5,
+        NonZeroU16::new(300).unwrap(),
+        [(1, "one".into()), (2, "two".into())].into_iter().collect(),
+        'x',
+    );
+    for_each_buffer(|data| {
+        let mut u = Unstructured::new(data);
+        while !u.is_empty() {
+            value.mutate(&mut u).unwrap();
+            assert!(value.1.start <= value.1.end);
+        }
+    });
+}
+
+#[test]
+fn arbitrary_in_range() {
+    use crate::ArbitraryInRange;
+    use std::{num::NonZeroI8, ops::Bound, time::Duration};
+
+    let one = NonZeroI8::new(1).unwrap();
+    let minus_two = NonZeroI8::new(-2).unwrap();
+    let two = NonZeroI8::new(2).unwrap();
+
+    for_each_buffer(|data| {
+        let mut u = Unstructured::new(data);
+        let port = u16::arbitrary_in_range(&mut u, 1024..=65535).unwrap();
+        assert!(port >= 1024);
+        let x = i8::arbitrary_in_range(&mut u, (Bound::Excluded(-5), Bound::Excluded(5))).unwrap();
+        assert!((-4..=4).contains(&x));
+        u64::arbitrary_in_range(&mut u, ..).unwrap();
+
+        let f = f64::arbitrary_in_range(&mut u, 0.0..1.0).unwrap();
+        assert!((0.0..1.0).contains(&f), "{f}");
+        let f = f32::arbitrary_in_range(&mut u, ..=-1.0).unwrap();
+        assert!(f <= -1.0, "{f}");
+        let f = f64::arbitrary_in_range(&mut u, ..).unwrap();
+        assert!(!f.is_nan());
+
+        let c = char::arbitrary_in_range(&mut u, 'a'..='z').unwrap();
+        assert!(c.is_ascii_lowercase());
+        let c = char::arbitrary_in_range(&mut u, '\u{D000}'..'\u{E010}').unwrap();
+        assert!(('\u{D000}'..'\u{E010}').contains(&c));
+
+        let n = NonZeroI8::arbitrary_in_range(&mut u, minus_two..=two).unwrap();
+        assert!((-2..=2).contains(&n.get()));
+        let n = NonZeroI8::arbitrary_in_range(&mut u, one..).unwrap();
+        assert!(n.get() > 0);
+
+        let d =
+            Duration::arbitrary_in_range(&mut u, Duration::from_secs(1)..Duration::from_secs(2))
+                .unwrap();
+        assert!(d >= Duration::from_secs(1) && d < Duration::from_secs(2));
+    });
+
+    // Every value in range can be generated, and nothing else.
+    let mut generated: HashSet<i8> = HashSet::new();
+    for byte in 0..=u8::MAX {
+        let data = [byte];
+        let mut u = Unstructured::new(&data);
+        generated.insert(
+            NonZeroI8::arbitrary_in_range(&mut u, minus_two..=two)
+                .unwrap()
+                .get(),
+        );
+    }
+    assert_eq!(generated, [-2, -1, 1, 2].into_iter().collect());
+
+    assert_eq!(u16::size_hint_in_range(0, 1024..=65535), (2, Some(2)));
+    assert_eq!(u64::size_hint_in_range(0, 7..8), (0, Some(0)));
+    assert_eq!(char::size_hint_in_range(0, 'a'..='z'), (1, Some(1)));
+    assert_eq!(f64::size_hint_in_range(0, ..), (8, Some(8)));
+}
+
+#[test]
+#[should_panic]
+fn arbitrary_in_empty_range() {
+    use crate::ArbitraryInRange;
+    let _ = u8::arbitrary_in_range(&mut Unstructured::new(&[]), 5..5);
+}
+
+#[test]
+fn choose_weighted_matches_weights() {
+    // Evenly spaced `u32`s hit every choice exactly in proportion to its
+    // weight.
+    let weights = [3, 0, 1, 4];
+    let mut counts = [0; 4];
+    for i in 0..1_u32 << 12 {
+        let data = (i << 20).to_le_bytes();
+        let mut u = Unstructured::new(&data);
+        counts[u.choose_weighted_index(&weights).unwrap()] += 1;
+    }
+    assert_eq!(counts, [1536, 0, 512, 2048]);
+
+    let mut u = Unstructured::new(&[0xff; 4]);
+    assert_eq!(
+        u.choose_weighted(&['a', 'b', 'c'], &[1, 1, 0]).unwrap(),
+        &'b'
+    );
+    assert!(u.is_empty());
+
+    assert!(Unstructured::new(&[]).choose_weighted_index(&[]).is_err());
+    assert!(Unstructured::new(&[1; 4])
+        .choose_weighted_index(&[0, 0])
+        .is_err());
+}
+
+#[test]
+fn choose_weighted_with_equal_weights_matches_multiply_shift() {
+    for x in [0, 1, 0x5555_5555, 0x8000_0000, 0xdead_beef, u32::MAX] {
+        let data = x.to_le_bytes();
+        let expected = ((u64::from(x) * 7) >> 32) as usize;
+        let mut u = Unstructured::new(&data);
+        assert_eq!(u.choose_weighted_index(&[1; 7]).unwrap(), expected);
+        let mut u = Unstructured::new(&data);
+        assert_eq!(u.choose_weighted_index(&[5; 7]).unwrap(), expected);
+    }
+}
+
+#[test]
+fn choose_tagged_only_picks_exact_tags() {
+    let tags = [1000, 10, u32::MAX - 5, 500];
+    let more_tags = [1000, 10, u32::MAX - 5, 500, 700];
+    for x in (0..2000).chain([u32::MAX - 5, u32::MAX]) {
+        let data = u32::to_le_bytes(x);
+        let before = Unstructured::new(&data).choose_tagged_index(&tags);
+        let after = Unstructured::new(&data).choose_tagged_index(&more_tags);
+        match tags.iter().position(|&tag| tag == x) {
+            Some(index) => assert_eq!(before, Ok(index)),
+            None => assert_eq!(before, Err(crate::Error::IncorrectFormat)),
+        }
+        // A new tag only takes over its own value.
+        if x == 700 {
+            assert_eq!(after, Ok(4));
+        } else {
+            assert_eq!(after, before);
+        }
+    }
+}
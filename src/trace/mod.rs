@@ -0,0 +1,67 @@
+// Copyright © 2019 The Rust Fuzz Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Recording which parts of the raw data went into which values.
+//!
+//! Create an [`Unstructured`][crate::Unstructured] with
+//! [`Unstructured::with_trace`][crate::Unstructured::with_trace] to record
+//! every call that consumes raw data into a [`Trace`]. Derived `Arbitrary`
+//! implementations annotate the records with the path to the value being
+//! generated, like `AddressBook.friends[2].Buddy.name`.
+//!
+//! ```
+//! # #[cfg(all(feature = "derive", feature = "std"))] fn foo() -> arbitrary::Result<()> {
+//! use arbitrary::{Arbitrary, Trace, Unstructured};
+//!
+//! #[derive(Arbitrary, Debug)]
+//! struct AddressBook {
+//!     friends: Vec<Friend>,
+//! }
+//!
+//! #[derive(Arbitrary, Debug)]
+//! enum Friend {
+//!     Buddy { name: String },
+//!     Pal { age: u8 },
+//! }
+//!
+//! let data = [1, 0, 0, 0, 0, 2, 104, 105, 0, 3];
+//! let trace = Trace::new();
+//! let mut u = Unstructured::with_trace(&data, &trace);
+//! let book = AddressBook::arbitrary(&mut u)?;
+//!
+//! for record in trace.records() {
+//!     println!("{:?} {:?}: {}", record.range, record.operation, record.path);
+//! }
+//! println!("{}", trace.hexdump());
+//! # Ok(()) }
+//! ```
+
+#[cfg(feature = "std")]
+mod record;
+
+#[cfg(feature = "std")]
+pub(crate) use self::record::Tracer;
+#[cfg(feature = "std")]
+pub use self::record::{End, Operation, Record, Trace};
+
+/// One segment of the path to the value that is being generated.
+///
+/// See [`Unstructured::trace_scope`][crate::Unstructured::trace_scope].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Segment {
+    /// A value of the named type. This only shows up in paths when it is the
+    /// outermost value, like `AddressBook` in `AddressBook.friends`.
+    Type(&'static str),
+    /// The named field of a struct or enum variant, like `.friends`.
+    Field(&'static str),
+    /// The named enum variant, like `.Buddy`.
+    Variant(&'static str),
+    /// The element of a collection at the given index, like `[2]`.
+    Index(usize),
+}
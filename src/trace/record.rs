@@ -0,0 +1,276 @@
+//! The `Trace` itself, which requires the `"std"` cargo feature.
+
+use super::Segment;
+use std::{
+    fmt::{self, Write},
+    ops::Range,
+    string::String,
+    sync::{Mutex, PoisonError},
+    vec::Vec,
+};
+
+/// A log of the raw data that an [`Unstructured`][crate::Unstructured]
+/// consumed, and the values it went into.
+///
+/// Requires the `"std"` cargo feature.
+#[derive(Debug, Default)]
+pub struct Trace {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    input: Vec<u8>,
+    records: Vec<Record>,
+    scopes: Vec<(Segment, &'static str)>,
+}
+
+/// A single call that consumed raw data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Record {
+    /// The method that consumed the data.
+    pub operation: Operation,
+    /// Which end of the raw data the bytes were taken from.
+    pub end: End,
+    /// The offsets of the consumed bytes within the whole raw data. This may
+    /// be empty, e.g. if the data ran out.
+    pub range: Range<usize>,
+    /// The `core::any::type_name` of the innermost value being generated, if
+    /// any.
+    pub type_name: Option<&'static str>,
+    /// The number of segments in `path`.
+    pub depth: usize,
+    /// The path to the value being generated, like
+    /// `AddressBook.friends[2].Buddy.name`.
+    pub path: String,
+}
+
+/// The [`Unstructured`][crate::Unstructured] methods that consume raw data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Operation {
+    /// [`Unstructured::bytes`][crate::Unstructured::bytes].
+    Bytes,
+    /// [`Unstructured::fill_buffer`][crate::Unstructured::fill_buffer].
+    FillBuffer,
+    /// [`Unstructured::int_in_range`][crate::Unstructured::int_in_range].
+    IntInRange,
+    /// [`Unstructured::choose_index`][crate::Unstructured::choose_index].
+    ChooseIndex,
+    /// The byte size behind
+    /// [`Unstructured::arbitrary_len`][crate::Unstructured::arbitrary_len].
+    ByteSize,
+    /// [`Unstructured::take_rest`][crate::Unstructured::take_rest].
+    TakeRest,
+}
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Bytes => "bytes",
+            Operation::FillBuffer => "fill_buffer",
+            Operation::IntInRange => "int_in_range",
+            Operation::ChooseIndex => "choose_index",
+            Operation::ByteSize => "byte_size",
+            Operation::TakeRest => "take_rest",
+        }
+    }
+}
+
+/// The end of the raw data that bytes are consumed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum End {
+    /// The front, which is where most data comes from.
+    Front,
+    /// The back, which is where lengths come from.
+    Back,
+}
+
+impl Trace {
+    /// Create a new, empty `Trace`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the records of all calls that consumed raw data, in order.
+    pub fn records(&self) -> Vec<Record> {
+        self.state().records.clone()
+    }
+
+    /// Render an annotated hexdump of the raw data, with one line for every
+    /// 16 bytes of every record, sorted by offset. Each record is annotated
+    /// with the end of the data it came from, the operation, the path and the
+    /// type of the value being generated. Bytes that no call consumed are
+    /// marked as unused.
+    ///
+    /// ```
+    /// use arbitrary::{Arbitrary, Trace, Unstructured};
+    ///
+    /// let trace = Trace::new();
+    /// let mut u = Unstructured::with_trace(&[1, 2, 3, 4, 5], &trace);
+    /// let _ = <(u16, bool)>::arbitrary(&mut u);
+    ///
+    /// let dump = trace.hexdump();
+    /// let lines: Vec<&str> = dump.lines().map(str::trim_end).collect();
+    /// assert_eq!(lines, [
+    ///     "00000000  01 02                                            front fill_buffer",
+    ///     "00000002  03                                               front fill_buffer",
+    ///     "00000003  04 05                                            unused",
+    /// ]);
+    /// ```
+    pub fn hexdump(&self) -> String {
+        let state = self.state();
+        let mut records: Vec<&Record> = state
+            .records
+            .iter()
+            .filter(|r| !r.range.is_empty())
+            .collect();
+        records.sort_by_key(|r| r.range.start);
+
+        let mut out = String::new();
+        let mut offset = 0;
+        for record in records {
+            if offset < record.range.start {
+                hexdump_lines(&mut out, &state.input, offset..record.range.start, |out| {
+                    out.push_str("unused")
+                });
+            }
+            hexdump_lines(&mut out, &state.input, record.range.clone(), |out| {
+                let end = match record.end {
+                    End::Front => "front",
+                    End::Back => "back",
+                };
+                let _ = write!(
+                    out,
+                    "{:<5} {:<12} {}",
+                    end,
+                    record.operation.name(),
+                    record.path
+                );
+                if let Some(type_name) = record.type_name {
+                    let _ = write!(out, ": {}", type_name);
+                }
+            });
+            offset = offset.max(record.range.end);
+        }
+        if offset < state.input.len() {
+            hexdump_lines(&mut out, &state.input, offset..state.input.len(), |out| {
+                out.push_str("unused")
+            });
+        }
+        out
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn start(&self, input: &[u8]) {
+        let mut state = self.state();
+        state.input = input.to_vec();
+        state.records.clear();
+        state.scopes.clear();
+    }
+
+    pub(crate) fn push_scope(&self, segment: Segment, type_name: &'static str) {
+        self.state().scopes.push((segment, type_name));
+    }
+
+    pub(crate) fn pop_scope(&self) {
+        self.state().scopes.pop();
+    }
+
+    pub(crate) fn record(&self, operation: Operation, end: End, range: Range<usize>) {
+        let mut state = self.state();
+        let mut path = String::new();
+        let mut depth = 0;
+        for (segment, _) in &state.scopes {
+            let _ = match *segment {
+                Segment::Type(name) if path.is_empty() => write!(path, "{}", name),
+                Segment::Type(_) => continue,
+                Segment::Field(name) | Segment::Variant(name) if path.is_empty() => {
+                    write!(path, "{}", name)
+                }
+                Segment::Field(name) | Segment::Variant(name) => write!(path, ".{}", name),
+                Segment::Index(index) => write!(path, "[{}]", index),
+            };
+            depth += 1;
+        }
+        let type_name = state.scopes.last().map(|(_, type_name)| *type_name);
+        state.records.push(Record {
+            operation,
+            end,
+            range,
+            type_name,
+            depth,
+            path,
+        });
+    }
+}
+
+/// Write the given bytes, 16 per line, and annotate the first line.
+fn hexdump_lines(
+    out: &mut String,
+    input: &[u8],
+    range: Range<usize>,
+    annotate: impl FnOnce(&mut String),
+) {
+    let mut annotate = Some(annotate);
+    let mut start = range.start;
+    while start < range.end {
+        let end = core::cmp::min(start + 16, range.end);
+        let hex = HexBytes(&input[start..end]);
+        let _ = write!(out, "{:08x}  {:<47}  ", start, hex);
+        if let Some(annotate) = annotate.take() {
+            annotate(out);
+        }
+        out.push('\n');
+        start = end;
+    }
+}
+
+struct HexBytes<'a>(&'a [u8]);
+
+impl fmt::Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hex = String::with_capacity(self.0.len() * 3);
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                hex.push(' ');
+            }
+            let _ = write!(hex, "{:02x}", byte);
+        }
+        f.pad(&hex)
+    }
+}
+
+/// The connection between an `Unstructured` and its `Trace`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Tracer<'a> {
+    pub(crate) trace: &'a Trace,
+    /// The address of the start of the raw data, to turn the slices that the
+    /// `Unstructured` consumes into offsets.
+    base: usize,
+}
+
+impl<'a> Tracer<'a> {
+    pub(crate) fn new(trace: &'a Trace, input: &[u8]) -> Self {
+        trace.start(input);
+        Tracer {
+            trace,
+            base: input.as_ptr() as usize,
+        }
+    }
+
+    /// Record that `len` bytes are about to be consumed from the given end of
+    /// the remaining `data`.
+    pub(crate) fn record(&self, data: &[u8], operation: Operation, end: End, len: usize) {
+        let front = data.as_ptr() as usize - self.base;
+        let start = match end {
+            End::Front => front,
+            End::Back => front + data.len() - len,
+        };
+        self.trace.record(operation, end, start..start + len);
+    }
+}
@@ -8,10 +8,21 @@
 
 //! Wrappers around raw, unstructured bytes.
 
-use crate::{Arbitrary, Error, Result};
-use std::marker::PhantomData;
-use std::ops::ControlFlow;
-use std::{mem, ops};
+use crate::{trace::Segment, Arbitrary, ArbitraryWith, Error, Result};
+use core::marker::PhantomData;
+use core::ops::ControlFlow;
+use core::{fmt, mem, ops};
+
+#[cfg(feature = "std")]
+use crate::choices::{Choice, Recorder};
+#[cfg(feature = "std")]
+use crate::trace::{End, Operation, Trace, Tracer};
+
+#[cfg(feature = "alloc")]
+use {
+    crate::source::{ByteSource, Unread},
+    alloc::{boxed::Box, string::String},
+};
 
 /// A source of unstructured data.
 ///
@@ -68,12 +79,47 @@ use std::{mem, ops};
 /// }
 /// # }
 /// ```
-#[derive(Debug)]
+///
+/// Instead of a byte slice, an `Unstructured` can also pull its data from a
+/// [`ByteSource`][crate::source::ByteSource], like a seeded pseudo-random
+/// number generator. See [`Unstructured::from_source`].
 pub struct Unstructured<'a> {
     data: &'a [u8],
+    /// How many calls to `nested` are currently running.
+    depth: usize,
+    max_depth: usize,
+    #[cfg(feature = "std")]
+    tracer: Option<Tracer<'a>>,
+    #[cfg(feature = "std")]
+    recorder: Option<&'a Recorder>,
+    /// When set, all data comes from here and `data` is empty.
+    #[cfg(feature = "alloc")]
+    source: Option<Unread<'a>>,
+}
+
+impl fmt::Debug for Unstructured<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Unstructured");
+        s.field("data", &self.data);
+        s.field("depth", &self.depth);
+        s.field("max_depth", &self.max_depth);
+        #[cfg(feature = "std")]
+        s.field("tracer", &self.tracer);
+        #[cfg(feature = "std")]
+        s.field("recorder", &self.recorder);
+        #[cfg(feature = "alloc")]
+        if self.source.is_some() {
+            return s.finish_non_exhaustive();
+        }
+        s.finish()
+    }
 }
 
 impl<'a> Unstructured<'a> {
+    /// The maximum depth of a new `Unstructured`, see
+    /// [`Unstructured::with_max_depth`].
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+
     /// Create a new `Unstructured` from the given raw data.
     ///
     /// # Example
@@ -84,12 +130,371 @@ impl<'a> Unstructured<'a> {
     /// let u = Unstructured::new(&[1, 2, 3, 4]);
     /// ```
     pub fn new(data: &'a [u8]) -> Self {
-        Unstructured { data }
+        Unstructured {
+            data,
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            #[cfg(feature = "std")]
+            tracer: None,
+            #[cfg(feature = "std")]
+            recorder: None,
+            #[cfg(feature = "alloc")]
+            source: None,
+        }
+    }
+
+    /// Create a new `Unstructured` that pulls its raw data from the given
+    /// source, instead of a byte slice.
+    ///
+    /// All `Arbitrary` implementations work with any source, except that
+    /// borrowed slices like `&[u8]` and `&str` can only be generated from a
+    /// byte slice. See the [`source`][crate::source] module for details.
+    ///
+    /// Requires the `"alloc"` cargo feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{source::Prng, Arbitrary, Unstructured};
+    ///
+    /// let mut u = Unstructured::from_source(Prng::new(1234));
+    ///
+    /// // A pseudo-random number generator never runs out of data.
+    /// for _ in 0..1000 {
+    ///     let _ = <(u64, String)>::arbitrary(&mut u).unwrap();
+    /// }
+    /// assert!(!u.is_empty());
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn from_source(source: impl ByteSource + Send + Sync + 'a) -> Self {
+        Unstructured {
+            data: &[],
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            #[cfg(feature = "std")]
+            tracer: None,
+            #[cfg(feature = "std")]
+            recorder: None,
+            source: Some(Unread::new(Box::new(source))),
+        }
+    }
+
+    /// Create a new `Unstructured` from the given raw data that records every
+    /// call that consumes the data in the given [`Trace`].
+    ///
+    /// This replaces anything that was previously recorded in the `Trace`.
+    /// See the [`trace`][crate::trace] module for details.
+    ///
+    /// Requires the `"std"` cargo feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{trace::Operation, Trace, Unstructured};
+    ///
+    /// let trace = Trace::new();
+    /// let mut u = Unstructured::with_trace(&[1, 2, 3, 4], &trace);
+    /// let x: u8 = u.int_in_range(0..=9).unwrap();
+    ///
+    /// let records = trace.records();
+    /// assert_eq!(records[0].operation, Operation::IntInRange);
+    /// assert_eq!(records[0].range, 0..1);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_trace(data: &'a [u8], trace: &'a Trace) -> Self {
+        Unstructured {
+            data,
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            tracer: Some(Tracer::new(trace, data)),
+            recorder: None,
+            source: None,
+        }
+    }
+
+    /// Create a new `Unstructured` from the given raw data that records every
+    /// decision it makes in the given [`Recorder`].
+    ///
+    /// This replaces anything that was previously recorded in the `Recorder`.
+    /// See the [`choices`][crate::choices] module for details.
+    ///
+    /// Requires the `"std"` cargo feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{choices::{Choice, Recorder}, Unstructured};
+    ///
+    /// let recorder = Recorder::new();
+    /// let mut u = Unstructured::with_recorder(&[1, 2, 3, 4], &recorder);
+    /// let x: u8 = u.int_in_range(0..=9).unwrap();
+    ///
+    /// let choices = recorder.sequence().choices;
+    /// assert_eq!(choices, [Choice::IntInRange { value: 1, max: 9 }]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_recorder(data: &'a [u8], recorder: &'a Recorder) -> Self {
+        recorder.reset();
+        Unstructured {
+            data,
+            depth: 0,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            tracer: None,
+            recorder: Some(recorder),
+            source: None,
+        }
+    }
+
+    /// Generate a value with the given function, within a named scope.
+    ///
+    /// When tracing, the scopes make up the path to the value that is being
+    /// generated, like `AddressBook.friends[2].Buddy.name`, which is recorded
+    /// along with all calls that consume data inside the function. Derived
+    /// `Arbitrary` implementations enter a scope for every type, field and
+    /// enum variant, and [`Unstructured::arbitrary_iter`] for every element.
+    ///
+    /// When not tracing, this simply calls the function.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{trace::Segment, Arbitrary, Result, Unstructured};
+    ///
+    /// pub struct Rgb {
+    ///     r: u8,
+    ///     g: u8,
+    ///     b: u8,
+    /// }
+    ///
+    /// impl<'a> Arbitrary<'a> for Rgb {
+    ///     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+    ///         u.trace_scope(Segment::Type("Rgb"), |u| {
+    ///             Ok(Rgb {
+    ///                 r: u.trace_scope(Segment::Field("r"), |u| u.arbitrary())?,
+    ///                 g: u.trace_scope(Segment::Field("g"), |u| u.arbitrary())?,
+    ///                 b: u.trace_scope(Segment::Field("b"), |u| u.arbitrary())?,
+    ///             })
+    ///         })
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn trace_scope<T>(
+        &mut self,
+        segment: Segment,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        #[cfg(feature = "std")]
+        if let Some(tracer) = self.tracer {
+            tracer
+                .trace
+                .push_scope(segment, core::any::type_name::<T>());
+            let result = f(self);
+            tracer.trace.pop_scope();
+            return result;
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(recorder) = self.recorder {
+            recorder.push_span();
+            let result = f(self);
+            recorder.pop_span();
+            return result;
+        }
+
+        #[cfg(not(feature = "std"))]
+        let _ = segment;
+        f(self)
+    }
+
+    /// Like [`Unstructured::trace_scope`], but for generating a value from all
+    /// of the remaining data, such as in
+    /// [`Arbitrary::arbitrary_take_rest`].
+    #[inline]
+    pub fn trace_scope_take_rest<T>(
+        self,
+        segment: Segment,
+        f: impl FnOnce(Self) -> Result<T>,
+    ) -> Result<T> {
+        #[cfg(feature = "std")]
+        if let Some(tracer) = self.tracer {
+            tracer
+                .trace
+                .push_scope(segment, core::any::type_name::<T>());
+            let result = f(self);
+            tracer.trace.pop_scope();
+            return result;
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(recorder) = self.recorder {
+            recorder.push_span();
+            let result = f(self);
+            recorder.pop_span();
+            return result;
+        }
+
+        #[cfg(not(feature = "std"))]
+        let _ = segment;
+        f(self)
+    }
+
+    /// Set the maximum depth to which generated values may nest.
+    ///
+    /// Every derived `Arbitrary` implementation generates its value one level
+    /// deeper with [`Unstructured::nested`], which fails with
+    /// [`Error::MaxDepthReached`][crate::Error::MaxDepthReached] once the
+    /// maximum depth is reached. When a variant of a derived enum fails that
+    /// way, anywhere inside of it, the enum falls back to the next variant; an
+    /// `Option` falls back to `None`, and a collection ends. This bounds the
+    /// depth of recursive types like syntax trees, no matter how much raw data
+    /// there is, and still generates a value as long as the type has one that
+    /// fits.
+    ///
+    /// The default is [`Unstructured::DEFAULT_MAX_DEPTH`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[cfg(feature = "derive")] fn foo() {
+    /// use arbitrary::{Arbitrary, Unstructured};
+    ///
+    /// #[derive(Arbitrary, Debug, PartialEq)]
+    /// enum Nat {
+    ///     Succ(Box<Nat>),
+    ///     Zero,
+    /// }
+    ///
+    /// // Always choosing `Succ` would go on forever, but at depth 3 `Nat`
+    /// // falls back to `Zero`.
+    /// let mut u = Unstructured::new(&[0; 1024]).with_max_depth(3);
+    /// let nat = Nat::arbitrary(&mut u).unwrap();
+    /// assert_eq!(nat, Nat::Succ(Box::new(Nat::Succ(Box::new(Nat::Zero)))));
+    /// # }
+    /// ```
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Get the number of calls to [`Unstructured::nested`] that are currently
+    /// running.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the maximum depth, see [`Unstructured::with_max_depth`].
+    #[inline]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Is the depth budget spent, so that [`Unstructured::nested`] would fail?
+    #[inline]
+    pub fn is_depth_exhausted(&self) -> bool {
+        self.depth >= self.max_depth
+    }
+
+    /// Generate a value with the given function, one level deeper.
+    ///
+    /// Fails with [`Error::MaxDepthReached`][crate::Error::MaxDepthReached]
+    /// without calling the function if the depth budget is spent. Derived
+    /// `Arbitrary` implementations generate every value this way; hand-written
+    /// implementations of recursive types should do the same, and check
+    /// [`Unstructured::is_depth_exhausted`] to pick a case that does not
+    /// recurse.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{Arbitrary, Result, Unstructured};
+    ///
+    /// #[derive(Debug)]
+    /// enum Expr {
+    ///     Lit(u8),
+    ///     Add(Box<Expr>, Box<Expr>),
+    /// }
+    ///
+    /// impl<'a> Arbitrary<'a> for Expr {
+    ///     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+    ///         u.nested(|u| {
+    ///             if u.is_depth_exhausted() || u.arbitrary()? {
+    ///                 Ok(Expr::Lit(u.arbitrary()?))
+    ///             } else {
+    ///                 Ok(Expr::Add(u.arbitrary()?, u.arbitrary()?))
+    ///             }
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// let mut u = Unstructured::new(&[0; 100]).with_max_depth(2);
+    /// assert!(matches!(Expr::arbitrary(&mut u).unwrap(), Expr::Add(..)));
+    /// assert_eq!(u.depth(), 0);
+    /// ```
+    #[inline]
+    pub fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.is_depth_exhausted() {
+            return Err(Error::MaxDepthReached);
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Like [`Unstructured::nested`], but for generating a value from all of
+    /// the remaining data, such as in [`Arbitrary::arbitrary_take_rest`].
+    #[inline]
+    pub fn nested_take_rest<T>(mut self, f: impl FnOnce(Self) -> Result<T>) -> Result<T> {
+        if self.is_depth_exhausted() {
+            return Err(Error::MaxDepthReached);
+        }
+        self.depth += 1;
+        f(self)
+    }
+
+    /// Record that `len` bytes are about to be consumed from the given end of
+    /// the data.
+    #[cfg(feature = "std")]
+    #[inline]
+    fn trace(&self, operation: Operation, end: End, len: usize) {
+        if let Some(tracer) = &self.tracer {
+            tracer.record(self.data, operation, end, len);
+        }
+    }
+
+    /// Record a decision, unless it is part of a higher-level decision.
+    ///
+    /// Decisions that take no data, because there is only one option or
+    /// because the data ran out, are not recorded: decoding makes them the
+    /// same way without any data.
+    #[cfg(feature = "std")]
+    #[inline]
+    fn record(&self, choice: impl FnOnce() -> Choice) {
+        if let Some(recorder) = self.recorder {
+            recorder.record(choice());
+        }
+    }
+
+    /// Decide whether a collection gets another element.
+    pub(crate) fn arbitrary_continue(&mut self) -> bool {
+        #[cfg(feature = "std")]
+        if let Some(recorder) = self.recorder.filter(|_| !self.data.is_empty()) {
+            return recorder.muted(
+                || self.arbitrary().unwrap_or(false),
+                |keep_going| Choice::Continue(*keep_going),
+            );
+        }
+        self.arbitrary().unwrap_or(false)
     }
 
     /// Get the number of remaining bytes of underlying data that are still
     /// available.
     ///
+    /// For an `Unstructured` created with [`Unstructured::from_source`], this
+    /// is `usize::MAX` if the source does not know how much data it has left.
+    ///
     /// # Example
     ///
     /// ```
@@ -107,6 +512,10 @@ impl<'a> Unstructured<'a> {
     /// ```
     #[inline]
     pub fn len(&self) -> usize {
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &self.source {
+            return source.remaining().unwrap_or(usize::MAX);
+        }
         self.data.len()
     }
 
@@ -218,15 +627,87 @@ impl<'a> Unstructured<'a> {
         let byte_size = self.arbitrary_byte_size()?;
         let (lower, upper) = <ElementType as Arbitrary>::size_hint(0);
         let elem_size = upper.unwrap_or(lower * 2);
-        let elem_size = std::cmp::max(1, elem_size);
+        let elem_size = core::cmp::max(1, elem_size);
         Ok(byte_size / elem_size)
     }
 
-    fn arbitrary_byte_size(&mut self) -> Result<usize> {
+    /// Get the number of elements to insert when building up a collection of
+    /// arbitrary `ElementType`s, within the given range.
+    ///
+    /// Like [`Unstructured::arbitrary_len`], this chooses a length that the
+    /// remaining data most likely covers, and then wraps it into the range, so
+    /// that the lengths are spread over the whole range rather than piling up
+    /// at its end. Fixed lengths, like `4..=4`, consume no data at all.
+    ///
+    /// This should only be called within an `Arbitrary` implementation. For
+    /// collections that implement [`ArbitraryLen`][crate::ArbitraryLen],
+    /// see [`ArbitraryLen::arbitrary_with_len_in`][crate::ArbitraryLen::arbitrary_with_len_in].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::Unstructured;
+    ///
+    /// let mut u = Unstructured::new(&[]);
+    /// assert_eq!(u.arbitrary_len_in::<u32>(1..=16).unwrap(), 1);
+    ///
+    /// let mut u = Unstructured::new(&[0xff; 1024]);
+    /// let len = u.arbitrary_len_in::<u32>(1..=16).unwrap();
+    /// assert!((1..=16).contains(&len));
+    /// assert_eq!(u.arbitrary_len_in::<u32>(4..=4).unwrap(), 4);
+    /// ```
+    pub fn arbitrary_len_in<ElementType>(
+        &mut self,
+        range: impl ops::RangeBounds<usize>,
+    ) -> Result<usize>
+    where
+        ElementType: Arbitrary<'a>,
+    {
+        let (min, max) = len_bounds(&range);
+        if min == max {
+            return Ok(min);
+        }
+        // Wrap the length around instead of clamping it, so that plenty of
+        // data doesn't always end up with the largest length.
+        let len = self.arbitrary_len::<ElementType>()?;
+        match (max - min).checked_add(1) {
+            Some(span) => Ok(min + len % span),
+            None => Ok(len),
+        }
+    }
+
+    pub(crate) fn arbitrary_byte_size(&mut self) -> Result<usize> {
+        // A source can't be read from the end, so take the size from the
+        // front, and never more than fits in a single byte.
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &self.source {
+            let max_size = core::cmp::min(source.remaining().unwrap_or(usize::MAX), 255);
+            if max_size == 0 {
+                return Ok(0);
+            }
+            return self.int_in_range(0..=max_size as u8).map(usize::from);
+        }
+
+        #[cfg(feature = "std")]
+        if self.recorder.is_some() && !self.data.is_empty() {
+            let byte_size = self.arbitrary_byte_size_impl()?;
+            self.record(|| Choice::Len { byte_size });
+            return Ok(byte_size);
+        }
+        self.arbitrary_byte_size_impl()
+    }
+
+    fn arbitrary_byte_size_impl(&mut self) -> Result<usize> {
         if self.data.is_empty() {
             Ok(0)
         } else if self.data.len() == 1 {
-            self.data = &[];
+            #[cfg(feature = "std")]
+            self.trace(Operation::ByteSize, End::Back, 1);
+            self.data = &self.data[1..];
             Ok(0)
         } else {
             // Take lengths from the end of the data, since the `libFuzzer` folks
@@ -240,24 +721,32 @@ impl<'a> Unstructured<'a> {
             // Note: We cast to u64 so we don't overflow when checking u32::MAX + 4 on 32-bit archs
             let len = if self.data.len() as u64 <= u8::MAX as u64 + 1 {
                 let bytes = 1;
+                #[cfg(feature = "std")]
+                self.trace(Operation::ByteSize, End::Back, bytes);
                 let max_size = self.data.len() - bytes;
                 let (rest, for_size) = self.data.split_at(max_size);
                 self.data = rest;
                 Self::int_in_range_impl(0..=max_size as u8, for_size.iter().copied())?.0 as usize
             } else if self.data.len() as u64 <= u16::MAX as u64 + 2 {
                 let bytes = 2;
+                #[cfg(feature = "std")]
+                self.trace(Operation::ByteSize, End::Back, bytes);
                 let max_size = self.data.len() - bytes;
                 let (rest, for_size) = self.data.split_at(max_size);
                 self.data = rest;
                 Self::int_in_range_impl(0..=max_size as u16, for_size.iter().copied())?.0 as usize
             } else if self.data.len() as u64 <= u32::MAX as u64 + 4 {
                 let bytes = 4;
+                #[cfg(feature = "std")]
+                self.trace(Operation::ByteSize, End::Back, bytes);
                 let max_size = self.data.len() - bytes;
                 let (rest, for_size) = self.data.split_at(max_size);
                 self.data = rest;
                 Self::int_in_range_impl(0..=max_size as u32, for_size.iter().copied())?.0 as usize
             } else {
                 let bytes = 8;
+                #[cfg(feature = "std")]
+                self.trace(Operation::ByteSize, End::Back, bytes);
                 let max_size = self.data.len() - bytes;
                 let (rest, for_size) = self.data.split_at(max_size);
                 self.data = rest;
@@ -296,7 +785,26 @@ impl<'a> Unstructured<'a> {
     where
         T: Int,
     {
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &mut self.source {
+            return Ok(Self::int_in_range_impl(range, source_bytes(source))?.0);
+        }
+
+        let start = *range.start();
+        let end = *range.end();
         let (result, bytes_consumed) = Self::int_in_range_impl(range, self.data.iter().cloned())?;
+        #[cfg(feature = "std")]
+        self.trace(Operation::IntInRange, End::Front, bytes_consumed);
+        #[cfg(feature = "std")]
+        if bytes_consumed > 0 {
+            let start = start.to_unsigned();
+            self.record(|| Choice::IntInRange {
+                value: result.to_unsigned().wrapping_sub(start).to_u128(),
+                max: end.to_unsigned().wrapping_sub(start).to_u128(),
+            });
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = (start, end);
         self.data = &self.data[bytes_consumed..];
         Ok(result)
     }
@@ -488,10 +996,144 @@ impl<'a> Unstructured<'a> {
         if len == 0 {
             return Err(Error::EmptyChoose);
         }
-        let idx = self.int_in_range(0..=len - 1)?;
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &mut self.source {
+            return Ok(Self::int_in_range_impl(0..=len - 1, source_bytes(source))?.0);
+        }
+        let (idx, bytes_consumed) =
+            Self::int_in_range_impl(0..=len - 1, self.data.iter().cloned())?;
+        #[cfg(feature = "std")]
+        self.trace(Operation::ChooseIndex, End::Front, bytes_consumed);
+        #[cfg(feature = "std")]
+        if bytes_consumed > 0 {
+            self.record(|| Choice::ChooseIndex {
+                value: idx as u128,
+                max: (len - 1) as u128,
+            });
+        }
+        self.data = &self.data[bytes_consumed..];
         Ok(idx)
     }
 
+    /// Choose one of the given choices, where each choice is picked with a
+    /// probability proportional to its weight.
+    ///
+    /// This should only be used inside of `Arbitrary` implementations.
+    ///
+    /// Returns an error if there is not enough underlying data to make a
+    /// choice or if all weights are zero. Choices with a weight of zero are
+    /// never picked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `choices` and `weights` have different lengths.
+    ///
+    /// # Examples
+    ///
+    /// Picking `'a'` three times as often as `'b'` or `'c'`:
+    ///
+    /// ```
+    /// use arbitrary::Unstructured;
+    ///
+    /// let mut u = Unstructured::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 0]);
+    /// let choices = ['a', 'b', 'c'];
+    ///
+    /// let choice = u.choose_weighted(&choices, &[3, 1, 1]).unwrap();
+    ///
+    /// println!("chose {}", choice);
+    /// ```
+    pub fn choose_weighted<'b, T>(&mut self, choices: &'b [T], weights: &[u32]) -> Result<&'b T> {
+        assert_eq!(
+            choices.len(),
+            weights.len(),
+            "`arbitrary::Unstructured::choose_weighted` requires one weight per choice"
+        );
+        let idx = self.choose_weighted_index(weights)?;
+        Ok(&choices[idx])
+    }
+
+    /// Choose an index into `weights`, where each index is picked with a
+    /// probability proportional to the weight at that index.
+    ///
+    /// This consumes a `u32` and scales it onto the cumulative weights, which
+    /// is how `#[derive(Arbitrary)]` picks enum variants: when all weights are
+    /// equal, the result is the same as for an unweighted enum.
+    ///
+    /// Returns an error if `weights` is empty or all weights are zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arbitrary::Unstructured;
+    ///
+    /// // The first `u32` is the smallest one, which picks the first index
+    /// // with a non-zero weight.
+    /// let mut u = Unstructured::new(&[0, 0, 0, 0]);
+    /// assert_eq!(u.choose_weighted_index(&[0, 2, 1]).unwrap(), 1);
+    ///
+    /// let mut u = Unstructured::new(&[1, 2, 3, 4]);
+    /// assert!(u.choose_weighted_index(&[0, 0]).is_err());
+    /// ```
+    pub fn choose_weighted_index(&mut self, weights: &[u32]) -> Result<usize> {
+        let total: u64 = weights.iter().map(|&w| u64::from(w)).sum();
+        if total == 0 {
+            return Err(Error::EmptyChoose);
+        }
+        let x = u32::arbitrary(self)?;
+        let point = (u128::from(x) * u128::from(total)) >> 32;
+        let mut cumulative = 0;
+        for (idx, &weight) in weights.iter().enumerate() {
+            cumulative += u128::from(weight);
+            if point < cumulative {
+                return Ok(idx);
+            }
+        }
+        unreachable!("the scaled point is always below the total weight")
+    }
+
+    /// Choose the index of the tag in `tags` that is equal to a `u32`.
+    ///
+    /// This consumes a `u32` and returns the index of the tag that is equal
+    /// to it, or [`Error::IncorrectFormat`] if there is none. Since only the
+    /// tag itself picks an index, adding tags never changes what the data for
+    /// the other tags decodes to. This is how `#[derive(Arbitrary)]` picks the
+    /// variants of enums with the `#[arbitrary(stable_tags)]` attribute.
+    ///
+    /// Random data rarely hits a tag, so this suits inputs that come from a
+    /// corpus, or from [`Dearbitrary`][crate::Dearbitrary], and fuzzers that
+    /// learn the values that the data is compared against.
+    ///
+    /// If a tag occurs more than once, the first index with it is picked.
+    /// Returns an error if `tags` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arbitrary::{Error, Unstructured};
+    ///
+    /// let tags = [100, 300, 200];
+    ///
+    /// let data = 300_u32.to_le_bytes();
+    /// let mut u = Unstructured::new(&data);
+    /// assert_eq!(u.choose_tagged_index(&tags).unwrap(), 1);
+    ///
+    /// let data = 250_u32.to_le_bytes();
+    /// let mut u = Unstructured::new(&data);
+    /// assert_eq!(u.choose_tagged_index(&tags), Err(Error::IncorrectFormat));
+    ///
+    /// let mut u = Unstructured::new(&[1, 2, 3, 4]);
+    /// assert!(u.choose_tagged_index(&[]).is_err());
+    /// ```
+    pub fn choose_tagged_index(&mut self, tags: &[u32]) -> Result<usize> {
+        if tags.is_empty() {
+            return Err(Error::EmptyChoose);
+        }
+        let x = u32::arbitrary(self)?;
+        tags.iter()
+            .position(|&tag| tag == x)
+            .ok_or(Error::IncorrectFormat)
+    }
+
     /// Generate a boolean according to the given ratio.
     ///
     /// # Panics
@@ -523,6 +1165,19 @@ impl<'a> Unstructured<'a> {
     {
         assert!(T::ZERO < numerator);
         assert!(numerator <= denominator);
+        #[cfg(feature = "std")]
+        if let Some(recorder) = self.recorder.filter(|_| !self.data.is_empty()) {
+            if T::ONE < denominator {
+                let x = recorder.muted(
+                    || self.int_in_range(T::ONE..=denominator),
+                    |x| Choice::Ratio {
+                        value: x.map_or(0, |x| x.wrapping_sub(T::ONE).to_u128()),
+                        max: denominator.wrapping_sub(T::ONE).to_u128(),
+                    },
+                )?;
+                return Ok(x <= numerator);
+            }
+        }
         let x = self.int_in_range(T::ONE..=denominator)?;
         Ok(x <= numerator)
     }
@@ -556,7 +1211,22 @@ impl<'a> Unstructured<'a> {
     /// assert_eq!(buf, [0, 0]);
     /// ```
     pub fn fill_buffer(&mut self, buffer: &mut [u8]) -> Result<()> {
-        let n = std::cmp::min(buffer.len(), self.data.len());
+        #[cfg(feature = "alloc")]
+        if let Some(source) = &mut self.source {
+            let n = source.fill(buffer);
+            for byte in buffer[n..].iter_mut() {
+                *byte = 0;
+            }
+            return Ok(());
+        }
+
+        let n = core::cmp::min(buffer.len(), self.data.len());
+        #[cfg(feature = "std")]
+        self.trace(Operation::FillBuffer, End::Front, n);
+        #[cfg(feature = "std")]
+        if n > 0 {
+            self.record(|| Choice::Bytes(self.data[..n].to_vec()));
+        }
         buffer[..n].copy_from_slice(&self.data[..n]);
         for byte in buffer[n..].iter_mut() {
             *byte = 0;
@@ -565,6 +1235,15 @@ impl<'a> Unstructured<'a> {
         Ok(())
     }
 
+    /// Put bytes that were read from the source, but not used, back in front
+    /// of it, where they are left for the next value like in a byte slice.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn unread(&mut self, bytes: &[u8]) {
+        if let Some(source) = &mut self.source {
+            source.unread(bytes);
+        }
+    }
+
     /// Provide `size` bytes from the underlying raw data.
     ///
     /// This should only be called within an `Arbitrary` implementation. This is
@@ -582,11 +1261,21 @@ impl<'a> Unstructured<'a> {
     /// assert!(u.bytes(2).unwrap() == &[1, 2]);
     /// assert!(u.bytes(2).unwrap() == &[3, 4]);
     /// ```
+    ///
+    /// The data of an `Unstructured` created with
+    /// [`Unstructured::from_source`] cannot be borrowed, so this returns an
+    /// error for any non-zero `size`.
     pub fn bytes(&mut self, size: usize) -> Result<&'a [u8]> {
         if self.data.len() < size {
             return Err(Error::NotEnoughData);
         }
 
+        #[cfg(feature = "std")]
+        self.trace(Operation::Bytes, End::Front, size);
+        #[cfg(feature = "std")]
+        if size > 0 {
+            self.record(|| Choice::Bytes(self.data[..size].to_vec()));
+        }
         let (for_buf, rest) = self.data.split_at(size);
         self.data = rest;
         Ok(for_buf)
@@ -597,7 +1286,8 @@ impl<'a> Unstructured<'a> {
     /// Does not consume the bytes, only peeks at them.
     ///
     /// Returns `None` if there are not `size` bytes left in the underlying raw
-    /// input.
+    /// input, or if `size` is non-zero and the data comes from a
+    /// [`ByteSource`][crate::source::ByteSource].
     ///
     /// # Example
     ///
@@ -619,7 +1309,8 @@ impl<'a> Unstructured<'a> {
 
     /// Consume all of the rest of the remaining underlying bytes.
     ///
-    /// Returns a slice of all the remaining, unconsumed bytes.
+    /// Returns a slice of all the remaining, unconsumed bytes. This is always
+    /// empty for an `Unstructured` created with [`Unstructured::from_source`].
     ///
     /// # Example
     ///
@@ -633,6 +1324,12 @@ impl<'a> Unstructured<'a> {
     /// assert_eq!(remaining, [1, 2, 3]);
     /// ```
     pub fn take_rest(mut self) -> &'a [u8] {
+        #[cfg(feature = "std")]
+        self.trace(Operation::TakeRest, End::Front, self.data.len());
+        #[cfg(feature = "std")]
+        if !self.data.is_empty() {
+            self.record(|| Choice::Bytes(self.data.to_vec()));
+        }
         mem::take(&mut self.data)
     }
 
@@ -645,6 +1342,7 @@ impl<'a> Unstructured<'a> {
     ) -> Result<ArbitraryIter<'a, 'b, ElementType>> {
         Ok(ArbitraryIter {
             u: &mut *self,
+            index: 0,
             _marker: PhantomData,
         })
     }
@@ -659,10 +1357,45 @@ impl<'a> Unstructured<'a> {
     ) -> Result<ArbitraryTakeRestIter<'a, ElementType>> {
         Ok(ArbitraryTakeRestIter {
             u: self,
+            index: 0,
             _marker: PhantomData,
         })
     }
 
+    /// Provide an iterator over elements that are generated with the given
+    /// context, for constructing a collection.
+    ///
+    /// This is useful for implementing [`ArbitraryWith::arbitrary_with`] on
+    /// collections since the implementation is simply
+    /// `u.arbitrary_iter_with(ctx)?.collect()`
+    pub fn arbitrary_iter_with<'b, ElementType, Ctx>(
+        &'b mut self,
+        ctx: &'b Ctx,
+    ) -> Result<ArbitraryIterWith<'a, 'b, ElementType, Ctx>>
+    where
+        ElementType: ArbitraryWith<'a, Ctx>,
+        Ctx: ?Sized,
+    {
+        Ok(ArbitraryIterWith {
+            u: &mut *self,
+            ctx,
+            index: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Generate `len` arbitrary elements for a collection in a row.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn arbitrary_elements<ElementType, C>(&mut self, len: usize) -> Result<C>
+    where
+        ElementType: Arbitrary<'a>,
+        C: FromIterator<ElementType>,
+    {
+        (0..len)
+            .map(|index| self.trace_scope(Segment::Index(index), Arbitrary::arbitrary))
+            .collect()
+    }
+
     /// Call the given function an arbitrary number of times.
     ///
     /// The function is given this `Unstructured` so that it can continue to
@@ -744,20 +1477,167 @@ impl<'a> Unstructured<'a> {
 
         Ok(())
     }
+
+    /// Generate a string that matches the given regular expression.
+    ///
+    /// This compiles the pattern on every call. To compile it once, keep a
+    /// [`Regex`][crate::regex::Regex] or a
+    /// [`LazyRegex`][crate::regex::LazyRegex] around instead. See the
+    /// [`regex`][crate::regex] module for the supported syntax.
+    ///
+    /// Requires the `"alloc"` cargo feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is not a valid regular expression.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::Unstructured;
+    ///
+    /// let mut u = Unstructured::new(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    /// let ident = u.string_matching("[a-z_][a-z0-9_]{0,7}").unwrap();
+    /// assert!(!ident.is_empty() && ident.len() <= 8);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn string_matching(&mut self, pattern: &str) -> Result<String> {
+        crate::regex::compile(pattern).generate(self)
+    }
+
+    /// Generate a string from as many bytes as `String`'s `Arbitrary`
+    /// implementation would take, decoding all of them into characters
+    /// instead of stopping at the first invalid UTF-8 byte.
+    ///
+    /// Valid UTF-8 decodes into the same string either way. See
+    /// [`LosslessString`][crate::charset::LosslessString], which generates
+    /// its strings with this method, for how invalid UTF-8 is decoded.
+    ///
+    /// Requires the `"alloc"` cargo feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arbitrary::{Arbitrary, Unstructured};
+    ///
+    /// let data = [b'a', 0xf0, 0x9f, 0xa6, 0x80, 0xff, b'z', 7];
+    /// let s = Unstructured::new(&data).arbitrary_string_lossless().unwrap();
+    /// assert_eq!(s, "a🦀ÿz");
+    ///
+    /// let s = String::arbitrary(&mut Unstructured::new(&data)).unwrap();
+    /// assert_eq!(s, "a🦀");
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn arbitrary_string_lossless(&mut self) -> Result<String> {
+        crate::charset::arbitrary_lossless(self)
+    }
+}
+
+/// The bytes of a source, one at a time, until it is exhausted.
+#[cfg(feature = "alloc")]
+fn source_bytes(mut source: impl ByteSource) -> impl Iterator<Item = u8> {
+    core::iter::from_fn(move || {
+        let mut byte = [0];
+        (source.fill(&mut byte) == 1).then(|| byte[0])
+    })
+}
+
+/// The range of indices of the values within the given range, where `index`
+/// maps the values to integers in the same order, and `full` is the range of
+/// indices of all values.
+///
+/// # Panics
+///
+/// Panics if the range is empty.
+pub(crate) fn index_bounds<T, I>(
+    range: &impl ops::RangeBounds<T>,
+    index: impl Fn(&T) -> I,
+    full: ops::RangeInclusive<I>,
+) -> ops::RangeInclusive<I>
+where
+    I: Int,
+{
+    let start = match range.start_bound() {
+        ops::Bound::Included(start) => Some(index(start)),
+        ops::Bound::Excluded(start) => index(start).checked_add(I::ONE),
+        ops::Bound::Unbounded => Some(*full.start()),
+    };
+    let end = match range.end_bound() {
+        ops::Bound::Included(end) => Some(index(end)),
+        ops::Bound::Excluded(end) => {
+            let end = index(end);
+            (end > *full.start()).then(|| end.wrapping_sub(I::ONE))
+        }
+        ops::Bound::Unbounded => Some(*full.end()),
+    };
+    match (start, end) {
+        (Some(start), Some(end)) if start <= end => start..=end,
+        _ => panic!("`arbitrary::ArbitraryInRange::arbitrary_in_range` requires a non-empty range"),
+    }
+}
+
+/// The number of bytes that [`Unstructured::int_in_range`] consumes for the
+/// given range, if there is enough data.
+pub(crate) fn int_in_range_size<T: Int>(range: &ops::RangeInclusive<T>) -> usize {
+    let delta = range
+        .end()
+        .to_unsigned()
+        .wrapping_sub(range.start().to_unsigned());
+    let mut bytes = 0;
+    while bytes < mem::size_of::<T>()
+        && (delta >> T::Unsigned::from_usize(bytes * 8)) > T::Unsigned::ZERO
+    {
+        bytes += 1;
+    }
+    bytes
+}
+
+/// The smallest and largest length within the given range.
+///
+/// # Panics
+///
+/// Panics if the range is empty.
+pub(crate) fn len_bounds(range: &impl ops::RangeBounds<usize>) -> (usize, usize) {
+    let min = match range.start_bound() {
+        ops::Bound::Included(&start) => Some(start),
+        ops::Bound::Excluded(&start) => start.checked_add(1),
+        ops::Bound::Unbounded => Some(0),
+    };
+    let max = match range.end_bound() {
+        ops::Bound::Included(&end) => Some(end),
+        ops::Bound::Excluded(&end) => end.checked_sub(1),
+        ops::Bound::Unbounded => Some(usize::MAX),
+    };
+    match (min, max) {
+        (Some(min), Some(max)) if min <= max => (min, max),
+        _ => panic!("`arbitrary::Unstructured::arbitrary_len_in` requires a non-empty range"),
+    }
+}
+
+/// Once an element would nest deeper than the maximum depth, a collection
+/// ends instead of failing, so that recursive types still get a value.
+fn end_at_max_depth<T>(element: Result<T>) -> Option<Result<T>> {
+    match element {
+        Err(Error::MaxDepthReached) => None,
+        element => Some(element),
+    }
 }
 
 /// Utility iterator produced by [`Unstructured::arbitrary_iter`]
 pub struct ArbitraryIter<'a, 'b, ElementType> {
     u: &'b mut Unstructured<'a>,
+    index: usize,
     _marker: PhantomData<ElementType>,
 }
 
 impl<'a, 'b, ElementType: Arbitrary<'a>> Iterator for ArbitraryIter<'a, 'b, ElementType> {
     type Item = Result<ElementType>;
     fn next(&mut self) -> Option<Result<ElementType>> {
-        let keep_going = self.u.arbitrary().unwrap_or(false);
+        let keep_going = self.u.arbitrary_continue();
         if keep_going {
-            Some(Arbitrary::arbitrary(self.u))
+            let segment = Segment::Index(self.index);
+            self.index += 1;
+            end_at_max_depth(self.u.trace_scope(segment, Arbitrary::arbitrary))
         } else {
             None
         }
@@ -767,15 +1647,48 @@ impl<'a, 'b, ElementType: Arbitrary<'a>> Iterator for ArbitraryIter<'a, 'b, Elem
 /// Utility iterator produced by [`Unstructured::arbitrary_take_rest_iter`]
 pub struct ArbitraryTakeRestIter<'a, ElementType> {
     u: Unstructured<'a>,
+    index: usize,
     _marker: PhantomData<ElementType>,
 }
 
 impl<'a, ElementType: Arbitrary<'a>> Iterator for ArbitraryTakeRestIter<'a, ElementType> {
     type Item = Result<ElementType>;
     fn next(&mut self) -> Option<Result<ElementType>> {
-        let keep_going = self.u.arbitrary().unwrap_or(false);
+        let keep_going = self.u.arbitrary_continue();
         if keep_going {
-            Some(Arbitrary::arbitrary(&mut self.u))
+            let segment = Segment::Index(self.index);
+            self.index += 1;
+            end_at_max_depth(self.u.trace_scope(segment, Arbitrary::arbitrary))
+        } else {
+            None
+        }
+    }
+}
+
+/// Utility iterator produced by [`Unstructured::arbitrary_iter_with`]
+pub struct ArbitraryIterWith<'a, 'b, ElementType, Ctx: ?Sized> {
+    u: &'b mut Unstructured<'a>,
+    ctx: &'b Ctx,
+    index: usize,
+    _marker: PhantomData<ElementType>,
+}
+
+impl<'a, 'b, ElementType, Ctx> Iterator for ArbitraryIterWith<'a, 'b, ElementType, Ctx>
+where
+    ElementType: ArbitraryWith<'a, Ctx>,
+    Ctx: ?Sized,
+{
+    type Item = Result<ElementType>;
+    fn next(&mut self) -> Option<Result<ElementType>> {
+        let keep_going = self.u.arbitrary_continue();
+        if keep_going {
+            let segment = Segment::Index(self.index);
+            self.index += 1;
+            let ctx = self.ctx;
+            end_at_max_depth(
+                self.u
+                    .trace_scope(segment, |u| ElementType::arbitrary_with(u, ctx)),
+            )
         } else {
             None
         }
@@ -800,7 +1713,7 @@ impl<'a, ElementType: Arbitrary<'a>> Iterator for ArbitraryTakeRestIter<'a, Elem
 /// Don't implement this trait yourself.
 pub trait Int:
     Copy
-    + std::fmt::Debug
+    + core::fmt::Debug
     + PartialOrd
     + Ord
     + ops::Sub<Self, Output = Self>
@@ -827,6 +1740,12 @@ pub trait Int:
     #[doc(hidden)]
     fn from_usize(u: usize) -> Self;
 
+    #[doc(hidden)]
+    fn to_u8(self) -> u8;
+
+    #[doc(hidden)]
+    fn to_u128(self) -> u128;
+
     #[doc(hidden)]
     fn checked_add(self, rhs: Self) -> Option<Self>;
 
@@ -863,6 +1782,14 @@ macro_rules! impl_int {
                     u as Self
                 }
 
+                fn to_u8(self) -> u8 {
+                    self as u8
+                }
+
+                fn to_u128(self) -> u128 {
+                    self as u128
+                }
+
                 fn checked_add(self, rhs: Self) -> Option<Self> {
                     <$ty>::checked_add(self, rhs)
                 }
@@ -912,15 +1839,32 @@ mod tests {
         // Should take one byte off the end
         assert_eq!(u.arbitrary_byte_size().unwrap(), 6);
         assert_eq!(u.len(), 9);
-        let mut v = vec![0; 260];
-        v.push(1);
-        v.push(4);
+        let mut v = [0; 262];
+        v[260] = 1;
+        v[261] = 4;
         let mut u = Unstructured::new(&v);
         // Should read two bytes off the end
         assert_eq!(u.arbitrary_byte_size().unwrap(), 0x104);
         assert_eq!(u.len(), 260);
     }
 
+    #[test]
+    fn len_in_is_spread_over_the_range() {
+        let mut counts = [0; 17];
+        for last in u8::MIN..=u8::MAX {
+            let mut data = [0; 64];
+            data[63] = last;
+            let mut u = Unstructured::new(&data);
+            counts[u.arbitrary_len_in::<u8>(1..=16).unwrap()] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        // 256 inputs over 16 lengths is 16 per length; allow some slack for
+        // the byte size not being exactly uniform.
+        for (len, &count) in counts.iter().enumerate().skip(1) {
+            assert!((8..=32).contains(&count), "{len}: {counts:?}");
+        }
+    }
+
     #[test]
     fn int_in_range_of_one() {
         let mut u = Unstructured::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 6]);
@@ -945,6 +1889,24 @@ mod tests {
         assert!(u.is_empty());
     }
 
+    #[test]
+    fn int_in_range_size_is_exact() {
+        fn check<T: Int>(range: ops::RangeInclusive<T>) {
+            let data = [0xff; 16];
+            let mut u = Unstructured::new(&data);
+            u.int_in_range(range.clone()).unwrap();
+            assert_eq!(int_in_range_size(&range), data.len() - u.len(), "{range:?}");
+        }
+
+        check(5..=5_u8);
+        check(0..=u8::MAX);
+        check(-128..=127_i8);
+        check(0..=256_u32);
+        check(1024..=65535_u16);
+        check(0..=u64::MAX);
+        check(i128::MIN..=i128::MAX);
+    }
+
     #[test]
     fn int_in_range_in_bounds() {
         for input in u8::MIN..=u8::MAX {
@@ -1049,4 +2011,41 @@ mod tests {
             assert!(covered, "narrow[{}] should have been generated", i);
         }
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn trace_records_operations() {
+        use crate::trace::{End, Operation, Segment};
+
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let trace = Trace::new();
+        let mut u = Unstructured::with_trace(&data, &trace);
+        u.trace_scope(Segment::Field("x"), |u| {
+            u.bytes(2)?;
+            u.fill_buffer(&mut [0; 1])?;
+            u.int_in_range(0..=1000_u16)?;
+            u.choose_index(3)?;
+            u.arbitrary_byte_size()
+        })
+        .unwrap();
+        u.take_rest();
+
+        let records: Vec<_> = trace
+            .records()
+            .into_iter()
+            .map(|r| (r.operation, r.end, r.range, r.path))
+            .collect();
+        let x = || String::from("x");
+        assert_eq!(
+            records,
+            [
+                (Operation::Bytes, End::Front, 0..2, x()),
+                (Operation::FillBuffer, End::Front, 2..3, x()),
+                (Operation::IntInRange, End::Front, 3..5, x()),
+                (Operation::ChooseIndex, End::Front, 5..6, x()),
+                (Operation::ByteSize, End::Back, 9..10, x()),
+                (Operation::TakeRest, End::Front, 6..9, String::new()),
+            ]
+        );
+    }
 }
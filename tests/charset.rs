@@ -0,0 +1,181 @@
+#![cfg(feature = "alloc")]
+
+use arbitrary::{
+    charset::{
+        Alphanumeric, AsciiString, Charset, CharsetString, Identifier, LosslessString, Printable,
+    },
+    source::Prng,
+    Arbitrary, Dearbitrary, Mutate, Shrink, Unstructured,
+};
+
+mod common;
+
+use common::inputs;
+use std::ops::RangeInclusive;
+
+/// Greek letters, which take more than one byte of UTF-8 each.
+struct Greek;
+
+impl Charset for Greek {
+    const RANGES: &'static [RangeInclusive<char>] = &['α'..='ω'];
+}
+
+#[test]
+fn characters_are_in_the_charset() {
+    for data in inputs(200) {
+        let mut u = Unstructured::new(&data);
+        let ascii = AsciiString::arbitrary(&mut u).unwrap();
+        let alphanumeric = Alphanumeric::arbitrary(&mut u).unwrap();
+        let printable = Printable::arbitrary(&mut u).unwrap();
+        let greek = CharsetString::<Greek>::arbitrary(&mut u).unwrap();
+        let identifier = Identifier::arbitrary(&mut u).unwrap();
+        assert!(ascii.is_ascii());
+        assert!(alphanumeric.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert!(printable.chars().all(|c| c == ' ' || c.is_ascii_graphic()));
+        assert!(greek.chars().all(|c| ('α'..='ω').contains(&c)));
+        assert!(Identifier::new(identifier.as_str()).is_some());
+    }
+}
+
+#[test]
+fn no_data_is_wasted() {
+    // The last byte is the length, and every other byte is a character.
+    let data: Vec<u8> = (0..=255).chain([255]).collect();
+    let mut u = Unstructured::new(&data);
+    let ascii = AsciiString::arbitrary(&mut u).unwrap();
+    assert_eq!(ascii.len(), 255);
+    assert!(u.is_empty());
+
+    let ascii = AsciiString::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+    assert_eq!(ascii.len(), data.len());
+}
+
+#[test]
+fn take_rest_from_an_endless_source() {
+    for seed in 0..20 {
+        let u = Unstructured::from_source(Prng::new(seed));
+        let ascii = AsciiString::arbitrary_take_rest(u).unwrap();
+        assert!(ascii.len() <= 255);
+        let u = Unstructured::from_source(Prng::new(seed));
+        let greek = CharsetString::<Greek>::arbitrary_take_rest(u).unwrap();
+        assert!(greek.chars().count() <= 255);
+        let u = Unstructured::from_source(Prng::new(seed));
+        let identifier = Identifier::arbitrary_take_rest(u).unwrap();
+        assert!(identifier.len() <= 256);
+    }
+}
+
+#[test]
+fn dearbitrary_round_trips() {
+    for data in inputs(200) {
+        let greek = CharsetString::<Greek>::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        let identifier = Identifier::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        let value = (greek, identifier);
+        let data = value.dearbitrary_to_vec().unwrap();
+        let decoded =
+            <(CharsetString<Greek>, Identifier)>::arbitrary(&mut Unstructured::new(&data));
+        assert_eq!(decoded.unwrap(), value);
+    }
+}
+
+#[test]
+fn shrinks_and_mutations_stay_in_the_charset() {
+    let mut u = Unstructured::from_source(Prng::new(3));
+    for _ in 0..20 {
+        let mut identifier = Identifier::arbitrary(&mut u).unwrap();
+        let mut alphanumeric = Alphanumeric::arbitrary(&mut u).unwrap();
+        for candidate in identifier.shrink() {
+            assert!(
+                Identifier::new(candidate.as_str()).is_some(),
+                "{}",
+                candidate
+            );
+        }
+        for candidate in alphanumeric.shrink() {
+            assert!(
+                Alphanumeric::new(candidate.as_str()).is_some(),
+                "{}",
+                candidate
+            );
+        }
+        identifier.mutate(&mut u).unwrap();
+        alphanumeric.mutate(&mut u).unwrap();
+        assert!(
+            Identifier::new(identifier.as_str()).is_some(),
+            "{}",
+            identifier
+        );
+        assert!(
+            Alphanumeric::new(alphanumeric.as_str()).is_some(),
+            "{}",
+            alphanumeric
+        );
+    }
+}
+
+#[test]
+fn lossless_strings_use_every_byte() {
+    for data in inputs(200) {
+        let mut u = Unstructured::new(&data);
+        let string = String::arbitrary(&mut u).unwrap();
+        let consumed = data.len() - u.len();
+        let mut u = Unstructured::new(&data);
+        let lossless = u.arbitrary_string_lossless().unwrap();
+        assert!(data.len() - u.len() >= consumed);
+        assert!(lossless.starts_with(&string));
+
+        let lossless = LosslessString::arbitrary_take_rest(Unstructured::new(&data)).unwrap();
+        assert!(lossless.chars().count() >= data.len() / 4);
+    }
+
+    // `String` stops before the first byte, a stray continuation byte.
+    let mut data = vec![0x80; 100];
+    data.push(100);
+    assert_eq!(
+        String::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+        ""
+    );
+    let mut u = Unstructured::new(&data);
+    assert_eq!(u.arbitrary_string_lossless().unwrap(), "\u{80}".repeat(100));
+    assert!(u.is_empty());
+
+    let mut u = Unstructured::from_source(Prng::new(5));
+    for _ in 0..100 {
+        u.arbitrary_string_lossless().unwrap();
+    }
+}
+
+#[test]
+fn changing_a_byte_changes_one_character() {
+    let text = "Grüße, 世界! 🦀 Привет";
+    let chars: Vec<char> = text.chars().collect();
+    for i in 0..text.len() {
+        // Bytes that keep the groups in place: anything in place of a
+        // continuation byte, and anything but a leading byte in place of an
+        // ASCII one.
+        let replacements = match text.as_bytes()[i] {
+            0x80..=0xbf => 0x00..=0xff,
+            0x00..=0x7f => 0x00..=0xbf,
+            _ => continue,
+        };
+        for byte in replacements {
+            let mut bytes = text.as_bytes().to_vec();
+            bytes[i] = byte;
+            let lossless = LosslessString::arbitrary_take_rest(Unstructured::new(&bytes)).unwrap();
+            let changed: Vec<char> = lossless.chars().collect();
+            assert_eq!(changed.len(), chars.len(), "{:?}", lossless);
+            let differences = chars.iter().zip(&changed).filter(|(a, b)| a != b).count();
+            assert!(differences <= 1, "{:?}", lossless);
+        }
+    }
+}
+
+#[test]
+fn lossless_dearbitrary_round_trips() {
+    for data in inputs(200) {
+        let value = LosslessString::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        let data = value.dearbitrary_to_vec().unwrap();
+        let decoded = LosslessString::arbitrary(&mut Unstructured::new(&data)).unwrap();
+        assert_eq!(decoded, value);
+    }
+}
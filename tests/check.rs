@@ -0,0 +1,146 @@
+#![cfg(feature = "std")]
+
+use arbitrary::{
+    check::{Config, Failure},
+    Arbitrary, Error, Result, Unstructured,
+};
+use std::{env, fs, process};
+
+#[test]
+fn passing_property() {
+    arbitrary::check(|(a, b): (u8, u8)| {
+        if u16::from(a) + u16::from(b) <= 510 {
+            Ok(())
+        } else {
+            Err("overflow")
+        }
+    });
+}
+
+#[test]
+fn failure_is_shrunk() {
+    let failure = Config::new()
+        .seed(7)
+        .try_run(|x: u32| if x < 1000 { Ok(()) } else { Err(x) })
+        .unwrap_err();
+    match failure {
+        Failure::Failed {
+            seed, input, value, ..
+        } => {
+            assert_eq!(seed, Some(7));
+            assert_eq!(input.len(), 2);
+            let x = u32::arbitrary_take_rest(Unstructured::new(&input)).unwrap();
+            assert!(x >= 1000);
+            assert_eq!(value, x.to_string());
+        }
+        other => panic!("unexpected failure: {:?}", other),
+    }
+}
+
+#[test]
+fn panics_are_failures() {
+    let failure = Config::new()
+        .seed(1)
+        .try_run(|v: Vec<u8>| {
+            assert!(v.len() < 3, "too long");
+            Ok::<(), ()>(())
+        })
+        .unwrap_err();
+    match failure {
+        Failure::Failed { value, error, .. } => {
+            assert_eq!(value, "[0, 0, 0]");
+            assert_eq!(error, "panicked: too long");
+        }
+        other => panic!("unexpected failure: {:?}", other),
+    }
+}
+
+#[test]
+fn failure_message_has_seed_and_input() {
+    let failure = Config::new()
+        .seed(99)
+        .shrink(false)
+        .try_run(|_: bool| Err::<(), _>("nope"))
+        .unwrap_err();
+    let message = failure.to_string();
+    assert!(message.contains("property failed: \"nope\""), "{}", message);
+    assert!(message.contains("ARBITRARY_SEED=99"), "{}", message);
+    assert!(message.contains("input: "), "{}", message);
+}
+
+struct Never;
+
+impl<'a> Arbitrary<'a> for Never {
+    fn arbitrary(_: &mut Unstructured<'a>) -> Result<Self> {
+        Err(Error::IncorrectFormat)
+    }
+}
+
+impl std::fmt::Debug for Never {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Never")
+    }
+}
+
+#[test]
+fn gives_up_after_too_many_discards() {
+    let failure = Config::new()
+        .seed(3)
+        .max_discards(10)
+        .try_run(|_: Never| Ok::<(), ()>(()))
+        .unwrap_err();
+    assert_eq!(
+        failure,
+        Failure::GaveUp {
+            seed: 3,
+            passed: 0,
+            discards: 11
+        }
+    );
+}
+
+#[test]
+fn regressions_are_replayed_first() {
+    let path = env::temp_dir()
+        .join(format!("arbitrary-check-{}", process::id()))
+        .join("regressions.txt");
+    let _ = fs::remove_file(&path);
+    let property = |x: u16| if x % 1000 != 999 { Ok(()) } else { Err(x) };
+
+    let failure = Config::new()
+        .seed(5)
+        .cases(100_000)
+        .regressions(&path)
+        .try_run(property)
+        .unwrap_err();
+    let input = match failure {
+        Failure::Failed {
+            input, regressions, ..
+        } => {
+            assert_eq!(regressions.as_deref(), Some(path.as_path()));
+            input
+        }
+        other => panic!("unexpected failure: {:?}", other),
+    };
+    let saved = fs::read_to_string(&path).unwrap();
+    assert_eq!(saved.lines().count(), 1);
+
+    // No new cases at all, but the saved input still fails.
+    let failure = Config::new()
+        .cases(0)
+        .regressions(&path)
+        .try_run(property)
+        .unwrap_err();
+    match failure {
+        Failure::Failed {
+            seed, input: again, ..
+        } => {
+            assert_eq!(seed, None);
+            assert_eq!(again, input);
+        }
+        other => panic!("unexpected failure: {:?}", other),
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
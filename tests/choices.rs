@@ -0,0 +1,63 @@
+#![cfg(all(feature = "derive", feature = "std"))]
+
+use arbitrary::{choices::minimize, Arbitrary, Dearbitrary, Unstructured};
+
+#[derive(Arbitrary, Dearbitrary, Clone, Debug, PartialEq)]
+enum Expr {
+    Num(u16),
+    Neg(Box<Expr>),
+    Sum(Vec<Expr>),
+}
+
+impl Expr {
+    fn has_negated_sum(&self) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Neg(inner) => matches!(**inner, Expr::Sum(_)) || inner.has_negated_sum(),
+            Expr::Sum(terms) => terms.iter().any(Expr::has_negated_sum),
+        }
+    }
+}
+
+fn decode(data: &[u8]) -> Option<Expr> {
+    Expr::arbitrary(&mut Unstructured::new(data)).ok()
+}
+
+#[test]
+fn minimize_derived_value() {
+    let expr = Expr::Sum(vec![
+        Expr::Num(1234),
+        Expr::Neg(Box::new(Expr::Num(7))),
+        Expr::Neg(Box::new(Expr::Sum(vec![Expr::Num(1), Expr::Num(2)]))),
+        Expr::Num(99),
+    ]);
+    let input = expr.dearbitrary_to_vec().unwrap();
+    assert_eq!(decode(&input), Some(expr));
+
+    let fails = |mut u: Unstructured<'_>| {
+        Expr::arbitrary(&mut u).map_or(false, |expr| expr.has_negated_sum())
+    };
+    let minimal = minimize(&input, fails);
+    assert!(minimal.len() < input.len());
+    assert_eq!(
+        decode(&minimal),
+        Some(Expr::Neg(Box::new(Expr::Sum(Vec::new()))))
+    );
+}
+
+#[test]
+fn minimize_is_idempotent() {
+    let input = vec![
+        Expr::Num(9),
+        Expr::Sum(vec![Expr::Num(3), Expr::Num(4)]),
+        Expr::Neg(Box::new(Expr::Num(5))),
+    ]
+    .dearbitrary_to_vec()
+    .unwrap();
+    let fails = |mut u: Unstructured<'_>| {
+        Vec::<Expr>::arbitrary(&mut u).map_or(false, |exprs| exprs.len() >= 2)
+    };
+    assert!(fails(Unstructured::new(&input)));
+    let minimal = minimize(&input, fails);
+    assert_eq!(minimize(&minimal, fails), minimal);
+}
@@ -0,0 +1,10 @@
+use arbitrary::source::{ByteSource, Prng};
+
+/// `count` pseudo-random inputs, of every length from zero up.
+pub fn inputs(count: u64) -> impl Iterator<Item = Vec<u8>> {
+    (0..count).map(|i| {
+        let mut data = vec![0; i as usize];
+        Prng::new(i).fill(&mut data);
+        data
+    })
+}
@@ -0,0 +1,276 @@
+#![cfg(all(feature = "derive", feature = "alloc"))]
+
+use arbitrary::{Arbitrary, Dearbitrary, Dearbitrator, Error, Unstructured};
+
+fn arbitrary_from<'a, T: Arbitrary<'a>>(input: &'a [u8]) -> T {
+    let mut buf = Unstructured::new(input);
+    T::arbitrary(&mut buf).expect("can create arbitrary instance OK")
+}
+
+fn dearbitrary_round_trip<'a, T>(value: &T, data: &'a mut Vec<u8>) -> T
+where
+    T: Arbitrary<'a> + Dearbitrary,
+{
+    *data = value.dearbitrary_to_vec().expect("can dearbitrary OK");
+    arbitrary_from(data)
+}
+
+#[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+struct DearbitraryStruct<'a, T> {
+    unit: (),
+    name: &'a str,
+    inner: T,
+    list: Vec<DearbitraryTuple>,
+}
+
+#[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+struct DearbitraryTuple(u8, bool, Option<u32>);
+
+#[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+struct DearbitraryUnit;
+
+#[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+enum DearbitraryTree {
+    Leaf(u16),
+    Node {
+        left: Box<DearbitraryTree>,
+        right: Box<DearbitraryTree>,
+    },
+    Empty,
+}
+
+#[test]
+fn dearbitrary_struct() {
+    let value = DearbitraryStruct {
+        unit: (),
+        name: "hello",
+        inner: [1_u64, 2, 3],
+        list: vec![
+            DearbitraryTuple(1, true, None),
+            DearbitraryTuple(2, false, Some(42)),
+        ],
+    };
+    assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    assert_eq!(
+        dearbitrary_round_trip(&DearbitraryUnit, &mut Vec::new()),
+        DearbitraryUnit
+    );
+}
+
+#[test]
+fn dearbitrary_enum() {
+    use DearbitraryTree::*;
+
+    let leaf = |x| Box::new(Leaf(x));
+    let values = [
+        Leaf(7),
+        Empty,
+        Node {
+            left: leaf(1),
+            right: Box::new(Node {
+                left: leaf(2),
+                right: Box::new(Empty),
+            }),
+        },
+    ];
+    for value in values {
+        assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    }
+}
+
+#[test]
+fn dearbitrary_enum_selects_every_variant() {
+    // The variant selector must survive the multiply-shift for any number of
+    // variants, not just the ones that divide 2^32 evenly.
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    enum Seven {
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+    }
+
+    for value in [
+        Seven::A,
+        Seven::B,
+        Seven::C,
+        Seven::D,
+        Seven::E,
+        Seven::F,
+        Seven::G,
+    ] {
+        assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    }
+}
+
+#[test]
+fn dearbitrary_weighted_variants() {
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    enum Weighted {
+        #[arbitrary(weight = 0)]
+        Never,
+        #[arbitrary(weight = 7)]
+        Often(u8),
+        Rarely,
+        #[arbitrary(weight = 3)]
+        Sometimes {
+            x: u16,
+        },
+    }
+
+    for value in [
+        Weighted::Often(9),
+        Weighted::Rarely,
+        Weighted::Sometimes { x: 300 },
+    ] {
+        assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    }
+    assert_eq!(
+        Weighted::Never.dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+
+    // The weights add up to more than `u32::MAX`, so no selector picks the
+    // light variant between the heavy ones.
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    enum Heavy {
+        #[arbitrary(weight = 4294967295)]
+        A,
+        B,
+        #[arbitrary(weight = 4294967295)]
+        C,
+        #[arbitrary(weight = 4294967295)]
+        D,
+    }
+
+    for value in [Heavy::A, Heavy::C, Heavy::D] {
+        assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    }
+    assert_eq!(Heavy::B.dearbitrary_to_vec(), Err(Error::Unrepresentable));
+}
+
+#[test]
+fn dearbitrary_stable_tags() {
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    #[arbitrary(stable_tags)]
+    enum Tagged {
+        Plain,
+        #[arbitrary(tag = 7)]
+        Seven(u8),
+        #[arbitrary(skip)]
+        Skipped,
+        Named {
+            x: u16,
+        },
+    }
+
+    for value in [Tagged::Plain, Tagged::Seven(9), Tagged::Named { x: 300 }] {
+        let mut data = Vec::new();
+        assert_eq!(dearbitrary_round_trip(&value, &mut data), value);
+    }
+    let mut data = Vec::new();
+    dearbitrary_round_trip(&Tagged::Seven(1), &mut data);
+    assert_eq!(data[..4], 7_u32.to_le_bytes());
+    assert_eq!(
+        Tagged::Skipped.dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+}
+
+#[test]
+fn dearbitrary_field_attributes() {
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    struct Parcel {
+        #[arbitrary(default)]
+        width: u8,
+
+        #[arbitrary(value = 2 + 2)]
+        length: u8,
+
+        height: u8,
+    }
+
+    let value = Parcel {
+        width: 0,
+        length: 4,
+        height: 42,
+    };
+    assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+
+    #[derive(Arbitrary, Dearbitrary, Debug)]
+    #[allow(dead_code)]
+    struct Custom {
+        #[arbitrary(with = |u: &mut Unstructured| u.int_in_range(0..=100))]
+        price: u8,
+    }
+
+    assert_eq!(
+        Custom { price: 42 }.dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+}
+
+#[test]
+fn dearbitrary_skip_variant() {
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    enum Skip {
+        #[arbitrary(skip)]
+        Never(u8),
+        Always(u8),
+        #[arbitrary(skip)]
+        Nope,
+        Sometimes {
+            x: u16,
+        },
+    }
+
+    let value = Skip::Always(3);
+    assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    let value = Skip::Sometimes { x: 300 };
+    assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+
+    assert_eq!(
+        Skip::Never(1).dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+    assert_eq!(Skip::Nope.dearbitrary_to_vec(), Err(Error::Unrepresentable));
+}
+
+#[test]
+fn dearbitrary_max_depth() {
+    #[derive(Arbitrary, Dearbitrary, Debug, PartialEq)]
+    struct List(Option<Box<List>>);
+
+    fn list(len: usize) -> List {
+        (1..len).fold(List(None), |next, _| List(Some(Box::new(next))))
+    }
+
+    let value = list(Unstructured::DEFAULT_MAX_DEPTH);
+    assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    assert_eq!(
+        list(Unstructured::DEFAULT_MAX_DEPTH + 1).dearbitrary_to_vec(),
+        Err(Error::Unrepresentable)
+    );
+    assert_eq!(list(70).dearbitrary_to_vec(), Err(Error::Unrepresentable));
+
+    let mut d = Dearbitrator::new().with_max_depth(3);
+    d.push(&list(3)).unwrap();
+    let data = d.finish();
+    let mut u = Unstructured::new(&data).with_max_depth(3);
+    assert_eq!(List::arbitrary(&mut u).unwrap(), list(3));
+    let mut d = Dearbitrator::new().with_max_depth(3);
+    assert_eq!(d.push(&list(4)), Err(Error::Unrepresentable));
+}
+
+#[test]
+fn dearbitrary_generated_values() {
+    let mut raw = Vec::new();
+    for i in 0..=255_u8 {
+        raw.push(i.wrapping_mul(97));
+        let value: DearbitraryStruct<'_, DearbitraryTree> = arbitrary_from(&raw);
+        assert_eq!(dearbitrary_round_trip(&value, &mut Vec::new()), value);
+    }
+}
@@ -6,7 +6,7 @@
 // In this case, using assert! is less explicit and readable
 #![allow(clippy::bool_assert_comparison)]
 
-use arbitrary::*;
+use arbitrary::{charset, Arbitrary, ArbitraryWith, Dearbitrary, Error, Result, Unstructured};
 
 fn arbitrary_from<'a, T: Arbitrary<'a>>(input: &'a [u8]) -> T {
     let mut buf = Unstructured::new(input);
@@ -143,6 +143,62 @@ fn test_skip_variant() {
     })
 }
 
+#[derive(Arbitrary, Debug)]
+enum WeightedVariant {
+    #[arbitrary(weight = 5)]
+    Often(u8),
+    Rarely,
+    #[arbitrary(weight = 2)]
+    Sometimes {
+        x: u8,
+    },
+    #[arbitrary(weight = 0)]
+    Never,
+}
+
+#[test]
+fn test_weighted_variants() {
+    // Evenly spaced selectors pick the variants in proportion to their
+    // weights.
+    let mut counts = [0; 4];
+    for i in 0..1_u32 << 12 {
+        let data = (i << 20).to_le_bytes();
+        let index = match WeightedVariant::arbitrary(&mut Unstructured::new(&data)).unwrap() {
+            WeightedVariant::Often(_) => 0,
+            WeightedVariant::Rarely => 1,
+            WeightedVariant::Sometimes { .. } => 2,
+            WeightedVariant::Never => 3,
+        };
+        counts[index] += 1;
+    }
+    let total = counts.iter().sum::<u32>();
+    assert_eq!(counts, [total * 5 / 8, total / 8, total * 2 / 8, 0]);
+
+    // Skipping over the whole `u32` space with a step that is coprime to
+    // everything else also lands close to the weights.
+    let mut counts = [0_u64; 4];
+    let step = 0x9e37_79b9_u32;
+    let mut x = 0_u32;
+    for _ in 0..80_000 {
+        x = x.wrapping_add(step);
+        let data = x.to_le_bytes();
+        let index = match WeightedVariant::arbitrary_take_rest(Unstructured::new(&data)).unwrap() {
+            WeightedVariant::Often(_) => 0,
+            WeightedVariant::Rarely => 1,
+            WeightedVariant::Sometimes { .. } => 2,
+            WeightedVariant::Never => 3,
+        };
+        counts[index] += 1;
+    }
+    assert_eq!(counts[3], 0);
+    for (count, weight) in counts.iter().zip([5, 1, 2]) {
+        let expected = 80_000 * weight / 8;
+        assert!(count.abs_diff(expected) < expected / 100, "{counts:?}");
+    }
+
+    assert_eq!((4, Some(5)), <WeightedVariant as Arbitrary>::size_hint(0));
+}
+
 #[derive(Arbitrary, Debug)]
 enum RecursiveTree {
     Leaf,
@@ -332,6 +388,98 @@ fn recursive_and_empty_input() {
     let _ = Nat5::arbitrary(&mut Unstructured::new(&[]));
 }
 
+#[test]
+fn recursive_depth_is_bounded() {
+    #[derive(Debug, Arbitrary, PartialEq)]
+    enum Nat {
+        Succ(Box<Nat>),
+        Zero,
+    }
+
+    fn depth(nat: &Nat) -> usize {
+        match nat {
+            Nat::Succ(n) => 1 + depth(n),
+            Nat::Zero => 1,
+        }
+    }
+
+    // Always choosing `Succ` falls back to `Zero` at the maximum depth, even
+    // with plenty of data left.
+    let data = [0; 4096];
+    let mut u = Unstructured::new(&data);
+    let nat = Nat::arbitrary(&mut u).unwrap();
+    assert_eq!(depth(&nat), Unstructured::DEFAULT_MAX_DEPTH);
+    assert!(!u.is_empty());
+    assert_eq!(u.depth(), 0);
+
+    let nat = Nat::arbitrary_take_rest(Unstructured::new(&data).with_max_depth(3)).unwrap();
+    assert_eq!(depth(&nat), 3);
+
+    let mut u = Unstructured::new(&data).with_max_depth(0);
+    assert_eq!(
+        Nat::arbitrary(&mut u),
+        Err(arbitrary::Error::MaxDepthReached)
+    );
+
+    // The budget can also run out behind a struct, or in an `Option`.
+    #[derive(Debug, Arbitrary)]
+    struct List(Option<Box<List>>);
+
+    fn len(list: &List) -> usize {
+        list.0.as_ref().map_or(1, |next| 1 + len(next))
+    }
+
+    let data = [0xff; 4096];
+    let mut u = Unstructured::new(&data).with_max_depth(10);
+    assert_eq!(len(&List::arbitrary(&mut u).unwrap()), 10);
+    assert_eq!(u.depth(), 0);
+    let list = List::arbitrary(&mut Unstructured::new(&data)).unwrap();
+    assert_eq!(len(&list), Unstructured::DEFAULT_MAX_DEPTH);
+}
+
+#[test]
+fn recursion_through_structs_falls_back() {
+    use arbitrary::source::Prng;
+
+    #[derive(Debug, Arbitrary)]
+    enum Expr {
+        Lit(u8),
+        Add(Box<Pair>),
+    }
+
+    #[derive(Debug, Arbitrary)]
+    struct Pair(Expr, Expr);
+
+    fn depth(expr: &Expr) -> usize {
+        match expr {
+            Expr::Lit(_) => 1,
+            Expr::Add(pair) => 2 + depth(&pair.0).max(depth(&pair.1)),
+        }
+    }
+
+    for seed in 0..200 {
+        let mut u = Unstructured::from_source(Prng::new(seed)).with_max_depth(9);
+        let expr = Expr::arbitrary(&mut u).unwrap();
+        assert!(depth(&expr) <= 9, "{:?}", expr);
+        assert_eq!(u.depth(), 0);
+    }
+
+    let data = [0xff; 4096];
+    let expr = Expr::arbitrary(&mut Unstructured::new(&data)).unwrap();
+    assert!(depth(&expr) <= Unstructured::DEFAULT_MAX_DEPTH);
+
+    // Collections end where their elements would nest too deeply.
+    #[derive(Debug, Arbitrary)]
+    struct Tree(Vec<Tree>);
+
+    fn height(tree: &Tree) -> usize {
+        1 + tree.0.iter().map(height).max().unwrap_or(0)
+    }
+
+    let tree = Tree::arbitrary(&mut Unstructured::new(&data).with_max_depth(5)).unwrap();
+    assert_eq!(height(&tree), 5);
+}
+
 #[test]
 fn test_field_attributes() {
     // A type that DOES NOT implement Arbitrary
@@ -377,6 +525,180 @@ fn test_field_attributes() {
     assert_eq!(parcel.price, 17);
 }
 
+#[test]
+#[cfg(feature = "std")]
+fn test_len_attribute() {
+    use std::collections::{BTreeSet, HashMap, VecDeque};
+
+    #[derive(Debug, Arbitrary)]
+    struct Bounded {
+        #[arbitrary(len = 1..=16)]
+        items: Vec<u32>,
+        #[arbitrary(len = ..3)]
+        name: String,
+        #[arbitrary(len = 2..=2)]
+        pair: VecDeque<u8>,
+        #[arbitrary(len = 4..)]
+        map: HashMap<u8, bool>,
+        #[arbitrary(len = 1..4)]
+        set: BTreeSet<u64>,
+    }
+
+    for seed in 0..=255_u8 {
+        let data: Vec<u8> = (0..200).map(|i| seed.wrapping_mul(31) ^ i).collect();
+        for b in [
+            Bounded::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+            Bounded::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+        ] {
+            assert!((1..=16).contains(&b.items.len()));
+            assert!(b.name.chars().count() < 3);
+            assert_eq!(b.pair.len(), 2);
+            // Duplicate keys collapse.
+            assert!(!b.map.is_empty());
+            assert!((1..4).contains(&b.set.len()));
+        }
+    }
+
+    // Without any data, collections get the shortest length.
+    let b: Bounded = arbitrary_from(&[]);
+    assert_eq!((b.items.len(), b.name.len(), b.pair.len()), (1, 0, 2));
+    assert_eq!((b.map.len(), b.set.len()), (1, 1));
+
+    let (lower, upper) = Bounded::size_hint(0);
+    assert_eq!(lower, 4 + 2 + 4 * 2 + 8);
+    assert_eq!(upper, None);
+}
+
+#[test]
+fn test_range_attribute() {
+    use core::num::NonZeroU32;
+    use core::time::Duration;
+
+    #[derive(Debug, Arbitrary)]
+    struct Config {
+        #[arbitrary(range = 1024..=65535)]
+        port: u16,
+        #[arbitrary(range = -1.0..1.0)]
+        balance: f32,
+        #[arbitrary(range = 'a'..='f')]
+        drive: char,
+        #[arbitrary(range = NonZeroU32::new(100).unwrap()..)]
+        workers: NonZeroU32,
+        #[arbitrary(range = ..Duration::from_millis(500))]
+        timeout: Duration,
+        #[arbitrary(range = 3..4)]
+        retries: u8,
+    }
+
+    for seed in 0..=255_u8 {
+        let data: Vec<u8> = (0..64).map(|i| seed.wrapping_mul(31) ^ i).collect();
+        for config in [
+            Config::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+            Config::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+        ] {
+            assert!(config.port >= 1024);
+            assert!((-1.0..1.0).contains(&config.balance));
+            assert!(('a'..='f').contains(&config.drive));
+            assert!(config.workers.get() >= 100);
+            assert!(config.timeout < Duration::from_millis(500));
+            assert_eq!(config.retries, 3);
+        }
+    }
+
+    // 2 bytes for the port, 4 for the float, 1 for the char, 4 for the
+    // integer and the nanoseconds of half a second fit in 4 bytes.
+    assert_eq!(Config::size_hint(0), (15, Some(15)));
+}
+
+#[test]
+fn test_regex_attribute() {
+    use std::borrow::Cow;
+
+    #[derive(Debug, Arbitrary)]
+    struct Package {
+        #[arbitrary(regex = "[a-z][a-z0-9_-]{0,7}")]
+        name: String,
+        #[arbitrary(regex = r"[0-9]\.[0-9]")]
+        version: Box<str>,
+        #[arbitrary(regex = "(alpha|beta)?")]
+        channel: Cow<'static, str>,
+    }
+
+    for seed in 0..=255_u8 {
+        let data: Vec<u8> = (0..32).map(|i| seed.wrapping_mul(37) ^ i).collect();
+        for package in [
+            Package::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+            Package::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+        ] {
+            let mut name = package.name.chars();
+            assert!(name.next().unwrap().is_ascii_lowercase());
+            assert!(name.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+            assert!(package.name.len() <= 8);
+            assert_eq!(package.version.len(), 3);
+            assert!(["", "alpha", "beta"].contains(&&*package.channel));
+        }
+    }
+
+    let package: Package = arbitrary_from(&[]);
+    assert_eq!(
+        (&*package.name, &*package.version, &*package.channel),
+        ("a", "0.0", "")
+    );
+    assert_eq!(Package::size_hint(0), (0, None));
+}
+
+#[test]
+fn test_charset_attribute() {
+    use std::{borrow::Cow, ops::RangeInclusive};
+
+    struct Vowels;
+
+    impl charset::Charset for Vowels {
+        const RANGES: &'static [RangeInclusive<char>] =
+            &['a'..='a', 'e'..='e', 'i'..='i', 'o'..='o', 'u'..='u'];
+    }
+
+    #[derive(Debug, Arbitrary)]
+    struct Variable {
+        #[arbitrary(charset = "identifier")]
+        name: String,
+        #[arbitrary(charset = "printable")]
+        value: Box<str>,
+        #[arbitrary(charset = "ascii_graphic")]
+        token: Cow<'static, str>,
+        #[arbitrary(charset = Vowels)]
+        sound: String,
+    }
+
+    for seed in 0..=255_u8 {
+        let data: Vec<u8> = (0..32).map(|i| seed.wrapping_mul(37) ^ i).collect();
+        for variable in [
+            Variable::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+            Variable::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+        ] {
+            assert!(charset::Identifier::new(variable.name).is_some());
+            assert!(variable
+                .value
+                .chars()
+                .all(|c| c == ' ' || c.is_ascii_graphic()));
+            assert!(variable.token.chars().all(|c| c.is_ascii_graphic()));
+            assert!(variable.sound.chars().all(|c| "aeiou".contains(c)));
+        }
+    }
+
+    let variable: Variable = arbitrary_from(&[]);
+    assert_eq!(
+        (
+            &*variable.name,
+            &*variable.value,
+            &*variable.token,
+            &*variable.sound
+        ),
+        ("a", "", "", "")
+    );
+    assert_eq!(Variable::size_hint(0), (0, None));
+}
+
 #[test]
 fn derive_structs_named_same_as_core() {
     #[derive(Debug, Arbitrary)]
@@ -393,3 +715,513 @@ fn derive_structs_named_same_as_core() {
 
     let _ = Default::arbitrary(&mut Unstructured::new(&[]));
 }
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_context() {
+    struct Registers {
+        count: u8,
+    }
+
+    #[derive(Debug)]
+    struct Reg(u8);
+
+    impl<'a> ArbitraryWith<'a, Registers> for Reg {
+        fn arbitrary_with(u: &mut Unstructured<'a>, regs: &Registers) -> Result<Self> {
+            Ok(Reg(u.int_in_range(0..=regs.count - 1)?))
+        }
+
+        fn size_hint_with(_depth: usize) -> (usize, Option<usize>) {
+            (1, Some(1))
+        }
+    }
+
+    #[derive(Arbitrary, Debug)]
+    #[arbitrary(context = Registers)]
+    enum Instr {
+        Mov(#[arbitrary(context)] Reg, #[arbitrary(context)] Reg),
+        Load {
+            #[arbitrary(context)]
+            dst: Reg,
+            imm: u16,
+        },
+        Branch(#[arbitrary(context)] Box<Instr>),
+        Nop,
+    }
+
+    #[derive(Arbitrary, Debug)]
+    #[arbitrary(context = Registers)]
+    struct Program {
+        name: u8,
+        #[arbitrary(context)]
+        entry: Option<[Reg; 2]>,
+        #[arbitrary(context)]
+        instrs: Vec<Instr>,
+    }
+
+    fn check_instr(instr: &Instr, count: u8) {
+        match instr {
+            Instr::Mov(a, b) => assert!(a.0 < count && b.0 < count),
+            Instr::Load { dst, .. } => assert!(dst.0 < count),
+            Instr::Branch(instr) => check_instr(instr, count),
+            Instr::Nop => {}
+        }
+    }
+
+    let regs = Registers { count: 3 };
+    let mut saw_instrs = false;
+    for seed in 0..=255_u8 {
+        let data: Vec<u8> = (0..64_u8)
+            .map(|i| seed.wrapping_mul(97) ^ i.wrapping_mul(13))
+            .collect();
+        for program in [
+            Program::arbitrary_with(&mut Unstructured::new(&data), &regs).unwrap(),
+            Program::arbitrary_take_rest_with(Unstructured::new(&data), &regs).unwrap(),
+        ] {
+            for reg in program.entry.iter().flatten() {
+                assert!(reg.0 < regs.count);
+            }
+            for instr in &program.instrs {
+                check_instr(instr, regs.count);
+            }
+            saw_instrs |= !program.instrs.is_empty();
+        }
+    }
+    assert!(saw_instrs);
+
+    // The name, then `Option` and two registers.
+    assert_eq!(
+        <Program as ArbitraryWith<Registers>>::size_hint_with(0),
+        (2, None)
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_context_given_as_string() {
+    #[derive(Debug)]
+    struct Key(usize);
+
+    impl<'a> ArbitraryWith<'a, [&str]> for Key {
+        fn arbitrary_with(u: &mut Unstructured<'a>, keys: &[&str]) -> Result<Self> {
+            u.choose_index(keys.len()).map(Key)
+        }
+    }
+
+    #[derive(Arbitrary, Debug)]
+    #[arbitrary(context = "[&str]")]
+    struct Edit {
+        #[arbitrary(context)]
+        key: Key,
+        value: u8,
+    }
+
+    let keys = ["a", "b", "c"];
+    let mut u = Unstructured::new(&[2, 7]);
+    let edit = Edit::arbitrary_with(&mut u, &keys[..]).unwrap();
+    assert_eq!(keys[edit.key.0], "c");
+    assert_eq!(edit.value, 7);
+
+    // There is no key to pick in an empty context.
+    let mut u = Unstructured::new(&[]);
+    assert!(Edit::arbitrary_with(&mut u, &[][..]).is_err());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_fields_refer_to_earlier_fields() {
+    #[derive(Arbitrary, Debug)]
+    struct Message {
+        len: u8,
+        #[arbitrary(with = |u: &mut Unstructured, len: &u8| {
+            (0..*len).map(|_| u.arbitrary()).collect()
+        })]
+        data: Vec<u8>,
+        #[arbitrary(value = *len as u32 * 2)]
+        doubled: u32,
+        #[arbitrary(len = *len as usize..=*len as usize)]
+        copy: Vec<u16>,
+        #[arbitrary(range = *len..)]
+        at_least_len: u8,
+    }
+
+    for seed in 0..=255_u8 {
+        let data: Vec<u8> = (0..64_u8).map(|i| seed ^ i.wrapping_mul(7)).collect();
+        for message in [
+            Message::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+            Message::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+        ] {
+            assert_eq!(message.data.len(), usize::from(message.len));
+            assert_eq!(message.doubled, u32::from(message.len) * 2);
+            assert_eq!(message.copy.len(), usize::from(message.len));
+            assert!(message.at_least_len >= message.len);
+        }
+    }
+
+    // `len`, the size of a `Vec` for `data`, and `at_least_len`. The length
+    // of `copy` depends on `len`, so its size is unknown.
+    assert_eq!(Message::size_hint(0), (26, None));
+}
+
+#[test]
+fn test_field_named_u() {
+    // The field named `u` does not shadow the `Unstructured` in the
+    // attributes of the fields after it.
+    #[derive(Arbitrary, Debug)]
+    enum Shape {
+        Point {
+            u: u8,
+            #[arbitrary(with = |u: &mut Unstructured| u.int_in_range(0..=9))]
+            v: u8,
+            #[arbitrary(value = *v + 1)]
+            w: u8,
+        },
+    }
+
+    // The first four bytes choose the variant.
+    let mut u = Unstructured::new(&[0, 0, 0, 0, 4, 5, 6]);
+    let Shape::Point { u: x, v, w } = Shape::arbitrary(&mut u).unwrap();
+    assert_eq!((x, v, w), (4, 5, 6));
+}
+
+#[test]
+fn test_filter_and_fixup() {
+    #[derive(Arbitrary, Debug, PartialEq)]
+    #[arbitrary(filter = |b: &Bounds| b.min <= b.max)]
+    #[arbitrary(retries = 3)]
+    struct Bounds {
+        min: u8,
+        max: u8,
+    }
+
+    // The first two values are rejected, the third one is not.
+    let mut u = Unstructured::new(&[5, 1, 9, 2, 1, 2, 7]);
+    assert_eq!(
+        Bounds::arbitrary(&mut u).unwrap(),
+        Bounds { min: 1, max: 2 }
+    );
+    assert_eq!(u.len(), 1);
+
+    // Rejecting every value runs out of retries.
+    let mut u = Unstructured::new(&[9, 1, 9, 1, 9, 1, 9, 1, 0, 0]);
+    assert_eq!(Bounds::arbitrary(&mut u), Err(Error::IncorrectFormat));
+
+    // `arbitrary_take_rest` doesn't retry.
+    assert_eq!(
+        Bounds::arbitrary_take_rest(Unstructured::new(&[1, 2])).unwrap(),
+        Bounds { min: 1, max: 2 }
+    );
+    assert_eq!(
+        Bounds::arbitrary_take_rest(Unstructured::new(&[2, 1])),
+        Err(Error::IncorrectFormat)
+    );
+
+    #[derive(Arbitrary, Debug)]
+    #[arbitrary(fixup = Packet::seal)]
+    struct Packet {
+        payload: [u8; 3],
+        checksum: u8,
+    }
+
+    impl Packet {
+        fn seal(&mut self) {
+            self.checksum = self.payload.iter().fold(0, |a, b| a ^ b);
+        }
+    }
+
+    for seed in 0..=255_u8 {
+        let data = [seed, seed.wrapping_mul(3), seed.wrapping_mul(5), seed];
+        for packet in [
+            Packet::arbitrary(&mut Unstructured::new(&data)).unwrap(),
+            Packet::arbitrary_take_rest(Unstructured::new(&data)).unwrap(),
+        ] {
+            assert_eq!(
+                packet.checksum,
+                packet.payload[0] ^ packet.payload[1] ^ packet.payload[2]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_enum_filter() {
+    #[derive(Arbitrary, Debug, PartialEq)]
+    #[arbitrary(filter = |op: &Op| !matches!(op, Op::Div(0)))]
+    #[arbitrary(retries = 1)]
+    enum Op {
+        Nop,
+        Div(u8),
+    }
+
+    // `Div(0)` is rejected, and the next value is generated instead.
+    let mut u = Unstructured::new(&[0, 0, 0, 0x80, 0, 0, 0, 0, 0x80, 3]);
+    assert_eq!(Op::arbitrary(&mut u).unwrap(), Op::Div(3));
+    assert_eq!(
+        Op::arbitrary_take_rest(Unstructured::new(&[0, 0, 0, 0x80, 0])),
+        Err(Error::IncorrectFormat)
+    );
+    assert_eq!(
+        Op::arbitrary_take_rest(Unstructured::new(&[0, 0, 0, 0x80, 7])).unwrap(),
+        Op::Div(7)
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_field_filter_and_fixup() {
+    #[derive(Arbitrary, Debug, PartialEq)]
+    struct Span {
+        start: u8,
+        // Filters and fixups of fields can refer to the fields before them.
+        #[arbitrary(filter = |end: &u8| *end >= *start)]
+        #[arbitrary(retries = 2)]
+        end: u8,
+        #[arbitrary(with = |u: &mut Unstructured| u.int_in_range(0..=9))]
+        #[arbitrary(fixup = |digit: &mut u8| *digit += b'0')]
+        digit: u8,
+    }
+
+    let mut u = Unstructured::new(&[5, 1, 2, 6, 3]);
+    assert_eq!(
+        Span::arbitrary(&mut u).unwrap(),
+        Span {
+            start: 5,
+            end: 6,
+            digit: b'3'
+        }
+    );
+
+    let mut u = Unstructured::new(&[5, 1, 2, 3, 3]);
+    assert_eq!(Span::arbitrary(&mut u), Err(Error::IncorrectFormat));
+
+    // The last field takes the rest of the data, and is not retried.
+    #[derive(Arbitrary, Debug, PartialEq)]
+    struct Tail {
+        #[arbitrary(filter = |v: &Vec<u8>| !v.is_empty())]
+        #[arbitrary(retries = 2)]
+        rest: Vec<u8>,
+    }
+
+    assert_eq!(
+        Tail::arbitrary_take_rest(Unstructured::new(&[1, 2])).unwrap(),
+        Tail {
+            rest: Vec::arbitrary_take_rest(Unstructured::new(&[1, 2])).unwrap()
+        }
+    );
+    assert_eq!(
+        Tail::arbitrary_take_rest(Unstructured::new(&[])),
+        Err(Error::IncorrectFormat)
+    );
+}
+
+#[test]
+fn test_remote() {
+    // Stands in for another crate, whose types can't implement `Arbitrary`
+    // here.
+    mod other {
+        #[derive(Debug, PartialEq)]
+        pub struct Color {
+            pub rgb: (u8, u8, u8),
+            pub name: &'static str,
+        }
+
+        #[derive(Debug, PartialEq)]
+        pub enum Shape<T> {
+            Point,
+            Circle(T),
+            Rect { width: T, height: T },
+        }
+    }
+
+    #[derive(Arbitrary)]
+    #[arbitrary(remote = "other::Color")]
+    struct ColorDef {
+        rgb: (u8, u8, u8),
+        #[arbitrary(value = "custom")]
+        name: &'static str,
+    }
+
+    #[derive(Arbitrary)]
+    #[arbitrary(remote = other::Shape)]
+    enum ShapeDef<T> {
+        Point,
+        Circle(T),
+        Rect { width: T, height: T },
+    }
+
+    #[derive(Arbitrary, Debug)]
+    struct Drawing {
+        #[arbitrary(with = ColorDef::arbitrary)]
+        color: other::Color,
+        #[arbitrary(with = ShapeDef::arbitrary)]
+        shape: other::Shape<u8>,
+    }
+
+    let mut u = Unstructured::new(&[1, 2, 3]);
+    assert_eq!(
+        ColorDef::arbitrary(&mut u).unwrap(),
+        other::Color {
+            rgb: (1, 2, 3),
+            name: "custom"
+        }
+    );
+    assert_eq!(
+        ColorDef::arbitrary_take_rest(Unstructured::new(&[4, 5, 6])).unwrap(),
+        other::Color {
+            rgb: (4, 5, 6),
+            name: "custom"
+        }
+    );
+    assert_eq!(ColorDef::size_hint(0), (3, Some(3)));
+
+    let mut u = Unstructured::new(&[0, 0, 0, 0xff, 7, 3]);
+    assert_eq!(
+        ShapeDef::<u8>::arbitrary(&mut u).unwrap(),
+        other::Shape::Rect {
+            width: 7,
+            height: 3
+        }
+    );
+    assert_eq!(ShapeDef::<u8>::size_hint(0), (4, Some(6)));
+
+    let drawing = Drawing::arbitrary(&mut Unstructured::new(&[9, 8, 7, 0, 0, 0, 0x80, 6])).unwrap();
+    assert_eq!(drawing.color.rgb, (9, 8, 7));
+    assert_eq!(drawing.shape, other::Shape::Circle(6));
+}
+
+#[test]
+fn test_from_and_try_from() {
+    #[derive(Arbitrary, Debug, PartialEq)]
+    #[arbitrary(from = u8)]
+    struct Percent(u8);
+
+    impl From<u8> for Percent {
+        fn from(raw: u8) -> Self {
+            Percent(raw % 101)
+        }
+    }
+
+    assert_eq!(
+        Percent::arbitrary(&mut Unstructured::new(&[250])).unwrap(),
+        Percent(48)
+    );
+    assert_eq!(
+        Percent::arbitrary_take_rest(Unstructured::new(&[7])).unwrap(),
+        Percent(7)
+    );
+    assert_eq!(Percent::size_hint(0), (1, Some(1)));
+
+    #[derive(Arbitrary, Debug, PartialEq)]
+    #[arbitrary(try_from = "(u8, u8)")]
+    enum Interval {
+        Empty,
+        Closed { start: u8, end: u8 },
+    }
+
+    impl TryFrom<(u8, u8)> for Interval {
+        type Error = ();
+
+        fn try_from((start, end): (u8, u8)) -> Result<Self, ()> {
+            match start.cmp(&end) {
+                std::cmp::Ordering::Less => Ok(Interval::Closed { start, end }),
+                std::cmp::Ordering::Equal => Ok(Interval::Empty),
+                std::cmp::Ordering::Greater => Err(()),
+            }
+        }
+    }
+
+    assert_eq!(
+        Interval::arbitrary(&mut Unstructured::new(&[1, 2])).unwrap(),
+        Interval::Closed { start: 1, end: 2 }
+    );
+    assert_eq!(
+        Interval::arbitrary(&mut Unstructured::new(&[3, 3])).unwrap(),
+        Interval::Empty
+    );
+    assert_eq!(
+        Interval::arbitrary(&mut Unstructured::new(&[2, 1])),
+        Err(Error::IncorrectFormat)
+    );
+    assert_eq!(
+        Interval::arbitrary_take_rest(Unstructured::new(&[2, 1])),
+        Err(Error::IncorrectFormat)
+    );
+    assert_eq!(Interval::size_hint(0), (2, Some(2)));
+    assert_eq!(
+        Interval::try_size_hint(0).unwrap(),
+        <(u8, u8)>::try_size_hint(0).unwrap()
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_stable_tags() {
+    // The same enum, before and after adding a variant, and skipping one.
+    #[derive(Arbitrary, Dearbitrary, Debug)]
+    #[arbitrary(stable_tags)]
+    enum Before {
+        Read(u8),
+        Write(u8),
+        #[arbitrary(tag = 0x8000_0000)]
+        Close,
+    }
+
+    #[derive(Arbitrary, Debug)]
+    #[arbitrary(stable_tags)]
+    enum After {
+        Open,
+        Read(u8),
+        #[arbitrary(skip)]
+        Write(u8),
+        #[arbitrary(tag = 0x8000_0000)]
+        Close,
+        #[arbitrary(tag = 0x4000_0000)]
+        Seek(u8),
+    }
+
+    fn name<T: core::fmt::Debug>(value: &T) -> String {
+        let debug = format!("{:?}", value);
+        debug.split('(').next().unwrap().to_string()
+    }
+
+    // Every input that decodes to a variant before decodes to the same one
+    // after, unless that variant is skipped now.
+    let tags = [
+        Before::Read(1).dearbitrary_to_vec().unwrap(),
+        Before::Write(2).dearbitrary_to_vec().unwrap(),
+        Before::Close.dearbitrary_to_vec().unwrap(),
+    ];
+    let mut decoded = 0;
+    for data in tags.iter().cloned().chain((0..=u16::MAX).map(|i| {
+        let x = u32::from(i) << 16 | u32::from(i.wrapping_mul(40503));
+        let mut data = x.to_le_bytes().to_vec();
+        data.push(42);
+        data
+    })) {
+        let before = Before::arbitrary(&mut Unstructured::new(&data));
+        let after = After::arbitrary(&mut Unstructured::new(&data));
+        match (before, after) {
+            (Ok(before), Ok(after)) => {
+                assert_eq!(name(&before), name(&after));
+                decoded += 1;
+            }
+            (Ok(Before::Write(_)), Err(Error::IncorrectFormat)) => {}
+            (Err(Error::IncorrectFormat), Err(Error::IncorrectFormat)) => {}
+            (before, after) => panic!("{:?} became {:?}", before, after),
+        }
+    }
+    assert_eq!(decoded, 2);
+
+    // A variant's tag always picks it, and other values are rejected.
+    let mut u = Unstructured::new(&[0, 0, 0, 0x80]);
+    assert!(matches!(Before::arbitrary(&mut u).unwrap(), Before::Close));
+    let mut u = Unstructured::new(&[0, 0, 0, 0x80]);
+    assert!(matches!(After::arbitrary(&mut u).unwrap(), After::Close));
+    let mut u = Unstructured::new(&[0, 0, 0, 0x40, 3]);
+    assert!(matches!(After::arbitrary(&mut u).unwrap(), After::Seek(3)));
+    let mut u = Unstructured::new(&[0, 0, 0, 0x40, 3]);
+    assert_eq!(
+        Before::arbitrary(&mut u).unwrap_err(),
+        Error::IncorrectFormat
+    );
+}
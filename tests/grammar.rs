@@ -0,0 +1,161 @@
+#![cfg(feature = "alloc")]
+
+use arbitrary::{
+    grammar::{Expr, Grammar, Node},
+    Error, Unstructured,
+};
+
+mod common;
+
+use common::inputs;
+
+const ARITHMETIC: &str = r#"
+    (* Integer arithmetic. *)
+    expr   = term { ("+" | "-") term } ;
+    term   = factor { ("*" | "/") factor } ;
+    factor = number | "-" factor | "(" expr ")" ;
+    number = /0|[1-9][0-9]{0,3}/ ;
+"#;
+
+fn arithmetic() -> Grammar {
+    Grammar::new([
+        (
+            "expr",
+            Expr::seq([
+                Expr::rule("term"),
+                Expr::seq([
+                    Expr::choice([Expr::terminal("+"), Expr::terminal("-")]),
+                    Expr::rule("term"),
+                ])
+                .zero_or_more(),
+            ]),
+        ),
+        (
+            "term",
+            Expr::seq([
+                Expr::rule("factor"),
+                Expr::seq([
+                    Expr::choice([Expr::terminal("*"), Expr::terminal("/")]),
+                    Expr::rule("factor"),
+                ])
+                .zero_or_more(),
+            ]),
+        ),
+        (
+            "factor",
+            Expr::choice([
+                Expr::rule("number"),
+                Expr::seq([Expr::terminal("-"), Expr::rule("factor")]),
+                Expr::seq([Expr::terminal("("), Expr::rule("expr"), Expr::terminal(")")]),
+            ]),
+        ),
+        ("number", Expr::pattern("0|[1-9][0-9]{0,3}")),
+    ])
+    .unwrap()
+}
+
+/// A recognizer for the arithmetic grammar, which returns the rest of the
+/// input after an expression.
+fn expr(s: &[u8]) -> Option<&[u8]> {
+    let mut s = term(s)?;
+    while let [b'+' | b'-', rest @ ..] = s {
+        s = term(rest)?;
+    }
+    Some(s)
+}
+
+fn term(s: &[u8]) -> Option<&[u8]> {
+    let mut s = factor(s)?;
+    while let [b'*' | b'/', rest @ ..] = s {
+        s = factor(rest)?;
+    }
+    Some(s)
+}
+
+fn factor(s: &[u8]) -> Option<&[u8]> {
+    match s {
+        [b'-', rest @ ..] => factor(rest),
+        [b'(', rest @ ..] => match expr(rest)? {
+            [b')', rest @ ..] => Some(rest),
+            _ => None,
+        },
+        [b'0', rest @ ..] => Some(rest),
+        [b'1'..=b'9', ..] => {
+            let digits = s.iter().take_while(|c| c.is_ascii_digit()).count();
+            (digits <= 4).then(|| &s[digits..])
+        }
+        _ => None,
+    }
+}
+
+fn height(node: &Node) -> usize {
+    match node {
+        Node::Rule { children, .. } => 1 + children.iter().map(height).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[test]
+fn generated_sentences_parse() {
+    let grammar = Grammar::from_ebnf(ARITHMETIC).unwrap();
+    for data in inputs(300) {
+        let mut u = Unstructured::new(&data);
+        let sentence = grammar.generate(&mut u).unwrap();
+        assert_eq!(expr(sentence.as_bytes()), Some(&b""[..]), "{:?}", sentence);
+    }
+}
+
+#[test]
+fn builder_matches_ebnf() {
+    let ebnf = Grammar::from_ebnf(ARITHMETIC).unwrap();
+    let built = arithmetic();
+    for data in inputs(300) {
+        let mut u = Unstructured::new(&data);
+        let mut v = Unstructured::new(&data);
+        assert_eq!(ebnf.generate(&mut u), built.generate(&mut v));
+        assert_eq!(u.len(), v.len());
+    }
+}
+
+#[test]
+fn tree_matches_string() {
+    let grammar = arithmetic();
+    for data in inputs(300) {
+        let sentence = grammar.generate(&mut Unstructured::new(&data)).unwrap();
+        let tree = grammar
+            .generate_tree(&mut Unstructured::new(&data))
+            .unwrap();
+        assert!(matches!(&tree, Node::Rule { name, .. } if name == "expr"));
+        assert_eq!(tree.to_string(), sentence);
+    }
+}
+
+#[test]
+fn recursion_respects_max_depth() {
+    let grammar = arithmetic();
+    for max_depth in 4..10 {
+        for data in inputs(300) {
+            let mut u = Unstructured::new(&data).with_max_depth(max_depth);
+            let tree = grammar.generate_tree(&mut u).unwrap();
+            assert!(height(&tree) <= max_depth, "{:?}", tree);
+        }
+    }
+
+    // `expr`, `term`, `factor` and `number` is the shortest derivation.
+    let mut u = Unstructured::new(&[0xff; 64]).with_max_depth(3);
+    assert_eq!(grammar.generate(&mut u), Err(Error::MaxDepthReached));
+}
+
+#[test]
+fn no_data_is_the_shortest_sentence() {
+    let grammar = Grammar::from_ebnf(
+        r#"
+        list  = "[" items "]" ;
+        items = item { "," item } ;
+        item  = list | "x" | "yy" ;
+        "#,
+    )
+    .unwrap();
+    let mut u = Unstructured::new(&[]);
+    assert_eq!(grammar.generate(&mut u).unwrap(), "[x]");
+}
@@ -0,0 +1,116 @@
+#![cfg(all(feature = "derive", feature = "alloc"))]
+
+use arbitrary::{Arbitrary, Mutate, Unstructured};
+
+#[derive(Arbitrary, Mutate, Clone, Debug, PartialEq)]
+struct Packet {
+    len: u8,
+    payload: Vec<u8>,
+    #[arbitrary(value = 7)]
+    version: u8,
+}
+
+#[derive(Arbitrary, Mutate, Clone, Debug, PartialEq)]
+enum Ast {
+    Nop,
+    Num(u64),
+    Neg(Box<Ast>),
+    Block {
+        label: String,
+        body: Vec<Ast>,
+    },
+    #[arbitrary(skip)]
+    Hole(u8),
+}
+
+#[derive(Arbitrary, Mutate, Clone, Debug, PartialEq)]
+struct Wrapper<'a, T>(Option<T>, &'a str);
+
+#[derive(Arbitrary, Mutate, Clone, Debug, PartialEq)]
+struct Unit;
+
+fn mutated<T: for<'a> Mutate<'a>>(mut value: T, data: &[u8]) -> T {
+    value.mutate(&mut Unstructured::new(data)).unwrap();
+    value
+}
+
+#[test]
+fn mutate_struct_field() {
+    let packet = Packet {
+        len: 2,
+        payload: vec![1, 2],
+        version: 100,
+    };
+    // Replace `len` with the maximum value.
+    assert_eq!(
+        mutated(packet.clone(), &[0, 1, 2]),
+        Packet {
+            len: 255,
+            ..packet.clone()
+        }
+    );
+    // Remove the first byte of the payload.
+    assert_eq!(
+        mutated(packet.clone(), &[1, 2, 0]),
+        Packet {
+            payload: vec![2],
+            ..packet.clone()
+        }
+    );
+    assert_eq!(mutated(Unit, &[1, 2, 3]), Unit);
+}
+
+#[test]
+fn mutate_enum_variant_or_field() {
+    // Switch to the second variant other than `Num`.
+    assert_eq!(
+        mutated(Ast::Num(5), &[0, 1, 0]),
+        Ast::Neg(Box::new(Ast::Nop))
+    );
+    // Mutate the field of `Num` instead.
+    assert_eq!(mutated(Ast::Num(5), &[1, 0, 1]), Ast::Num(7));
+    // `Nop` has no fields, so it can only switch.
+    assert_eq!(mutated(Ast::Nop, &[0]), Ast::Num(0));
+    // Skipped variants switch to any other variant.
+    assert_eq!(mutated(Ast::Hole(1), &[0, 0]), Ast::Nop);
+    assert_eq!(mutated(Ast::Hole(1), &[1, 3]), Ast::Hole(0));
+}
+
+#[test]
+fn mutate_nested() {
+    let ast = Ast::Block {
+        label: "a".into(),
+        body: vec![Ast::Num(1)],
+    };
+    // Mutate `body`, then its only element, then the field of `Num`.
+    let data = [2, 0, 1, 3, 42];
+    assert_eq!(
+        mutated(ast, &data),
+        Ast::Block {
+            label: "a".into(),
+            body: vec![Ast::Num(42)],
+        }
+    );
+}
+
+#[test]
+fn mutate_never_produces_skipped_variants() {
+    let mut ast = Ast::Nop;
+    let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+    let mut u = Unstructured::new(&data);
+    while !u.is_empty() {
+        if ast.mutate(&mut u).is_err() {
+            break;
+        }
+        assert!(!matches!(ast, Ast::Hole(_)));
+    }
+}
+
+#[test]
+fn mutate_borrowed_data() {
+    // Replace the `&str` with one that borrows from the data.
+    let data = [1, b'h', b'i', 2];
+    let mut wrapper = Wrapper(Some(1_u32), "");
+    wrapper.mutate(&mut Unstructured::new(&data)).unwrap();
+    assert_eq!(wrapper, Wrapper(Some(1), "hi"));
+}
@@ -0,0 +1,51 @@
+#![cfg(all(feature = "derive", feature = "std"))]
+
+use arbitrary::Arbitrary;
+use std::collections::HashMap;
+
+#[derive(Arbitrary, Debug, Clone, PartialEq)]
+enum Op {
+    Push(u8),
+    Pop,
+}
+
+#[arbitrary::test]
+fn reverse_twice(list: Vec<u8>) {
+    let mut reversed = list.clone();
+    reversed.reverse();
+    reversed.reverse();
+    assert_eq!(reversed, list);
+}
+
+#[arbitrary::test(cases = 50, sizes = 0..=32)]
+fn several_arguments(a: u32, b: u32, ops: Vec<Op>, map: HashMap<u8, (u8, bool)>) {
+    assert_eq!(a.wrapping_add(b), b.wrapping_add(a));
+    assert!(ops.len() <= 32);
+    assert!(map.len() <= 32);
+}
+
+#[arbitrary::test(seed = 3, shrink = false)]
+fn returns_result(x: u16, (a, b): (u8, u8)) -> Result<(), String> {
+    if u32::from(x) + u32::from(a) + u32::from(b) <= u32::from(u16::MAX) + 510 {
+        Ok(())
+    } else {
+        Err(format!("{} overflowed", x))
+    }
+}
+
+#[arbitrary::test]
+#[should_panic(expected = "property failed: panicked: too long")]
+fn failures_panic(list: Vec<u8>) {
+    assert!(list.len() < 4, "too long");
+}
+
+#[arbitrary::test]
+#[should_panic(expected = "value: ([0, 0, 0, 0],)")]
+fn failures_show_shrunk_arguments(list: Vec<u8>) {
+    assert!(list.len() < 4, "too long");
+}
+
+#[arbitrary::property_test(cases = 10)]
+fn property_test_is_an_alias(x: u8) {
+    assert_eq!(x.wrapping_add(1).wrapping_sub(1), x);
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "alloc")]
+
+use arbitrary::{
+    regex::{LazyRegex, Regex},
+    Unstructured,
+};
+
+mod common;
+#[path = "regex/patterns.rs"]
+mod patterns;
+
+use common::inputs;
+
+const PATTERNS: &[&str] = &[
+    "[a-z_][a-z0-9_]*",
+    r"[a-z][a-z0-9-]{0,62}(\.[a-z][a-z0-9-]{0,62}){0,3}",
+    r"v?(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)(-[0-9A-Za-z.]+)?",
+    r"\d{3}-\d{4}|\(\d{3}\) \d{3}-\d{4}",
+    r"[[:alpha:]][[:alnum:]]{2,}\s*=\s*\w+",
+    r"[^a-z]+x?.?",
+    r"(?P<user>[\w.]+)@(?<host>\w+)\.(com|org)",
+    r"\x41{2}\u{1F600}[à-ÿ]+?",
+    r"a\{,b\}c*?",
+    r"^(ab|cd){2,4}$",
+];
+
+#[test]
+fn generated_strings_match() {
+    for pattern in PATTERNS {
+        let regex = Regex::new(pattern).unwrap();
+        let matcher = ::regex::Regex::new(&format!("^(?:{})$", pattern)).unwrap();
+        for data in inputs(300) {
+            let s = regex.generate(&mut Unstructured::new(&data)).unwrap();
+            assert!(matcher.is_match(&s), "{:?} does not match {}", s, pattern);
+        }
+    }
+}
+
+#[test]
+fn mutations_are_local() {
+    let regex = Regex::new("[a-z]{8}").unwrap();
+    let data = [0, 1, 2, 3, 4, 5, 6, 7];
+    let original = regex.generate(&mut Unstructured::new(&data)).unwrap();
+    assert_eq!(original, "abcdefgh");
+
+    let mut mutated = data;
+    mutated[5] = 25;
+    let s = regex.generate(&mut Unstructured::new(&mutated)).unwrap();
+    assert_eq!(s, "abcdezgh");
+}
+
+#[test]
+fn lazy_regex() {
+    static PATTERN: LazyRegex = LazyRegex::new("[0-9a-f]{4}");
+    for data in inputs(300) {
+        let s = PATTERN.generate(&mut Unstructured::new(&data)).unwrap();
+        assert_eq!(s.len(), 4);
+        assert!(s.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
+
+#[test]
+fn string_matching() {
+    let mut u = Unstructured::new(&[1, 2, 3]);
+    assert_eq!(u.string_matching("x|y").unwrap(), "y");
+    assert_eq!(u.len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "invalid regex `[a-`: unclosed character class at byte 0 of the pattern")]
+fn invalid_pattern_panics() {
+    let _ = Unstructured::new(&[]).string_matching("[a-");
+}
+
+#[test]
+fn patterns_shared_with_the_derive() {
+    for pattern in patterns::VALID {
+        assert!(Regex::new(pattern).is_ok(), "pattern: {}", pattern);
+    }
+    for &(pattern, offset, message) in patterns::INVALID {
+        let error = Regex::new(pattern).unwrap_err();
+        assert_eq!(
+            (error.offset, error.message),
+            (offset, message),
+            "pattern: {}",
+            pattern
+        );
+    }
+}
@@ -0,0 +1,70 @@
+// Patterns for both `arbitrary::regex` and the check in the derive, which
+// includes this file, so that the two parsers agree.
+
+/// Patterns that both parsers accept.
+pub const VALID: &[&str] = &[
+    "abc",
+    "a|bc",
+    "x*y+z?",
+    "[b-d]{2,}",
+    r"\d{3}-\w",
+    "(?:ab|cd){2}",
+    "(?P<year>[0-9]{4})",
+    "^a$",
+    "[^\0]",
+    r"\x41\u{1F600}",
+    "a{,",
+    "[]a]",
+    "[a-]",
+    "[[:upper:]]",
+    "[a-z_][a-z0-9_]*",
+    r"[a-z][a-z0-9-]{0,62}(\.[a-z][a-z0-9-]{0,62}){0,3}",
+    r"v?(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)\.(0|[1-9][0-9]*)(-[0-9A-Za-z.]+)?",
+    r"\d{3}-\d{4}|\(\d{3}\) \d{3}-\d{4}",
+    r"[[:alpha:]][[:alnum:]]{2,}\s*=\s*\w+",
+    r"[^a-z]+x?.?",
+    r"(?P<user>[\w.]+)@(?<host>\w+)\.(com|org)",
+    r"\x41{2}\u{1F600}[à-ÿ]+?",
+    r"a\{,b\}c*?",
+    r"^(ab|cd){2,4}$",
+    "",
+    "()",
+    "a||b",
+    r"[\d\-_]",
+    r"\n\t\.\\",
+    "(?<1a>b)",
+];
+
+/// Patterns that both parsers reject, with the byte offset and message of the
+/// error.
+pub const INVALID: &[(&str, usize, &str)] = &[
+    ("(ab", 0, "unclosed group"),
+    ("ab)", 2, "unopened group"),
+    ("a**", 2, "repetition operator missing expression"),
+    ("+a", 0, "repetition operator missing expression"),
+    (
+        "((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((\
+         a))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))",
+        64,
+        "groups are nested too deeply",
+    ),
+    ("(?i)a", 0, "unsupported group flags"),
+    ("(?=a)", 0, "unsupported group flags"),
+    ("(?P<>a)", 4, "invalid capture group name"),
+    ("a{99999999999}", 1, "invalid repetition count"),
+    ("a{3,2}", 1, "invalid repetition range"),
+    ("a\\", 1, "incomplete escape sequence"),
+    (r"\bword", 0, "word boundaries are not supported"),
+    (r"\pL", 0, "Unicode classes are not supported"),
+    (r"\q", 0, "unknown escape sequence"),
+    (r"\x{41", 0, "unclosed hex escape"),
+    (r"\x{D800}", 0, "invalid hex escape"),
+    (r"\xZZ", 0, "invalid hex escape"),
+    ("[ab", 0, "unclosed character class"),
+    ("[a-", 0, "unclosed character class"),
+    ("[z-a]", 1, "invalid character class range"),
+    (r"[a-\d]", 1, "invalid character class range"),
+    ("[^\0-\u{10FFFF}]", 0, "empty character class"),
+    ("[[:alpha", 1, "unclosed ASCII class"),
+    ("[[:nope:]]", 1, "unknown ASCII class"),
+];
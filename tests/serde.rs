@@ -0,0 +1,129 @@
+#![cfg(feature = "serde")]
+
+use arbitrary::{
+    serde::{from_unstructured, Deserializer},
+    source::Prng,
+    Dearbitrary, Error, Unstructured,
+};
+
+mod common;
+
+use common::inputs;
+use serde::Deserialize;
+use std::{collections::BTreeMap, num::NonZeroU8};
+
+#[cfg(feature = "derive")]
+use arbitrary::Arbitrary;
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "derive", derive(Arbitrary))]
+struct Record<'a> {
+    id: u32,
+    name: &'a str,
+    tags: Vec<String>,
+    score: Option<i16>,
+    shape: Shape,
+    pair: (u8, bool),
+    attrs: BTreeMap<u8, u64>,
+    key: [u8; 3],
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "derive", derive(Arbitrary))]
+enum Shape {
+    Point,
+    Circle(u16),
+    Rect { w: u8, h: u8 },
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn decodes_like_arbitrary() {
+    for data in inputs(200) {
+        let mut expected_u = Unstructured::new(&data);
+        let expected = Record::arbitrary(&mut expected_u);
+        let mut u = Unstructured::new(&data);
+        let actual = from_unstructured::<Record<'_>>(&mut u);
+        assert_eq!(actual, expected, "input: {:?}", data);
+        assert_eq!(u.len(), expected_u.len());
+    }
+}
+
+#[test]
+fn decodes_structs_and_enums() {
+    let mut decoded = 0;
+    for data in inputs(200) {
+        let mut u = Unstructured::new(&data);
+        if let Ok(record) = from_unstructured::<Record<'_>>(&mut u) {
+            let name = record.name.as_bytes().as_ptr_range();
+            assert!(record.name.is_empty() || data.as_ptr_range().contains(&name.start));
+            decoded += 1;
+        }
+    }
+    assert!(decoded > 100);
+}
+
+#[test]
+fn borrows_from_data() {
+    let data = ("hello", &b"world"[..]).dearbitrary_to_vec().unwrap();
+    let mut u = Unstructured::new(&data);
+    let (s, bytes): (&str, &[u8]) = from_unstructured(&mut u).unwrap();
+    assert_eq!((s, bytes), ("hello", &b"world"[..]));
+    assert!(data.as_ptr_range().contains(&s.as_ptr()));
+    assert!(data.as_ptr_range().contains(&bytes.as_ptr()));
+}
+
+#[test]
+fn rejected_values_are_incorrect_format() {
+    let mut u = Unstructured::new(&[0]);
+    assert_eq!(
+        from_unstructured::<NonZeroU8>(&mut u),
+        Err(Error::IncorrectFormat)
+    );
+}
+
+#[test]
+fn owned_strings_from_source() {
+    let mut u = Unstructured::from_source(Prng::new(7));
+    for _ in 0..100 {
+        let _: (String, Vec<u8>, BTreeMap<String, Shape>) = from_unstructured(&mut u).unwrap();
+    }
+}
+
+// The scalar payloads are only there to be deserialized.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<Value>),
+    Object(BTreeMap<String, Value>),
+}
+
+fn depth(value: &Value) -> usize {
+    match value {
+        Value::List(values) => 1 + values.iter().map(depth).max().unwrap_or(0),
+        Value::Object(values) => 1 + values.values().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[test]
+fn deserialize_any_respects_max_depth() {
+    let mut decoded = 0;
+    for data in inputs(200) {
+        let mut u = Unstructured::new(&data).with_max_depth(3);
+        match Value::deserialize(Deserializer::new(&mut u)) {
+            Ok(value) => {
+                assert!(depth(&value) <= 3, "{:?}", value);
+                decoded += 1;
+            }
+            Err(e) => assert_eq!(e, Error::IncorrectFormat),
+        }
+    }
+    assert!(decoded > 100);
+}
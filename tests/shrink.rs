@@ -0,0 +1,132 @@
+#![cfg(all(feature = "derive", feature = "alloc"))]
+
+use arbitrary::{shrink::minimize, Arbitrary, Shrink};
+
+#[derive(Arbitrary, Shrink, Clone, Debug, PartialEq)]
+struct Point {
+    x: i32,
+    y: i32,
+    #[arbitrary(value = 7)]
+    z: i32,
+}
+
+#[derive(Arbitrary, Shrink, Clone, Debug, PartialEq)]
+enum Ast {
+    Nop,
+    Num(u64),
+    Neg(Box<Ast>),
+    Block {
+        label: String,
+        body: Vec<Ast>,
+    },
+    #[arbitrary(skip)]
+    Hole(u8),
+}
+
+#[derive(Arbitrary, Shrink, Clone, Debug, PartialEq)]
+struct Wrapper<T>(Option<T>, bool);
+
+#[derive(Arbitrary, Shrink, Clone, Debug, PartialEq)]
+enum Single {
+    Only { a: u8 },
+}
+
+#[test]
+fn shrink_struct_fields_in_order() {
+    let point = Point {
+        x: 2,
+        y: -1,
+        z: 100,
+    };
+    assert_eq!(
+        point.shrink().collect::<Vec<_>>(),
+        [
+            Point {
+                x: 0,
+                y: -1,
+                z: 100
+            },
+            Point {
+                x: 1,
+                y: -1,
+                z: 100
+            },
+            Point { x: 2, y: 0, z: 100 },
+            Point { x: 2, y: 1, z: 100 },
+        ]
+    );
+}
+
+#[test]
+fn shrink_enum_toward_earlier_variants() {
+    assert_eq!(Ast::Nop.shrink().count(), 0);
+    assert_eq!(
+        Ast::Num(1).shrink().collect::<Vec<_>>(),
+        [Ast::Nop, Ast::Num(0)]
+    );
+    assert_eq!(
+        Ast::Neg(Box::new(Ast::Nop)).shrink().collect::<Vec<_>>(),
+        [Ast::Nop, Ast::Num(0)]
+    );
+    // Skipped variants shrink toward the others, which never shrink toward
+    // skipped variants.
+    assert_eq!(
+        Ast::Hole(3).shrink().collect::<Vec<_>>(),
+        [
+            Ast::Nop,
+            Ast::Num(0),
+            Ast::Block {
+                label: String::new(),
+                body: vec![]
+            },
+            Ast::Hole(0),
+            Ast::Hole(2),
+        ]
+    );
+    assert!(!Ast::Block {
+        label: String::new(),
+        body: vec![Ast::Num(1)]
+    }
+    .shrink()
+    .any(|ast| matches!(ast, Ast::Hole(_))));
+}
+
+#[test]
+fn minimize_derived_values() {
+    let ast = Ast::Block {
+        label: "outer".into(),
+        body: vec![
+            Ast::Num(10),
+            Ast::Neg(Box::new(Ast::Num(12345))),
+            Ast::Block {
+                label: "inner".into(),
+                body: vec![Ast::Nop, Ast::Num(99)],
+            },
+        ],
+    };
+    fn has_big_num(ast: &Ast) -> bool {
+        match ast {
+            Ast::Num(n) => *n > 1000,
+            Ast::Neg(inner) => has_big_num(inner),
+            Ast::Block { body, .. } => body.iter().any(has_big_num),
+            Ast::Nop | Ast::Hole(_) => false,
+        }
+    }
+    // Values don't shrink to their children, so this is where it gets stuck.
+    assert_eq!(
+        minimize(ast, has_big_num),
+        Ast::Block {
+            label: String::new(),
+            body: vec![Ast::Neg(Box::new(Ast::Num(1001)))],
+        }
+    );
+
+    assert_eq!(
+        minimize(Wrapper(Some(vec![1_u8, 2, 3]), true), |w| w.0.is_some()),
+        Wrapper(Some(vec![]), false)
+    );
+    assert_eq!(
+        minimize(Single::Only { a: 200 }, |_| true),
+        Single::Only { a: 0 }
+    );
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "std")]
+
+use arbitrary::{
+    check::{Config, Failure},
+    stateful::{self, StateMachine},
+    Result, Unstructured,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    Push(u8),
+    Pop,
+}
+
+/// A stack that silently drops pushes once it holds `capacity` elements.
+struct LossyStack {
+    items: Vec<u8>,
+    capacity: usize,
+}
+
+struct Stack {
+    capacity: usize,
+}
+
+impl StateMachine for Stack {
+    type State = Vec<u8>;
+    type Sut = LossyStack;
+    type Op = Op;
+    type Output = Option<u8>;
+
+    fn init_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn init_sut(&self, _: &Vec<u8>) -> LossyStack {
+        LossyStack {
+            items: Vec::new(),
+            capacity: self.capacity,
+        }
+    }
+
+    fn arbitrary_op(&self, u: &mut Unstructured<'_>, state: &Vec<u8>) -> Result<Op> {
+        if state.is_empty() || u.arbitrary()? {
+            Ok(Op::Push(u.arbitrary()?))
+        } else {
+            Ok(Op::Pop)
+        }
+    }
+
+    fn precondition(&self, state: &Vec<u8>, op: &Op) -> bool {
+        *op != Op::Pop || !state.is_empty()
+    }
+
+    fn apply(&self, sut: &mut LossyStack, op: &Op) -> Option<u8> {
+        match op {
+            Op::Push(x) => {
+                if sut.items.len() < sut.capacity {
+                    sut.items.push(*x);
+                }
+                None
+            }
+            Op::Pop => sut.items.pop(),
+        }
+    }
+
+    fn postcondition(&self, state: &Vec<u8>, op: &Op, output: &Option<u8>) -> bool {
+        match op {
+            Op::Push(_) => output.is_none(),
+            Op::Pop => output.as_ref() == state.last(),
+        }
+    }
+
+    fn next_state(&self, state: &mut Vec<u8>, op: &Op) {
+        match op {
+            Op::Push(x) => state.push(*x),
+            Op::Pop => {
+                state.pop();
+            }
+        }
+    }
+}
+
+#[test]
+fn run_ops_finds_divergence() {
+    let machine = Stack { capacity: 2 };
+    let ops = [Op::Push(1), Op::Push(2), Op::Pop, Op::Pop];
+    assert!(stateful::run_ops(&machine, &ops).is_ok());
+
+    let ops = [Op::Push(1), Op::Push(2), Op::Push(3), Op::Pop];
+    let divergence = stateful::run_ops(&machine, &ops).unwrap_err();
+    assert_eq!(divergence.step, 3);
+    assert_eq!(
+        divergence.to_string(),
+        "step 3: unexpected output Some(2) for Pop in state [1, 2, 3]"
+    );
+}
+
+#[test]
+fn run_ops_skips_invalid_ops() {
+    let machine = Stack { capacity: 2 };
+    assert!(stateful::run_ops(&machine, &[Op::Pop, Op::Push(1), Op::Pop, Op::Pop]).is_ok());
+}
+
+#[test]
+fn arbitrary_ops_respects_preconditions() {
+    let machine = Stack { capacity: 100 };
+    let data: Vec<u8> = (0..=255).rev().collect();
+    let ops = stateful::arbitrary_ops(&machine, &mut Unstructured::new(&data)).unwrap();
+    assert!(!ops.is_empty());
+    assert!(ops.len() <= Stack::MAX_OPS as usize);
+    let mut depth = 0_usize;
+    for op in &ops {
+        match op {
+            Op::Push(_) => depth += 1,
+            Op::Pop => depth = depth.checked_sub(1).expect("pop from an empty stack"),
+        }
+    }
+}
+
+#[test]
+fn correct_system_passes() {
+    stateful::check(
+        &Stack {
+            capacity: usize::MAX,
+        },
+        &Config::new().seed(1),
+    );
+}
+
+#[test]
+fn divergence_is_shrunk() {
+    let failure = stateful::try_check(&Stack { capacity: 3 }, &Config::new().seed(2)).unwrap_err();
+    match failure {
+        Failure::Failed { value, error, .. } => {
+            assert_eq!(
+                value,
+                "[Push(0), Push(0), Push(0), Push(0), Pop, Pop, Pop, Pop]"
+            );
+            assert_eq!(error, "step 7: unexpected output None for Pop in state [0]");
+        }
+        other => panic!("unexpected failure: {:?}", other),
+    }
+}
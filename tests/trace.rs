@@ -0,0 +1,142 @@
+#![cfg(all(feature = "derive", feature = "std"))]
+// Various structs/fields that we are deriving `Arbitrary` for aren't actually
+// used except to exercise the derive.
+#![allow(dead_code)]
+
+use arbitrary::{
+    trace::{End, Operation, Record},
+    Arbitrary, Trace, Unstructured,
+};
+
+#[derive(Arbitrary, Debug)]
+struct AddressBook {
+    owner: u8,
+    friends: Vec<Friend>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Friend {
+    Buddy { name: String },
+    Pal(u16),
+    Nobody,
+}
+
+// `owner`, then one `Buddy` named "hi", then the end of the friends. The last
+// byte is the length of the name.
+const DATA: [u8; 11] = [7, 1, 0, 0, 0, 0, b'h', b'i', 0, 0xff, 2];
+
+fn summary(records: &[Record]) -> Vec<(Operation, End, std::ops::Range<usize>, &str)> {
+    records
+        .iter()
+        .map(|r| (r.operation, r.end, r.range.clone(), r.path.as_str()))
+        .collect()
+}
+
+#[test]
+fn trace_derived_paths() {
+    let trace = Trace::new();
+    let mut u = Unstructured::with_trace(&DATA, &trace);
+    AddressBook::arbitrary(&mut u).unwrap();
+
+    let records = trace.records();
+    assert_eq!(
+        summary(&records),
+        [
+            (Operation::FillBuffer, End::Front, 0..1, "AddressBook.owner"),
+            (
+                Operation::FillBuffer,
+                End::Front,
+                1..2,
+                "AddressBook.friends"
+            ),
+            (
+                Operation::FillBuffer,
+                End::Front,
+                2..6,
+                "AddressBook.friends[0]"
+            ),
+            (
+                Operation::ByteSize,
+                End::Back,
+                10..11,
+                "AddressBook.friends[0].Buddy.name"
+            ),
+            (
+                Operation::Bytes,
+                End::Front,
+                6..8,
+                "AddressBook.friends[0].Buddy.name"
+            ),
+            (
+                Operation::FillBuffer,
+                End::Front,
+                8..9,
+                "AddressBook.friends"
+            ),
+        ]
+    );
+
+    assert_eq!(records[0].type_name, Some("u8"));
+    assert_eq!(records[2].type_name, Some(std::any::type_name::<Friend>()));
+    assert_eq!(records[4].type_name, Some("alloc::string::String"));
+    let depths: Vec<usize> = records.iter().map(|r| r.depth).collect();
+    assert_eq!(depths, [2, 2, 3, 5, 5, 2]);
+}
+
+#[test]
+fn trace_take_rest_paths() {
+    let trace = Trace::new();
+    AddressBook::arbitrary_take_rest(Unstructured::with_trace(&DATA, &trace)).unwrap();
+
+    let paths: Vec<String> = trace.records().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths[0], "AddressBook.owner");
+    assert!(paths
+        .iter()
+        .any(|p| p == "AddressBook.friends[0].Buddy.name"));
+}
+
+#[test]
+fn trace_tuple_struct_fields() {
+    #[derive(Arbitrary, Debug)]
+    struct Pair(u8, (u8, u8));
+
+    let trace = Trace::new();
+    let mut u = Unstructured::with_trace(&[1, 2, 3], &trace);
+    Pair::arbitrary(&mut u).unwrap();
+
+    let paths: Vec<String> = trace.records().into_iter().map(|r| r.path).collect();
+    assert_eq!(paths, ["Pair.0", "Pair.1", "Pair.1"]);
+}
+
+#[test]
+fn trace_hexdump() {
+    let trace = Trace::new();
+    let mut u = Unstructured::with_trace(&DATA, &trace);
+    AddressBook::arbitrary(&mut u).unwrap();
+
+    let dump = trace.hexdump();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert!(lines[0].starts_with("00000000  07 "));
+    assert!(lines[0].ends_with("front fill_buffer  AddressBook.owner: u8"));
+    assert!(lines[3].starts_with("00000006  68 69 "));
+    assert!(lines[3].contains("front bytes        AddressBook.friends[0].Buddy.name"));
+    assert!(lines[5].starts_with("00000009  ff "));
+    assert!(lines[5].ends_with("unused"));
+    assert!(lines[6].starts_with("0000000a  02 "));
+    assert!(lines[6].contains("back  byte_size    AddressBook.friends[0].Buddy.name"));
+}
+
+#[test]
+fn trace_does_not_change_generated_values() {
+    let traced = {
+        let trace = Trace::new();
+        let mut u = Unstructured::with_trace(&DATA, &trace);
+        format!("{:?}", AddressBook::arbitrary(&mut u).unwrap())
+    };
+    let untraced = format!(
+        "{:?}",
+        AddressBook::arbitrary(&mut Unstructured::new(&DATA)).unwrap()
+    );
+    assert_eq!(traced, untraced);
+}